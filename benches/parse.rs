@@ -0,0 +1,57 @@
+use app::{mds_to_html, parse};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+// Representative documents, repeated to a few hundred KiB so each run measures steady
+// scanning rather than setup.
+const PROSE: &str = include_str!("fixtures/prose.md");
+const TABLES: &str = include_str!("fixtures/tables.md");
+const LISTS: &str = include_str!("fixtures/lists.md");
+
+fn fixtures() -> Vec<(&'static str, String)> {
+    let (head, rows) = TABLES.split_at(TABLES.match_indices('\n').nth(2).map_or(0, |(i, _)| i + 1));
+    let table = format!("{}{}", head, rows.repeat(250));
+    vec!(
+        ("prose", format!("{}\n", PROSE).repeat(200)),
+        ("tables", format!("{}\n", table).repeat(10)),
+        ("lists", format!("{}\n", LISTS).repeat(400)),
+    )
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, text) in fixtures() {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &text, |b, text| b.iter(|| parse(text)));
+    }
+    group.finish();
+}
+
+fn bench_render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    for (name, text) in fixtures() {
+        let mds = parse(&text);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &mds, |b, mds| b.iter(|| mds_to_html(mds)));
+    }
+    group.finish();
+}
+
+#[cfg(feature = "rayon")]
+fn bench_parse_parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_parallel");
+    for (name, text) in fixtures() {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &text, |b, text| {
+            b.iter(|| app::parse_parallel(text, &Default::default()))
+        });
+    }
+    group.finish();
+}
+
+#[cfg(not(feature = "rayon"))]
+fn bench_parse_parallel(_: &mut Criterion) {}
+
+// Run with `cargo bench --bench parse`, or `cargo bench --bench parse -- prose` for one
+// fixture; add `--features rayon` to include `parse_parallel`.
+criterion_group!(benches, bench_parse, bench_render, bench_parse_parallel);
+criterion_main!(benches);
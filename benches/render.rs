@@ -0,0 +1,31 @@
+use app::convert::convert::mds_to_html;
+use app::parser::parser::parse;
+use std::time::Instant;
+
+fn document(sections: usize) -> String {
+    let section = "## Section **bold** and *italic*\n\
+        Some text with ==marks== and ~~strike~~ and a [[Ctrl]] key.\n\
+        | A | B | C |\n|:-|:-:|-:|\n| a | **b** | c |\n| d | e | f |\n\
+        - item one\n  - nested *item*\n- item two\n\
+        > quoted **text**\n\
+        ```rust\nfn main() {}\n```\n";
+    section.repeat(sections)
+}
+
+// Run with `cargo bench --bench render`; doubling the input should roughly double both
+// timings, since parsing and rendering are linear in the document size.
+fn main() {
+    for sections in [5_000, 10_000, 20_000] {
+        let text = document(sections);
+        let start = Instant::now();
+        let mds = parse(&text);
+        let parsed = start.elapsed();
+        let start = Instant::now();
+        let html = mds_to_html(&mds);
+        let rendered = start.elapsed();
+        println!(
+            "{:>6} KiB markdown: parse {:>8.2?}, render {:>8.2?} ({} KiB html)",
+            text.len() / 1024, parsed, rendered, html.len() / 1024
+        );
+    }
+}
@@ -0,0 +1,24 @@
+#![no_main]
+
+// `cargo +nightly fuzz run parse`: parsing and rendering must not panic on any input.
+use app::{parse, parse_with, try_parse_strict, mds_to_html, spans, events, ParserOptions};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    mds_to_html(&parse(&text));
+
+    let options = ParserOptions{
+        loose_tables: true,
+        ragged_rows: true,
+        table_spans: true,
+        commonmark_underscores: true,
+        strip_zero_width: true,
+        ..Default::default()
+    };
+    let mds = parse_with(&text, &options);
+    mds_to_html(&mds);
+    spans(&text, &mds, &options);
+    events(&text, &options).for_each(drop);
+    let _ = try_parse_strict(&text, &options);
+});
@@ -0,0 +1,54 @@
+use app::parser::parser::parse_with;
+use app::parser::options::ParserOptions;
+use app::convert::convert::mds_to_html;
+use napi_derive::napi;
+use serde_json::Value;
+
+// The second argument of `parse` and `toHtml`, e.g. `{ looseTables: true }`.
+#[napi(object)]
+#[derive(Default)]
+pub struct Options {
+    pub loose_tables: Option<bool>,
+    pub commonmark_underscores: Option<bool>,
+}
+
+fn parser_options(options: Option<Options>) -> ParserOptions {
+    let options = options.unwrap_or_default();
+    ParserOptions{
+        loose_tables: options.loose_tables.unwrap_or(false),
+        commonmark_underscores: options.commonmark_underscores.unwrap_or(false),
+        ..Default::default()
+    }
+}
+
+// An array with one object per block, keyed by its variant like `to_json`,
+// e.g. `[{ Heading: [1, [{ Normal: "Title" }]] }]`.
+#[napi]
+pub fn parse(text: String, options: Option<Options>) -> Value {
+    let mds = parse_with(&text, &parser_options(options));
+    serde_json::to_value(&mds).expect("the markdown ast always serializes")
+}
+
+#[napi(js_name = "toHtml")]
+pub fn to_html(text: String, options: Option<Options>) -> String {
+    mds_to_html(&parse_with(&text, &parser_options(options)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let value = parse("# Title".into(), None);
+        assert_eq!(value.to_string(), "[{\"Heading\":[1,[{\"Normal\":\"Title\"}]]}]");
+        assert_eq!(parse(String::new(), None), Value::Array(vec!()));
+    }
+
+    #[test]
+    fn test_to_html() {
+        assert_eq!(to_html("__a__ ~~b~~".into(), None), "<u>a</u> <s>b</s><br />");
+        let options = Options{ commonmark_underscores: Some(true), ..Default::default() };
+        assert_eq!(to_html("__a__".into(), Some(options)), "<b>a</b><br />");
+    }
+}
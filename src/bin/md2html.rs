@@ -0,0 +1,311 @@
+use app::config::{load_config, Config, CONFIG_FILE};
+use app::convert::convert::HtmlWriter;
+use app::convert::document::DocumentOptions;
+use app::convert::options::{Direction, RenderOptions};
+use app::parser::diagnostics::parse_with_diagnostics;
+use app::parser::error::try_parse_strict;
+use app::report::{annotate, Annotation};
+use app::cli::{completions, man_page};
+use app::convert::ansi::to_ansi;
+use app::convert::latex::to_latex;
+use app::convert::man::to_man;
+use app::convert::text::ToPlainText;
+use app::parser::dump::dump;
+use app::parser::options::ParserOptions;
+use app::parser::reader::BlockReader;
+use app::include::expand_includes;
+use app::pass::Substitute;
+use app::pipeline::Pipeline;
+use app::cache::RenderCache;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::exit;
+
+const USAGE: &str = "usage: md2html [options] [<file.md> | -]
+       md2html [options] <dir> -o <out-dir>
+
+Reads stdin when no file or `-` is given, and writes to stdout unless `-o` is set. Each
+block of html is written as soon as it is read, except with --standalone, --strict, --var
+or --includes, which need the whole input; a table of contents, abbreviations and footnotes
+hold back the blocks from the first one on. Other formats are written once all is read.
+
+Options are read from --config, or from markdown.toml in the working directory if there
+is one; flags override them.
+
+Given a directory, converts every .md file under it to an .html file at the same place
+under the output directory, pointing links between the documents at the html files.
+
+options:
+  -o, --output <file>       write the output to a file
+  -f, --format <html|text|ansi|latex|json|man>
+                            output format (default: html); json needs the json feature
+  --dump-ast                print the parsed document as an indented tree instead
+  --config <file.toml>      read the parser and converter options from a file
+  --standalone              wrap the html in a complete document
+  --title <text>            document title, instead of the first heading
+  --lang <code>             document language (default: en)
+  --dir <ltr|rtl|auto>      document direction
+  --description <text>      meta description, instead of the front matter or first paragraph
+  --url <url>               where the page is published, for its Open Graph tags
+  --no-open-graph           leave out the Open Graph tags
+  --css <url>               stylesheet link, may be repeated
+  --template <file.html>    page to put the html in, at {{content}}; {{title}}, {{lang}},
+                            {{description}}, {{meta}}, {{css}}, {{toc}} and front matter
+                            fields fill the rest
+  --semantic                <strong>, <em>, <del> and <ins> instead of <b>, <i>, <s> and <u>
+  --escape                  escape raw html and drop unsafe urls, for untrusted input
+  --anchors                 add anchor links to headings
+  --smart                   curly quotes, dashes and ellipses
+  --minify                  drop the newlines between tags
+  --mark-rtl                give headings and paragraphs in Arabic, Hebrew and other
+                            right-to-left scripts a dir attribute
+  --var <name=value>        replace {{name}} in the text and urls, may be repeated; front
+                            matter fields fill the placeholders left over
+  --includes <dir>          replace <!-- include: file.md --> lines with the file, which
+                            must be under <dir>; paths are relative to the including file
+  --cache <dir>             keep the html of whole documents in <dir> and reuse it while
+                            the text and options are unchanged; not used with --var
+  --strict                  fail on unclosed fences and containers and malformed tables,
+                            and warn about unclosed emphasis, undefined footnotes and
+                            skipped heading levels, showing the source line of each;
+                            colored on a terminal unless NO_COLOR is set
+  --completions <bash|zsh|fish>
+                            print a completion script for the shell
+  --man                     print this help as a man page
+  -h, --help                show this message
+
+exit status: 0 on success, 1 on a parse or render error, 2 on a usage or io error";
+
+#[derive(Default, PartialEq)]
+enum Format {
+    #[default]
+    Html,
+    Text,
+    Ansi,
+    Latex,
+    Json,
+    Man,
+    // The parsed tree, from --dump-ast.
+    Ast,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "html" => Ok(Format::Html),
+            "text" => Ok(Format::Text),
+            "ansi" => Ok(Format::Ansi),
+            "latex" => Ok(Format::Latex),
+            "json" if cfg!(feature = "json") => Ok(Format::Json),
+            "json" => Err("json output needs md2html built with the json feature".to_string()),
+            "man" => Ok(Format::Man),
+            _ => Err(format!("unknown format `{}`, expected html, text, ansi, latex, json or man", text)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    input: Option<String>,
+    output: Option<String>,
+    format: Format,
+    options: RenderOptions,
+    parser: ParserOptions,
+    strict: bool,
+    vars: BTreeMap<String, String>,
+    includes: Option<String>,
+    cache: Option<String>,
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("md2html: {}\n{}", message, USAGE);
+    exit(2)
+}
+
+fn document(options: &mut RenderOptions) -> &mut DocumentOptions {
+    options.document.get_or_insert_with(Default::default)
+}
+
+// The `--config` file, or `markdown.toml` if it exists.
+fn config(args: &[String]) -> Config {
+    let path = match args.iter().position(|arg| arg == "--config") {
+        Some(i) => args.get(i + 1).map(String::as_str).unwrap_or_else(|| usage_error("--config needs a value")),
+        None if Path::new(CONFIG_FILE).is_file() => CONFIG_FILE,
+        None => return Config::default(),
+    };
+    load_config(path).unwrap_or_else(|err| fail(path, err, 2))
+}
+
+fn parse_args(args: Vec<String>) -> Args {
+    let Config{ parser, render } = config(&args);
+    let mut parsed = Args{ options: render, parser, ..Default::default() };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage_error(&format!("{} needs a value", arg)));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0)
+            },
+            "--completions" => {
+                let shell = value().parse().unwrap_or_else(|err: String| usage_error(&err));
+                print!("{}", completions("md2html", USAGE, shell));
+                exit(0)
+            },
+            "--man" => {
+                print!("{}", man_page("md2html", "convert markdown to html", USAGE));
+                exit(0)
+            },
+            "-o" | "--output" => parsed.output = Some(value()),
+            "-f" | "--format" => parsed.format = value().parse().unwrap_or_else(|err: String| usage_error(&err)),
+            "--dump-ast" => parsed.format = Format::Ast,
+            "--config" => { value(); },
+            "--standalone" => { document(&mut parsed.options); },
+            "--title" => document(&mut parsed.options).title = Some(value()),
+            "--lang" => document(&mut parsed.options).lang = value(),
+            "--dir" => {
+                let dir = value().parse().unwrap_or_else(|err: String| usage_error(&err));
+                document(&mut parsed.options).dir = Some(dir);
+            },
+            "--css" => document(&mut parsed.options).css.push(value()),
+            "--description" => document(&mut parsed.options).description = Some(value()),
+            "--url" => document(&mut parsed.options).url = Some(value()),
+            "--no-open-graph" => document(&mut parsed.options).open_graph = false,
+            "--template" => {
+                let path = value();
+                let template = fs::read_to_string(&path).unwrap_or_else(|err| fail(&path, err, 2));
+                document(&mut parsed.options).template = Some(template);
+            },
+            "--semantic" => parsed.options.html.semantic_tags = true,
+            "--escape" => parsed.options.html.sanitize = true,
+            "--anchors" => parsed.options.heading_anchors = true,
+            "--smart" => parsed.options.smart_punctuation = true,
+            "--minify" => parsed.options.html.minify = true,
+            "--mark-rtl" => parsed.options.html.dir = Some(Direction::Rtl),
+            "--strict" => parsed.strict = true,
+            "--includes" => parsed.includes = Some(value()),
+            "--cache" => parsed.cache = Some(value()),
+            "--var" => {
+                let var = value();
+                let Some((name, value)) = var.split_once('=') else { usage_error("--var needs <name=value>") };
+                parsed.vars.insert(name.trim().to_string(), value.to_string());
+            },
+            "-" => parsed.input = None,
+            flag if flag.starts_with('-') => usage_error(&format!("unknown option {}", flag)),
+            _ if parsed.input.is_some() => usage_error("more than one input file"),
+            path => parsed.input = Some(path.to_string()),
+        }
+    }
+    parsed
+}
+
+fn fail(name: &str, err: impl Display, code: i32) -> ! {
+    eprintln!("md2html: {}: {}", name, err);
+    exit(code)
+}
+
+fn open_input(path: Option<&str>) -> io::Result<Box<dyn BufRead>> {
+    Ok(match path {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(io::stdin().lock()),
+    })
+}
+
+fn open_output(path: Option<&str>) -> io::Result<Box<dyn Write>> {
+    Ok(match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+// Parses and writes one block at a time, so a pipe sees html while input is still arriving.
+fn stream(input: Box<dyn BufRead>, out: Box<dyn Write>, args: &Args, name: &str, out_name: &str) -> Box<dyn Write> {
+    let mut writer = HtmlWriter::new(out, &args.options);
+    for md in BlockReader::new(input, &args.parser) {
+        let md = md.unwrap_or_else(|err| fail(name, err, 2));
+        writer.write(&md).unwrap_or_else(|err| fail(out_name, err, 2));
+    }
+    writer.finish().unwrap_or_else(|err| fail(out_name, err, 2))
+}
+
+fn pipeline(args: Args) -> Pipeline {
+    let mut pipeline = Pipeline::new(args.options).parser_options(args.parser);
+    if let Some(root) = args.includes { pipeline = pipeline.includes(root) }
+    // Substituted values aren't part of the cache key.
+    if let (Some(dir), true) = (args.cache, args.vars.is_empty()) { pipeline = pipeline.cache(RenderCache::with_dir(dir)) }
+    if args.vars.is_empty() { pipeline } else { pipeline.pass(Substitute(args.vars)) }
+}
+
+fn render(mut input: Box<dyn BufRead>, mut args: Args, name: &str) -> String {
+    let mut text = String::new();
+    input.read_to_string(&mut text).unwrap_or_else(|err| fail(name, err, 2));
+    if let Some(root) = &args.includes {
+        let dir = args.input.as_deref().and_then(|input| Path::new(input).parent()).unwrap_or(Path::new(""));
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        text = expand_includes(&text, dir, Path::new(root)).unwrap_or_else(|err| fail(name, err, 2));
+    }
+    if args.strict {
+        let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+        for diagnostic in &parse_with_diagnostics(&text, &args.parser).1 {
+            eprintln!("{}", annotate(name, &text, &Annotation::from(diagnostic), color));
+        }
+        if let Err(err) = try_parse_strict(&text, &args.parser) {
+            eprintln!("{}", annotate(name, &text, &Annotation::from(&err), color));
+            exit(1)
+        }
+    }
+    let format = std::mem::take(&mut args.format);
+    let pipeline = pipeline(args);
+    let mds = match format {
+        Format::Html => return pipeline.render(&text).unwrap_or_else(|err| fail(name, err, 1)),
+        _ => pipeline.parse(&text),
+    };
+    match format {
+        Format::Text => mds.to_plain_text(),
+        Format::Ansi => to_ansi(&mds),
+        Format::Latex => to_latex(&mds),
+        #[cfg(feature = "json")]
+        Format::Json => app::json::to_json(&mds),
+        Format::Man => to_man(&mds, 1),
+        Format::Ast => dump(&mds).trim_end().to_string(),
+        _ => unreachable!("checked when the flag was read"),
+    }
+}
+
+fn convert_dir(input: &str, args: Args) {
+    let Some(output) = args.output.clone() else { usage_error("a directory needs -o <out-dir>") };
+    if args.format != Format::Html { usage_error("a directory is only converted to html") }
+    let written = pipeline(args).convert_dir(Path::new(input), Path::new(&output));
+    for path in written.unwrap_or_else(|err| fail(input, err, 2)) {
+        eprintln!("md2html: wrote {}", path.display());
+    }
+}
+
+fn main() {
+    let args = parse_args(env::args().skip(1).collect());
+    let input = args.input.clone();
+    if let Some(dir) = input.as_deref().filter(|input| Path::new(input).is_dir()) {
+        return convert_dir(dir, args)
+    }
+    let name = input.as_deref().unwrap_or("<stdin>");
+    let output = args.output.clone();
+    let out_name = output.as_deref().unwrap_or("<stdout>");
+    let reader = open_input(input.as_deref()).unwrap_or_else(|err| fail(name, err, 2));
+    let whole = args.strict || args.format != Format::Html || args.options.document.is_some() || !args.vars.is_empty() || args.includes.is_some();
+    let mut out = if whole {
+        let html = render(reader, args, name);
+        let mut out = open_output(output.as_deref()).unwrap_or_else(|err| fail(out_name, err, 2));
+        out.write_all(html.as_bytes()).unwrap_or_else(|err| fail(out_name, err, 2));
+        out
+    } else {
+        let out = open_output(output.as_deref()).unwrap_or_else(|err| fail(out_name, err, 2));
+        stream(reader, out, &args, name, out_name)
+    };
+    out.write_all(b"\n").and_then(|_| out.flush()).unwrap_or_else(|err| fail(out_name, err, 2));
+}
@@ -0,0 +1,141 @@
+use app::convert::document::DocumentOptions;
+use app::convert::options::RenderOptions;
+use app::diff::diff;
+use app::format::format::format_markdown;
+use app::format::options::FormatOptions;
+use app::links::check_links;
+use app::parser::parser::parse;
+use app::pipeline::Pipeline;
+use app::preview::Preview;
+use std::env;
+use std::fs;
+use std::net::TcpListener;
+use std::ops::ControlFlow;
+use std::process::exit;
+
+const USAGE: &str = "usage: mdconv diff <old.md> <new.md>\n       mdconv fmt [--width <n>] <file.md>\n       mdconv watch <file.md>...\n       mdconv serve [--port <n>] <file.md | dir>\n       mdconv check <file.md | dir>...\n       mdconv build [--template <file.html>] <content-dir> <out-dir>";
+
+fn read_file_to_string(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("mdconv: {}: {}", path, err);
+        exit(2)
+    })
+}
+
+fn run_diff(old: &str, new: &str) -> i32 {
+    let (old, new) = (read_file_to_string(old), read_file_to_string(new));
+    let (old, new) = (parse(&old), parse(&new));
+    let changes = diff(&old, &new);
+    for change in &changes {
+        println!("{}", change);
+        for inline in change.inline() {
+            println!("    {}", inline);
+        }
+    }
+    if changes.is_empty() { 0 } else { 1 }
+}
+
+fn run_fmt(path: &str, width: Option<&str>) -> i32 {
+    let wrap = match width.map(str::parse) {
+        Some(Ok(width)) => Some(width),
+        Some(Err(_)) => {
+            eprintln!("{}", USAGE);
+            return 2
+        },
+        None => None,
+    };
+    let options = FormatOptions{ align_tables: true, heading_spacing: true, wrap, ..Default::default() };
+    println!("{}", format_markdown(&read_file_to_string(path), &options));
+    0
+}
+
+fn run_watch(paths: &[String]) -> i32 {
+    Pipeline::new(RenderOptions::default()).watch(paths, |path, html| {
+        let output = path.with_extension("html");
+        match html.map(|html| fs::write(&output, html)) {
+            Ok(Ok(())) => eprintln!("mdconv: wrote {}", output.display()),
+            Ok(Err(err)) => eprintln!("mdconv: {}: {}", output.display(), err),
+            Err(err) => eprintln!("mdconv: {}: {}", path.display(), err),
+        }
+        ControlFlow::Continue(())
+    });
+    0
+}
+
+fn run_serve(path: &str, port: Option<&str>) -> i32 {
+    let Ok(port) = port.unwrap_or("8000").parse::<u16>() else {
+        eprintln!("{}", USAGE);
+        return 2
+    };
+    if !std::path::Path::new(path).exists() {
+        eprintln!("mdconv: {}: no such file or directory", path);
+        return 2
+    }
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("mdconv: 127.0.0.1:{}: {}", port, err);
+            return 2
+        },
+    };
+    eprintln!("mdconv: serving {} at http://127.0.0.1:{}/", path, port);
+    match Preview::new(path, RenderOptions::default()).serve(listener) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("mdconv: {}", err);
+            1
+        },
+    }
+}
+
+fn run_check(paths: &[String]) -> i32 {
+    match check_links(paths) {
+        Ok(broken) => {
+            for link in &broken {
+                println!("{}", link);
+            }
+            if broken.is_empty() { 0 } else { 1 }
+        },
+        Err(err) => {
+            eprintln!("mdconv: {}", err);
+            2
+        },
+    }
+}
+
+fn run_build(input: &str, output: &str, template: Option<&str>) -> i32 {
+    let document = DocumentOptions{ template: template.map(read_file_to_string), ..Default::default() };
+    let pipeline = Pipeline::new(RenderOptions{ document: Some(document), ..Default::default() });
+    match pipeline.build_site(std::path::Path::new(input), std::path::Path::new(output)) {
+        Ok(written) => {
+            eprintln!("mdconv: wrote {} files to {}", written.len(), output);
+            0
+        },
+        Err(err) => {
+            eprintln!("mdconv: {}", err);
+            1
+        },
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = match args.as_slice() {
+        [command, old, new] if command == "diff" => run_diff(old, new),
+        [command, path] if command == "fmt" => run_fmt(path, None),
+        [command, flag, width, path] if command == "fmt" && flag == "--width" => run_fmt(path, Some(width)),
+        [command, paths @ ..] if command == "watch" && !paths.is_empty() => run_watch(paths),
+        [command, path] if command == "serve" => run_serve(path, None),
+        [command, flag, port, path] if command == "serve" && flag == "--port" => run_serve(path, Some(port)),
+        [command, paths @ ..] if command == "check" && !paths.is_empty() => run_check(paths),
+        [command, input, output] if command == "build" => run_build(input, output, None),
+        [command, flag, template, input, output] if command == "build" && flag == "--template" => {
+            run_build(input, output, Some(template))
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        },
+    };
+    exit(code)
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// FNV-1a, which unlike `DefaultHasher` gives the same hash in every build, so keys written
+// to disk by one run still match in the next.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3))
+}
+
+// The key for `text` rendered with `options`, e.g. the `{:?}` of the options used. The
+// crate version is part of it, so html from an older renderer is never returned.
+pub fn cache_key(text: &str, options: &str) -> u64 {
+    [env!("CARGO_PKG_VERSION"), options, text].iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, part| fnv1a(fnv1a(hash, part.as_bytes()), &[0]))
+}
+
+// Rendered html by `cache_key`, kept in memory and, with a directory, on disk as
+// `<key>.html` so it outlives the process.
+#[derive(Debug, Default)]
+pub struct RenderCache {
+    memory: Mutex<HashMap<u64, String>>,
+    dir: Option<PathBuf>,
+}
+
+impl RenderCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        RenderCache{ dir: Some(dir.into()), ..Default::default() }
+    }
+
+    fn path(&self, key: u64) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(format!("{:016x}.html", key)))
+    }
+
+    pub fn get(&self, key: u64) -> Option<String> {
+        let mut memory = self.memory.lock().unwrap();
+        if let Some(html) = memory.get(&key) { return Some(html.clone()) }
+        let html = fs::read_to_string(self.path(key)?).ok()?;
+        memory.insert(key, html.clone());
+        Some(html)
+    }
+
+    // Keeps the html in memory, then writes it to the directory if there is one.
+    pub fn insert(&self, key: u64, html: &str) -> io::Result<()> {
+        self.memory.lock().unwrap().insert(key, html.to_string());
+        let Some(path) = self.path(key) else { return Ok(()) };
+        fs::create_dir_all(path.parent().unwrap_or(&path))?;
+        fs::write(path, html)
+    }
+
+    // The number of documents held in memory.
+    pub fn len(&self) -> usize {
+        self.memory.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Empties the memory; files already on disk stay.
+    pub fn clear(&self) {
+        self.memory.lock().unwrap().clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        assert_eq!(cache_key("# A", "options"), cache_key("# A", "options"));
+        assert_ne!(cache_key("# A", "options"), cache_key("# B", "options"));
+        assert_ne!(cache_key("# A", "options"), cache_key("# A", "other"));
+        assert_ne!(cache_key("ab", "c"), cache_key("a", "bc"));
+    }
+
+    #[test]
+    fn test_render_cache() {
+        let cache = RenderCache::new();
+        assert_eq!(cache.get(1), None);
+        cache.insert(1, "<p>a</p>").unwrap();
+        assert_eq!(cache.get(1).as_deref(), Some("<p>a</p>"));
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+
+        let dir = std::env::temp_dir().join(format!("app-cache-{}", std::process::id()));
+        RenderCache::with_dir(&dir).insert(2, "<p>b</p>").unwrap();
+        let cache = RenderCache::with_dir(&dir);
+        assert_eq!(cache.get(2).as_deref(), Some("<p>b</p>"));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(3), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
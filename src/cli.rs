@@ -0,0 +1,247 @@
+use crate::convert::man::{escape_roff, guard};
+
+// A flag from the `options:` list of a usage text, e.g.
+// `  -o, --output <file.html>  write the html to a file`, with the lines indented under it
+// joined onto its help.
+#[derive(Debug, PartialEq)]
+pub struct Flag {
+    pub names: Vec<String>,
+    pub value: Option<String>,
+    pub help: String,
+}
+
+impl Flag {
+    // The values spelled out in e.g. `<ltr|rtl|auto>`, or none for a free-form value.
+    fn choices(&self) -> Vec<&str> {
+        let value = self.value.as_deref().unwrap_or("").trim_start_matches('<').trim_end_matches('>');
+        if value.contains('|') { value.split('|').collect() } else { vec!() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl std::str::FromStr for Shell {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("unknown shell `{}`, expected bash, zsh or fish", text)),
+        }
+    }
+}
+
+// The flags listed under `options:` in `usage`, up to the next blank line.
+pub fn flags(usage: &str) -> Vec<Flag> {
+    let Some((_, options)) = usage.split_once("\noptions:\n") else { return vec!() };
+    let mut flags: Vec<Flag> = vec!();
+    for line in options.lines().take_while(|line| !line.trim().is_empty()) {
+        let line = line.trim_start();
+        if !line.starts_with('-') {
+            if let Some(flag) = flags.last_mut() { flag.help = format!("{} {}", flag.help, line).trim().to_string() }
+            continue
+        }
+        let (spec, help) = line.split_once("  ").unwrap_or((line, ""));
+        let names = spec.split(", ").filter_map(|name| name.split(' ').next()).map(str::to_string).collect();
+        let value = spec.find('<').map(|i| spec[i..].to_string());
+        flags.push(Flag{ names, value, help: help.trim().to_string() });
+    }
+    flags
+}
+
+fn bash(program: &str, flags: &[Flag]) -> String {
+    let function = format!("_{}", program.replace('-', "_"));
+    let names: Vec<&str> = flags.iter().flat_map(|flag| flag.names.iter().map(String::as_str)).collect();
+    let cases: String = flags.iter().filter(|flag| flag.value.is_some()).map(|flag| {
+        let choices = flag.choices();
+        let reply = if choices.is_empty() { "compgen -f -- \"$cur\"".to_string() } else { format!("compgen -W \"{}\" -- \"$cur\"", choices.join(" ")) };
+        format!("        {})\n            COMPREPLY=($({}))\n            return;;\n", flag.names.join("|"), reply)
+    })
+    .collect();
+    format!(
+        "{function}() {{\n    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n    case \"$prev\" in\n{cases}    esac\n    \
+        if [[ \"$cur\" == -* ]]; then\n        COMPREPLY=($(compgen -W \"{names}\" -- \"$cur\"))\n    else\n        COMPREPLY=($(compgen -f -- \"$cur\"))\n    fi\n}}\n\
+        complete -F {function} {program}\n",
+        names = names.join(" "),
+    )
+}
+
+fn zsh(program: &str, flags: &[Flag]) -> String {
+    let quote = |text: &str| text.replace('\'', "'\\''").replace('[', "\\[").replace(']', "\\]");
+    let specs: String = flags.iter().map(|flag| {
+        let names = match flag.names.as_slice() {
+            [name] => format!("'{}", name),
+            names => format!("'({})'{{{}}}'", names.join(" "), names.join(",")),
+        };
+        let value = flag.value.as_deref().map_or(String::new(), |value| {
+            let choices = flag.choices();
+            let action = if choices.is_empty() { "_files".to_string() } else { format!("({})", choices.join(" ")) };
+            format!(":{}:{}", value.trim_start_matches('<').trim_end_matches('>').replace(':', "\\:"), action)
+        });
+        format!("    {}[{}]{}' \\\n", names, quote(&flag.help), value)
+    })
+    .collect();
+    format!("#compdef {program}\n\n_arguments -s \\\n{specs}    '*:file:_files'\n")
+}
+
+fn fish(program: &str, flags: &[Flag]) -> String {
+    flags.iter().map(|flag| {
+        let mut line = format!("complete -c {}", program);
+        for name in &flag.names {
+            match name.strip_prefix("--") {
+                Some(long) => line.push_str(&format!(" -l {}", long)),
+                None => line.push_str(&format!(" -s {}", name.trim_start_matches('-'))),
+            }
+        }
+        let choices = flag.choices();
+        if !choices.is_empty() {
+            line.push_str(&format!(" -x -a '{}'", choices.join(" ")));
+        } else if flag.value.is_some() {
+            line.push_str(" -r -F");
+        }
+        format!("{} -d '{}'\n", line, flag.help.replace('\\', "\\\\").replace('\'', "\\'"))
+    })
+    .collect()
+}
+
+// A completion script for `program` in `shell`, completing the flags of `usage` and files.
+pub fn completions(program: &str, usage: &str, shell: Shell) -> String {
+    let flags = flags(usage);
+    match shell {
+        Shell::Bash => bash(program, &flags),
+        Shell::Zsh => zsh(program, &flags),
+        Shell::Fish => fish(program, &flags),
+    }
+}
+
+fn flag_to_man(flag: &Flag) -> String {
+    let names: Vec<String> = flag.names.iter().map(|name| format!("\\fB{}\\fR", escape_roff(name))).collect();
+    let value = flag.value.as_deref().map_or(String::new(), |value| format!(" \\fI{}\\fR", escape_roff(value)));
+    format!(".TP\n{}{}\n{}\n", names.join(", "), value, guard(escape_roff(&flag.help)))
+}
+
+// A man page for `program` from its usage text: the `usage:` lines make the synopsis, the
+// paragraphs before `options:` the description, the flags the options, and a closing
+// `exit status:` paragraph the exit status. `about` is the one-line summary in NAME.
+pub fn man_page(program: &str, about: &str, usage: &str) -> String {
+    let (head, tail) = usage.split_once("\noptions:\n").unwrap_or((usage, ""));
+    let mut paragraphs = head.split("\n\n");
+    let synopsis: Vec<String> = paragraphs.next().unwrap_or("").lines()
+        .map(|line| guard(escape_roff(line.trim_start_matches("usage:").trim())))
+        .collect();
+    let mut page = format!(
+        ".TH {} 1\n.SH NAME\n{} \\- {}\n.SH SYNOPSIS\n.nf\n{}\n.fi\n",
+        program.to_uppercase(), program, escape_roff(about), synopsis.join("\n"),
+    );
+    let description: Vec<String> = paragraphs.filter(|paragraph| !paragraph.trim().is_empty())
+        .map(|paragraph| guard(escape_roff(&paragraph.trim().replace('\n', " "))))
+        .collect();
+    if !description.is_empty() {
+        page.push_str(&format!(".SH DESCRIPTION\n{}\n", description.join("\n.PP\n")));
+    }
+    let flags = flags(usage);
+    if !flags.is_empty() {
+        page.push_str(".SH OPTIONS\n");
+        page.extend(flags.iter().map(flag_to_man));
+    }
+    let after = tail.split("\n\n").skip(1).map(|paragraph| paragraph.trim().replace('\n', " "));
+    for paragraph in after {
+        match paragraph.strip_prefix("exit status:") {
+            Some(status) => page.push_str(&format!(".SH EXIT STATUS\n{}\n", guard(escape_roff(status.trim())))),
+            None => page.push_str(&format!(".PP\n{}\n", guard(escape_roff(&paragraph)))),
+        }
+    }
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USAGE: &str = "usage: tool [options] <file.md>
+       tool --version
+
+Converts a file.
+
+options:
+  -o, --output <file.html>  write the html
+                            to a file
+  --dir <ltr|rtl|auto>      direction
+  --strict                  fail on 'bad' [input]
+  --completions <bash|zsh|fish>
+                            print a script
+
+exit status: 0 on success, 1 on failure";
+
+    #[test]
+    fn test_flags() {
+        let flags = flags(USAGE);
+        assert_eq!(flags[0], Flag{
+            names: vec!("-o".to_string(), "--output".to_string()), value: Some("<file.html>".to_string()),
+            help: "write the html to a file".to_string(),
+        });
+        assert_eq!(flags[1].choices(), vec!("ltr", "rtl", "auto"));
+        assert_eq!((flags[2].names.as_slice(), flags[2].value.as_deref()), (&["--strict".to_string()][..], None));
+        assert_eq!(flags[3].help, "print a script");
+        assert_eq!(super::flags("usage: tool"), vec!());
+        assert_eq!("elvish".parse::<Shell>(), Err("unknown shell `elvish`, expected bash, zsh or fish".to_string()));
+    }
+
+    #[test]
+    fn test_completions() {
+        let bash = completions("tool", USAGE, Shell::Bash);
+        assert!(bash.contains("compgen -W \"-o --output --dir --strict --completions\" -- \"$cur\""));
+        assert!(bash.contains("        --dir)\n            COMPREPLY=($(compgen -W \"ltr rtl auto\" -- \"$cur\"))\n"));
+        assert!(bash.ends_with("complete -F _tool tool\n"));
+
+        let zsh = completions("tool", USAGE, Shell::Zsh);
+        assert!(zsh.starts_with("#compdef tool\n\n_arguments -s \\\n"));
+        assert!(zsh.contains("    '(-o --output)'{-o,--output}'[write the html to a file]:file.html:_files' \\\n"));
+        assert!(zsh.contains("    '--dir[direction]:ltr|rtl|auto:(ltr rtl auto)' \\\n"));
+        assert!(zsh.contains("    '--strict[fail on '\\''bad'\\'' \\[input\\]]' \\\n"));
+
+        let fish = completions("tool", USAGE, Shell::Fish);
+        assert_eq!(fish, "complete -c tool -s o -l output -r -F -d 'write the html to a file'\n\
+            complete -c tool -l dir -x -a 'ltr rtl auto' -d 'direction'\n\
+            complete -c tool -l strict -d 'fail on \\'bad\\' [input]'\n\
+            complete -c tool -l completions -x -a 'bash zsh fish' -d 'print a script'\n");
+    }
+
+    #[test]
+    fn test_man_page() {
+        let expect = ".TH TOOL 1
+.SH NAME
+tool \\- convert files
+.SH SYNOPSIS
+.nf
+tool [options] <file.md>
+tool \\-\\-version
+.fi
+.SH DESCRIPTION
+Converts a file.
+.SH OPTIONS
+.TP
+\\fB\\-o\\fR, \\fB\\-\\-output\\fR \\fI<file.html>\\fR
+write the html to a file
+.TP
+\\fB\\-\\-dir\\fR \\fI<ltr|rtl|auto>\\fR
+direction
+.TP
+\\fB\\-\\-strict\\fR
+fail on 'bad' [input]
+.TP
+\\fB\\-\\-completions\\fR \\fI<bash|zsh|fish>\\fR
+print a script
+.SH EXIT STATUS
+0 on success, 1 on failure
+";
+        assert_eq!(man_page("tool", "convert files", USAGE), expect);
+    }
+}
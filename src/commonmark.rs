@@ -0,0 +1,165 @@
+use crate::parser::parser::parse_with;
+use crate::parser::options::ParserOptions;
+use crate::convert::convert::mds_to_html_with_renderer;
+use crate::convert::options::{Direction, RenderOptions};
+use crate::convert::renderer::Renderer;
+use std::collections::BTreeMap;
+use std::fmt;
+
+const FENCE: &str = "````````````````````````````````";
+
+// Html shaped like the CommonMark reference renderer's: headings without ids and each
+// sentence in a `<p>`, with blank lines left out. The parser keeps the lines of a paragraph
+// apart, so a paragraph of several lines comes out as several, or as one with `<br />`
+// between the lines with `HtmlOptions::hard_breaks`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CommonMarkRenderer;
+
+impl Renderer for CommonMarkRenderer {
+    fn heading(&self, level: usize, _id: &str, content: &str) -> String {
+        format!("<h{}>{}</h{}>", level, content, level)
+    }
+
+    fn sentence(&self, content: &str) -> String {
+        if content.is_empty() { return "".to_string() }
+        format!("<p>{}</p>", content)
+    }
+
+    fn paragraph(&self, dir: Option<Direction>, content: &str) -> String {
+        let Some(dir) = dir else { return self.sentence(content) };
+        format!("<p dir=\"{}\">{}</p>", dir.as_str(), content)
+    }
+}
+
+// One example of the CommonMark spec: its number, the section it is in, and the markdown
+// with the html the reference renderer gives for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecExample {
+    pub number: usize,
+    pub section: String,
+    pub markdown: String,
+    pub html: String,
+}
+
+// Reads the examples out of the spec's `spec.txt`, where `→` stands for a tab.
+pub fn parse_spec(text: &str) -> Vec<SpecExample> {
+    let mut examples = vec!();
+    let mut section = String::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        if line.starts_with(FENCE) && line.trim_start_matches('`').trim() == "example" {
+            let mut parts = (String::new(), String::new());
+            let mut in_html = false;
+            for line in lines.by_ref().take_while(|line| !line.starts_with(FENCE)) {
+                if line == "." && !in_html {
+                    in_html = true;
+                    continue
+                }
+                let part = if in_html { &mut parts.1 } else { &mut parts.0 };
+                part.push_str(&line.replace('→', "\t"));
+                part.push('\n');
+            }
+            let (markdown, html) = parts;
+            examples.push(SpecExample{ number: examples.len() + 1, section: section.clone(), markdown, html });
+        } else if let Some(title) = line.strip_prefix("## ").or_else(|| line.strip_prefix("# ")) {
+            section = title.trim().to_string();
+        }
+    }
+    examples
+}
+
+// Html compared without the whitespace between tags or around the whole, and with every
+// void element written the same way.
+fn normalize(html: &str) -> String {
+    let html = html.replace("<br>", "<br />").replace("<hr>", "<hr />");
+    let mut normalized = String::new();
+    for line in html.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let between_tags = normalized.ends_with('>') && line.starts_with('<');
+        if !normalized.is_empty() && !between_tags { normalized.push('\n') }
+        normalized.push_str(line);
+    }
+    normalized
+}
+
+// Which examples the parser and renderer get right with the CommonMark options.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SpecReport {
+    pub passed: Vec<usize>,
+    // Failed examples with the html rendered for them.
+    pub failed: Vec<(usize, String)>,
+    // Passed and total examples by section.
+    pub sections: BTreeMap<String, (usize, usize)>,
+}
+
+impl SpecReport {
+    pub fn total(&self) -> usize {
+        self.passed.len() + self.failed.len()
+    }
+
+    pub fn percent(&self) -> f64 {
+        if self.total() == 0 { return 0.0 }
+        self.passed.len() as f64 * 100.0 / self.total() as f64
+    }
+}
+
+impl fmt::Display for SpecReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (section, (passed, total)) in &self.sections {
+            writeln!(f, "{:>4}/{:<4} {}", passed, total, section)?;
+        }
+        write!(f, "{}/{} examples pass ({:.1}%)", self.passed.len(), self.total(), self.percent())
+    }
+}
+
+pub fn run_spec(examples: &[SpecExample]) -> SpecReport {
+    let (parser, renderer) = (ParserOptions::commonmark(), RenderOptions::commonmark());
+    let mut report = SpecReport::default();
+    for example in examples {
+        let mds = parse_with(&example.markdown, &parser);
+        let html = mds_to_html_with_renderer(&mds, &renderer, &CommonMarkRenderer).unwrap_or_default();
+        let counts = report.sections.entry(example.section.clone()).or_default();
+        counts.1 += 1;
+        if normalize(&html) == normalize(&example.html) {
+            counts.0 += 1;
+            report.passed.push(example.number);
+        } else {
+            report.failed.push((example.number, html));
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = "# Introduction\n\nProse.\n\n## Tabs\n\n```````````````````````````````` example\n→foo\n.\n<pre><code>foo\n</code></pre>\n````````````````````````````````\n\n## ATX headings\n\n```````````````````````````````` example\n# foo\n.\n<h1>foo</h1>\n````````````````````````````````\n\n```````````````````````````````` example\n*a* _b_\n.\n<p><em>a</em> <em>b</em></p>\n````````````````````````````````\n";
+
+    #[test]
+    fn test_parse_spec() {
+        let examples = parse_spec(SPEC);
+        assert_eq!(examples.len(), 3);
+        assert_eq!(examples[0], SpecExample{
+            number: 1,
+            section: "Tabs".to_string(),
+            markdown: "\tfoo\n".to_string(),
+            html: "<pre><code>foo\n</code></pre>\n".to_string(),
+        });
+        assert_eq!((examples[2].number, examples[2].section.as_str()), (3, "ATX headings"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("<ul>\n  <li>a</li>\n</ul>\n"), "<ul><li>a</li></ul>");
+        assert_eq!(normalize("<p>a<br>\nb</p>"), "<p>a<br />\nb</p>");
+    }
+
+    #[test]
+    fn test_run_spec() {
+        let report = run_spec(&parse_spec(SPEC));
+        assert_eq!(report.total(), 3);
+        assert_eq!(report.passed, vec!(2, 3));
+        assert_eq!(report.sections["ATX headings"].1, 2);
+        assert!(report.to_string().ends_with(&format!("{}/3 examples pass ({:.1}%)", report.passed.len(), report.percent())));
+    }
+}
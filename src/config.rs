@@ -0,0 +1,252 @@
+use crate::convert::options::RenderOptions;
+use crate::parser::options::ParserOptions;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// The file the CLI reads from the working directory when no `--config` is given.
+pub const CONFIG_FILE: &str = "markdown.toml";
+
+// Parser and renderer options read from a `markdown.toml`, e.g.
+//
+// ```toml
+// [parser]
+// loose_tables = true
+//
+// [render]
+// heading_offset = 1
+//
+// [html]
+// sanitize = true
+// base_url = "https://example.com/docs/"
+//
+// [document]
+// css = ["style.css"]
+// url = "https://example.com/docs/guide.html"
+// ```
+//
+// A `[document]` table, even an empty one, wraps the html in a complete page. Keys left out
+// keep their defaults.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Config {
+    pub parser: ParserOptions,
+    pub render: RenderOptions,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    // The 1-based line and what is wrong with it.
+    Invalid(usize, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::Invalid(line, message) => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Bool(bool),
+    Integer(usize),
+    Text(String),
+    List(Vec<String>),
+}
+
+// A basic `"..."` string with its escapes, or a literal `'...'` one, and the text after it.
+fn string(text: &str) -> Option<(String, &str)> {
+    if let Some(rest) = text.strip_prefix('\'') {
+        let end = rest.find('\'')?;
+        return Some((rest[..end].to_string(), &rest[end + 1..]))
+    }
+    let mut chars = text.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[i + 2..])),
+            '\\' => value.push(match chars.next()?.1 {
+                'n' => '\n',
+                't' => '\t',
+                c @ ('"' | '\\') => c,
+                _ => return None,
+            }),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+fn strip_comment(text: &str) -> Option<&str> {
+    let text = text.trim_start();
+    if text.is_empty() || text.starts_with('#') { Some("") } else { None }
+}
+
+fn value(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut items = vec!();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                strip_comment(after)?;
+                return Some(Value::List(items))
+            }
+            let (item, after) = string(rest)?;
+            items.push(item);
+            let after = after.trim_start();
+            rest = after.strip_prefix(',').unwrap_or(after);
+            if !rest.trim_start().starts_with(']') && rest.len() == after.len() { return None }
+        }
+    }
+    if text.starts_with(['"', '\'']) {
+        let (value, rest) = string(text)?;
+        strip_comment(rest)?;
+        return Some(Value::Text(value))
+    }
+    let text = text.split('#').next()?.trim();
+    match text {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => text.replace('_', "").parse().ok().map(Value::Integer),
+    }
+}
+
+fn set(config: &mut Config, section: &str, key: &str, value: Value) -> Result<(), String> {
+    let Config{ parser, render } = config;
+    let document = &mut render.document;
+    match (section, key, value) {
+        ("parser", "loose_tables", Value::Bool(on)) => parser.loose_tables = on,
+        ("parser", "ragged_rows", Value::Bool(on)) => parser.ragged_rows = on,
+        ("parser", "table_spans", Value::Bool(on)) => parser.table_spans = on,
+        ("parser", "commonmark_underscores", Value::Bool(on)) => parser.commonmark_underscores = on,
+        ("parser", "autolinks", Value::Bool(on)) => parser.autolinks = on,
+        ("parser", "task_lists", Value::Bool(on)) => parser.task_lists = on,
+        ("parser", "single_tilde_strikethrough", Value::Bool(on)) => parser.single_tilde_strikethrough = on,
+        ("parser", "strip_zero_width", Value::Bool(on)) => parser.strip_zero_width = on,
+        ("parser", "tab_width", Value::Integer(n)) => parser.tab_width = n,
+        ("parser", "max_nesting", Value::Integer(n)) => parser.max_nesting = n,
+        ("render", "heading_anchors", Value::Bool(on)) => render.heading_anchors = on,
+        ("render", "number_headings", Value::Bool(on)) => render.number_headings = on,
+        ("render", "smart_punctuation", Value::Bool(on)) => render.smart_punctuation = on,
+        ("render", "heading_offset", Value::Integer(n)) => render.heading_offset = n,
+        ("html", "semantic_tags", Value::Bool(on)) => render.html.semantic_tags = on,
+        ("html", "minify", Value::Bool(on)) => render.html.minify = on,
+        ("html", "line_numbers", Value::Bool(on)) => render.html.line_numbers = on,
+        ("html", "sanitize", Value::Bool(on)) => render.html.sanitize = on,
+        ("html", "md_links", Value::Bool(on)) => render.html.md_links = on,
+        ("html", "escape_raw", Value::Bool(on)) => render.html.escape_raw = on,
+        ("html", "table_sections", Value::Bool(on)) => render.html.table_sections = on,
+        ("html", "hard_breaks", Value::Bool(on)) => render.html.hard_breaks = on,
+        ("html", "base_url", Value::Text(url)) => render.html.base_url = Some(url),
+        ("html", "site_host", Value::Text(host)) => render.html.site_host = Some(host),
+        ("html", "dir", Value::Text(dir)) => render.html.dir = Some(dir.parse()?),
+        ("document", "lang", Value::Text(lang)) => document.get_or_insert_with(Default::default).lang = lang,
+        ("document", "title", Value::Text(title)) => document.get_or_insert_with(Default::default).title = Some(title),
+        ("document", "css", Value::List(css)) => document.get_or_insert_with(Default::default).css = css,
+        ("document", "description", Value::Text(text)) => document.get_or_insert_with(Default::default).description = Some(text),
+        ("document", "url", Value::Text(url)) => document.get_or_insert_with(Default::default).url = Some(url),
+        ("document", "open_graph", Value::Bool(on)) => document.get_or_insert_with(Default::default).open_graph = on,
+        ("document", "dir", Value::Text(dir)) => document.get_or_insert_with(Default::default).dir = Some(dir.parse()?),
+        ("parser" | "render" | "html" | "document", _, value) => {
+            return Err(format!("unknown key or wrong type: {} = {:?}", key, value))
+        },
+        ("", _, _) => return Err(format!("{} is outside a table", key)),
+        _ => return Err(format!("unknown table [{}]", section)),
+    }
+    Ok(())
+}
+
+pub fn parse_config(text: &str) -> Result<Config, ConfigError> {
+    let mut config = Config::default();
+    let mut section = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let invalid = |message: &str| ConfigError::Invalid(i + 1, message.to_string());
+        if line.is_empty() || line.starts_with('#') { continue }
+        if let Some(rest) = line.strip_prefix('[') {
+            let (name, after) = rest.split_once(']').ok_or_else(|| invalid("unclosed table header"))?;
+            strip_comment(after).ok_or_else(|| invalid("text after the table header"))?;
+            section = name.trim().to_string();
+            if section == "document" { config.render.document.get_or_insert_with(Default::default); }
+            continue
+        }
+        let (key, value) = line.split_once('=').ok_or_else(|| invalid("expected `key = value`"))?;
+        let value = self::value(value).ok_or_else(|| invalid("expected a bool, integer, string or list of strings"))?;
+        set(&mut config, &section, key.trim(), value).map_err(|message| invalid(&message))?;
+    }
+    Ok(config)
+}
+
+pub fn load_config(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+    parse_config(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::options::Direction;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config("\
+            # Site settings
+            [parser]
+            loose_tables = true   # keep broken tables
+            tab_width = 2
+            task_lists = true
+
+            [render]
+            heading_offset = 1
+
+            [html]
+            sanitize = true
+            dir = \"rtl\"
+            base_url = \"https://example.com/docs/\"
+
+            [document]
+            css = [\"a.css\", 'b.css',]
+            title = \"Say \\\"hi\\\" # not a comment\"
+            open_graph = false
+        ").unwrap();
+        assert!(config.parser.loose_tables);
+        assert_eq!(config.parser.tab_width, 2);
+        assert!(config.parser.task_lists && !config.parser.autolinks);
+        assert_eq!(config.render.heading_offset, 1);
+        assert!(config.render.html.sanitize);
+        assert_eq!(config.render.html.dir, Some(Direction::Rtl));
+        assert_eq!(config.render.html.base_url.as_deref(), Some("https://example.com/docs/"));
+        let document = config.render.document.unwrap();
+        assert_eq!(document.css, vec!("a.css".to_string(), "b.css".to_string()));
+        assert_eq!(document.title.as_deref(), Some("Say \"hi\" # not a comment"));
+        assert_eq!(document.lang, "en");
+        assert!(!document.open_graph);
+
+        assert_eq!(parse_config("").unwrap(), Config::default());
+        assert!(parse_config("[document]").unwrap().render.document.is_some());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let message = |text: &str| parse_config(text).unwrap_err().to_string();
+        assert_eq!(message("[parser]\nloose_tables = yes"), "line 2: expected a bool, integer, string or list of strings");
+        assert_eq!(message("[parser]\nloose_tables = 1"), "line 2: unknown key or wrong type: loose_tables = Integer(1)");
+        assert_eq!(message("[fonts]\nsize = 1"), "line 2: unknown table [fonts]");
+        assert_eq!(message("[html\n"), "line 1: unclosed table header");
+        assert_eq!(message("[html]\ndir = \"up\""), "line 2: unknown direction `up`, expected ltr, rtl or auto");
+        assert_eq!(message("[document]\ncss = [\"a.css\" \"b.css\"]"), "line 2: expected a bool, integer, string or list of strings");
+        assert!(matches!(load_config("/nonexistent/markdown.toml"), Err(ConfigError::Io(_))));
+    }
+}
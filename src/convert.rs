@@ -1 +1,28 @@
-pub mod convert;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod convert;
+pub mod options;
+pub mod slug;
+pub mod url;
+pub mod text;
+pub mod toc;
+pub mod abbr;
+pub mod footnote;
+pub mod smart;
+pub mod renderer;
+pub mod minify;
+pub mod document;
+pub mod highlight;
+pub mod sanitize;
+pub mod void;
+pub mod ansi;
+pub mod latex;
+pub mod man;
+pub mod asciidoc;
+pub mod rst;
+pub mod csv;
+pub mod tree;
+pub mod stream;
+#[cfg(feature = "docx")]
+pub mod docx;
+#[cfg(feature = "pdf")]
+pub mod pdf;
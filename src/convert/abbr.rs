@@ -0,0 +1,138 @@
+use crate::parser::parser::*;
+use super::renderer::{escape_attribute, escape_html};
+use std::fmt::Write as _;
+
+fn collect(mds: &[Md], abbreviations: &mut Vec<(String, String)>) {
+    for md in mds {
+        match md {
+            Md::Abbreviation(name, title) => abbreviations.push((name.clone(), title.clone())),
+            Md::Container(_, mds) | Md::Details(_, _, mds) | Md::Quote(mds) => collect(mds, abbreviations),
+            Md::List(items) => items.0.iter().for_each(|item| collect(&item.1, abbreviations)),
+            _ => {},
+        }
+    }
+}
+
+// Longest names first so `HTML5` wins over `HTML`; the first definition of a name wins. An
+// empty name would match everywhere without consuming anything, so it is dropped.
+pub fn abbreviations(mds: &[Md]) -> Vec<(String, String)> {
+    let mut abbreviations = vec!();
+    collect(mds, &mut abbreviations);
+    let mut unique: Vec<(String, String)> = vec!();
+    for (name, title) in abbreviations {
+        if !name.is_empty() && !unique.iter().any(|(seen, _)| seen == &name) {
+            unique.push((name, title));
+        }
+    }
+    unique.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    unique
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// The abbreviation at the start of `rest`, when it stands as a whole word after `prev`.
+fn found<'t, 'd>(
+    rest: &'t str,
+    prev: Option<char>,
+    abbreviations: &'d [(String, String)]
+) -> Option<(&'d String, &'d String, &'t str)> {
+    if prev.is_some_and(is_word_char) { return None }
+    abbreviations.iter().find_map(|(name, title)| {
+        let after = rest.strip_prefix(name.as_str())?;
+        if after.chars().next().is_some_and(is_word_char) { return None }
+        Some((name, title, after))
+    })
+}
+
+pub fn abbreviate(text: &str, abbreviations: &[(String, String)]) -> String {
+    if abbreviations.is_empty() { return text.to_string() }
+    let mut html = String::new();
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        match found(rest, prev, abbreviations) {
+            Some((name, title, after)) => {
+                let _ = write!(html, "<abbr title=\"{}\">{}</abbr>", escape_attribute(title), escape_html(name));
+                prev = name.chars().last();
+                rest = after;
+            },
+            None => {
+                html.push(c);
+                prev = Some(c);
+                rest = &rest[c.len_utf8()..];
+            },
+        }
+    }
+    html
+}
+
+// How many `<abbr>` elements `abbreviate` would emit for `text`, and how many bytes they add
+// to it, counted without building the html.
+pub fn expansion(text: &str, abbreviations: &[(String, String)]) -> (usize, usize) {
+    let (mut elements, mut bytes) = (0, 0);
+    if abbreviations.is_empty() { return (elements, bytes) }
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        match found(rest, prev, abbreviations) {
+            Some((name, title, after)) => {
+                elements += 1;
+                bytes += "<abbr title=\"\"></abbr>".len() + escape_attribute(title).len() + escape_html(name).len() - name.len();
+                prev = name.chars().last();
+                rest = after;
+            },
+            None => {
+                prev = Some(c);
+                rest = &rest[c.len_utf8()..];
+            },
+        }
+    }
+    (elements, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abbreviations() {
+        let mds = parse("*[HTML]: HyperText Markup Language\n::: note\n*[HTML5]: HTML version 5\n:::\n*[HTML]: Other");
+        let expect = vec!(
+            ("HTML5".to_string(), "HTML version 5".to_string()),
+            ("HTML".to_string(), "HyperText Markup Language".to_string()),
+        );
+        assert_eq!(abbreviations(&mds), expect);
+        assert_eq!(abbreviations(&[Md::Abbreviation("".to_string(), "nothing".to_string())]), vec!());
+    }
+
+    #[test]
+    fn test_abbreviate() {
+        let abbreviations = vec!(
+            ("HTML5".to_string(), "HTML version 5".to_string()),
+            ("HTML".to_string(), "HyperText".to_string()),
+        );
+        assert_eq!(abbreviate("HTML and HTML5.", &abbreviations), "<abbr title=\"HyperText\">HTML</abbr> and <abbr title=\"HTML version 5\">HTML5</abbr>.".to_string());
+        assert_eq!(abbreviate("XHTML HTMLs", &abbreviations), "XHTML HTMLs".to_string());
+        assert_eq!(abbreviate("HTML", &[]), "HTML".to_string());
+
+        let abbreviations = vec!(("A<B".to_string(), "say \"hi\" <b>".to_string()));
+        assert_eq!(abbreviate("A<B", &abbreviations), "<abbr title=\"say &quot;hi&quot; &lt;b&gt;\">A&lt;B</abbr>");
+    }
+
+    #[test]
+    fn test_expansion() {
+        let abbreviations = vec!(
+            ("HTML5".to_string(), "HTML version 5".to_string()),
+            ("HTML".to_string(), "HyperText".to_string()),
+        );
+        for text in ["HTML and HTML5.", "XHTML HTMLs", "A<B"] {
+            let (elements, bytes) = expansion(text, &abbreviations);
+            assert_eq!(elements, abbreviate(text, &abbreviations).matches("<abbr").count());
+            assert_eq!(bytes, abbreviate(text, &abbreviations).len() - text.len());
+        }
+        let abbreviations = vec!(("A<B".to_string(), "say \"hi\"".to_string()));
+        assert_eq!(expansion("A<B", &abbreviations), (1, abbreviate("A<B", &abbreviations).len() - 3));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::parser::parser::*;
+use super::text::{word_to_text, words_to_text};
+
+const RESET: &str = "\x1b[0m";
+
+fn styled(on: &str, off: &str, words: &Words) -> String {
+    format!("\x1b[{}m{}\x1b[{}m", on, words_to_ansi(words), off)
+}
+
+fn word_to_ansi(word: &Word) -> String {
+    match word {
+        Word::Normal(_) | Word::Entity(..) | Word::Emoji(_) | Word::Custom(..) => word_to_text(word),
+        Word::Image(alt, ..) => format!("[{}]", alt),
+        Word::FootnoteRef(label) => format!("[{}]", label),
+        Word::Task(checked) => if *checked { "[x]" } else { "[ ]" }.to_string(),
+        // An OSC 8 hyperlink, which terminals without support show as plain underlined text.
+        Word::Link(words, url) => format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, styled("4", "24", words)),
+        Word::Bold(words) => styled("1", "22", words),
+        Word::Italic(words) => styled("3", "23", words),
+        Word::Underline(words) | Word::Insert(words) => styled("4", "24", words),
+        Word::StrikeThough(words) => styled("9", "29", words),
+        Word::Highlight(words) => styled("7", "27", words),
+        Word::Subscript(words) | Word::Superscript(words) => words_to_ansi(words),
+        Word::Kbd(key) => format!("\x1b[7m{}\x1b[27m", key),
+    }
+}
+
+fn words_to_ansi(words: &Words) -> String {
+    words.0.iter().map(word_to_ansi).collect()
+}
+
+// Table cells are padded by their visible text, which escape codes do not add to.
+fn width(words: &Words) -> usize {
+    words_to_text(words).chars().count()
+}
+
+fn pad(words: &Words, width: usize, align: &Align) -> String {
+    let space = width - self::width(words);
+    let text = words_to_ansi(words);
+    match align {
+        Align::Left => format!("{}{}", text, " ".repeat(space)),
+        Align::Right => format!("{}{}", " ".repeat(space), text),
+        Align::Center => format!("{}{}{}", " ".repeat(space / 2), text, " ".repeat(space - space / 2)),
+    }
+}
+
+fn rule(widths: &[usize], left: &str, middle: &str, right: &str) -> String {
+    let lines: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+    format!("{}{}{}", left, lines.join(middle), right)
+}
+
+fn row(cells: &[Words], widths: &[usize], aligns: &[Align], bold: bool) -> String {
+    let cells: Vec<String> = widths.iter().enumerate().map(|(i, width)| {
+        let empty = Words(vec!());
+        let words = cells.get(i).unwrap_or(&empty);
+        let cell = pad(words, *width, aligns.get(i).unwrap_or(&Align::Left));
+        if bold { format!("\x1b[1m{}\x1b[22m", cell) } else { cell }
+    })
+    .collect();
+    format!("│ {} │", cells.join(" │ "))
+}
+
+fn table_to_ansi(header: &Record, rows: &[Record], aligns: &[Align]) -> String {
+    let columns = rows.iter().map(|row| row.0.len()).chain([header.0.len()]).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns).map(|i| {
+        rows.iter().chain([header]).filter_map(|row| row.0.get(i)).map(width).max().unwrap_or(0)
+    })
+    .collect();
+    let mut lines = vec!(rule(&widths, "┌", "┬", "┐"), row(&header.0, &widths, aligns, true));
+    lines.push(rule(&widths, "├", "┼", "┤"));
+    lines.extend(rows.iter().map(|record| row(&record.0, &widths, aligns, false)));
+    lines.push(rule(&widths, "└", "┴", "┘"));
+    lines.join("\n")
+}
+
+fn indent(text: &str, prefix: &str) -> String {
+    text.split('\n').map(|line| format!("{}{}", prefix, line)).collect::<Vec<String>>().join("\n")
+}
+
+fn items_to_ansi(items: &Items) -> String {
+    let lines: Vec<String> = items.0.iter().enumerate().map(|(i, item)| {
+        let marker = match items.1 {
+            ListKind::Bullet(_) => "•".to_string(),
+            ListKind::Ordered(start) => format!("{}.", start.saturating_add(i)),
+        };
+        let text = indent(&words_to_ansi(&item.0), &" ".repeat(marker.chars().count() + 1));
+        let mut lines = format!("{} {}", marker, text.trim_start());
+        if !item.1.is_empty() {
+            lines.push('\n');
+            lines.push_str(&indent(&to_ansi(&item.1), "  "));
+        }
+        lines
+    })
+    .collect();
+    lines.join("\n")
+}
+
+fn md_to_ansi(md: &Md) -> String {
+    match md {
+        Md::Heading(size, words) => {
+            let indent = "  ".repeat(size - 1);
+            format!("{}\x1b[1;36m{} {}{}", indent, "#".repeat(*size), words_to_ansi(words), RESET)
+        },
+        Md::Sentence(words) => words_to_ansi(words),
+        Md::Footnote(label, words) => format!("[{}] {}", label, words_to_ansi(words)),
+        Md::Table(table) => {
+            let mut html = table_to_ansi(&table.header, &table.records, &table.align);
+            if let Some(caption) = &table.caption {
+                html = format!("\x1b[3m{}\x1b[23m\n{}", words_to_ansi(caption), html);
+            }
+            html
+        },
+        Md::LooseTable(table) => table_to_ansi(&table.header, &table.rows, &[]),
+        Md::List(items) => items_to_ansi(items),
+        Md::Container(name, mds) => format!("\x1b[1m[{}]\x1b[22m\n{}", name, indent(&to_ansi(mds), "  ")),
+        Md::Details(_, summary, mds) => format!("▶ {}\n{}", words_to_ansi(summary), indent(&to_ansi(mds), "  ")),
+        Md::Quote(mds) => indent(&to_ansi(mds), "\x1b[2m│\x1b[22m "),
+        Md::Code(_, code, _) => indent(code, "\x1b[2m    ").replace('\n', &format!("{}\n", RESET)) + RESET,
+        Md::Toc | Md::Abbreviation(..) | Md::FrontMatter(..) => "".to_string(),
+        Md::Raw(text) | Md::Custom(_, text) => text.clone(),
+    }
+}
+
+/// Renders markdown for a terminal, with escape codes for emphasis and box-drawn tables.
+pub fn to_ansi(mds: &[Md]) -> String {
+    mds.iter().map(md_to_ansi).filter(|text| !text.is_empty()).collect::<Vec<String>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{normal_word,words};
+
+    #[test]
+    fn test_words_to_ansi() {
+        let mds = parse("**b** *i* __u__ ~~s~~ [[Ctrl]]");
+        assert_eq!(to_ansi(&mds), "\x1b[1mb\x1b[22m \x1b[3mi\x1b[23m \x1b[4mu\x1b[24m \x1b[9ms\x1b[29m \x1b[7mCtrl\x1b[27m");
+    }
+
+    #[test]
+    fn test_heading_to_ansi() {
+        assert_eq!(to_ansi(&parse("## Title")), "  \x1b[1;36m## Title\x1b[0m");
+    }
+
+    #[test]
+    fn test_table_to_ansi() {
+        let mds = parse("| A | Long |\n|-:|:-:|\n| **abc** | x |");
+        let expect = [
+            "┌─────┬──────┐",
+            "│ \x1b[1m  A\x1b[22m │ \x1b[1mLong\x1b[22m │",
+            "├─────┼──────┤",
+            "│ \x1b[1mabc\x1b[22m │  x   │",
+            "└─────┴──────┘",
+        ].join("\n");
+        assert_eq!(to_ansi(&mds), expect);
+    }
+
+    #[test]
+    fn test_blocks_to_ansi() {
+        let mds = parse("- one\n  - two\n3. three\n> quoted\n```\ncode\n```");
+        let expect = "• one\n  • two\n3. three\n\x1b[2m│\x1b[22m quoted\n\x1b[2m    code\x1b[0m";
+        assert_eq!(to_ansi(&mds), expect);
+
+        let items = Items(vec!(Item(words!(normal_word!("a")), vec!()), Item(words!(normal_word!("b")), vec!())), ListKind::Ordered(usize::MAX));
+        assert!(to_ansi(&[Md::List(items)]).ends_with(&format!("{}. b", usize::MAX)));
+    }
+}
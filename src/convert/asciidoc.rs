@@ -0,0 +1,183 @@
+use crate::parser::parser::*;
+use super::text::{word_to_text, words_to_text};
+
+fn word_to_asciidoc(word: &Word) -> String {
+    match word {
+        Word::Normal(_) | Word::Entity(..) | Word::Emoji(_) | Word::Custom(..) => word_to_text(word),
+        Word::FootnoteRef(label) => format!("^[{}]^", label),
+        Word::Task(checked) => if *checked { "[x]" } else { "[ ]" }.to_string(),
+        Word::Bold(words) => format!("*{}*", words_to_asciidoc(words)),
+        Word::Italic(words) => format!("_{}_", words_to_asciidoc(words)),
+        Word::Underline(words) | Word::Insert(words) => format!("[.underline]#{}#", words_to_asciidoc(words)),
+        Word::StrikeThough(words) => format!("[.line-through]#{}#", words_to_asciidoc(words)),
+        Word::Highlight(words) => format!("#{}#", words_to_asciidoc(words)),
+        Word::Subscript(words) => format!("~{}~", words_to_asciidoc(words)),
+        Word::Superscript(words) => format!("^{}^", words_to_asciidoc(words)),
+        Word::Kbd(key) => format!("kbd:[{}]", key),
+        Word::Image(alt, src, size) => {
+            let mut attributes = vec!(alt.replace(']', "\\]"));
+            if let Some(width) = &size.width { attributes.push(format!("width={}", width)) }
+            if let Some(height) = &size.height { attributes.push(format!("height={}", height)) }
+            format!("image:{}[{}]", src, attributes.join(","))
+        },
+        Word::Link(words, url) => format!("link:{}[{}]", url, words_to_asciidoc(words).replace(']', "\\]")),
+    }
+}
+
+fn words_to_asciidoc(words: &Words) -> String {
+    words.0.iter().map(word_to_asciidoc).collect()
+}
+
+fn align_to_asciidoc(align: &Align) -> &'static str {
+    match align {
+        Align::Left => "<",
+        Align::Center => "^",
+        Align::Right => ">",
+    }
+}
+
+// Spanning cells take a `2+`, `.2+` or `2.3+` prefix and covered cells are left out.
+fn record_to_asciidoc(record: &Record) -> String {
+    let mut cells = vec!();
+    for (i, words) in record.0.iter().enumerate() {
+        let span = match record.1.get(i) {
+            Some(Span::Merged) => continue,
+            Some(Span::Cell(cols, rows)) if *cols > 1 && *rows > 1 => format!("{}.{}+", cols, rows),
+            Some(Span::Cell(cols, _)) if *cols > 1 => format!("{}+", cols),
+            Some(Span::Cell(_, rows)) if *rows > 1 => format!(".{}+", rows),
+            _ => "".to_string(),
+        };
+        cells.push(format!("{}|{}", span, words_to_asciidoc(words)));
+    }
+    cells.join(" ")
+}
+
+// The blank line after the first row marks it as the header.
+fn table_to_asciidoc(header: &Record, records: &[Record], aligns: &[&str], caption: Option<&Words>) -> String {
+    let mut lines = vec!();
+    if let Some(caption) = caption {
+        lines.push(format!(".{}", words_to_asciidoc(caption)));
+    }
+    lines.push(format!("[cols=\"{}\"]", aligns.join(",")));
+    lines.push("|===".to_string());
+    lines.push(record_to_asciidoc(header));
+    lines.push("".to_string());
+    lines.extend(records.iter().map(record_to_asciidoc));
+    lines.push("|===".to_string());
+    lines.join("\n")
+}
+
+fn items_to_asciidoc(items: &Items, depth: usize) -> String {
+    let mut lines = vec!();
+    let marker = match items.1 {
+        ListKind::Bullet(_) => "*".repeat(depth),
+        ListKind::Ordered(start) => {
+            if start != 1 { lines.push(format!("[start={}]", start)) }
+            ".".repeat(depth)
+        },
+    };
+    for item in &items.0 {
+        lines.push(format!("{} {}", marker, words_to_asciidoc(&item.0)));
+        for md in &item.1 {
+            match md {
+                Md::List(items) => lines.push(items_to_asciidoc(items, depth + 1)),
+                _ => lines.push(format!("+\n{}", md_to_asciidoc(md))),
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+fn ranges_to_asciidoc(ranges: &[(usize, usize)]) -> String {
+    let ranges: Vec<String> = ranges.iter().map(|(start, end)| {
+        if start == end { start.to_string() } else { format!("{}..{}", start, end) }
+    })
+    .collect();
+    ranges.join(";")
+}
+
+const ADMONITIONS: [&str; 5] = ["note", "tip", "important", "warning", "caution"];
+
+fn md_to_asciidoc(md: &Md) -> String {
+    match md {
+        Md::Heading(size, words) => format!("{} {}", "=".repeat(*size), words_to_asciidoc(words)),
+        Md::Sentence(words) => words_to_asciidoc(words),
+        Md::Footnote(label, words) => format!("^[{}]^ {}", label, words_to_asciidoc(words)),
+        Md::Table(table) => {
+            let aligns: Vec<&str> = table.align.iter().map(align_to_asciidoc).collect();
+            table_to_asciidoc(&table.header, &table.records, &aligns, table.caption.as_ref())
+        },
+        Md::LooseTable(table) => {
+            let columns = table.rows.iter().map(|row| row.0.len()).chain([table.header.0.len()]).max().unwrap_or(0);
+            table_to_asciidoc(&table.header, &table.rows, &vec!("<"; columns), None)
+        },
+        Md::List(items) => items_to_asciidoc(items, 1),
+        Md::Container(name, mds) => {
+            let style = if ADMONITIONS.contains(&name.as_str()) { name.to_uppercase() } else { format!(".{}", name) };
+            format!("[{}]\n====\n{}\n====", style, to_asciidoc(mds))
+        },
+        Md::Details(open, summary, mds) => {
+            let options = if *open { "%collapsible%open" } else { "%collapsible" };
+            format!(".{}\n[{}]\n====\n{}\n====", words_to_asciidoc(summary), options, to_asciidoc(mds))
+        },
+        Md::Quote(mds) => format!("____\n{}\n____", to_asciidoc(mds)),
+        Md::Code(lang, code, ranges) => {
+            let mut attributes = vec!("source".to_string());
+            if !lang.is_empty() { attributes.push(lang.clone()) }
+            if !ranges.is_empty() { attributes.push(format!("highlight={}", ranges_to_asciidoc(ranges))) }
+            format!("[{}]\n----\n{}\n----", attributes.join(","), code)
+        },
+        Md::Toc => "toc::[]".to_string(),
+        Md::Abbreviation(..) | Md::FrontMatter(..) => "".to_string(),
+        Md::Raw(text) => format!("++++\n{}\n++++", text),
+        Md::Custom(_, source) => source.clone(),
+    }
+}
+
+/// Renders markdown as AsciiDoc, with consecutive lines kept together by ` +` hard breaks.
+pub fn to_asciidoc(mds: &[Md]) -> String {
+    let mut asciidoc = String::new();
+    let mut line = false;
+    for md in mds {
+        let block = md_to_asciidoc(md);
+        match md {
+            Md::Sentence(words) if words_to_text(words).is_empty() => line = false,
+            Md::Sentence(_) if line => {
+                asciidoc.push_str(" +\n");
+                asciidoc.push_str(&block);
+            },
+            _ if block.is_empty() => {},
+            _ => {
+                if !asciidoc.is_empty() { asciidoc.push_str("\n\n") }
+                asciidoc.push_str(&block);
+                line = matches!(md, Md::Sentence(_));
+            },
+        }
+    }
+    asciidoc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_asciidoc() {
+        let mds = parse("# Title\n**bold** *it* [[Ctrl]]\nnext\n\n## Part");
+        assert_eq!(to_asciidoc(&mds), "= Title\n\n*bold* _it_ kbd:[Ctrl] +\nnext\n\n== Part");
+    }
+
+    #[test]
+    fn test_table_to_asciidoc() {
+        let mds = parse("| A | B | C |\n|:-|:-:|-:|\n| a | b | c |");
+        let expect = "[cols=\"<,^,>\"]\n|===\n|A |B |C\n\n|a |b |c\n|===";
+        assert_eq!(to_asciidoc(&mds), expect);
+    }
+
+    #[test]
+    fn test_blocks_to_asciidoc() {
+        let mds = parse("- a\n  - b\n::: warning\nCareful\n:::\n```rust {2-3}\nlet a;\n```");
+        let expect = "* a\n** b\n\n[WARNING]\n====\nCareful\n====\n\n[source,rust,highlight=2..3]\n----\nlet a;\n----";
+        assert_eq!(to_asciidoc(&mds), expect);
+    }
+}
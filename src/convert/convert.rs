@@ -1,114 +1,849 @@
 use crate::parser::parser::*;
+use super::options::*;
+use super::slug::Slugger;
+use super::text::{is_rtl, word_to_text, words_to_text};
+use super::smart::smarten;
+use super::toc::{section, toc, toc_elements, toc_to_html, Numbering, TocEntry};
+use super::abbr::{abbreviate, abbreviations, expansion};
+use super::footnote::footnotes;
+use super::url::{host, md_to_html_link, resolve};
+use super::renderer::{HtmlRenderer, Renderer};
+use super::minify::minify;
+use super::renderer::escape_html;
+use super::sanitize::sanitize_html;
+use super::void::void_elements;
+use super::document::{document_parts, wrap_document};
+use crate::parser::visit::{walk, walk_word, Visitor};
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::io;
+use std::ops::Range;
+#[cfg(feature = "emoji")]
+use crate::emoji::shortcode;
 
-fn word_to_html<'a>(word: &'a Word) -> String {
+struct Context<'a> {
+    options: RenderOptions,
+    renderer: &'a dyn Renderer,
+    slugger: Slugger,
+    toc: Vec<TocEntry>,
+    numbering: Numbering,
+    abbreviations: Vec<(String, String)>,
+    // Numbered note labels, and how many references to each have been rendered so far.
+    footnotes: Vec<String>,
+    footnote_refs: RefCell<Vec<usize>>,
+}
+
+impl Default for Context<'_> {
+    fn default() -> Self {
+        Context{
+            options: RenderOptions::default(),
+            renderer: &HtmlRenderer,
+            slugger: Slugger::new(),
+            toc: vec!(),
+            numbering: Numbering::default(),
+            abbreviations: vec!(),
+            footnotes: vec!(),
+            footnote_refs: RefCell::new(vec!()),
+        }
+    }
+}
+
+impl<'a> Context<'a> {
+    fn new(mds: &[Md], options: &RenderOptions, renderer: &'a dyn Renderer) -> Self {
+        Context{
+            options: options.clone(),
+            renderer,
+            slugger: Slugger::new(),
+            toc: toc(mds),
+            numbering: Numbering::new(mds),
+            abbreviations: abbreviations(mds),
+            footnotes: footnotes(mds).into_iter().map(|(label, _, _)| label).collect(),
+            footnote_refs: RefCell::new(vec!()),
+        }
+    }
+}
+
+fn emoji_to_html(name: &str) -> String {
+    #[cfg(feature = "emoji")]
+    if let Some(emoji) = shortcode(name) {
+        return emoji.to_string()
+    }
+    format!("<span class=\"emoji\">:{}:</span>", name)
+}
+
+fn emphasis_tag<'a>(presentational: &'a str, semantic: &'a str, options: &HtmlOptions) -> &'a str {
+    if options.semantic_tags { semantic } else { presentational }
+}
+
+fn tagged_to_html(tag: &str, words: &Words, context: &Context) -> String {
+    context.renderer.emphasis(tag, &words_to_html(words, context))
+}
+
+fn text_to_html(text: &str, context: &Context) -> String {
+    if context.options.html.sanitize {
+        return abbreviate(&context.renderer.text(&escape_html(text)), &context.abbreviations)
+    }
+    abbreviate(&context.renderer.text(text), &context.abbreviations)
+}
+
+fn custom_to_html(name: &str, source: &str, context: &Context) -> String {
+    match context.options.custom_words.get(name) {
+        Some(render) => render(source),
+        None => context.renderer.custom(name, &text_to_html(source, context)),
+    }
+}
+
+fn custom_block_to_html(name: &str, source: &str, context: &Context) -> String {
+    match context.options.custom_blocks.get(name) {
+        Some(render) => render(source),
+        None => context.renderer.custom_block(name, &text_to_html(source, context)),
+    }
+}
+
+fn word_to_html(word: &Word, context: &Context) -> String {
+    let html = &context.options.html;
     match word {
-        Word::Normal(val) => val.clone(),
-        Word::Italic(words) => format!("<i>{}</i>", words_to_html(&words)),
-        Word::Bold(words) => format!("<b>{}</b>", words_to_html(&words)),
-        Word::StrikeThough(words) => format!("<s>{}</s>", words_to_html(&words)),
-        Word::Underline(words) => format!("<u>{}</u>", words_to_html(&words)),
+        Word::Normal(val) => text_to_html(val, context),
+        Word::Italic(words) => tagged_to_html(emphasis_tag("i", "em", html), words, context),
+        Word::Bold(words) => tagged_to_html(emphasis_tag("b", "strong", html), words, context),
+        Word::StrikeThough(words) => tagged_to_html(emphasis_tag("s", "del", html), words, context),
+        Word::Underline(words) => tagged_to_html(emphasis_tag("u", "ins", html), words, context),
+        Word::Highlight(words) => tagged_to_html("mark", words, context),
+        Word::Subscript(words) => tagged_to_html("sub", words, context),
+        Word::Superscript(words) => tagged_to_html("sup", words, context),
+        Word::Insert(words) => tagged_to_html("ins", words, context),
+        Word::Kbd(key) => context.renderer.kbd(&escape_html(key)),
+        Word::Custom(name, source) => custom_to_html(name, source, context),
+        Word::Emoji(name) => emoji_to_html(name),
+        Word::FootnoteRef(label) => footnote_ref_to_html(label, context),
+        Word::Task(checked) => context.renderer.task(*checked),
+        // Decoded text is escaped again, so `&lt;` never turns into markup.
+        Word::Entity(_, text) => context.renderer.text(&escape_html(text)),
+        Word::Link(words, url) => link_to_html(words, url, context),
+        Word::Image(alt, src, size) => context.renderer.image(&resolved(src, context), alt, size),
+    }
+}
+
+fn resolved(url: &str, context: &Context) -> String {
+    match &context.options.html.base_url {
+        Some(base) => resolve(base, url),
+        None => url.to_string(),
     }
 }
 
-fn words_to_html<'a>(words: &'a Words) -> String {
+// With `site_host` set, links to any other host open in a new tab without handing the
+// page a reference back to the opener.
+fn link_to_html(words: &Words, url: &str, context: &Context) -> String {
+    let rewritten = context.options.html.md_links.then(|| md_to_html_link(url)).flatten();
+    let url = rewritten.as_deref().unwrap_or(url);
+    let html = context.renderer.link(&resolved(url, context), &words_to_html(words, context));
+    let Some(site) = &context.options.html.site_host else { return html };
+    match host(&resolved(url, context)) {
+        Some(host) if !host.eq_ignore_ascii_case(site) => {
+            let html = with_attribute(html, "target", "_blank");
+            with_attribute(html, "rel", "noopener noreferrer")
+        },
+        _ => html,
+    }
+}
+
+// References to an undefined note stay as written.
+fn footnote_ref_to_html(label: &str, context: &Context) -> String {
+    let Some(i) = context.footnotes.iter().position(|numbered| numbered == label) else {
+        return text_to_html(&format!("[^{}]", label), context)
+    };
+    let mut refs = context.footnote_refs.borrow_mut();
+    if refs.len() < context.footnotes.len() { refs.resize(context.footnotes.len(), 0) }
+    refs[i] += 1;
+    context.renderer.footnote_ref(i + 1, refs[i])
+}
+
+fn footnotes_to_html(mds: &[Md], context: &Context) -> String {
+    let notes: Vec<String> = footnotes(mds).iter().enumerate().map(|(i, (_, words, references))| {
+        context.renderer.footnote(i + 1, &words_to_html(words, context), *references)
+    })
+    .collect();
+    if notes.is_empty() { return "".to_string() }
+    context.renderer.footnotes(context.options.footnotes.heading.as_deref(), &notes)
+}
+
+/// Renders the notes section on its own, for pages that set `FootnotePlacement::Omit` and
+/// place it somewhere other than the end.
+pub fn footnotes_to_html_with(mds: &[Md], options: &RenderOptions) -> String {
+    let context = Context::new(mds, options, &HtmlRenderer);
+    finish_piece(&footnotes_to_html(mds, &context), options).0
+}
+
+fn smart_to_html(words: &[Word], i: usize, text: &str, context: &Context) -> String {
+    let prev = i.checked_sub(1).and_then(|i| word_to_text(&words[i]).chars().last());
+    let next = words.get(i + 1).and_then(|word| word_to_text(word).chars().next());
+    text_to_html(&smarten(text, prev, next), context)
+}
+
+fn words_to_html(words: &Words, context: &Context) -> String {
     let words = &words.0;
-    words.iter().map(|word| word_to_html(word))
-        .collect::<Vec<String>>()
-        .join("")
+    let mut html = String::new();
+    for (i, word) in words.iter().enumerate() {
+        match word {
+            Word::Normal(text) if context.options.smart_punctuation => {
+                html.push_str(&smart_to_html(words, i, text, context))
+            },
+            _ => html.push_str(&word_to_html(word, context)),
+        }
+    }
+    html
 }
 
-fn header_to_html(record: &Record) -> String {
-    let header = &record.0;
-    header.iter().map(
-        |words| format!("<th>{}</th>", words_to_html(words))
-    )
-    .collect::<Vec<String>>()
-    .join("")
+// `None` for cells covered by a spanning neighbour.
+fn span(record: &Record, i: usize) -> Option<(usize, usize)> {
+    match record.1.get(i) {
+        Some(Span::Merged) => None,
+        Some(Span::Cell(cols, rows)) => Some((*cols, *rows)),
+        None => Some((1, 1)),
+    }
 }
 
-fn align_to_string<'a>(align: &Align) -> &'a str {
-    match align {
-        Align::Right => "right",
-        Align::Center => "center",
-        Align::Left => "left",
+fn header_to_html(record: &Record, context: &Context) -> String {
+    let mut html = String::new();
+    for (i, words) in record.0.iter().enumerate() {
+        let Some(span) = span(record, i) else { continue };
+        html.push_str(&context.renderer.table_cell(true, None, span, &words_to_html(words, context)));
     }
+    html
 }
 
-fn record_to_html(record: &Record, aligns: &Vec<Align>) -> String {
-    let record = &record.0;
-    record.iter().zip(aligns.iter()).map(
-        |(words, align)| {
-            let align = align_to_string(align);
-            format!("<td align=\"{}\">{}</td>", align, words_to_html(words))
-    })
-    .collect::<Vec<String>>()
-    .join("")
+fn record_to_html(record: &Record, aligns: &[Align], context: &Context) -> String {
+    let mut html = String::new();
+    for (i, (words, align)) in record.0.iter().zip(aligns.iter()).enumerate() {
+        let Some(span) = span(record, i) else { continue };
+        html.push_str(&context.renderer.table_cell(false, Some(align), span, &words_to_html(words, context)));
+    }
+    html
 }
 
-fn records_to_html(records: &Vec<Record>, aligns: &Vec<Align>) -> String {
+fn records_to_html(records: &[Record], aligns: &[Align], context: &Context) -> Vec<String> {
     records.iter().map(|record| {
-        format!("<tr>{}</tr>\n", record_to_html(record, aligns))
+        context.renderer.table_row(&record_to_html(record, aligns, context))
     })
-    .collect::<Vec<String>>()
-    .join("")
+    .collect()
 }
 
-fn table_to_html(table: &Box<Table>) -> String {
-    let header = &table.header;
-    let aligns = &table.align;
-    let records = &table.records;
+fn table_to_html(table: &Table, context: &Context) -> String {
+    let header = context.renderer.table_row(&header_to_html(&table.header, context));
+    let records = records_to_html(&table.records, &table.align, context);
+    let caption = table.caption.as_ref().map(|caption| words_to_html(caption, context));
+    table_html(caption.as_deref(), &header, &records, context)
+}
 
-    let header = header_to_html(header);
-    let header = format!("<tr>{}</tr>", header);
-    let records = records_to_html(records, aligns);
-    format!("<table>\n{}\n{}</table>\n", header, records)
+fn table_html(caption: Option<&str>, header: &str, rows: &[String], context: &Context) -> String {
+    if context.options.html.table_sections {
+        return context.renderer.table_sections(caption, header, rows)
+    }
+    context.renderer.table(caption, header, rows)
+}
+
+fn cells_to_html(record: &Record, context: &Context) -> String {
+    let mut html = String::new();
+    for words in &record.0 {
+        html.push_str(&context.renderer.table_cell(false, None, (1, 1), &words_to_html(words, context)));
+    }
+    html
 }
 
-fn item_to_html(item: &Item) -> String {
-    let words = &item.0;
-    let words = words_to_html(words);
+fn loose_table_to_html(table: &LooseTable, context: &Context) -> String {
+    let header = context.renderer.table_row(&header_to_html(&table.header, context));
+    let rows: Vec<String> = table.rows.iter().map(|record| {
+        context.renderer.table_row(&cells_to_html(record, context))
+    })
+    .collect();
+    table_html(None, &header, &rows, context)
+}
+
+fn item_to_html(item: &Item, context: &mut Context) -> String {
+    let words = words_to_html(&item.0, context);
     let children = &item.1;
-    let children = if children.0.is_empty() {
-        "".to_string()
+    let children = if children.is_empty() {
+        None
     } else {
-        format!("\n{}", items_to_html(children))
+        Some(blocks_to_html(children, context))
     };
-    format!("<li>{}{}</li>", words, children)
+    let html = context.renderer.list_item(&words, children.as_deref());
+    with_class(html, "item", context)
 }
 
-fn items_to_html(items: &Items) -> String {
-    let items = &items.0;
-    let strings: Vec<String> = items.iter().map(|item| item_to_html(item)).collect();
-    let html = strings.join("\n");
-    format!("<ul>\n{}\n</ul>\n", html)
+fn items_to_html(items: &Items, context: &mut Context) -> String {
+    let strings: Vec<String> = items.0.iter().map(|item| item_to_html(item, context)).collect();
+    context.renderer.list(&items.1, &strings)
+}
+
+fn heading_to_html(size: &usize, words: &Words, context: &mut Context) -> String {
+    let slug = context.slugger.slug(&words_to_text(words));
+    let mut content = String::new();
+    if context.options.heading_anchors && !slug.is_empty() {
+        let _ = write!(content, "<a class=\"anchor\" href=\"#{}\" aria-hidden=\"true\">#</a>", slug);
+    }
+    let number = context.numbering.next(*size);
+    if context.options.number_headings {
+        let _ = write!(content, "<span class=\"section-number\">{}</span> ", section(&number));
+    }
+    content.push_str(&words_to_html(words, context));
+    let level = size.saturating_add(context.options.heading_offset).min(6);
+    let html = context.renderer.heading(level, &slug, &content);
+    match direction(words, context) {
+        Some(dir) => with_attribute(html, "dir", dir.as_str()),
+        None => html,
+    }
+}
+
+// The `dir` for a heading or paragraph, when one is asked for and its text is right-to-left.
+fn direction(words: &Words, context: &Context) -> Option<Direction> {
+    context.options.html.dir.filter(|_| is_rtl(&words_to_text(words)))
+}
+
+fn paragraph_to_html(lines: &[&Words], context: &Context) -> String {
+    let content: Vec<String> = lines.iter().map(|words| words_to_html(words, context)).collect();
+    let dir = lines.first().and_then(|words| direction(words, context));
+    context.renderer.paragraph(dir, &content.join("<br />\n"))
+}
+
+fn sentence_to_html(words: &Words, context: &Context) -> String {
+    paragraph_to_html(&[words], context)
+}
+
+// A sentence with text in it, unlike the empty one a blank line parses to.
+fn is_line(md: &Md) -> bool {
+    let blank = |word: &Word| matches!(word, Word::Normal(text) if text.is_empty());
+    matches!(md, Md::Sentence(words) if !words.0.iter().all(blank))
+}
+
+// The blocks that render as one: with `hard_breaks` the lines of each paragraph, otherwise
+// every block on its own.
+fn runs(mds: &[Md], options: &RenderOptions) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = vec!();
+    for (i, md) in mds.iter().enumerate() {
+        match runs.last_mut() {
+            Some(run) if options.html.hard_breaks && is_line(md) && is_line(&mds[run.end - 1]) => run.end = i + 1,
+            _ => runs.push(i..i + 1),
+        }
+    }
+    runs
+}
+
+fn run_to_html(mds: &[Md], context: &mut Context) -> String {
+    if let [md] = mds { return md_to_html(md, context) }
+    let lines: Vec<&Words> = mds.iter()
+        .filter_map(|md| if let Md::Sentence(words) = md { Some(words) } else { None })
+        .collect();
+    paragraph_to_html(&lines, context)
+}
+
+// Appends a non-empty block to `html`, separated from the previous one by a newline.
+fn push_block(html: &mut String, block: &str) {
+    if block.is_empty() { return }
+    if !html.is_empty() { html.push('\n') }
+    html.push_str(block);
+}
+
+fn blocks_to_html(mds: &[Md], context: &mut Context) -> String {
+    let mut html = String::new();
+    for run in runs(mds, &context.options) {
+        push_block(&mut html, &run_to_html(&mds[run], context));
+    }
+    html
+}
+
+fn container_to_html(name: &str, mds: &[Md], context: &mut Context) -> String {
+    let content = blocks_to_html(mds, context);
+    context.renderer.container(name, &content)
 }
 
-fn heading_to_html(size: &usize, words: &Words) -> String {
-    format!("<h{}>{}</h{}>", size, words_to_html(&words), size)
+fn details_to_html(open: &bool, summary: &Words, mds: &[Md], context: &mut Context) -> String {
+    let summary = words_to_html(summary, context);
+    let content = blocks_to_html(mds, context);
+    context.renderer.details(*open, &summary, &content)
 }
 
-fn sentence_to_html(words: &Words) -> String {
-    format!("{}<br />", words_to_html(&words))
+fn quote_to_html(mds: &[Md], context: &mut Context) -> String {
+    let content = blocks_to_html(mds, context);
+    context.renderer.quote(&content)
 }
 
-fn md_to_html(md: &Md) -> String {
+// Adds an attribute to the first tag of `html`, merging with an existing one of the
+// same name as `class` lists do.
+fn with_attribute(html: String, name: &str, value: &str) -> String {
+    let Some(start) = html.find('<') else { return html };
+    let Some(end) = html[start..].find('>').map(|end| start + end) else { return html };
+    let tag = &html[start..end];
+    let existing = format!(" {}=\"", name);
+    if let Some(i) = tag.find(&existing) {
+        let at = start + i + existing.len();
+        return format!("{}{} {}", &html[..at], value, &html[at..])
+    }
+    let after_name = tag[1..].find(|c: char| c.is_whitespace() || c == '/').map_or(end, |i| start + 1 + i);
+    format!("{} {}=\"{}\"{}", &html[..after_name], name, value, &html[after_name..])
+}
+
+fn with_class(html: String, key: &str, context: &Context) -> String {
+    match context.options.html.classes.get(key) {
+        Some(class) => with_attribute(html, "class", class),
+        None => html,
+    }
+}
+
+fn class_key(md: &Md) -> Option<String> {
+    let key = match md {
+        Md::Heading(size, _) => return Some(format!("heading-{}", size)),
+        Md::Table(_) | Md::LooseTable(_) => "table",
+        Md::List(_) => "list",
+        Md::Code(..) => "code",
+        Md::Quote(_) => "quote",
+        Md::Container(..) => "container",
+        Md::Details(..) => "details",
+        Md::Toc => "toc",
+        Md::Sentence(_) | Md::Abbreviation(..) | Md::Footnote(..) | Md::FrontMatter(..) | Md::Raw(_) | Md::Custom(..) => return None,
+    };
+    Some(key.to_string())
+}
+
+fn md_to_html(md: &Md, context: &mut Context) -> String {
+    let html = block_to_html(md, context);
+    match class_key(md) {
+        Some(key) => with_class(html, &key, context),
+        None => html,
+    }
+}
+
+fn block_to_html(md: &Md, context: &mut Context) -> String {
+    match md {
+        Md::Heading(size, words) => heading_to_html(size, words, context),
+        Md::Sentence(words) => sentence_to_html(words, context),
+        Md::Table(table) => table_to_html(table, context),
+        Md::LooseTable(table) => loose_table_to_html(table, context),
+        Md::List(items) => items_to_html(items, context),
+        Md::Container(name, mds) => container_to_html(name, mds, context),
+        Md::Details(open, summary, mds) => details_to_html(open, summary, mds, context),
+        Md::Toc => toc_to_html(&context.toc, context.options.number_headings),
+        Md::Abbreviation(..) | Md::Footnote(..) | Md::FrontMatter(..) => "".to_string(),
+        Md::Code(lang, code, lines) if context.options.html.line_numbers || !lines.is_empty() => {
+            context.renderer.code_lines(lang, code, lines)
+        },
+        Md::Code(lang, code, _) => context.renderer.code(lang, code),
+        Md::Quote(mds) => quote_to_html(mds, context),
+        Md::Raw(text) if context.options.html.escape_raw => escape_html(text),
+        Md::Raw(text) => text.clone(),
+        Md::Custom(name, source) => custom_block_to_html(name, source, context),
+    }
+}
+
+// Streams the html block by block, so only one top-level block is held in memory.
+pub fn write_html<W: io::Write>(mds: &[Md], mut out: W) -> io::Result<()> {
+    span!(debug_span, "render", blocks = mds.len());
+    let mut context = Context::new(mds, &RenderOptions::default(), &HtmlRenderer);
+    let mut first = true;
+    for md in mds {
+        let html = md_to_html(md, &mut context);
+        if html.is_empty() { continue }
+        if !first { out.write_all(b"\n")? }
+        out.write_all(html.as_bytes())?;
+        first = false;
+    }
+    let notes = footnotes_to_html(mds, &context);
+    if !notes.is_empty() && context.options.footnotes.placement == FootnotePlacement::End {
+        if !first { out.write_all(b"\n")? }
+        out.write_all(notes.as_bytes())?;
+    }
+    Ok(())
+}
+
+// Whether a block's html depends on blocks after it: a table of contents, abbreviations
+// and footnotes are resolved against the whole document.
+struct Lookahead(bool);
+
+impl Visitor<'_> for Lookahead {
+    fn visit_toc(&mut self) {
+        self.0 = true
+    }
+
+    fn visit_abbreviation(&mut self, _abbr: &str, _title: &str) {
+        self.0 = true
+    }
+
+    fn visit_footnote(&mut self, _label: &str, _words: &Words) {
+        self.0 = true
+    }
+
+    fn visit_word(&mut self, word: &Word) {
+        if let Word::FootnoteRef(_) = word { self.0 = true }
+        walk_word(self, word)
+    }
+}
+
+// Writes html as blocks arrive, e.g. from a `BlockReader`, so output can start before the
+// input ends. Each block goes out as soon as it is written, until one needs the rest of the
+// document (a table of contents, an abbreviation or a footnote); from there on blocks are
+// held and rendered together by `finish`, so such definitions only reach the held blocks.
+// Heading numbers count from the top level of the whole document, and a paragraph may go on
+// in the next block, so with `number_headings` or `hard_breaks` every block is held. The
+// output limits and `document` are not applied.
+pub struct HtmlWriter<W: io::Write> {
+    out: W,
+    options: RenderOptions,
+    slugger: Slugger,
+    first: bool,
+    held: Option<Vec<Md<'static>>>,
+}
+
+impl<W: io::Write> HtmlWriter<W> {
+    pub fn new(out: W, options: &RenderOptions) -> Self {
+        HtmlWriter{
+            out, options: options.clone(), slugger: Slugger::new(), first: true,
+            held: (options.number_headings || options.html.hard_breaks).then(Vec::new),
+        }
+    }
+
+    fn context(&mut self, mds: &[Md]) -> Context<'static> {
+        let mut context = Context::new(mds, &self.options, &HtmlRenderer);
+        context.slugger = std::mem::take(&mut self.slugger);
+        context
+    }
+
+    fn put(&mut self, html: &str) -> io::Result<()> {
+        if html.is_empty() { return Ok(()) }
+        let html = finish_piece(html, &self.options).0;
+        if !self.first && !self.options.html.minify { self.out.write_all(b"\n")? }
+        self.first = false;
+        self.out.write_all(html.as_bytes())?;
+        self.out.flush()
+    }
+
+    pub fn write(&mut self, md: &Md) -> io::Result<()> {
+        if let Some(held) = &mut self.held {
+            held.push(md.clone().into_owned());
+            return Ok(())
+        }
+        let mut lookahead = Lookahead(false);
+        lookahead.visit_md(md);
+        if lookahead.0 {
+            self.held = Some(vec!(md.clone().into_owned()));
+            return Ok(())
+        }
+        let mut context = self.context(std::slice::from_ref(md));
+        let html = md_to_html(md, &mut context);
+        self.slugger = context.slugger;
+        self.put(&html)
+    }
+
+    // The writer the html goes to, e.g. to take what a `Vec` collected so far.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    // Renders the held blocks and the footnotes, and hands back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let held = self.held.take().unwrap_or_default();
+        let mut context = self.context(&held);
+        for run in runs(&held, &self.options) {
+            let html = run_to_html(&held[run], &mut context);
+            self.put(&html)?;
+        }
+        if self.options.footnotes.placement == FootnotePlacement::End {
+            let notes = footnotes_to_html(&held, &context);
+            self.put(&notes)?;
+        }
+        Ok(self.out)
+    }
+}
+
+pub fn mds_to_html(mds: &[Md]) -> String {
+    let mut html: Vec<u8> = vec!();
+    write_html(mds, &mut html).expect("writing to a Vec never fails");
+    String::from_utf8(html).expect("rendered html is utf-8")
+}
+
+fn word_elements(word: &Word) -> usize {
+    match word {
+        Word::Normal(_) | Word::Entity(..) => 0,
+        Word::Kbd(_) | Word::Custom(..) | Word::Emoji(_) | Word::FootnoteRef(_) | Word::Task(_) | Word::Image(..) => 1,
+        Word::Italic(words)
+        | Word::Bold(words)
+        | Word::StrikeThough(words)
+        | Word::Underline(words)
+        | Word::Highlight(words)
+        | Word::Subscript(words)
+        | Word::Superscript(words)
+        | Word::Insert(words)
+        | Word::Link(words, _) => 1 + words_elements(words),
+    }
+}
+
+fn words_elements(words: &Words) -> usize {
+    words.0.iter().map(word_elements).sum()
+}
+
+fn record_elements(record: &Record) -> usize {
+    1 + record.0.iter().map(|words| 1 + words_elements(words)).sum::<usize>()
+}
+
+fn items_elements(items: &Items) -> usize {
+    let items = &items.0;
+    if items.is_empty() { return 0 }
+    1 + items.iter()
+        .map(|item| 1 + words_elements(&item.0) + item.1.iter().map(md_elements).sum::<usize>())
+        .sum::<usize>()
+}
+
+fn md_elements(md: &Md) -> usize {
     match md {
-        Md::Heading(size, words) => heading_to_html(size, words),
-        Md::Sentence(words) => sentence_to_html(&words),
-        Md::Table(table) => table_to_html(&table),
-        Md::List(items) => items_to_html(&items),
+        Md::Heading(_, words) => 1 + words_elements(words),
+        Md::Sentence(words) => 1 + words_elements(words),
+        Md::Table(table) => {
+            let records: usize = table.records.iter().map(record_elements).sum();
+            let caption = table.caption.as_ref().map_or(0, |caption| 1 + words_elements(caption));
+            1 + record_elements(&table.header) + records + caption
+        },
+        Md::LooseTable(table) => {
+            let rows: usize = table.rows.iter().map(record_elements).sum();
+            1 + record_elements(&table.header) + rows
+        },
+        Md::List(items) => items_elements(items),
+        Md::Container(_, mds) => 1 + mds.iter().map(md_elements).sum::<usize>(),
+        Md::Details(_, summary, mds) => {
+            2 + words_elements(summary) + mds.iter().map(md_elements).sum::<usize>()
+        },
+        Md::Toc => 1,
+        Md::Abbreviation(..) | Md::Footnote(..) | Md::FrontMatter(..) => 0,
+        Md::Code(..) => 2,
+        Md::Raw(_) => 0,
+        Md::Custom(..) => 1,
+        Md::Quote(mds) => 1 + mds.iter().map(md_elements).sum::<usize>(),
+    }
+}
+
+// What a block renders beyond `md_elements`: each `<abbr>` and the bytes it adds, and the
+// entries of a table of contents. Counted before the block is rendered, since a long title
+// used many times can turn a small input into a huge output.
+#[derive(Default)]
+struct Expansion {
+    elements: usize,
+    bytes: usize,
+}
+
+struct Expanding<'c> {
+    context: &'c Context<'c>,
+    expansion: Expansion,
+}
+
+impl Expanding<'_> {
+    fn text(&mut self, text: &str) {
+        let (elements, bytes) = expansion(text, &self.context.abbreviations);
+        self.expansion.elements += elements;
+        self.expansion.bytes += bytes;
+    }
+}
+
+impl Visitor<'_> for Expanding<'_> {
+    fn visit_toc(&mut self) {
+        // The `<nav>` itself is already counted by `md_elements`.
+        self.expansion.elements += toc_elements(&self.context.toc).saturating_sub(1);
+    }
+
+    fn visit_custom_block(&mut self, _name: &str, source: &str) {
+        self.text(source)
+    }
+
+    fn visit_word(&mut self, word: &Word) {
+        match word {
+            Word::Normal(text) => self.text(text),
+            Word::Custom(_, source) => self.text(source),
+            word => walk_word(self, word),
+        }
+    }
+}
+
+fn expansion_of(mds: &[Md], context: &Context) -> Expansion {
+    let mut expanding = Expanding{ context, expansion: Expansion::default() };
+    walk(&mut expanding, mds);
+    expanding.expansion
+}
+
+fn limit(mut html: String, options: &RenderOptions, err: RenderError) -> Result<String, RenderError> {
+    match options.on_limit {
+        LimitAction::Error => Err(err),
+        LimitAction::Truncate => {
+            push_block(&mut html, TRUNCATED);
+            Ok(html)
+        },
+    }
+}
+
+pub fn mds_to_html_with(mds: &[Md], options: &RenderOptions) -> Result<String, RenderError> {
+    mds_to_html_with_renderer(mds, options, &HtmlRenderer)
+}
+
+pub fn mds_to_html_with_renderer(
+    mds: &[Md],
+    options: &RenderOptions,
+    renderer: &dyn Renderer
+) -> Result<String, RenderError> {
+    let html = render(mds, None, options, renderer, None)?;
+    Ok(finish(html, mds, options))
+}
+
+fn finish(html: String, mds: &[Md], options: &RenderOptions) -> String {
+    let html = match &options.document {
+        Some(document) => wrap_document(&html, mds, document, options.number_headings),
+        None => html,
+    };
+    let html = if options.html.sanitize { sanitize_html(&html) } else { html };
+    let html = match options.html.void_style {
+        VoidStyle::Keep => html,
+        style => void_elements(&html, style),
+    };
+    if options.html.minify { minify(&html) } else { html }
+}
+
+// Renders one block on its own, for documents stored as separate fragments. Abbreviations
+// and the table of contents only see this block.
+pub fn md_to_html_with(md: &Md, options: &RenderOptions) -> String {
+    let mut context = Context::new(std::slice::from_ref(md), options, &HtmlRenderer);
+    finish_piece(&md_to_html(md, &mut context), options).0
+}
+
+pub fn words_to_html_with(words: &Words, options: &RenderOptions) -> String {
+    let context = Context::new(&[], options, &HtmlRenderer);
+    finish_piece(&words_to_html(words, &context), options).0
+}
+
+pub fn table_to_html_with(table: &Table, options: &RenderOptions) -> String {
+    let context = Context::new(&[], options, &HtmlRenderer);
+    finish_piece(&table_to_html(table, &context), options).0
+}
+
+// Adds `data-sourcepos` attributes to the top-level blocks that render a tag, so editors
+// can map the preview back to the markdown.
+pub fn mds_to_html_with_positions(
+    mds: &[Md],
+    positions: &[SourcePos],
+    options: &RenderOptions
+) -> Result<String, RenderError> {
+    let html = render(mds, Some(positions), options, &HtmlRenderer, None)?;
+    Ok(finish(html, mds, options))
+}
+
+// A top-level block's byte range in the markdown input and in the rendered html.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceMapping {
+    pub source: (usize, usize),
+    pub output: (usize, usize),
+}
+
+// The post-processing `finish` does, applied to one piece of the html. When minifying, the
+// newlines trimmed off the end are returned too, since they stay unless a tag follows.
+fn finish_piece(html: &str, options: &RenderOptions) -> (String, usize) {
+    let html = if options.html.sanitize { sanitize_html(html) } else { html.to_string() };
+    let html = match options.html.void_style {
+        VoidStyle::Keep => html,
+        style => void_elements(&html, style),
+    };
+    if !options.html.minify { return (html, 0) }
+    (minify(&html), html.len() - html.trim_end_matches('\n').len())
+}
+
+// Renders like `mds_to_html_with` and maps each top-level block that produced html back to
+// its bytes in the input, so a click in the preview can find the source line.
+pub fn mds_to_html_with_source_map(
+    mds: &[Md],
+    positions: &[SourcePos],
+    options: &RenderOptions
+) -> Result<(String, Vec<SourceMapping>), RenderError> {
+    let mut ranges = vec!();
+    let html = render(mds, None, options, &HtmlRenderer, Some(&mut ranges))?;
+    // Sanitizing, void rewriting and minifying only touch single tags and the breaks between
+    // them, so each block can be measured on its own.
+    let (mut offset, mut previous, mut pending) = (0, None, 0);
+    if let Some(document) = &options.document {
+        let (head, _) = document_parts(mds, document, options.number_headings);
+        let (prefix, trailing) = finish_piece(&head, options);
+        offset = prefix.len();
+        (previous, pending) = (Some(prefix), trailing);
+    }
+    let mut mappings = vec!();
+    for (run, (start, end)) in ranges {
+        let (block, trailing) = finish_piece(&html[start..end], options);
+        let joined = options.html.minify && block.starts_with('<')
+            && previous.as_ref().is_some_and(|last: &String| last.ends_with('>'));
+        if !joined { offset += pending }
+        if let Some(position) = run_position(positions, &run) {
+            mappings.push(SourceMapping{ source: position.bytes, output: (offset, offset + block.len()) });
+        }
+        offset += block.len();
+        (previous, pending) = (Some(block), trailing + 1);
     }
+    Ok((finish(html, mds, options), mappings))
+}
+
+// The blocks each piece of the html came from, with the piece's byte range in the html.
+type BlockRanges = Vec<(Range<usize>, (usize, usize))>;
+
+// Where a run of blocks is in the source, from the start of its first to the end of its last.
+fn run_position(positions: &[SourcePos], run: &Range<usize>) -> Option<SourcePos> {
+    let (first, last) = (positions.get(run.start)?, positions.get(run.end - 1)?);
+    Some(SourcePos{ start: first.start, end: last.end, bytes: (first.bytes.0, last.bytes.1) })
 }
 
-pub fn mds_to_html(mds: &Vec<Md>) -> String {
-    let strings = mds.iter().map(|md| md_to_html(md));
-    let strings: Vec<String> = strings.collect();
-    strings.join("\n")
+fn render(
+    mds: &[Md],
+    positions: Option<&[SourcePos]>,
+    options: &RenderOptions,
+    renderer: &dyn Renderer,
+    mut ranges: Option<&mut BlockRanges>
+) -> Result<String, RenderError> {
+    span!(debug_span, "render", blocks = mds.len());
+    let mut html = String::new();
+    let mut elements = 0;
+    let mut context = Context::new(mds, options, renderer);
+    for run in runs(mds, options) {
+        span!(trace_span, "render_block", index = run.start);
+        let expansion = expansion_of(&mds[run.clone()], &context);
+        elements += mds[run.clone()].iter().map(md_elements).sum::<usize>() + expansion.elements;
+        if let Some(max) = options.max_elements.filter(|max| elements > *max) {
+            return limit(html, options, RenderError::TooManyElements(max))
+        }
+        if let Some(max) = options.max_bytes.filter(|max| html.len() + expansion.bytes > *max) {
+            return limit(html, options, RenderError::TooManyBytes(max))
+        }
+        let mut block = run_to_html(&mds[run.clone()], &mut context);
+        if let Some(position) = positions.and_then(|positions| run_position(positions, &run)) {
+            if block.starts_with('<') {
+                block = with_attribute(block, "data-sourcepos", &position.to_string());
+            }
+        }
+        if block.is_empty() { continue }
+        let bytes = html.len() + block.len() + if html.is_empty() { 0 } else { 1 };
+        if let Some(max) = options.max_bytes.filter(|max| bytes > *max) {
+            return limit(html, options, RenderError::TooManyBytes(max))
+        }
+        push_block(&mut html, &block);
+        if let Some(ranges) = ranges.as_deref_mut() {
+            ranges.push((run, (html.len() - block.len(), html.len())));
+        }
+    }
+    if options.footnotes.placement == FootnotePlacement::End {
+        let notes = footnotes_to_html(mds, &context);
+        let bytes = html.len() + notes.len() + if html.is_empty() { 0 } else { 1 };
+        if let Some(max) = options.max_bytes.filter(|max| !notes.is_empty() && bytes > *max) {
+            return limit(html, options, RenderError::TooManyBytes(max))
+        }
+        push_block(&mut html, &notes);
+    }
+    Ok(html)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::options::ParserOptions;
+    use crate::convert::document::DocumentOptions;
     use crate::{normal_word,words,items};
 
     #[test]
@@ -121,47 +856,476 @@ mod tests {
         let world_sentence = Md::Sentence(words);
 
         let mds = vec!(heading, hello_sentence, world_sentence);
-        assert_eq!(mds_to_html(&mds), "<h1>Heading</h1>\nHello<br />\nWorld<br />".to_string());
+        assert_eq!(mds_to_html(&mds), "<h1 id=\"heading\">Heading</h1>\nHello<br />\nWorld<br />".to_string());
+    }
+
+    #[test]
+    fn test_write_html() {
+        let mds = parse("# Title\n*[T]: x\n- item\nEnd");
+        let mut out: Vec<u8> = vec!();
+        write_html(&mds, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), mds_to_html(&mds));
+
+        let mut out: [u8; 4] = [0; 4];
+        assert!(write_html(&mds, &mut out[..]).is_err());
+    }
+
+    fn streamed(text: &str, options: &RenderOptions) -> String {
+        let mut writer = HtmlWriter::new(vec!(), options);
+        for md in &parse(text) { writer.write(md).unwrap() }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_html_writer() {
+        let options = RenderOptions::default();
+        for text in [
+            "# Intro\n- item\n# Intro\n| A |\n| - |\n| a |\nEnd",
+            "[TOC]\n# A\n## B",
+            "Before\n\nA note[^1]\n# Notes\n[^1]: Here",
+            "",
+        ] {
+            assert_eq!(streamed(text, &options), mds_to_html_with(&parse(text), &options).unwrap());
+        }
+        let options = RenderOptions{ number_headings: true, ..Default::default() };
+        let text = "## A\n### B\n## C";
+        assert_eq!(streamed(text, &options), mds_to_html_with(&parse(text), &options).unwrap());
+
+        let options = RenderOptions{ html: HtmlOptions{ minify: true, ..Default::default() }, ..Default::default() };
+        assert_eq!(streamed("# A\n- b", &options), mds_to_html_with(&parse("# A\n- b"), &options).unwrap());
+
+        // Output starts before the writer is finished.
+        let mut writer = HtmlWriter::new(vec!(), &RenderOptions::default());
+        writer.write(&parse("**a**")[0]).unwrap();
+        writer.write(&parse("*[HTML]: Hyper Text")[0]).unwrap();
+        assert_eq!(writer.out, b"<b>a</b><br />");
+    }
+
+    #[test]
+    fn test_mds_to_html_with() {
+        let heading = Md::Heading(1, words!(normal_word!("Heading")));
+        let hello = Md::Sentence(words!(normal_word!("Hello")));
+        let mds = vec!(heading, hello);
+
+        let options = RenderOptions::default();
+        assert_eq!(mds_to_html_with(&mds, &options), Ok("<h1 id=\"heading\">Heading</h1>\nHello<br />".to_string()));
+
+        let options = RenderOptions{ max_bytes: Some(41), ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Ok("<h1 id=\"heading\">Heading</h1>\nHello<br />".to_string()));
+
+        let options = RenderOptions{ max_bytes: Some(40), ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Err(RenderError::TooManyBytes(40)));
+
+        let options = RenderOptions{ max_bytes: Some(40), on_limit: LimitAction::Truncate, ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Ok("<h1 id=\"heading\">Heading</h1>\n<!-- truncated -->".to_string()));
+
+        let options = RenderOptions{ max_elements: Some(1), ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Err(RenderError::TooManyElements(1)));
+
+        let options = RenderOptions{ max_elements: Some(0), on_limit: LimitAction::Truncate, ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Ok("<!-- truncated -->".to_string()));
+    }
+
+    #[test]
+    fn test_minified_html() {
+        let mds = parse("# Title\n| A |\n| - |\n| a |\n- item\n```\nx\n\ny\n```");
+        let options = RenderOptions{ html: HtmlOptions{ minify: true, ..Default::default() }, ..Default::default() };
+        let expect = "<h1 id=\"title\">Title</h1><table><tr><th scope=\"col\">A</th></tr><tr><td align=\"left\">a</td></tr></table><ul><li>item</li></ul><pre><code>x\n\ny</code></pre>";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_standalone_document() {
+        let mds = parse("---\ntitle: Guide\n---\n# Intro");
+        let document = DocumentOptions{ lang: "ja".to_string(), ..Default::default() };
+        let options = RenderOptions{ document: Some(document), ..Default::default() };
+        let expect = "<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n<meta charset=\"utf-8\">\n<title>Guide</title>\n<meta property=\"og:title\" content=\"Guide\">\n<meta property=\"og:type\" content=\"article\">\n</head>\n<body>\n<h1 id=\"intro\">Intro</h1>\n</body>\n</html>\n";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_classes() {
+        let mds = parse("# Title\n| A |\n| - |\n| a |\n- item\n::: note\nx\n:::");
+        let options = RenderOptions{ html: HtmlOptions::with_class_prefix("md-"), ..Default::default() };
+        let expect = "<h1 class=\"md-heading-1\" id=\"title\">Title</h1>\n<table class=\"md-table\">\n<tr><th scope=\"col\">A</th></tr>\n<tr><td align=\"left\">a</td></tr>\n</table>\n\n<ul class=\"md-list\">\n<li class=\"md-item\">item</li>\n</ul>\n\n<div class=\"md-container note\">\nx<br />\n</div>";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+
+        let mut html = HtmlOptions::default();
+        html.classes.insert("code".to_string(), "highlight".to_string());
+        let options = RenderOptions{ html, ..Default::default() };
+        let mds = parse("```\nx\n```\n## Sub");
+        let expect = "<pre class=\"highlight\"><code>x</code></pre>\n<h2 id=\"sub\">Sub</h2>";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_direction() {
+        let mds = parse("# שלום\nمرحبا **بالعالم**\nHello");
+        let options = RenderOptions{ html: HtmlOptions{ dir: Some(Direction::Rtl), ..Default::default() }, ..Default::default() };
+        let html = mds_to_html_with(&mds, &options).unwrap();
+        let expect = "<h1 dir=\"rtl\" id=\"שלום\">שלום</h1>\n<span dir=\"rtl\">مرحبا <b>بالعالم</b></span><br />\nHello<br />";
+        assert_eq!(html, expect);
+        assert!(!mds_to_html(&mds).contains("dir="));
+
+        let options = RenderOptions{ html: HtmlOptions{ dir: Some(Direction::Auto), ..Default::default() }, ..Default::default() };
+        let html = mds_to_html_with_renderer(&mds, &options, &crate::commonmark::CommonMarkRenderer).unwrap();
+        assert!(html.starts_with("<h1 dir=\"auto\">שלום</h1>\n<p dir=\"auto\">مرحبا "), "{}", html);
+        assert!(html.ends_with("<p>Hello</p>"), "{}", html);
+    }
+
+    #[test]
+    fn test_source_positions() {
+        let text = "# Title\n\n| A |\n| - |\n| ä |\nSome text\n- one\n- two";
+        let (mds, positions) = parse_with_positions(text, &ParserOptions::default());
+        assert_eq!(mds, parse(text));
+        let positions: Vec<String> = positions.iter().map(|position| position.to_string()).collect();
+        assert_eq!(positions, vec!("1:1-1:7", "2:1-2:1", "3:1-5:5", "6:1-6:9", "7:1-8:5"));
+
+        let (mds, positions) = parse_with_positions("# Title\nText", &ParserOptions::default());
+        let expect = "<h1 data-sourcepos=\"1:1-1:7\" id=\"title\">Title</h1>\nText<br />";
+        assert_eq!(mds_to_html_with_positions(&mds, &positions, &RenderOptions::default()), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_single_node_to_html() {
+        let mds = parse("## Part *one*\n| A |\n| - |\n| a |");
+        let options = RenderOptions::default();
+        assert_eq!(md_to_html_with(&mds[0], &options), "<h2 id=\"part-one\">Part <i>one</i></h2>");
+
+        let Md::Heading(_, words) = &mds[0] else { panic!("expected a heading") };
+        let options = RenderOptions{ html: HtmlOptions{ semantic_tags: true, ..Default::default() }, ..Default::default() };
+        assert_eq!(words_to_html_with(words, &options), "Part <em>one</em>");
+
+        let Md::Table(table) = &mds[1] else { panic!("expected a table") };
+        let options = RenderOptions{ html: HtmlOptions{ minify: true, ..Default::default() }, ..Default::default() };
+        assert_eq!(table_to_html_with(table, &options), "<table><tr><th scope=\"col\">A</th></tr><tr><td align=\"left\">a</td></tr></table>");
+    }
+
+    #[test]
+    fn test_source_map() {
+        let text = "\u{FEFF}# Title\r\n| A |\r\n| - |\r\n| a |\r\n*[T]: x\r\nText";
+        let (mds, positions) = parse_with_positions(text, &ParserOptions::default());
+        let (html, mappings) = mds_to_html_with_source_map(&mds, &positions, &RenderOptions::default()).unwrap();
+        let pairs: Vec<(&str, &str)> = mappings.iter()
+            .map(|mapping| (&text[mapping.source.0..mapping.source.1], &html[mapping.output.0..mapping.output.1]))
+            .collect();
+        let table = "<table>\n<tr><th scope=\"col\">A</th></tr>\n<tr><td align=\"left\">a</td></tr>\n</table>\n";
+        assert_eq!(pairs, vec!(
+            ("# Title", "<h1 id=\"title\">Title</h1>"),
+            ("| A |\r\n| - |\r\n| a |", table),
+            ("Text", "Text<br />"),
+        ));
+
+        let html_options = HtmlOptions{ minify: true, sanitize: true, void_style: VoidStyle::Html, ..Default::default() };
+        let options = RenderOptions{ html: html_options, document: Some(DocumentOptions::default()), ..Default::default() };
+        let (html, mappings) = mds_to_html_with_source_map(&mds, &positions, &options).unwrap();
+        assert_eq!(html, mds_to_html_with(&mds, &options).unwrap());
+        let outputs: Vec<&str> = mappings.iter().map(|mapping| &html[mapping.output.0..mapping.output.1]).collect();
+        let table = "<table><tr><th scope=\"col\">A</th></tr><tr><td align=\"left\">a</td></tr></table>";
+        assert_eq!(outputs, vec!("<h1 id=\"title\">Title</h1>", table, "Text<br>"));
+    }
+
+    #[test]
+    fn test_code_line_numbers() {
+        let mds = parse("```rust {2}\nlet a;\nlet b = a < 1;\n```");
+        let expect = "<pre><code class=\"language-rust\"><span class=\"line\" data-line=\"1\">let a;</span>\n<span class=\"line highlight\" data-line=\"2\">let b = a &lt; 1;</span></code></pre>";
+        assert_eq!(mds_to_html(&mds), expect.to_string());
+
+        let mds = parse("```\nx\n```");
+        let options = RenderOptions{ html: HtmlOptions{ line_numbers: true, ..Default::default() }, ..Default::default() };
+        let expect = "<pre><code><span class=\"line\" data-line=\"1\">x</span></code></pre>";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let mds = parse("# <img src=x onerror=alert(1)>\n<script>x</script> & **b**\n::: x\" onclick=\"y\n:::");
+        let options = RenderOptions{ html: HtmlOptions{ sanitize: true, ..Default::default() }, ..Default::default() };
+        let expect = "<h1 id=\"img-srcx-onerroralert1\">&lt;img src=x onerror=alert(1)&gt;</h1>\n&lt;script&gt;x&lt;/script&gt; &amp; <b>b</b><br />\n<div class=\"x&quot;\">\n\n</div>";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+
+        let mds = parse("[[<img src=x onerror=alert(1)>]]");
+        assert_eq!(mds_to_html_with(&mds, &options), Ok("<kbd>&lt;img src=x onerror=alert(1)&gt;</kbd><br />".to_string()));
+
+        let mds = parse("[s](/search?q=a&page=2) ![i](/i.png?w=1&h=2)");
+        let expect = "<a href=\"/search?q=a&amp;page=2\">s</a> <img src=\"/i.png?w=1&amp;h=2\" alt=\"i\" /><br />";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+        let mds = parse("<!-- md:off -->\n<!-- x --!><img src=x onerror=alert(1)> -->\n<!-- md:on -->");
+        assert_eq!(mds_to_html_with(&mds, &options), Ok("<img src=\"x\"> -->".to_string()));
+    }
+
+    #[test]
+    fn test_raw_to_html() {
+        let mds = parse("<!-- md:off -->\n<div>*kept* & as is</div>\n<!-- md:on -->\n*parsed*");
+        assert_eq!(mds_to_html(&mds), "<div>*kept* & as is</div>\n<i>parsed</i><br />");
+        let options = RenderOptions{ html: HtmlOptions{ escape_raw: true, ..Default::default() }, ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options).unwrap(), "&lt;div&gt;*kept* &amp; as is&lt;/div&gt;\n<i>parsed</i><br />");
+    }
+
+    #[test]
+    fn test_void_style() {
+        let mds = parse("a\n???+ b\n    c");
+        let options = RenderOptions{ html: HtmlOptions{ void_style: VoidStyle::Xhtml, ..Default::default() }, ..Default::default() };
+        let expect = "a<br/>\n<details open=\"open\">\n<summary>b</summary>\nc<br/>\n</details>";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_md_elements() {
+        let bold = Word::Bold(words!(normal_word!("Hello")));
+        let md = Md::Sentence(words!(bold, normal_word!("World")));
+        assert_eq!(md_elements(&md), 2);
+
+        let hello = words!(normal_word!("hello"));
+        let world = words!(normal_word!("world"));
+        let header = Record(vec!(hello), vec!());
+        let records = vec!(Record(vec!(world), vec!()));
+        let md = Md::Table(Box::new(Table{header, align: vec!(Align::Left), records, caption: None}));
+        assert_eq!(md_elements(&md), 5);
+
+        let child = Item(words!(normal_word!("child")), vec!());
+        let parent = Item(words!(normal_word!("parent")), vec!(Md::List(items!(child))));
+        let md = Md::List(items!(parent));
+        assert_eq!(md_elements(&md), 4);
     }
 
     #[test]
     fn test_to_html() {
         let words = words!(normal_word!("Hello"));
         let md = Md::Heading(1, words);
-        assert_eq!(md_to_html(&md), "<h1>Hello</h1>".to_string());
+        assert_eq!(md_to_html(&md, &mut Context::default()), "<h1 id=\"hello\">Hello</h1>".to_string());
 
         let words = words!(normal_word!("Hello"));
         let md = Md::Sentence(words);
-        assert_eq!(md_to_html(&md), "Hello<br />".to_string());
+        assert_eq!(md_to_html(&md, &mut Context::default()), "Hello<br />".to_string());
 
         let words = words!(normal_word!("item"));
-        let items = items!();
-        let item = Item(words, items);
+        let item = Item(words, vec!());
         let items = items!(item);
         let md = Md::List(items);
-        assert_eq!(md_to_html(&md), "<ul>\n<li>item</li>\n</ul>\n".to_string());
+        assert_eq!(md_to_html(&md, &mut Context::default()), "<ul>\n<li>item</li>\n</ul>\n".to_string());
+    }
+
+    #[test]
+    fn test_heading_to_html() {
+        let words = words!(normal_word!("Hello "), Word::Bold(words!(normal_word!("World!"))));
+        let options = RenderOptions::default();
+        assert_eq!(heading_to_html(&2, &words, &mut Context::new(&[], &options, &HtmlRenderer)), "<h2 id=\"hello-world\">Hello <b>World!</b></h2>".to_string());
+
+        let options = RenderOptions{ heading_anchors: true, ..Default::default() };
+        let expect = "<h2 id=\"hello-world\"><a class=\"anchor\" href=\"#hello-world\" aria-hidden=\"true\">#</a>Hello <b>World!</b></h2>";
+        assert_eq!(heading_to_html(&2, &words, &mut Context::new(&[], &options, &HtmlRenderer)), expect.to_string());
+
+        let words = words!(normal_word!("???"));
+        assert_eq!(heading_to_html(&1, &words, &mut Context::new(&[], &options, &HtmlRenderer)), "<h1>???</h1>".to_string());
+    }
+
+    #[test]
+    fn test_duplicate_heading_ids() {
+        let mds = parse("# Usage\n::: note\n## Usage\n:::\n### Usage");
+        let expect = "<h1 id=\"usage\">Usage</h1>\n<div class=\"note\">\n<h2 id=\"usage-1\">Usage</h2>\n</div>\n<h3 id=\"usage-2\">Usage</h3>";
+        assert_eq!(mds_to_html(&mds), expect.to_string());
+        assert_eq!(mds_to_html_with(&mds, &RenderOptions::default()), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_toc() {
+        let mds = parse("[TOC]\n# Title\n## Usage");
+        let expect = "<nav class=\"toc\">\n<ul>\n<li><a href=\"#title\">Title</a>\n<ul>\n<li><a href=\"#usage\">Usage</a></li>\n</ul>\n</li>\n</ul>\n</nav>\n<h1 id=\"title\">Title</h1>\n<h2 id=\"usage\">Usage</h2>";
+        assert_eq!(mds_to_html(&mds), expect.to_string());
+
+        let mds = parse("[TOC]\n# &lt;img src=x onerror=alert(1)&gt;");
+        assert!(!mds_to_html(&mds).contains("<img"));
+    }
+
+    #[test]
+    fn test_number_headings() {
+        let mds = parse("[TOC]\n# Title\n## Usage");
+        let options = RenderOptions{ number_headings: true, ..Default::default() };
+        let expect = concat!(
+            "<nav class=\"toc\">\n<ul>\n<li><a href=\"#title\">1 Title</a>\n<ul>\n<li><a href=\"#usage\">1.1 Usage</a></li>\n</ul>\n</li>\n</ul>\n</nav>\n",
+            "<h1 id=\"title\"><span class=\"section-number\">1</span> Title</h1>\n",
+            "<h2 id=\"usage\"><span class=\"section-number\">1.1</span> Usage</h2>",
+        );
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_abbreviation() {
+        let mds = parse("HTML is **HTML**\n*[HTML]: HyperText Markup Language\n| HTML |\n| - |\n| XHTML |");
+        let abbr = "<abbr title=\"HyperText Markup Language\">HTML</abbr>";
+        let expect = format!("{} is <b>{}</b><br />\n<table>\n<tr><th scope=\"col\">{}</th></tr>\n<tr><td align=\"left\">XHTML</td></tr>\n</table>\n", abbr, abbr, abbr);
+        assert_eq!(mds_to_html(&mds), expect);
+
+        let mds = parse("*[W3C]: \"World\" Wide <Web> Consortium\nW3C");
+        let options = RenderOptions{ html: HtmlOptions{ sanitize: true, ..Default::default() }, ..Default::default() };
+        let expect = "<abbr title=\"&quot;World&quot; Wide &lt;Web&gt; Consortium\">W3C</abbr><br />";
+        assert_eq!(mds_to_html(&mds), expect);
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_abbreviation_limits() {
+        let text = format!("*[a]: {}\n{}", "x".repeat(1000), "a ".repeat(200));
+        let mds = parse(&text);
+        let options = RenderOptions{ max_elements: Some(100), ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Err(RenderError::TooManyElements(100)));
+        let options = RenderOptions{ max_bytes: Some(1000), ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Err(RenderError::TooManyBytes(1000)));
+        let options = RenderOptions{ max_bytes: Some(1000), on_limit: LimitAction::Truncate, ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Ok("<!-- truncated -->".to_string()));
+
+        let mds = parse("[TOC]\n# A\n## B\n# C");
+        let options = RenderOptions{ max_elements: Some(6), ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Err(RenderError::TooManyElements(6)));
+    }
+
+    #[test]
+    fn test_links() {
+        let mds = parse("[Home](/about) [Blog](https://Example.com:8080/x) [Other](https://other.org/?q=\"a\") [Mail](mailto:a@b.c)");
+        let expect = "<a href=\"/about\">Home</a> <a href=\"https://Example.com:8080/x\">Blog</a> <a href=\"https://other.org/?q=&quot;a&quot;\">Other</a> <a href=\"mailto:a@b.c\">Mail</a><br />";
+        assert_eq!(mds_to_html(&mds), expect.to_string());
+
+        let html = HtmlOptions{ site_host: Some("example.com".to_string()), ..Default::default() };
+        let options = RenderOptions{ html, ..Default::default() };
+        let expect = concat!(
+            "<a href=\"/about\">Home</a> <a href=\"https://Example.com:8080/x\">Blog</a> ",
+            "<a rel=\"noopener noreferrer\" target=\"_blank\" href=\"https://other.org/?q=&quot;a&quot;\">Other</a> ",
+            "<a href=\"mailto:a@b.c\">Mail</a><br />",
+        );
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_image_size() {
+        let mds = parse("![a](a.png =640x480) ![b](b.png){width=50%} ![c](c.png)");
+        let expect = "<img src=\"a.png\" alt=\"a\" width=\"640\" height=\"480\" /> <img src=\"b.png\" alt=\"b\" width=\"50%\" /> <img src=\"c.png\" alt=\"c\" /><br />";
+        assert_eq!(mds_to_html(&mds), expect.to_string());
+    }
+
+    #[test]
+    fn test_heading_offset() {
+        let options = RenderOptions{ heading_offset: 1, ..Default::default() };
+        let html = mds_to_html_with(&parse("# Title\n### Deep"), &options).unwrap();
+        assert_eq!(html, "<h2 id=\"title\">Title</h2>\n<h4 id=\"deep\">Deep</h4>");
+        let options = RenderOptions{ heading_offset: 5, ..Default::default() };
+        assert_eq!(mds_to_html_with(&parse("## Deep"), &options).unwrap(), "<h6 id=\"deep\">Deep</h6>");
+    }
+
+    #[test]
+    fn test_md_links() {
+        let mds = parse("[Setup](guide/setup.md#install) and [site](https://example.com/a.md)");
+        let options = RenderOptions{ html: HtmlOptions{ md_links: true, ..Default::default() }, ..Default::default() };
+        let html = mds_to_html_with(&mds, &options).unwrap();
+        assert!(html.contains("href=\"guide/setup.html#install\""));
+        assert!(html.contains("href=\"https://example.com/a.md\""));
+        assert!(mds_to_html(&mds).contains("href=\"guide/setup.md#install\""));
+    }
+
+    #[test]
+    fn test_base_url() {
+        let mds = parse("[Guide](guide/intro.md) ![Logo](../img/logo.png) [Top](#top) [Home](/) [Ext](https://other.org)");
+        let html = HtmlOptions{ base_url: Some("https://example.com/docs/v1/".to_string()), ..Default::default() };
+        let options = RenderOptions{ html, ..Default::default() };
+        let expect = concat!(
+            "<a href=\"https://example.com/docs/v1/guide/intro.md\">Guide</a> ",
+            "<img src=\"https://example.com/docs/img/logo.png\" alt=\"Logo\" /> ",
+            "<a href=\"#top\">Top</a> <a href=\"https://example.com/\">Home</a> ",
+            "<a href=\"https://other.org\">Ext</a><br />",
+        );
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+        assert!(mds_to_html(&mds).starts_with("<a href=\"guide/intro.md\">Guide</a> <img src=\"../img/logo.png\" alt=\"Logo\" />"));
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let mds = parse("One[^a] two[^b] one[^a] none[^x]\n[^a]: First\n[^b]: **Second**");
+        let expect = concat!(
+            "One<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup>",
+            " two<sup class=\"footnote-ref\"><a href=\"#fn-2\" id=\"fnref-2\">2</a></sup>",
+            " one<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1-2\">1</a></sup> none[^x]<br />\n",
+            "<section class=\"footnotes\">\n<ol>\n",
+            "<li id=\"fn-1\">First <a href=\"#fnref-1\" class=\"footnote-backref\">↩</a>",
+            " <a href=\"#fnref-1-2\" class=\"footnote-backref\">↩<sup>2</sup></a></li>\n",
+            "<li id=\"fn-2\"><b>Second</b> <a href=\"#fnref-2\" class=\"footnote-backref\">↩</a></li>\n",
+            "</ol>\n</section>",
+        );
+        assert_eq!(mds_to_html(&mds), expect.to_string());
+        assert_eq!(mds_to_html_with(&mds, &RenderOptions::default()), Ok(expect.to_string()));
+
+        let mds = parse("Text[^1]\n[^1]: Note");
+        let footnotes = FootnoteOptions{ heading: Some("Notes".to_string()), placement: FootnotePlacement::End };
+        let options = RenderOptions{ footnotes, ..Default::default() };
+        let html = mds_to_html_with(&mds, &options).unwrap();
+        assert!(html.contains("<section class=\"footnotes\">\n<h2 class=\"footnotes-heading\">Notes</h2>\n<ol>"));
+
+        let footnotes = FootnoteOptions{ placement: FootnotePlacement::Omit, ..Default::default() };
+        let options = RenderOptions{ footnotes, ..Default::default() };
+        let html = mds_to_html_with(&mds, &options).unwrap();
+        assert!(!html.contains("<section"));
+        assert!(footnotes_to_html_with(&mds, &options).starts_with("<section class=\"footnotes\">\n<ol>\n<li id=\"fn-1\">Note"));
+    }
+
+    #[test]
+    fn test_smart_punctuation() {
+        let mds = parse("\"**Hello**\" -- it's ~~done~~...");
+        let options = RenderOptions{ smart_punctuation: true, ..Default::default() };
+        let expect = "“<b>Hello</b>” – it’s <s>done</s>…<br />";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+        assert_eq!(mds_to_html(&mds), "\"<b>Hello</b>\" -- it's <s>done</s>...<br />".to_string());
     }
 
     #[test]
     fn test_word() {
         let word = normal_word!("Hello");
-        assert_eq!(word_to_html(&word), "Hello".to_string());
+        assert_eq!(word_to_html(&word, &Context::default()), "Hello".to_string());
 
         let word = normal_word!("Hello");
         let italic = Word::Italic(words!(word));
-        assert_eq!(word_to_html(&italic), "<i>Hello</i>".to_string());
+        assert_eq!(word_to_html(&italic, &Context::default()), "<i>Hello</i>".to_string());
 
         let word = normal_word!("Hello");
         let bold = Word::Bold(words!(word));
-        assert_eq!(word_to_html(&bold), "<b>Hello</b>".to_string());
+        assert_eq!(word_to_html(&bold, &Context::default()), "<b>Hello</b>".to_string());
 
         let word = normal_word!("Hello");
         let strike = Word::StrikeThough(words!(word));
-        assert_eq!(word_to_html(&strike), "<s>Hello</s>".to_string());
+        assert_eq!(word_to_html(&strike, &Context::default()), "<s>Hello</s>".to_string());
 
         let word = normal_word!("Hello");
         let line = Word::Underline(words!(word));
-        assert_eq!(word_to_html(&line), "<u>Hello</u>".to_string());
+        assert_eq!(word_to_html(&line, &Context::default()), "<u>Hello</u>".to_string());
+
+        let word = normal_word!("Hello");
+        let mark = Word::Highlight(words!(word));
+        assert_eq!(word_to_html(&mark, &Context::default()), "<mark>Hello</mark>".to_string());
+
+        let word = normal_word!("2");
+        let sub = Word::Subscript(words!(word));
+        assert_eq!(word_to_html(&sub, &Context::default()), "<sub>2</sub>".to_string());
+
+        let word = normal_word!("2");
+        let sup = Word::Superscript(words!(word));
+        assert_eq!(word_to_html(&sup, &Context::default()), "<sup>2</sup>".to_string());
+
+        let word = normal_word!("Hello");
+        let ins = Word::Insert(words!(word));
+        assert_eq!(word_to_html(&ins, &Context::default()), "<ins>Hello</ins>".to_string());
+
+        let kbd = Word::Kbd("Ctrl".to_string());
+        assert_eq!(word_to_html(&kbd, &Context::default()), "<kbd>Ctrl</kbd>".to_string());
+    }
+
+    #[test]
+    fn test_emoji_to_html() {
+        let word = Word::Emoji("no_such_emoji".to_string());
+        assert_eq!(word_to_html(&word, &Context::default()), "<span class=\"emoji\">:no_such_emoji:</span>".to_string());
+
+        #[cfg(feature = "emoji")]
+        {
+            let word = Word::Emoji("smile".to_string());
+            assert_eq!(word_to_html(&word, &Context::default()), "😄".to_string());
+        }
     }
 
     #[test]
@@ -170,100 +1334,236 @@ mod tests {
         let word1 = normal_word!("World!");
         let bold = Word::Bold(words!(word1));
         let words = words!(word, bold);
-        assert_eq!(words_to_html(&words), "Hello<b>World!</b>".to_string());
+        assert_eq!(words_to_html(&words, &Context::default()), "Hello<b>World!</b>".to_string());
+    }
+
+    #[test]
+    fn test_semantic_tags() {
+        let words = words!(
+            Word::Bold(words!(normal_word!("b"))),
+            Word::Italic(words!(normal_word!("i"))),
+            Word::StrikeThough(words!(normal_word!("s"))),
+            Word::Underline(words!(normal_word!("u")))
+        );
+        let options = RenderOptions{ html: HtmlOptions{ semantic_tags: true, ..Default::default() }, ..Default::default() };
+        let expect = "<strong>b</strong><em>i</em><del>s</del><ins>u</ins>";
+        assert_eq!(words_to_html(&words, &Context::new(&[], &options, &HtmlRenderer)), expect.to_string());
+        assert_eq!(words_to_html(&words, &Context::default()), "<b>b</b><i>i</i><s>s</s><u>u</u>".to_string());
+    }
+
+    #[test]
+    fn test_hard_breaks() {
+        use crate::commonmark::CommonMarkRenderer;
+        let text = "One\nTwo\n\nThree\n> Four\n> Five";
+        let mds = parse(text);
+        let options = RenderOptions{ html: HtmlOptions{ hard_breaks: true, ..Default::default() }, ..Default::default() };
+        let expect = "<p>One<br />\nTwo</p>\n<p>Three</p>\n<blockquote>\n<p>Four<br />\nFive</p>\n</blockquote>";
+        assert_eq!(mds_to_html_with_renderer(&mds, &options, &CommonMarkRenderer), Ok(expect.to_string()));
+        let split = mds_to_html_with_renderer(&mds, &RenderOptions::default(), &CommonMarkRenderer).unwrap();
+        assert!(split.starts_with("<p>One</p>\n<p>Two</p>\n"));
+
+        // Every line ends in a break anyway with the default renderer.
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(mds_to_html(&mds)));
+        let writer = HtmlWriter::new(vec!(), &options);
+        let html = mds.iter().try_fold(writer, |mut writer, md| writer.write(md).map(|_| writer)).unwrap().finish().unwrap();
+        assert_eq!(String::from_utf8(html).unwrap(), mds_to_html(&mds));
+
+        let (mds, positions) = parse_with_positions("One\nTwo", &ParserOptions::default());
+        let (html, mappings) = mds_to_html_with_source_map(&mds, &positions, &options).unwrap();
+        assert_eq!(html, "One<br />\nTwo<br />");
+        assert_eq!(mappings, vec!(SourceMapping{ source: (0, 7), output: (0, html.len()) }));
+    }
+
+    #[test]
+    fn test_container_to_html() {
+        let words = words!(normal_word!("Hello"));
+        let mds = vec!(Md::Sentence(words));
+        let md = Md::Container("warning".to_string(), mds);
+        assert_eq!(md_to_html(&md, &mut Context::default()), "<div class=\"warning\">\nHello<br />\n</div>".to_string());
+
+        let md = Md::Container("a\" onclick=\"b()".to_string(), vec!());
+        assert_eq!(md_to_html(&md, &mut Context::default()), "<div class=\"a&quot; onclick=&quot;b()\">\n\n</div>".to_string());
+    }
+
+    #[test]
+    fn test_details_to_html() {
+        let summary = words!(normal_word!("Question"));
+        let mds = vec!(Md::Sentence(words!(normal_word!("Answer"))));
+        let md = Md::Details(false, summary, mds);
+        assert_eq!(md_to_html(&md, &mut Context::default()), "<details>\n<summary>Question</summary>\nAnswer<br />\n</details>".to_string());
+
+        let summary = words!(normal_word!("Question"));
+        let mds = vec!(Md::Sentence(words!(normal_word!("Answer"))));
+        let md = Md::Details(true, summary, mds);
+        assert_eq!(md_to_html(&md, &mut Context::default()), "<details open>\n<summary>Question</summary>\nAnswer<br />\n</details>".to_string());
     }
 
     #[test]
     fn test_header_to_html() {
         let hello = words!(normal_word!("hello"));
         let world = words!(normal_word!("world"));
-        let header = Record(vec!(hello, world));
-        assert_eq!(header_to_html(&header), "<th>hello</th><th>world</th>".to_string());
+        let header = Record(vec!(hello, world), vec!());
+        assert_eq!(header_to_html(&header, &Context::default()), "<th scope=\"col\">hello</th><th scope=\"col\">world</th>".to_string());
     }
 
     #[test]
     fn test_record_to_html() {
         let hello = words!(normal_word!("hello"));
         let world = words!(normal_word!("world"));
-        let record = Record(vec!(hello, world));
+        let record = Record(vec!(hello, world), vec!());
         let align = vec!(Align::Left, Align::Left);
-        assert_eq!(record_to_html(&record, &align), "<td align=\"left\">hello</td><td align=\"left\">world</td>".to_string());
+        assert_eq!(record_to_html(&record, &align, &Context::default()), "<td align=\"left\">hello</td><td align=\"left\">world</td>".to_string());
 
         let hello = words!(normal_word!("hello"));
         let world = words!(normal_word!("world"));
-        let record = Record(vec!(hello, world));
+        let record = Record(vec!(hello, world), vec!());
         let align = vec!(Align::Center, Align::Right);
-        assert_eq!(record_to_html(&record, &align), "<td align=\"center\">hello</td><td align=\"right\">world</td>".to_string());
+        assert_eq!(record_to_html(&record, &align, &Context::default()), "<td align=\"center\">hello</td><td align=\"right\">world</td>".to_string());
     }
 
     #[test]
     fn test_records_to_html() {
         let hello = words!(normal_word!("hello"));
-        let record0 = Record(vec!(hello));
+        let record0 = Record(vec!(hello), vec!());
         let world = words!(normal_word!("world"));
-        let record1 = Record(vec!(world));
+        let record1 = Record(vec!(world), vec!());
         let records = vec!(record0, record1);
         let aligns = vec!(Align::Left);
-        assert_eq!(records_to_html(&records, &aligns), "<tr><td align=\"left\">hello</td></tr>\n<tr><td align=\"left\">world</td></tr>\n".to_string());
+        let expect = vec!("<tr><td align=\"left\">hello</td></tr>", "<tr><td align=\"left\">world</td></tr>");
+        assert_eq!(records_to_html(&records, &aligns, &Context::default()), expect);
     }
 
     #[test]
     fn test_table_to_html() {
         let hello = words!(normal_word!("hello"));
-        let header = Record(vec!(hello));
+        let header = Record(vec!(hello), vec!());
         let world = words!(normal_word!("world"));
-        let record = Record(vec!(world));
+        let record = Record(vec!(world), vec!());
         let records = vec!(record);
         let aligns = vec!(Align::Left);
-        let table = Box::new(Table{header, align: aligns, records});
-        assert_eq!(table_to_html(&table), "<table>\n<tr><th>hello</th></tr>\n<tr><td align=\"left\">world</td></tr>\n</table>\n".to_string());
+        let table = Box::new(Table{header, align: aligns, records, caption: None});
+        assert_eq!(table_to_html(&table, &Context::default()), "<table>\n<tr><th scope=\"col\">hello</th></tr>\n<tr><td align=\"left\">world</td></tr>\n</table>\n".to_string());
+    }
+
+    #[test]
+    fn test_table_sections() {
+        let options = RenderOptions{ html: HtmlOptions{ table_sections: true, ..Default::default() }, ..Default::default() };
+        let mds = parse("| A | B |\n| - | -: |\n| a | b |\n| c | d |");
+        let expect = "<table>\n<thead>\n<tr><th scope=\"col\">A</th><th scope=\"col\">B</th></tr>\n</thead>\n<tbody>\n\
+            <tr><td align=\"left\">a</td><td align=\"right\">b</td></tr>\n<tr><td align=\"left\">c</td><td align=\"right\">d</td></tr>\n\
+            </tbody>\n</table>\n";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+
+        let mds = parse("| A |\n| - |");
+        let expect = "<table>\n<thead>\n<tr><th scope=\"col\">A</th></tr>\n</thead>\n</table>\n";
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+        let options = RenderOptions{ html: HtmlOptions{ table_sections: true, sanitize: true, ..Default::default() }, ..Default::default() };
+        assert_eq!(mds_to_html_with(&mds, &options), Ok(expect.to_string()));
+    }
+
+    #[test]
+    fn test_table_spans_to_html() {
+        let options = ParserOptions{ table_spans: true, ..Default::default() };
+        let mds = parse_with("| A | B |\n| - | - |\n| a | |\n| ^ | b |", &options);
+        let expect = "<table>\n<tr><th scope=\"col\">A</th><th scope=\"col\">B</th></tr>\n<tr><td align=\"left\" colspan=\"2\" rowspan=\"2\">a</td></tr>\n<tr><td align=\"left\">b</td></tr>\n</table>\n";
+        assert_eq!(mds_to_html(&mds), expect.to_string());
+    }
+
+    #[test]
+    fn test_table_caption_to_html() {
+        let mds = parse("| A |\n| - |\n| a |\nTable: **Results**");
+        let expect = "<table>\n<caption><b>Results</b></caption>\n<tr><th scope=\"col\">A</th></tr>\n<tr><td align=\"left\">a</td></tr>\n</table>\n";
+        assert_eq!(mds_to_html(&mds), expect.to_string());
+    }
+
+    #[test]
+    fn test_loose_table_to_html() {
+        let header = Record(vec!(words!(normal_word!("hello"))), vec!());
+        let rows = vec!(Record(vec!(words!(normal_word!("-b-"))), vec!()), Record(vec!(words!(normal_word!("world"))), vec!()));
+        let table = LooseTable{header, rows, issue: TableIssue::InvalidAlign};
+        let expect = "<table>\n<tr><th scope=\"col\">hello</th></tr>\n<tr><td>-b-</td></tr>\n<tr><td>world</td></tr>\n</table>\n";
+        assert_eq!(loose_table_to_html(&table, &Context::default()), expect.to_string());
+    }
+
+    #[test]
+    fn test_code_to_html() {
+        let md = Md::Code("rust".to_string(), "a < b && c".to_string(), vec!());
+        assert_eq!(md_to_html(&md, &mut Context::default()), "<pre><code class=\"language-rust\">a &lt; b &amp;&amp; c</code></pre>".to_string());
+        let md = Md::Code("".to_string(), "<b>".to_string(), vec!());
+        assert_eq!(md_to_html(&md, &mut Context::default()), "<pre><code>&lt;b&gt;</code></pre>".to_string());
+        let md = Md::Code("a\"onmouseover=\"x()".to_string(), "".to_string(), vec!((1, 1)));
+        assert!(md_to_html(&md, &mut Context::default()).starts_with("<pre><code class=\"language-a&quot;onmouseover=&quot;x()\">"));
+        let md = Md::Code("a\"onmouseover=\"x()".to_string(), "".to_string(), vec!());
+        assert_eq!(md_to_html(&md, &mut Context::default()), "<pre><code class=\"language-a&quot;onmouseover=&quot;x()\"></code></pre>".to_string());
+    }
+
+    #[test]
+    fn test_item_blocks_to_html() {
+        let mds = parse("- Hello\n  World\n\n  ```\n  code\n  ```\n- End");
+        let expect = "<ul>\n<li>Hello\nWorld\n<pre><code>code</code></pre></li>\n<li>End</li>\n</ul>\n";
+        assert_eq!(mds_to_html(&mds), expect.to_string());
+    }
+
+    #[test]
+    fn test_quote_to_html() {
+        let mds = parse("> ## Title\n> | A |\n> | - |\n> | a |");
+        let expect = "<blockquote>\n<h2 id=\"title\">Title</h2>\n<table>\n<tr><th scope=\"col\">A</th></tr>\n<tr><td align=\"left\">a</td></tr>\n</table>\n\n</blockquote>";
+        assert_eq!(mds_to_html(&mds), expect.to_string());
     }
 
     #[test]
     fn test_item_to_html() {
         let words = words!(normal_word!("item"));
-        let items = items!();
-        let item = Item(words, items);
-        assert_eq!(item_to_html(&item), "<li>item</li>".to_string());
+        let item = Item(words, vec!());
+        assert_eq!(item_to_html(&item, &mut Context::default()), "<li>item</li>".to_string());
 
         let words = words!(normal_word!("parent"));
         let words0 = words!(normal_word!("item"));
-        let item0 = Item(words0, items!());
+        let item0 = Item(words0, vec!());
         let words1 = words!(normal_word!("item1"));
-        let item1 = Item(words1, items!());
+        let item1 = Item(words1, vec!());
         let items = items!(item0, item1);
-        let item = Item(words, items);
+        let item = Item(words, vec!(Md::List(items)));
         let expect = "<li>parent\n<ul>\n<li>item</li>\n<li>item1</li>\n</ul>\n</li>".to_string();
-        assert_eq!(item_to_html(&item), expect);
+        assert_eq!(item_to_html(&item, &mut Context::default()), expect);
     }
 
     #[test]
     fn test_items_to_html() {
         let words0 = words!(normal_word!("item"));
-        let item0 = Item(words0, items!());
+        let item0 = Item(words0, vec!());
         let words1 = words!(normal_word!("item1"));
-        let item1 = Item(words1, items!());
+        let item1 = Item(words1, vec!());
         let items = items!(item0, item1);
-        assert_eq!(items_to_html(&items), "<ul>\n<li>item</li>\n<li>item1</li>\n</ul>\n".to_string());
+        assert_eq!(items_to_html(&items, &mut Context::default()), "<ul>\n<li>item</li>\n<li>item1</li>\n</ul>\n".to_string());
 
         let words = words!(normal_word!("parent"));
         let words0 = words!(normal_word!("item"));
-        let item0 = Item(words0, items!());
+        let item0 = Item(words0, vec!());
         let words1 = words!(normal_word!("item1"));
-        let item1 = Item(words1, items!());
+        let item1 = Item(words1, vec!());
         let items = items!(item0, item1);
-        let item0 = Item(words, items);
+        let item0 = Item(words, vec!(Md::List(items)));
     
         let words = words!(normal_word!("parent"));
         let words0 = words!(normal_word!("item"));
-        let item2 = Item(words0, items!());
+        let item2 = Item(words0, vec!());
         let words1 = words!(normal_word!("item1"));
-        let item3 = Item(words1, items!());
+        let item3 = Item(words1, vec!());
         let items = items!(item2, item3);
-        let item1 = Item(words, items);
+        let item1 = Item(words, vec!(Md::List(items)));
         let items = items!(item0, item1);
         let expect = "<ul>\n<li>parent\n<ul>\n<li>item</li>\n<li>item1</li>\n</ul>\n</li>\n<li>parent\n<ul>\n<li>item</li>\n<li>item1</li>\n</ul>\n</li>\n</ul>\n".to_string();
-        assert_eq!(items_to_html(&items), expect);
+        assert_eq!(items_to_html(&items, &mut Context::default()), expect);
 
+        let words0 = words!(normal_word!("item"));
+        let item0 = Item(words0, vec!());
+        let items = Items(vec!(item0), ListKind::Ordered(1));
+        assert_eq!(items_to_html(&items, &mut Context::default()), "<ol>\n<li>item</li>\n</ol>\n".to_string());
+
+        let words0 = words!(normal_word!("item"));
+        let item0 = Item(words0, vec!());
+        let items = Items(vec!(item0), ListKind::Ordered(4));
+        assert_eq!(items_to_html(&items, &mut Context::default()), "<ol start=\"4\">\n<li>item</li>\n</ol>\n".to_string());
     }
 }
\ No newline at end of file
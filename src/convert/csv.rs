@@ -0,0 +1,156 @@
+use crate::parser::parser::*;
+use super::text::words_to_text;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum CsvError {
+    Empty,
+    // 1-based line where the unclosed quoted field starts.
+    UnclosedQuote(usize),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsvError::Empty => write!(f, "csv has no header row"),
+            CsvError::UnclosedQuote(line) => write!(f, "unclosed quote in the field starting on line {}", line),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+// Quotes a field only when it holds a delimiter, a quote, a line break or edge whitespace.
+fn field(text: &str) -> String {
+    let quoted = text.contains([',', '"', '\n', '\r']) || text.trim() != text;
+    if quoted { format!("\"{}\"", text.replace('"', "\"\"")) } else { text.to_string() }
+}
+
+fn record_to_csv(record: &Record) -> String {
+    let fields: Vec<String> = record.0.iter().enumerate().map(|(i, words)| match record.1.get(i) {
+        Some(Span::Merged) => "".to_string(),
+        _ => field(&words_to_text(words)),
+    })
+    .collect();
+    fields.join(",")
+}
+
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut rows = vec!();
+    let mut row = vec!();
+    let mut field = String::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    let mut quoted = None;
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (Some(_), '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            },
+            (Some(_), '"') => quoted = None,
+            (Some(_), c) => {
+                if c == '\n' { line += 1 }
+                field.push(c);
+            },
+            (None, '"') if field.is_empty() => quoted = Some(line),
+            (None, ',') => row.push(std::mem::take(&mut field)),
+            (None, '\r') if chars.peek() == Some(&'\n') => {},
+            (None, '\n') => {
+                line += 1;
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            },
+            (None, c) => field.push(c),
+        }
+    }
+    if let Some(line) = quoted { return Err(CsvError::UnclosedQuote(line)) }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+// Line breaks inside a field would end the table row, so they become spaces.
+fn cell(text: &str) -> Words<'static> {
+    Words(vec!(Word::Normal(text.replace(['\r', '\n'], " ").into())))
+}
+
+impl Table<'static> {
+    /// A table whose first row is the header; every column is left aligned.
+    pub fn from_rows(rows: Vec<Vec<String>>) -> Self {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let align = (0..columns).map(|_| Align::Left).collect();
+        Table::with_align(rows, align)
+    }
+
+    fn with_align(rows: Vec<Vec<String>>, mut align: Vec<Align>) -> Self {
+        let mut records = rows.iter().map(|row| Record(row.iter().map(|text| cell(text)).collect(), vec!()));
+        let header = records.next().unwrap_or(Record(vec!(), vec!()));
+        let records: Vec<Record> = records.collect();
+        let columns = records.iter().map(|record| record.0.len()).chain([header.0.len()]).max().unwrap_or(0);
+        while align.len() < columns { align.push(Align::Left) }
+        Table{ header, align, records, caption: None }
+    }
+
+    /// Reads CSV with a header row; columns without an entry in `align` are left aligned.
+    pub fn from_csv(text: &str, align: Vec<Align>) -> Result<Self, CsvError> {
+        let rows = parse_csv(text)?;
+        if rows.is_empty() { return Err(CsvError::Empty) }
+        Ok(Table::with_align(rows, align))
+    }
+
+    /// The header and rows as CSV, with the cells reduced to plain text.
+    pub fn to_csv(&self) -> String {
+        let lines: Vec<String> = [&self.header].into_iter().chain(&self.records).map(record_to_csv).collect();
+        format!("{}\n", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::options::ParserOptions;
+    use crate::format::format::to_markdown;
+
+    fn table<'a>(text: &'a str, options: &ParserOptions) -> Table<'a> {
+        match parse_with(text, options).remove(0) {
+            Md::Table(table) => *table,
+            md => panic!("expected a table, got {:?}", md),
+        }
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let table = table("| Name | Note |\n| - | - |\n| **Ann** | says \"hi\", twice |\n| Bob | |", &ParserOptions::default());
+        assert_eq!(table.to_csv(), "Name,Note\nAnn,\"says \"\"hi\"\", twice\"\nBob,\n");
+    }
+
+    #[test]
+    fn test_spans_to_csv() {
+        let options = ParserOptions{ table_spans: true, ..Default::default() };
+        let table = table("| A | B |\n| - | - |\n| a | b |\n| ^ | c |", &options);
+        assert_eq!(table.to_csv(), "A,B\na,b\n,c\n");
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let csv = "Name,Note\r\nAnn,\"says \"\"hi\"\", twice\"\nBob,\"two\nlines\"\n";
+        let table = Table::from_csv(csv, vec!(Align::Right)).unwrap();
+        let expect = "| Name | Note |\n| --: | --- |\n| Ann | says \"hi\", twice |\n| Bob | two lines |";
+        assert_eq!(to_markdown(&[Md::Table(Box::new(table))]), expect);
+
+        let table = Table::from_csv("a,b\n1,2", vec!()).unwrap();
+        assert_eq!(table.to_csv(), "a,b\n1,2\n");
+        assert_eq!(Table::from_csv("", vec!()), Err(CsvError::Empty));
+        assert_eq!(Table::from_csv("a\n\"b,\nc", vec!()), Err(CsvError::UnclosedQuote(2)));
+    }
+
+    #[test]
+    fn test_from_rows() {
+        let rows = vec!(vec!("A".to_string(), "B".to_string()), vec!("1".to_string(), "2".to_string()));
+        let table = Table::from_rows(rows);
+        assert_eq!(to_markdown(&[Md::Table(Box::new(table))]), "| A | B |\n| --- | --- |\n| 1 | 2 |");
+    }
+}
@@ -0,0 +1,210 @@
+use crate::parser::parser::*;
+use super::text::words_to_text;
+use super::renderer::{escape_attribute, escape_html};
+use super::toc::{toc, toc_to_html};
+use super::options::Direction;
+use crate::extract::summary;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentOptions {
+    pub lang: String,
+    // Overrides the title taken from the front matter or the first heading.
+    pub title: Option<String>,
+    pub css: Vec<String>,
+    // A page to put the html in instead of the built-in one, see `wrap_document`.
+    pub template: Option<String>,
+    // The `dir` of the whole page, e.g. `Rtl` for a document in Arabic.
+    pub dir: Option<Direction>,
+    // Overrides the description taken from the front matter or the first paragraph.
+    pub description: Option<String>,
+    // Where the page is published, for `og:url`.
+    pub url: Option<String>,
+    // Adds Open Graph `og:` tags for link previews.
+    pub open_graph: bool,
+}
+
+impl Default for DocumentOptions {
+    fn default() -> Self {
+        DocumentOptions{
+            lang: "en".to_string(),
+            title: None,
+            css: vec!(),
+            template: None,
+            dir: None,
+            description: None,
+            url: None,
+            open_graph: true,
+        }
+    }
+}
+
+// The `key: value` lines of the front matter, with quotes around a value removed.
+pub fn front_matter_fields(mds: &[Md]) -> Vec<(String, String)> {
+    let Some(text) = mds.iter().find_map(|md| match md {
+        Md::FrontMatter(_, text) => Some(text),
+        _ => None,
+    }) else { return vec!() };
+    text.lines().filter_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        let value = value.trim();
+        let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+        Some((key.trim().to_string(), value.to_string()))
+    })
+    .collect()
+}
+
+// The front matter `title:` if there is one, otherwise the first heading's text.
+pub fn title(mds: &[Md]) -> Option<String> {
+    let front_matter = front_matter_fields(mds).into_iter().find(|(key, _)| key == "title");
+    front_matter.map(|(_, title)| title).or_else(|| mds.iter().find_map(|md| match md {
+        Md::Heading(_, words) => Some(words_to_text(words)),
+        _ => None,
+    }))
+}
+
+// The front matter `description:` if there is one, otherwise the start of the first paragraph.
+pub fn description(mds: &[Md]) -> Option<String> {
+    let front_matter = front_matter_fields(mds).into_iter().find(|(key, _)| key == "description");
+    front_matter.map(|(_, description)| description).or_else(|| summary(mds, 30))
+}
+
+// The description and Open Graph `<meta>` tags, one per line.
+fn meta_tags(mds: &[Md], options: &DocumentOptions, title: &str) -> String {
+    let description = options.description.clone().or_else(|| description(mds));
+    let mut tags = vec!();
+    if let Some(description) = &description {
+        tags.push(("name", "description", description.clone()));
+    }
+    if options.open_graph {
+        let image = front_matter_fields(mds).into_iter().find(|(key, _)| key == "image").map(|(_, image)| image);
+        let properties = [
+            ("og:title", Some(title.to_string()).filter(|title| !title.is_empty())),
+            ("og:description", description),
+            ("og:type", Some("article".to_string())),
+            ("og:url", options.url.clone()),
+            ("og:image", image),
+        ];
+        tags.extend(properties.into_iter().filter_map(|(property, value)| Some(("property", property, value?))));
+    }
+    tags.iter()
+        .map(|(attribute, name, content)| format!("<meta {}=\"{}\" content=\"{}\">\n", attribute, name, escape_attribute(content)))
+        .collect()
+}
+
+// Replaces each `{{name}}` with its value; names without one are left out.
+fn fill(template: &str, value: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else { break };
+        filled.push_str(&rest[..start]);
+        filled.push_str(&value(rest[start + 2..start + end].trim()).unwrap_or_default());
+        rest = &rest[start + end + 2..];
+    }
+    filled + rest
+}
+
+// The page before and after the body.
+pub fn document_parts(mds: &[Md], options: &DocumentOptions, numbered: bool) -> (String, String) {
+    let title = options.title.clone().or_else(|| title(mds)).unwrap_or_default();
+    let css: String = options.css.iter()
+        .map(|href| format!("<link rel=\"stylesheet\" href=\"{}\">\n", escape_attribute(href)))
+        .collect();
+    let dir = options.dir.map_or(String::new(), |dir| format!(" dir=\"{}\"", dir.as_str()));
+    let meta = meta_tags(mds, options, &title);
+    let Some(template) = &options.template else {
+        let head = format!(
+            "<!DOCTYPE html>\n<html lang=\"{}\"{}>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n{}{}</head>\n<body>\n",
+            escape_attribute(&options.lang), dir, escape_html(&title), meta, css
+        );
+        return (head, "\n</body>\n</html>\n".to_string())
+    };
+    let fields = front_matter_fields(mds);
+    let value = |name: &str| match name {
+        "title" => Some(escape_html(&title)),
+        "lang" => Some(escape_attribute(&options.lang)),
+        "dir" => options.dir.map(|dir| dir.as_str().to_string()),
+        "css" => Some(css.clone()),
+        "meta" => Some(meta.clone()),
+        "description" => options.description.clone().or_else(|| description(mds)).map(|description| escape_attribute(&description)),
+        "toc" => Some(toc_to_html(&toc(mds), numbered)),
+        _ => fields.iter().find(|(key, _)| key == name).map(|(_, value)| escape_html(value)),
+    };
+    let (before, after) = template.split_once("{{content}}").unwrap_or((template, ""));
+    (fill(before, &value), fill(after, &value))
+}
+
+// Puts the body in a complete page: the built-in one, or `options.template` with the first
+// `{{content}}` replaced by the body. `{{title}}`, `{{lang}}`, `{{dir}}`, `{{description}}`,
+// `{{meta}}` (the description and Open Graph tags), `{{css}}` (the stylesheet links),
+// `{{toc}}` and the front matter fields by name fill the rest of the template.
+pub fn wrap_document(body: &str, mds: &[Md], options: &DocumentOptions, numbered: bool) -> String {
+    let (head, tail) = document_parts(mds, options, numbered);
+    head + body + &tail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title() {
+        assert_eq!(title(&parse("---\ntitle: \"From meta\"\n---\n# Heading")), Some("From meta".to_string()));
+        assert_eq!(title(&parse("Intro\n## First *one*\n# Second")), Some("First one".to_string()));
+        assert_eq!(title(&parse("no headings")), None);
+    }
+
+    #[test]
+    fn test_wrap_document() {
+        let mds = parse("# A & B");
+        let options = DocumentOptions{ css: vec!("style.css".to_string()), ..Default::default() };
+        let expect = "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>A &amp; B</title>\n<meta property=\"og:title\" content=\"A &amp; B\">\n<meta property=\"og:type\" content=\"article\">\n<link rel=\"stylesheet\" href=\"style.css\">\n</head>\n<body>\n<p>x</p>\n</body>\n</html>\n";
+        assert_eq!(wrap_document("<p>x</p>", &mds, &options, false), expect);
+
+        let options = DocumentOptions{ lang: "ar".to_string(), dir: Some(Direction::Rtl), ..Default::default() };
+        assert!(wrap_document("", &mds, &options, false).contains("<html lang=\"ar\" dir=\"rtl\">"));
+        let options = DocumentOptions{ dir: Some(Direction::Auto), template: Some("<html dir=\"{{dir}}\">".to_string()), ..Default::default() };
+        assert_eq!(wrap_document("", &mds, &options, false), "<html dir=\"auto\">");
+    }
+
+    #[test]
+    fn test_meta_tags() {
+        let mds = parse("---\ntitle: Guide\ndescription: All about \"B\"\nimage: cover.png\n---\n# Guide\nFirst paragraph.");
+        let options = DocumentOptions{ url: Some("https://example.com/guide".to_string()), ..Default::default() };
+        let (head, _) = document_parts(&mds, &options, false);
+        let expect = "<title>Guide</title>\n\
+            <meta name=\"description\" content=\"All about &quot;B&quot;\">\n\
+            <meta property=\"og:title\" content=\"Guide\">\n\
+            <meta property=\"og:description\" content=\"All about &quot;B&quot;\">\n\
+            <meta property=\"og:type\" content=\"article\">\n\
+            <meta property=\"og:url\" content=\"https://example.com/guide\">\n\
+            <meta property=\"og:image\" content=\"cover.png\">\n</head>";
+        assert!(head.contains(expect), "{}", head);
+
+        let mds = parse("# Notes\nThe first paragraph sums it up.");
+        assert_eq!(description(&mds), Some("The first paragraph sums it up.".to_string()));
+        let options = DocumentOptions{ open_graph: false, ..Default::default() };
+        let (head, _) = document_parts(&mds, &options, false);
+        assert!(head.contains("<title>Notes</title>\n<meta name=\"description\" content=\"The first paragraph sums it up.\">\n</head>"));
+        let options = DocumentOptions{
+            description: Some("Set".to_string()),
+            template: Some("{{description}}|{{meta}}".to_string()),
+            open_graph: false,
+            ..Default::default()
+        };
+        assert_eq!(wrap_document("", &mds, &options, false), "Set|<meta name=\"description\" content=\"Set\">\n");
+    }
+
+    #[test]
+    fn test_template() {
+        let mds = parse("---\ntitle: Notes\nauthor: \"A <B>\"\n---\n# One\n## Two");
+        let template = "<html lang=\"{{lang}}\"><title>{{ title }}</title>{{css}}<body>{{toc}}<main>{{content}}</main>\
+            <footer>{{author}}{{missing}} {{</footer>";
+        let options = DocumentOptions{ css: vec!("a.css".to_string()), template: Some(template.to_string()), ..Default::default() };
+        let expect = "<html lang=\"en\"><title>Notes</title><link rel=\"stylesheet\" href=\"a.css\">\n<body>\
+            <nav class=\"toc\">\n<ul>\n<li><a href=\"#one\">One</a>\n<ul>\n<li><a href=\"#two\">Two</a></li>\n</ul>\n</li>\n</ul>\n</nav>\
+            <main><p>x</p></main><footer>A &lt;B&gt; {{</footer>";
+        assert_eq!(wrap_document("<p>x</p>", &mds, &options, false), expect);
+        assert_eq!(front_matter_fields(&parse("text")), vec!());
+    }
+}
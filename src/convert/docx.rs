@@ -0,0 +1,315 @@
+use crate::parser::parser::*;
+use super::text::{emoji_to_text, words_to_text};
+use std::io::{self, Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+const CONTENT_TYPES: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+    "<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">",
+    "<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>",
+    "<Default Extension=\"xml\" ContentType=\"application/xml\"/>",
+    "<Override PartName=\"/word/document.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>",
+    "<Override PartName=\"/word/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>",
+    "</Types>",
+);
+
+const RELS: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+    "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    "<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"word/document.xml\"/>",
+    "</Relationships>",
+);
+
+const DOCUMENT_RELS: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+    "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    "<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" Target=\"styles.xml\"/>",
+    "</Relationships>",
+);
+
+const NAMESPACE: &str = "xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\"";
+
+// Styles named like Word's built-in ones, so documents pick up the user's theme when restyled.
+fn styles_xml() -> String {
+    let mut styles = String::new();
+    for (level, size) in [(1, 32), (2, 28), (3, 26), (4, 24), (5, 22), (6, 22)] {
+        styles.push_str(&format!(
+            "<w:style w:type=\"paragraph\" w:styleId=\"Heading{0}\"><w:name w:val=\"heading {0}\"/>\
+             <w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>\
+             <w:pPr><w:keepNext/><w:spacing w:before=\"240\" w:after=\"60\"/><w:outlineLvl w:val=\"{1}\"/></w:pPr>\
+             <w:rPr><w:b/><w:sz w:val=\"{2}\"/></w:rPr></w:style>",
+            level, level - 1, size
+        ));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:styles {}>\
+         <w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/><w:qFormat/></w:style>\
+         {}\
+         <w:style w:type=\"paragraph\" w:styleId=\"Quote\"><w:name w:val=\"Quote\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:pPr><w:ind w:left=\"720\"/></w:pPr><w:rPr><w:i/></w:rPr></w:style>\
+         <w:style w:type=\"paragraph\" w:styleId=\"Caption\"><w:name w:val=\"caption\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:rPr><w:i/><w:sz w:val=\"18\"/></w:rPr></w:style>\
+         <w:style w:type=\"paragraph\" w:styleId=\"SourceCode\"><w:name w:val=\"Source Code\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:pPr><w:spacing w:after=\"0\"/></w:pPr><w:rPr><w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\"/></w:rPr></w:style>\
+         <w:style w:type=\"table\" w:styleId=\"TableGrid\"><w:name w:val=\"Table Grid\"/><w:tblPr><w:tblBorders>\
+         <w:top w:val=\"single\" w:sz=\"4\"/><w:left w:val=\"single\" w:sz=\"4\"/><w:bottom w:val=\"single\" w:sz=\"4\"/>\
+         <w:right w:val=\"single\" w:sz=\"4\"/><w:insideH w:val=\"single\" w:sz=\"4\"/><w:insideV w:val=\"single\" w:sz=\"4\"/>\
+         </w:tblBorders></w:tblPr></w:style>\
+         </w:styles>",
+        NAMESPACE, styles
+    )
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Formatting inherited by the runs inside nested emphasis.
+#[derive(Clone, Copy, Default)]
+struct Format {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strike: bool,
+    highlight: bool,
+    monospace: bool,
+    align: Option<&'static str>,
+}
+
+fn run(text: &str, format: Format) -> String {
+    let mut properties = String::new();
+    if format.monospace { properties.push_str("<w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\"/>") }
+    if format.bold { properties.push_str("<w:b/>") }
+    if format.italic { properties.push_str("<w:i/>") }
+    if format.strike { properties.push_str("<w:strike/>") }
+    if format.underline { properties.push_str("<w:u w:val=\"single\"/>") }
+    if format.highlight { properties.push_str("<w:highlight w:val=\"yellow\"/>") }
+    if let Some(align) = format.align { properties.push_str(&format!("<w:vertAlign w:val=\"{}\"/>", align)) }
+    if !properties.is_empty() { properties = format!("<w:rPr>{}</w:rPr>", properties) }
+    format!("<w:r>{}<w:t xml:space=\"preserve\">{}</w:t></w:r>", properties, escape_xml(text))
+}
+
+fn words_to_runs(words: &Words, format: Format) -> String {
+    words.0.iter().map(|word| match word {
+        Word::Normal(text) => run(text, format),
+        Word::Entity(_, text) => run(text, format),
+        Word::Emoji(name) => run(&emoji_to_text(name), format),
+        Word::Image(alt, ..) => run(alt, Format{ italic: true, ..format }),
+        Word::FootnoteRef(label) => run(label, Format{ align: Some("superscript"), ..format }),
+        Word::Task(checked) => run(if *checked { "☑" } else { "☐" }, format),
+        Word::Kbd(key) => run(key, Format{ monospace: true, ..format }),
+        Word::Custom(_, source) => run(source, format),
+        Word::Bold(words) => words_to_runs(words, Format{ bold: true, ..format }),
+        Word::Italic(words) => words_to_runs(words, Format{ italic: true, ..format }),
+        Word::Underline(words) | Word::Insert(words) => words_to_runs(words, Format{ underline: true, ..format }),
+        Word::StrikeThough(words) => words_to_runs(words, Format{ strike: true, ..format }),
+        Word::Highlight(words) => words_to_runs(words, Format{ highlight: true, ..format }),
+        Word::Subscript(words) => words_to_runs(words, Format{ align: Some("subscript"), ..format }),
+        Word::Superscript(words) => words_to_runs(words, Format{ align: Some("superscript"), ..format }),
+        // A HYPERLINK field, which needs no relationship entry unlike `w:hyperlink`.
+        Word::Link(words, url) => format!(
+            "<w:r><w:fldChar w:fldCharType=\"begin\"/></w:r><w:r><w:instrText xml:space=\"preserve\"> HYPERLINK \"{}\" </w:instrText></w:r><w:r><w:fldChar w:fldCharType=\"separate\"/></w:r>{}<w:r><w:fldChar w:fldCharType=\"end\"/></w:r>",
+            escape_xml(url), words_to_runs(words, Format{ underline: true, ..format })
+        ),
+    })
+    .collect()
+}
+
+fn paragraph(properties: &str, runs: &str) -> String {
+    if properties.is_empty() {
+        format!("<w:p>{}</w:p>", runs)
+    } else {
+        format!("<w:p><w:pPr>{}</w:pPr>{}</w:p>", properties, runs)
+    }
+}
+
+fn style(name: &str) -> String {
+    format!("<w:pStyle w:val=\"{}\"/>", name)
+}
+
+fn align_to_docx(align: Option<&Align>) -> &'static str {
+    match align {
+        Some(Align::Center) => "<w:jc w:val=\"center\"/>",
+        Some(Align::Right) => "<w:jc w:val=\"right\"/>",
+        _ => "",
+    }
+}
+
+// Cells under a row span continue its vertical merge; cells beside a column span are left out.
+fn table_to_docx(header: &Record, records: &[Record], aligns: &[Align]) -> String {
+    let rows: Vec<&Record> = [header].into_iter().chain(records).collect();
+    let columns = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+    let mut below = vec!(vec!(0usize; columns); rows.len());
+    for (r, row) in rows.iter().enumerate() {
+        for (i, span) in row.1.iter().enumerate() {
+            let Span::Cell(cols, spanned) = span else { continue };
+            for covered in below.iter_mut().skip(r + 1).take(spanned.saturating_sub(1)) {
+                covered[i] = *cols;
+            }
+        }
+    }
+
+    let mut xml = String::from("<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/><w:tblW w:w=\"0\" w:type=\"auto\"/></w:tblPr><w:tblGrid>");
+    xml.push_str(&"<w:gridCol/>".repeat(columns));
+    xml.push_str("</w:tblGrid>");
+    for (r, row) in rows.iter().enumerate() {
+        xml.push_str("<w:tr>");
+        if r == 0 { xml.push_str("<w:trPr><w:tblHeader/></w:trPr>") }
+        let format = Format{ bold: r == 0, ..Default::default() };
+        for (i, words) in row.0.iter().enumerate() {
+            let (cols, properties, content) = match row.1.get(i) {
+                Some(Span::Merged) if below[r][i] > 0 => (below[r][i], "<w:vMerge/>", "".to_string()),
+                Some(Span::Merged) => continue,
+                Some(Span::Cell(cols, rows)) if *rows > 1 => (*cols, "<w:vMerge w:val=\"restart\"/>", words_to_runs(words, format)),
+                Some(Span::Cell(cols, _)) => (*cols, "", words_to_runs(words, format)),
+                None => (1, "", words_to_runs(words, format)),
+            };
+            let span = if cols > 1 { format!("<w:gridSpan w:val=\"{}\"/>", cols) } else { "".to_string() };
+            xml.push_str(&format!("<w:tc><w:tcPr>{}{}</w:tcPr>", span, properties));
+            xml.push_str(&paragraph(align_to_docx(aligns.get(i)), &content));
+            xml.push_str("</w:tc>");
+        }
+        xml.push_str("</w:tr>");
+    }
+    xml.push_str("</w:tbl>");
+    xml
+}
+
+// Without a numbering part, list markers are written into the text and nesting is an indent.
+fn items_to_docx(items: &Items, depth: usize) -> String {
+    let indent = format!("<w:ind w:left=\"{}\" w:hanging=\"360\"/>", 720 * (depth + 1));
+    let mut xml = String::new();
+    for (i, item) in items.0.iter().enumerate() {
+        let marker = match items.1 {
+            ListKind::Bullet(_) => "•\t".to_string(),
+            ListKind::Ordered(start) => format!("{}.\t", start.saturating_add(i)),
+        };
+        let runs = run(&marker, Format::default()) + &words_to_runs(&item.0, Format::default());
+        xml.push_str(&paragraph(&indent, &runs));
+        for md in &item.1 {
+            match md {
+                Md::List(items) => xml.push_str(&items_to_docx(items, depth + 1)),
+                md => xml.push_str(&md_to_docx(md, None)),
+            }
+        }
+    }
+    xml
+}
+
+fn toc_field() -> String {
+    concat!(
+        "<w:p><w:r><w:fldChar w:fldCharType=\"begin\"/></w:r>",
+        "<w:r><w:instrText xml:space=\"preserve\"> TOC \\o \"1-3\" \\h \\z \\u </w:instrText></w:r>",
+        "<w:r><w:fldChar w:fldCharType=\"separate\"/></w:r>",
+        "<w:r><w:fldChar w:fldCharType=\"end\"/></w:r></w:p>",
+    ).to_string()
+}
+
+// `paragraph_style` carries a quote's style down to the sentences inside it.
+fn md_to_docx(md: &Md, paragraph_style: Option<&str>) -> String {
+    let bold = Format{ bold: true, ..Default::default() };
+    match md {
+        Md::Heading(size, words) => paragraph(&style(&format!("Heading{}", size.min(&6))), &words_to_runs(words, Format::default())),
+        Md::Sentence(words) if words_to_text(words).is_empty() => "".to_string(),
+        Md::Sentence(words) => paragraph(&paragraph_style.map(style).unwrap_or_default(), &words_to_runs(words, Format::default())),
+        Md::Table(table) => {
+            let mut xml = String::new();
+            if let Some(caption) = &table.caption {
+                xml.push_str(&paragraph(&style("Caption"), &words_to_runs(caption, Format::default())));
+            }
+            xml.push_str(&table_to_docx(&table.header, &table.records, &table.align));
+            xml
+        },
+        Md::LooseTable(table) => table_to_docx(&table.header, &table.rows, &[]),
+        Md::List(items) => items_to_docx(items, 0),
+        Md::Container(name, mds) => paragraph("", &run(name, bold)) + &blocks_to_docx(mds, paragraph_style),
+        Md::Details(_, summary, mds) => paragraph("", &words_to_runs(summary, bold)) + &blocks_to_docx(mds, paragraph_style),
+        Md::Quote(mds) => blocks_to_docx(mds, Some("Quote")),
+        Md::Code(_, code, _) => code.lines().map(|line| paragraph(&style("SourceCode"), &run(line, Format::default()))).collect(),
+        Md::Toc => toc_field(),
+        Md::Footnote(label, words) => {
+            let label = run(label, Format{ align: Some("superscript"), ..Default::default() });
+            paragraph("", &format!("{}{}{}", label, run(" ", Format::default()), words_to_runs(words, Format::default())))
+        },
+        Md::Abbreviation(..) | Md::FrontMatter(..) => "".to_string(),
+        Md::Raw(text) | Md::Custom(_, text) => text.lines().map(|line| paragraph("", &run(line, Format::default()))).collect(),
+    }
+}
+
+fn blocks_to_docx(mds: &[Md], paragraph_style: Option<&str>) -> String {
+    mds.iter().map(|md| md_to_docx(md, paragraph_style)).collect()
+}
+
+/// The `word/document.xml` part for the markdown.
+pub fn document_xml(mds: &[Md]) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:document {}><w:body>{}<w:sectPr/></w:body></w:document>",
+        NAMESPACE, blocks_to_docx(mds, None)
+    )
+}
+
+/// Renders markdown as the bytes of a `.docx` file.
+pub fn to_docx(mds: &[Md]) -> io::Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(vec!()));
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES.to_string()),
+        ("_rels/.rels", RELS.to_string()),
+        ("word/_rels/document.xml.rels", DOCUMENT_RELS.to_string()),
+        ("word/styles.xml", styles_xml()),
+        ("word/document.xml", document_xml(mds)),
+    ];
+    for (name, xml) in parts {
+        zip.start_file(name, SimpleFileOptions::default())?;
+        zip.write_all(xml.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    fn body(mds: &[Md]) -> String {
+        let xml = document_xml(mds);
+        let start = xml.find("<w:body>").unwrap() + "<w:body>".len();
+        let end = xml.find("<w:sectPr/>").unwrap();
+        xml[start..end].to_string()
+    }
+
+    #[test]
+    fn test_paragraphs_to_docx() {
+        let mds = parse("## Title\n**a** ~~b~~ <x>");
+        let expect = concat!(
+            "<w:p><w:pPr><w:pStyle w:val=\"Heading2\"/></w:pPr><w:r><w:t xml:space=\"preserve\">Title</w:t></w:r></w:p>",
+            "<w:p><w:r><w:rPr><w:b/></w:rPr><w:t xml:space=\"preserve\">a</w:t></w:r>",
+            "<w:r><w:t xml:space=\"preserve\"> </w:t></w:r>",
+            "<w:r><w:rPr><w:strike/></w:rPr><w:t xml:space=\"preserve\">b</w:t></w:r>",
+            "<w:r><w:t xml:space=\"preserve\"> &lt;x&gt;</w:t></w:r></w:p>",
+        );
+        assert_eq!(body(&mds), expect);
+    }
+
+    #[test]
+    fn test_table_to_docx() {
+        let xml = body(&parse("| A | B |\n|-|:-:|\n| a | b |"));
+        assert!(xml.starts_with("<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/>"));
+        assert!(xml.contains("<w:tblGrid><w:gridCol/><w:gridCol/></w:tblGrid>"));
+        assert!(xml.contains("<w:tr><w:trPr><w:tblHeader/></w:trPr>"));
+        assert!(xml.contains("<w:p><w:pPr><w:jc w:val=\"center\"/></w:pPr><w:r><w:t xml:space=\"preserve\">b</w:t></w:r></w:p>"));
+    }
+
+    #[test]
+    fn test_to_docx() {
+        let bytes = to_docx(&parse("# Hello")).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, vec!("[Content_Types].xml", "_rels/.rels", "word/_rels/document.xml.rels", "word/document.xml", "word/styles.xml"));
+        let mut document = String::new();
+        archive.by_name("word/document.xml").unwrap().read_to_string(&mut document).unwrap();
+        assert_eq!(document, document_xml(&parse("# Hello")));
+    }
+}
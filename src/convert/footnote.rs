@@ -0,0 +1,52 @@
+use crate::parser::parser::*;
+use crate::parser::visit::{walk, Visitor};
+
+#[derive(Default)]
+struct Collector<'a> {
+    references: Vec<(String, usize)>,
+    definitions: Vec<(&'a str, &'a Words<'a>)>,
+}
+
+impl<'a> Visitor<'a> for Collector<'a> {
+    fn visit_footnote(&mut self, label: &'a str, words: &'a Words<'a>) {
+        if !self.definitions.iter().any(|(seen, _)| *seen == label) {
+            self.definitions.push((label, words));
+        }
+        self.visit_words(words)
+    }
+
+    fn visit_footnote_ref(&mut self, label: &'a str) {
+        match self.references.iter_mut().find(|(seen, _)| seen == label) {
+            Some((_, count)) => *count += 1,
+            None => self.references.push((label.to_string(), 1)),
+        }
+    }
+}
+
+// The notes that are both defined and referenced, numbered by their first reference, with
+// the definition and how many times the note is referenced. The first definition of a label wins.
+pub fn footnotes<'a>(mds: &'a [Md<'a>]) -> Vec<(String, &'a Words<'a>, usize)> {
+    let mut collector = Collector::default();
+    walk(&mut collector, mds);
+    let Collector{ references, definitions } = collector;
+    references.into_iter().filter_map(|(label, count)| {
+        let (_, words) = definitions.iter().find(|(defined, _)| *defined == label)?;
+        Some((label, *words, count))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normal_word;
+
+    #[test]
+    fn test_footnotes() {
+        let mds = parse("See[^b] and[^a].\n- again[^b]\n[^a]: First\n[^b]: Second\n[^c]: Unused\n[^a]: Ignored\nMissing[^d]");
+        let notes = footnotes(&mds);
+        let labels: Vec<(&str, usize)> = notes.iter().map(|(label, _, count)| (label.as_str(), *count)).collect();
+        assert_eq!(labels, vec!(("b", 2), ("a", 1)));
+        assert_eq!(notes[1].1, &Words(vec!(normal_word!("First"))));
+    }
+}
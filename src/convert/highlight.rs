@@ -0,0 +1,91 @@
+use super::renderer::{language_class, HtmlRenderer, Renderer};
+
+pub trait Highlighter {
+    // Highlighted html for the inside of `<code>`, or `None` to fall back to plain
+    // escaped code, e.g. for an unknown language.
+    fn highlight(&self, lang: &str, code: &str) -> Option<String>;
+}
+
+/// Renders code blocks through a [`Highlighter`] and everything else as html.
+pub struct Highlighted<H>(pub H);
+
+impl<H: Highlighter> Renderer for Highlighted<H> {
+    fn code(&self, lang: &str, code: &str) -> String {
+        match self.0.highlight(lang, code) {
+            Some(html) => format!("<pre><code{}>{}</code></pre>", language_class(lang), html),
+            None => HtmlRenderer.code(lang, code),
+        }
+    }
+}
+
+#[cfg(feature = "highlight")]
+pub use self::syntect_highlighter::SyntectHighlighter;
+
+#[cfg(feature = "highlight")]
+mod syntect_highlighter {
+    use super::Highlighter;
+    use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+    use syntect::parsing::SyntaxSet;
+    use syntect::util::LinesWithEndings;
+
+    // Emits `<span class="hl-...">` tokens, to be styled with a syntect css theme.
+    pub struct SyntectHighlighter {
+        syntaxes: SyntaxSet,
+    }
+
+    impl Default for SyntectHighlighter {
+        fn default() -> Self {
+            SyntectHighlighter{ syntaxes: SyntaxSet::load_defaults_newlines() }
+        }
+    }
+
+    impl Highlighter for SyntectHighlighter {
+        fn highlight(&self, lang: &str, code: &str) -> Option<String> {
+            if lang.is_empty() { return None }
+            let syntax = self.syntaxes.find_syntax_by_token(lang)?;
+            let style = ClassStyle::SpacedPrefixed{ prefix: "hl-" };
+            let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &self.syntaxes, style);
+            for line in LinesWithEndings::from(code) {
+                generator.parse_html_for_line_which_includes_newline(line).ok()?;
+            }
+            Some(generator.finalize())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::convert::mds_to_html_with_renderer;
+    use crate::convert::options::RenderOptions;
+    use crate::parser::parser::parse;
+
+    struct Upper;
+
+    impl Highlighter for Upper {
+        fn highlight(&self, lang: &str, code: &str) -> Option<String> {
+            lang.starts_with("shout").then(|| format!("<span>{}</span>", code.to_uppercase()))
+        }
+    }
+
+    #[test]
+    fn test_highlighted() {
+        let mds = parse("```shout\nhi\n```\n```rust\na<b\n```");
+        let expect = "<pre><code class=\"language-shout\"><span>HI</span></code></pre>\n<pre><code class=\"language-rust\">a&lt;b</code></pre>";
+        assert_eq!(mds_to_html_with_renderer(&mds, &RenderOptions::default(), &Highlighted(Upper)), Ok(expect.to_string()));
+
+        let html = Highlighted(Upper).code("shout\"onclick=\"x()", "hi");
+        assert_eq!(html, "<pre><code class=\"language-shout&quot;onclick=&quot;x()\"><span>HI</span></code></pre>");
+    }
+
+    #[test]
+    #[cfg(feature = "highlight")]
+    fn test_syntect_highlighter() {
+        let highlighter = SyntectHighlighter::default();
+        let html = highlighter.highlight("rust", "fn main() {}\n").unwrap();
+        assert!(html.contains("<span class=\"hl-"));
+        assert!(html.contains("main"));
+        assert_eq!(highlighter.highlight("no-such-language", "x"), None);
+        assert_eq!(highlighter.highlight("", "x"), None);
+    }
+}
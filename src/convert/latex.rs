@@ -0,0 +1,197 @@
+use crate::parser::parser::*;
+use super::text::word_to_text;
+
+pub fn escape_latex(text: &str) -> String {
+    let mut latex = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => latex.push_str("\\textbackslash{}"),
+            '~' => latex.push_str("\\textasciitilde{}"),
+            '^' => latex.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                latex.push('\\');
+                latex.push(c);
+            },
+            _ => latex.push(c),
+        }
+    }
+    latex
+}
+
+fn command(name: &str, words: &Words) -> String {
+    format!("\\{}{{{}}}", name, words_to_latex(words))
+}
+
+// Strikethrough needs `ulem`, highlight `soul`, links `hyperref` and images `graphicx` in the
+// preamble.
+fn word_to_latex(word: &Word) -> String {
+    match word {
+        Word::Normal(_) | Word::Entity(..) | Word::Emoji(_) | Word::Custom(..) => escape_latex(&word_to_text(word)),
+        Word::FootnoteRef(label) => format!("\\textsuperscript{{{}}}", escape_latex(label)),
+        Word::Task(checked) => if *checked { "$\\boxtimes$" } else { "$\\square$" }.to_string(),
+        Word::Bold(words) => command("textbf", words),
+        Word::Italic(words) => command("emph", words),
+        Word::Underline(words) | Word::Insert(words) => command("underline", words),
+        Word::StrikeThough(words) => command("sout", words),
+        Word::Highlight(words) => command("hl", words),
+        Word::Subscript(words) => command("textsubscript", words),
+        Word::Superscript(words) => command("textsuperscript", words),
+        Word::Kbd(key) => format!("\\texttt{{{}}}", escape_latex(key)),
+        Word::Image(_, src, _) => format!("\\includegraphics{{{}}}", src),
+        Word::Link(words, url) => format!("\\href{{{}}}{{{}}}", url.replace('%', "\\%").replace('#', "\\#"), words_to_latex(words)),
+    }
+}
+
+fn words_to_latex(words: &Words) -> String {
+    words.0.iter().map(word_to_latex).collect()
+}
+
+fn align_to_latex(align: &Align) -> &'static str {
+    match align {
+        Align::Left => "l",
+        Align::Center => "c",
+        Align::Right => "r",
+    }
+}
+
+// Column spans become `\multicolumn`; cells covered by a row span are left empty.
+fn record_to_latex(record: &Record, aligns: &[&str], header: bool) -> String {
+    let mut cells = vec!();
+    let mut covered = 0;
+    for (i, words) in record.0.iter().enumerate() {
+        let cell = if header { command("textbf", words) } else { words_to_latex(words) };
+        match record.1.get(i) {
+            Some(Span::Merged) if covered > 0 => covered -= 1,
+            Some(Span::Merged) => cells.push("".to_string()),
+            Some(Span::Cell(cols, _)) if *cols > 1 => {
+                covered = cols - 1;
+                let align = aligns.get(i).unwrap_or(&"l");
+                cells.push(format!("\\multicolumn{{{}}}{{{}}}{{{}}}", cols, align, cell));
+            },
+            _ => cells.push(cell),
+        }
+    }
+    format!("{} \\\\", cells.join(" & "))
+}
+
+fn table_to_latex(header: &Record, records: &[Record], aligns: &[&str], caption: Option<&Words>) -> String {
+    let mut lines = vec!(format!("\\begin{{tabular}}{{{}}}", aligns.concat()), "\\hline".to_string());
+    lines.push(record_to_latex(header, aligns, true));
+    lines.push("\\hline".to_string());
+    lines.extend(records.iter().map(|record| record_to_latex(record, aligns, false)));
+    lines.push("\\hline".to_string());
+    lines.push("\\end{tabular}".to_string());
+    let tabular = lines.join("\n");
+    match caption {
+        Some(caption) => format!(
+            "\\begin{{table}}[h]\n\\centering\n\\caption{{{}}}\n{}\n\\end{{table}}",
+            words_to_latex(caption), tabular
+        ),
+        None => tabular,
+    }
+}
+
+fn items_to_latex(items: &Items) -> String {
+    let mut lines = vec!();
+    match items.1 {
+        ListKind::Bullet(_) => lines.push("\\begin{itemize}".to_string()),
+        ListKind::Ordered(start) => {
+            lines.push("\\begin{enumerate}".to_string());
+            if start != 1 {
+                lines.push(format!("\\setcounter{{enumi}}{{{}}}", start.saturating_sub(1)));
+            }
+        },
+    }
+    for item in &items.0 {
+        lines.push(format!("\\item {}", words_to_latex(&item.0)));
+        if !item.1.is_empty() {
+            lines.push(to_latex(&item.1));
+        }
+    }
+    match items.1 {
+        ListKind::Bullet(_) => lines.push("\\end{itemize}".to_string()),
+        ListKind::Ordered(_) => lines.push("\\end{enumerate}".to_string()),
+    }
+    lines.join("\n")
+}
+
+fn md_to_latex(md: &Md) -> String {
+    match md {
+        Md::Heading(size, words) => {
+            let section = match size {
+                1 => "section",
+                2 => "subsection",
+                3 => "subsubsection",
+                _ => "paragraph",
+            };
+            command(section, words)
+        },
+        Md::Sentence(words) => words_to_latex(words),
+        Md::Footnote(label, words) => format!("\\textsuperscript{{{}}} {}", escape_latex(label), words_to_latex(words)),
+        Md::Table(table) => {
+            let aligns: Vec<&str> = table.align.iter().map(align_to_latex).collect();
+            table_to_latex(&table.header, &table.records, &aligns, table.caption.as_ref())
+        },
+        Md::LooseTable(table) => {
+            let columns = table.rows.iter().map(|row| row.0.len()).chain([table.header.0.len()]).max().unwrap_or(0);
+            table_to_latex(&table.header, &table.rows, &vec!("l"; columns), None)
+        },
+        Md::List(items) => items_to_latex(items),
+        Md::Container(name, mds) => format!("\\paragraph{{{}}}\n{}", escape_latex(name), to_latex(mds)),
+        Md::Details(_, summary, mds) => format!("{}\n{}", command("paragraph", summary), to_latex(mds)),
+        Md::Quote(mds) => format!("\\begin{{quote}}\n{}\n\\end{{quote}}", to_latex(mds)),
+        Md::Code(_, code, _) => format!("\\begin{{verbatim}}\n{}\n\\end{{verbatim}}", code),
+        Md::Toc => "\\tableofcontents".to_string(),
+        Md::Abbreviation(..) | Md::FrontMatter(..) => "".to_string(),
+        Md::Raw(text) => format!("\\begin{{verbatim}}\n{}\n\\end{{verbatim}}", text),
+        Md::Custom(_, source) => escape_latex(source),
+    }
+}
+
+/// Renders markdown as a LaTeX body, one paragraph per block, to be included in a document.
+pub fn to_latex(mds: &[Md]) -> String {
+    mds.iter().map(md_to_latex).filter(|latex| !latex.is_empty()).collect::<Vec<String>>().join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_latex() {
+        assert_eq!(escape_latex("100% & $5_a {b} #1 ~^\\"), "100\\% \\& \\$5\\_a \\{b\\} \\#1 \\textasciitilde{}\\textasciicircum{}\\textbackslash{}");
+    }
+
+    #[test]
+    fn test_words_to_latex() {
+        let mds = parse("# Title\n### Sub\n**bold** *it* ~~gone~~");
+        assert_eq!(to_latex(&mds), "\\section{Title}\n\n\\subsubsection{Sub}\n\n\\textbf{bold} \\emph{it} \\sout{gone}");
+        assert_eq!(to_latex(&parse("Tom &amp; Jerry &copy;")), "Tom \\& Jerry ©");
+    }
+
+    #[test]
+    fn test_table_to_latex() {
+        let mds = parse("| A | B | C |\n|:-|:-:|-:|\n| a | b | c |");
+        let expect = [
+            "\\begin{tabular}{lcr}",
+            "\\hline",
+            "\\textbf{A} & \\textbf{B} & \\textbf{C} \\\\",
+            "\\hline",
+            "a & b & c \\\\",
+            "\\hline",
+            "\\end{tabular}",
+        ].join("\n");
+        assert_eq!(to_latex(&mds), expect);
+    }
+
+    #[test]
+    fn test_blocks_to_latex() {
+        let mds = parse("3. one\n4. two\n> quoted\n```rust\nlet a = 1;\n```");
+        let expect = [
+            "\\begin{enumerate}\n\\setcounter{enumi}{2}\n\\item one\n\\item two\n\\end{enumerate}",
+            "\\begin{quote}\nquoted\n\\end{quote}",
+            "\\begin{verbatim}\nlet a = 1;\n\\end{verbatim}",
+        ].join("\n\n");
+        assert_eq!(to_latex(&mds), expect);
+    }
+}
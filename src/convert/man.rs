@@ -0,0 +1,195 @@
+use crate::parser::parser::*;
+use super::document::title;
+use super::text::{word_to_text, words_to_text};
+use crate::pass::for_each_word_mut;
+use std::borrow::Cow;
+
+// Backslashes and hyphens are escaped, and lines that would read as a request get a `\&` guard.
+pub fn escape_roff(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
+
+pub(crate) fn guard(line: String) -> String {
+    if line.starts_with('.') || line.starts_with('\'') { format!("\\&{}", line) } else { line }
+}
+
+fn font(name: &str, words: &Words) -> String {
+    format!("\\f{}{}\\fP", name, words_to_man(words))
+}
+
+// Underline has no terminal form in man pages, so it reads as italic like in most pagers.
+fn word_to_man(word: &Word) -> String {
+    match word {
+        Word::Normal(_) | Word::Entity(..) | Word::Emoji(_) | Word::Custom(..) => escape_roff(&word_to_text(word)),
+        Word::FootnoteRef(label) => format!("[{}]", escape_roff(label)),
+        Word::Task(checked) => if *checked { "[x]" } else { "[ ]" }.to_string(),
+        Word::Bold(words) => font("B", words),
+        Word::Italic(words) | Word::Underline(words) | Word::Insert(words) => font("I", words),
+        Word::StrikeThough(words)
+        | Word::Highlight(words)
+        | Word::Subscript(words)
+        | Word::Superscript(words) => words_to_man(words),
+        Word::Kbd(key) => format!("\\fB{}\\fP", escape_roff(key)),
+        Word::Image(alt, ..) => format!("[{}]", escape_roff(alt)),
+        Word::Link(words, url) => format!("{} <{}>", words_to_man(words), escape_roff(url)),
+    }
+}
+
+fn words_to_man(words: &Words) -> String {
+    words.0.iter().map(word_to_man).collect()
+}
+
+// Section names are upper case. Only the text is, before it's escaped, so font escapes like
+// `\fB` keep their meaning.
+fn uppercase_to_man(words: &Words) -> String {
+    let mut heading = [Md::Heading(1, words.clone())];
+    for_each_word_mut(&mut heading, &mut |word| match word {
+        Word::Normal(text) => *text = Cow::Owned(text.to_uppercase()),
+        Word::Entity(_, text) => *text = text.to_uppercase(),
+        _ => {},
+    });
+    let [Md::Heading(_, words)] = &heading else { unreachable!("a heading stays a heading") };
+    words_to_man(words)
+}
+
+fn align_to_tbl(align: &Align) -> &'static str {
+    match align {
+        Align::Left => "l",
+        Align::Center => "c",
+        Align::Right => "r",
+    }
+}
+
+fn table_to_man(header: &Record, records: &[Record], aligns: &[&str]) -> String {
+    let record = |record: &Record| {
+        record.0.iter().map(words_to_man).collect::<Vec<String>>().join("\t")
+    };
+    let bold: Vec<String> = aligns.iter().map(|align| format!("{}b", align)).collect();
+    let mut lines = vec!(".TS".to_string(), "allbox;".to_string(), bold.join(" "));
+    lines.push(format!("{}.", aligns.join(" ")));
+    lines.push(record(header));
+    lines.extend(records.iter().map(record));
+    lines.push(".TE".to_string());
+    lines.join("\n")
+}
+
+fn items_to_man(items: &Items) -> String {
+    let mut lines = vec!();
+    for (i, item) in items.0.iter().enumerate() {
+        match items.1 {
+            ListKind::Bullet(_) => lines.push(".IP \\(bu 2".to_string()),
+            ListKind::Ordered(start) => lines.push(format!(".IP {}. 4", start.saturating_add(i))),
+        }
+        lines.push(guard(words_to_man(&item.0)));
+        if !item.1.is_empty() {
+            lines.push(format!(".RS\n{}\n.RE", blocks_to_man(&item.1)));
+        }
+    }
+    lines.join("\n")
+}
+
+fn md_to_man(md: &Md) -> String {
+    match md {
+        Md::Heading(1, words) => format!(".SH {}", uppercase_to_man(words)),
+        Md::Heading(_, words) => format!(".SS {}", words_to_man(words)),
+        Md::Sentence(words) => guard(words_to_man(words)),
+        Md::Footnote(label, words) => format!(".PP\n[{}] {}", escape_roff(label), words_to_man(words)),
+        Md::Table(table) => {
+            let aligns: Vec<&str> = table.align.iter().map(align_to_tbl).collect();
+            table_to_man(&table.header, &table.records, &aligns)
+        },
+        Md::LooseTable(table) => {
+            let columns = table.rows.iter().map(|row| row.0.len()).chain([table.header.0.len()]).max().unwrap_or(0);
+            table_to_man(&table.header, &table.rows, &vec!("l"; columns))
+        },
+        Md::List(items) => items_to_man(items),
+        Md::Container(name, mds) => format!(".PP\n\\fB{}\\fP\n.RS\n{}\n.RE", escape_roff(name), blocks_to_man(mds)),
+        Md::Details(_, summary, mds) => format!(".PP\n{}\n.RS\n{}\n.RE", font("B", summary), blocks_to_man(mds)),
+        Md::Quote(mds) => format!(".RS\n{}\n.RE", blocks_to_man(mds)),
+        Md::Code(_, code, _) => {
+            let code: Vec<String> = code.lines().map(|line| guard(escape_roff(line))).collect();
+            format!(".PP\n.RS 4\n.nf\n{}\n.fi\n.RE", code.join("\n"))
+        },
+        Md::Toc | Md::Abbreviation(..) | Md::FrontMatter(..) => "".to_string(),
+        Md::Raw(text) => {
+            let text: Vec<String> = text.lines().map(|line| guard(escape_roff(line))).collect();
+            format!(".nf\n{}\n.fi", text.join("\n"))
+        },
+        Md::Custom(_, source) => guard(escape_roff(source)),
+    }
+}
+
+// Consecutive lines share a paragraph with a `.br` between them; a blank line starts a new one.
+fn blocks_to_man(mds: &[Md]) -> String {
+    let mut lines = vec!();
+    let mut paragraph = false;
+    for md in mds {
+        match md {
+            Md::Sentence(words) if words_to_text(words).is_empty() => paragraph = false,
+            Md::Sentence(_) => {
+                lines.push(if paragraph { ".br" } else { ".PP" }.to_string());
+                lines.push(md_to_man(md));
+                paragraph = true;
+            },
+            _ => {
+                let man = md_to_man(md);
+                if !man.is_empty() { lines.push(man) }
+                paragraph = false;
+            },
+        }
+    }
+    lines.join("\n")
+}
+
+/// Renders a man page in the given section, titled after the front matter or the first heading.
+pub fn to_man(mds: &[Md], section: usize) -> String {
+    let name = title(mds).unwrap_or_default().to_uppercase();
+    format!(".TH \"{}\" {}\n{}\n", escape_roff(&name), section, blocks_to_man(mds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_roff() {
+        assert_eq!(escape_roff("a-b \\n"), "a\\-b \\en");
+        assert_eq!(guard(".hidden".to_string()), "\\&.hidden");
+    }
+
+    #[test]
+    fn test_to_man() {
+        let mds = parse("---\ntitle: mdconv\n---\n# Name\nmdconv - convert **markdown**\nto *html*\n\n## Options\n- one\n- two");
+        let expect = [
+            ".TH \"MDCONV\" 1",
+            ".SH NAME",
+            ".PP",
+            "mdconv \\- convert \\fBmarkdown\\fP",
+            ".br",
+            "to \\fIhtml\\fP",
+            ".SS Options",
+            ".IP \\(bu 2",
+            "one",
+            ".IP \\(bu 2",
+            "two",
+            "",
+        ].join("\n");
+        assert_eq!(to_man(&mds, 1), expect);
+
+        let mds = parse("# Use **this** tool");
+        assert_eq!(blocks_to_man(&mds), ".SH USE \\fBTHIS\\fP TOOL");
+    }
+
+    #[test]
+    fn test_table_to_man() {
+        let mds = parse("| A | B |\n|:-:|-:|\n| a | b |");
+        let expect = ".TS\nallbox;\ncb rb\nc r.\nA\tB\na\tb\n.TE";
+        assert_eq!(blocks_to_man(&mds), expect);
+    }
+
+    #[test]
+    fn test_code_to_man() {
+        let mds = parse("```\n.start\n```");
+        assert_eq!(blocks_to_man(&mds), ".PP\n.RS 4\n.nf\n\\&.start\n.fi\n.RE");
+    }
+}
@@ -0,0 +1,40 @@
+// Drops the line breaks the renderer puts between tags, keeping `<pre>` contents and
+// whitespace that separates inline text untouched.
+pub fn minify(html: &str) -> String {
+    let mut minified = String::with_capacity(html.len());
+    let mut pre = 0usize;
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        let starts = |tag: &str| rest.as_bytes().get(..tag.len()).is_some_and(|b| b.eq_ignore_ascii_case(tag.as_bytes()));
+        if starts("<pre") {
+            pre += 1;
+        } else if starts("</pre") {
+            pre = pre.saturating_sub(1);
+        }
+        if c.is_whitespace() && pre == 0 && minified.ends_with('>') {
+            let end = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+            let (space, after) = rest.split_at(end);
+            if space.contains('\n') && (after.is_empty() || after.starts_with('<')) {
+                rest = after;
+                continue
+            }
+        }
+        minified.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    minified.truncate(minified.trim_end_matches('\n').len());
+    minified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify() {
+        let html = "<table>\n<tr><th>A</th></tr>\n<tr><td>a</td></tr>\n</table>\n";
+        assert_eq!(minify(html), "<table><tr><th>A</th></tr><tr><td>a</td></tr></table>");
+        assert_eq!(minify("<b>a</b> <i>b</i><br />\nnext<br />\n"), "<b>a</b> <i>b</i><br />\nnext<br />");
+        assert_eq!(minify("<pre><code>a\n\n<b>\n</code></pre>\n<p>"), "<pre><code>a\n\n<b>\n</code></pre><p>");
+    }
+}
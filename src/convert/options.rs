@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use super::document::DocumentOptions;
+
+pub const TRUNCATED: &str = "<!-- truncated -->";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum LimitAction {
+    #[default]
+    Error,
+    Truncate,
+}
+
+// How void elements like `<br>` are closed; `Keep` leaves the renderer's output alone.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum VoidStyle {
+    #[default]
+    Keep,
+    Spaced,
+    Html,
+    Xhtml,
+}
+
+// A `dir` attribute value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+    Auto,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Ltr => "ltr",
+            Direction::Rtl => "rtl",
+            Direction::Auto => "auto",
+        }
+    }
+}
+
+impl std::str::FromStr for Direction {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "ltr" => Ok(Direction::Ltr),
+            "rtl" => Ok(Direction::Rtl),
+            "auto" => Ok(Direction::Auto),
+            _ => Err(format!("unknown direction `{}`, expected ltr, rtl or auto", text)),
+        }
+    }
+}
+
+// Tag choices for the generated html.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HtmlOptions {
+    // `<strong>`, `<em>`, `<del>` and `<ins>` instead of `<b>`, `<i>`, `<s>` and `<u>`.
+    pub semantic_tags: bool,
+    // Drops the newlines between tags and at the end, for byte-stable snapshots.
+    pub minify: bool,
+    // Numbers code block lines; blocks with a `{3-5}` range are numbered regardless.
+    pub line_numbers: bool,
+    // Escapes raw html in the text and strips tags, attributes and urls outside a whitelist,
+    // for untrusted input.
+    pub sanitize: bool,
+    pub void_style: VoidStyle,
+    // Host of the site the html is published on, e.g. `example.com`; links to other hosts
+    // get `target="_blank"` and `rel="noopener noreferrer"`.
+    pub site_host: Option<String>,
+    // Root that relative link and image urls are resolved against, e.g. `https://example.com/docs/`.
+    pub base_url: Option<String>,
+    // Points relative links to `.md` files at the `.html` files they convert to.
+    pub md_links: bool,
+    // Shows `<!-- md:off -->` regions as escaped text instead of passing them through.
+    pub escape_raw: bool,
+    // Class added to each element kind: `heading-1` to `heading-3`, `table`, `list`, `item`,
+    // `code`, `quote`, `container`, `details` and `toc`.
+    pub classes: BTreeMap<String, String>,
+    // Given as `dir` to headings and paragraphs whose text is mostly right-to-left, like
+    // Arabic or Hebrew; usually `Rtl`, or `Auto` to let the browser decide.
+    pub dir: Option<Direction>,
+    // Puts a table's header row in `<thead>` and the rest in `<tbody>`.
+    pub table_sections: bool,
+    // Renders the lines of a paragraph together, with a `<br />` where each newline was, so
+    // a renderer that wraps sentences in `<p>` gives one paragraph and not one per line. The
+    // default renderer ends every line with a break already and renders the same.
+    pub hard_breaks: bool,
+}
+
+pub const CLASS_KEYS: [&str; 11] = [
+    "heading-1", "heading-2", "heading-3", "table", "list", "item",
+    "code", "quote", "container", "details", "toc",
+];
+
+impl HtmlOptions {
+    // Gives every element kind a `<prefix><key>` class, e.g. `md-table`.
+    pub fn with_class_prefix(prefix: &str) -> Self {
+        let classes = CLASS_KEYS.iter()
+            .map(|key| (key.to_string(), format!("{}{}", prefix, key)))
+            .collect();
+        HtmlOptions{ classes, ..Default::default() }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FootnotePlacement {
+    #[default]
+    End,
+    // Leaves the notes out, for callers that place `footnotes_to_html_with` themselves.
+    Omit,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FootnoteOptions {
+    // Shown above the notes, e.g. `Notes`.
+    pub heading: Option<String>,
+    pub placement: FootnotePlacement,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RenderOptions {
+    pub max_bytes: Option<usize>,
+    pub max_elements: Option<usize>,
+    pub on_limit: LimitAction,
+    pub heading_anchors: bool,
+    // Prefixes headings and table of contents entries with section numbers like 1.2.
+    pub number_headings: bool,
+    // Renders headings this many levels lower, e.g. `#` as `<h2>` with 1, stopping at `<h6>`.
+    pub heading_offset: usize,
+    pub smart_punctuation: bool,
+    pub html: HtmlOptions,
+    // Wraps the fragment in a complete html document when set.
+    pub document: Option<DocumentOptions>,
+    pub footnotes: FootnoteOptions,
+    // Html for each `Word::Custom` by extension name, given the text the extension matched.
+    pub custom_words: BTreeMap<String, fn(&str) -> String>,
+    // The same for `Md::Custom` blocks.
+    pub custom_blocks: BTreeMap<String, fn(&str) -> String>,
+}
+
+impl RenderOptions {
+    // The tags and void element style of the CommonMark reference renderer.
+    pub fn commonmark() -> Self {
+        let html = HtmlOptions{ semantic_tags: true, void_style: VoidStyle::Xhtml, ..Default::default() };
+        RenderOptions{ html, ..Default::default() }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RenderError {
+    TooManyBytes(usize),
+    TooManyElements(usize),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::TooManyBytes(max) => write!(f, "rendered html exceeds {} bytes", max),
+            RenderError::TooManyElements(max) => write!(f, "rendered html exceeds {} elements", max),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
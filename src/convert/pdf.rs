@@ -0,0 +1,396 @@
+use crate::parser::parser::*;
+use super::text::{emoji_to_text, words_to_text};
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const BODY_SIZE: f32 = 11.0;
+const CODE_SIZE: f32 = 9.0;
+const INDENT: f32 = 18.0;
+const PADDING: f32 = 4.0;
+
+// The standard Type 1 fonts every PDF reader has, so nothing needs embedding.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Font {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+const FONTS: [(Font, &str); 5] = [
+    (Font::Regular, "Helvetica"),
+    (Font::Bold, "Helvetica-Bold"),
+    (Font::Italic, "Helvetica-Oblique"),
+    (Font::BoldItalic, "Helvetica-BoldOblique"),
+    (Font::Mono, "Courier"),
+];
+
+impl Font {
+    fn name(self) -> String {
+        let index = FONTS.iter().position(|(font, _)| *font == self).unwrap_or(0);
+        format!("/F{}", index + 1)
+    }
+
+    fn bold(self) -> Font {
+        match self {
+            Font::Italic | Font::BoldItalic => Font::BoldItalic,
+            Font::Mono => Font::Mono,
+            _ => Font::Bold,
+        }
+    }
+
+    fn italic(self) -> Font {
+        match self {
+            Font::Bold | Font::BoldItalic => Font::BoldItalic,
+            Font::Mono => Font::Mono,
+            _ => Font::Italic,
+        }
+    }
+}
+
+// Helvetica advance widths for printable ascii, in thousandths of the font size.
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+// Bold faces are measured as a slightly wider Helvetica, which is close enough for wrapping.
+fn width(text: &str, font: Font, size: f32) -> f32 {
+    let units: f32 = text.chars().map(|c| match (font, c) {
+        (Font::Mono, _) => 600.0,
+        (_, ' '..='~') => HELVETICA[c as usize - 32] as f32,
+        _ => 556.0,
+    })
+    .sum();
+    let scale = if matches!(font, Font::Bold | Font::BoldItalic) { 1.08 } else { 1.0 };
+    units * scale * size / 1000.0
+}
+
+// Strings are WinAnsi encoded; characters outside it print as `?`.
+fn encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for c in text.chars() {
+        let byte = match c {
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '\u{20}'..='\u{7e}' | '\u{a0}'..='\u{ff}' => c as u32,
+            _ => '?' as u32,
+        };
+        match byte {
+            0x5c => encoded.push_str("\\\\"),
+            0x28 => encoded.push_str("\\("),
+            0x29 => encoded.push_str("\\)"),
+            0x20..=0x7e => encoded.push(byte as u8 as char),
+            _ => encoded.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    encoded
+}
+
+struct Span(Font, String);
+
+fn words_to_spans(words: &Words, font: Font, spans: &mut Vec<Span>) {
+    for word in &words.0 {
+        match word {
+            Word::Normal(text) => spans.push(Span(font, text.to_string())),
+            Word::Entity(_, text) => spans.push(Span(font, text.clone())),
+            Word::Emoji(name) => spans.push(Span(font, emoji_to_text(name))),
+            Word::Image(alt, ..) => spans.push(Span(font.italic(), alt.clone())),
+            Word::FootnoteRef(label) => spans.push(Span(font, format!("[{}]", label))),
+            Word::Task(checked) => spans.push(Span(font, if *checked { "[x]" } else { "[ ]" }.to_string())),
+            Word::Kbd(key) => spans.push(Span(Font::Mono, key.clone())),
+            Word::Custom(_, source) => spans.push(Span(font, source.clone())),
+            Word::Bold(words) => words_to_spans(words, font.bold(), spans),
+            Word::Italic(words) => words_to_spans(words, font.italic(), spans),
+            Word::Underline(words)
+            | Word::StrikeThough(words)
+            | Word::Highlight(words)
+            | Word::Subscript(words)
+            | Word::Superscript(words)
+            | Word::Insert(words)
+            | Word::Link(words, _) => words_to_spans(words, font, spans),
+        }
+    }
+}
+
+fn spans(words: &Words, font: Font) -> Vec<Span> {
+    let mut spans = vec!();
+    words_to_spans(words, font, &mut spans);
+    spans
+}
+
+// Greedy line breaking at spaces; a word wider than the line gets a line of its own.
+fn wrap(spans: &[Span], size: f32, max_width: f32) -> Vec<Vec<Span>> {
+    let mut lines = vec!();
+    let mut line: Vec<Span> = vec!();
+    let mut line_width = 0.0;
+    let mut pending = false;
+    for Span(font, text) in spans {
+        for (i, piece) in text.split(' ').enumerate() {
+            pending |= i > 0;
+            if piece.is_empty() { continue }
+            let space = if pending && !line.is_empty() { " " } else { "" };
+            pending = false;
+            let piece_width = width(space, *font, size) + width(piece, *font, size);
+            if line_width + piece_width > max_width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                line_width = width(piece, *font, size);
+                line.push(Span(*font, piece.to_string()));
+                continue;
+            }
+            line_width += piece_width;
+            match line.last_mut() {
+                Some(Span(last, text)) if last == font => {
+                    text.push_str(space);
+                    text.push_str(piece);
+                },
+                _ => line.push(Span(*font, format!("{}{}", space, piece))),
+            }
+        }
+    }
+    if !line.is_empty() { lines.push(line) }
+    lines
+}
+
+struct Layout {
+    pages: Vec<String>,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Layout{ pages: vec!(String::new()), y: PAGE_HEIGHT - MARGIN }
+    }
+
+    fn page(&mut self) -> &mut String {
+        self.pages.last_mut().expect("a layout always has a page")
+    }
+
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN && self.y < PAGE_HEIGHT - MARGIN {
+            self.pages.push(String::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn text(&mut self, x: f32, y: f32, line: &[Span], size: f32) {
+        let mut stream = format!("BT {:.2} {:.2} Td", x, y);
+        for Span(font, text) in line {
+            stream.push_str(&format!(" {} {} Tf ({}) Tj", font.name(), size, encode(text)));
+        }
+        stream.push_str(" ET\n");
+        self.page().push_str(&stream);
+    }
+
+    fn paragraph(&mut self, x: f32, spans: &[Span], size: f32) {
+        let leading = size * 1.4;
+        for line in wrap(spans, size, PAGE_WIDTH - MARGIN - x) {
+            self.reserve(leading);
+            self.y -= leading;
+            let y = self.y + size * 0.3;
+            self.text(x, y, &line, size);
+        }
+    }
+
+    fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn table(&mut self, x: f32, header: &Record, records: &[Record], aligns: &[Align]) {
+        let columns = records.iter().map(|row| row.0.len()).chain([header.0.len()]).max().unwrap_or(0).max(1);
+        let column = (PAGE_WIDTH - MARGIN - x) / columns as f32;
+        let leading = BODY_SIZE * 1.4;
+        for (r, record) in [header].into_iter().chain(records).enumerate() {
+            let font = if r == 0 { Font::Bold } else { Font::Regular };
+            let cells: Vec<Vec<Vec<Span>>> = record.0.iter()
+                .map(|words| wrap(&spans(words, font), BODY_SIZE, column - PADDING * 2.0))
+                .collect();
+            let lines = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+            let height = lines as f32 * leading + PADDING * 2.0;
+            self.reserve(height);
+            let top = self.y;
+            for i in 0..columns {
+                let left = x + column * i as f32;
+                self.page().push_str(&format!("{:.2} {:.2} {:.2} {:.2} re S\n", left, top - height, column, height));
+                let Some(cell) = cells.get(i) else { continue };
+                for (n, line) in cell.iter().enumerate() {
+                    let line_width: f32 = line.iter().map(|Span(font, text)| width(text, *font, BODY_SIZE)).sum();
+                    let free = column - PADDING * 2.0 - line_width;
+                    let offset = match aligns.get(i) {
+                        Some(Align::Center) if r > 0 => free / 2.0,
+                        Some(Align::Right) if r > 0 => free,
+                        _ => 0.0,
+                    };
+                    let y = top - PADDING - leading * (n + 1) as f32 + BODY_SIZE * 0.3;
+                    self.text(left + PADDING + offset.max(0.0), y, line, BODY_SIZE);
+                }
+            }
+            self.y = top - height;
+        }
+    }
+}
+
+fn items_to_pdf(layout: &mut Layout, items: &Items, x: f32) {
+    for (i, item) in items.0.iter().enumerate() {
+        let marker = match items.1 {
+            ListKind::Bullet(_) => "•".to_string(),
+            ListKind::Ordered(start) => format!("{}.", start.saturating_add(i)),
+        };
+        layout.reserve(BODY_SIZE * 1.4);
+        let y = layout.y - BODY_SIZE * 1.4 + BODY_SIZE * 0.3;
+        layout.text(x, y, &[Span(Font::Regular, marker)], BODY_SIZE);
+        layout.paragraph(x + INDENT, &spans(&item.0, Font::Regular), BODY_SIZE);
+        blocks_to_pdf(layout, &item.1, x + INDENT);
+    }
+}
+
+fn md_to_pdf(layout: &mut Layout, md: &Md, x: f32) {
+    match md {
+        Md::Heading(size, words) => {
+            let size = [22.0, 18.0, 15.0, 13.0, 12.0, 11.0][(size - 1).min(5)];
+            layout.space(size * 0.5);
+            layout.reserve(size * 3.0);
+            layout.paragraph(x, &spans(words, Font::Bold), size);
+            layout.space(size * 0.3);
+        },
+        Md::Sentence(words) if words_to_text(words).is_empty() => layout.space(BODY_SIZE * 0.6),
+        Md::Sentence(words) => layout.paragraph(x, &spans(words, Font::Regular), BODY_SIZE),
+        Md::Footnote(label, words) => {
+            let mut note = vec!(Span(Font::Regular, format!("[{}] ", label)));
+            note.extend(spans(words, Font::Regular));
+            layout.paragraph(x, &note, BODY_SIZE);
+        },
+        Md::Table(table) => {
+            if let Some(caption) = &table.caption {
+                layout.paragraph(x, &spans(caption, Font::Italic), BODY_SIZE);
+            }
+            layout.table(x, &table.header, &table.records, &table.align);
+            layout.space(BODY_SIZE * 0.6);
+        },
+        Md::LooseTable(table) => {
+            layout.table(x, &table.header, &table.rows, &[]);
+            layout.space(BODY_SIZE * 0.6);
+        },
+        Md::List(items) => items_to_pdf(layout, items, x),
+        Md::Container(name, mds) => {
+            layout.paragraph(x, &[Span(Font::Bold, name.clone())], BODY_SIZE);
+            blocks_to_pdf(layout, mds, x + INDENT);
+        },
+        Md::Details(_, summary, mds) => {
+            layout.paragraph(x, &spans(summary, Font::Bold), BODY_SIZE);
+            blocks_to_pdf(layout, mds, x + INDENT);
+        },
+        Md::Quote(mds) => blocks_to_pdf(layout, mds, x + INDENT),
+        Md::Code(_, code, _) => {
+            for line in code.lines() {
+                layout.paragraph(x + INDENT, &[Span(Font::Mono, line.to_string())], CODE_SIZE);
+            }
+            layout.space(BODY_SIZE * 0.6);
+        },
+        Md::Toc | Md::Abbreviation(..) | Md::FrontMatter(..) => {},
+        Md::Raw(text) | Md::Custom(_, text) => layout.paragraph(x, &[Span(Font::Regular, text.clone())], BODY_SIZE),
+    }
+}
+
+fn blocks_to_pdf(layout: &mut Layout, mds: &[Md], x: f32) {
+    for md in mds {
+        md_to_pdf(layout, md, x);
+    }
+}
+
+/// Lays the markdown out on A4 pages and renders it as the bytes of a PDF file.
+///
+/// Text uses the standard Helvetica and Courier fonts, which only cover Latin-1.
+pub fn to_pdf(mds: &[Md]) -> Vec<u8> {
+    let mut layout = Layout::new();
+    blocks_to_pdf(&mut layout, mds, MARGIN);
+
+    let mut objects = vec!(
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        String::new(),
+    );
+    let fonts: Vec<String> = FONTS.iter().enumerate().map(|(i, (_, name))| {
+        objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", name));
+        format!("/F{} {} 0 R", i + 1, objects.len())
+    })
+    .collect();
+    let mut kids = vec!();
+    for stream in &layout.pages {
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", stream.len(), stream));
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << {} >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT, fonts.join(" "), objects.len()
+        ));
+        kids.push(format!("{} 0 R", objects.len()));
+    }
+    objects[1] = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), kids.len());
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = vec!();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref));
+    pdf.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(pdf: &[u8]) -> String {
+        String::from_utf8(pdf.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("a(b)\\ é • 日"), "a\\(b\\)\\\\ \\351 \\225 ?");
+    }
+
+    #[test]
+    fn test_wrap() {
+        let spans = [Span(Font::Regular, "aaa bbb ".to_string()), Span(Font::Bold, "ccc".to_string())];
+        let lines = wrap(&spans, 10.0, 40.0);
+        let lines: Vec<Vec<(Font, &str)>> = lines.iter()
+            .map(|line| line.iter().map(|Span(font, text)| (*font, text.as_str())).collect())
+            .collect();
+        assert_eq!(lines, vec!(vec!((Font::Regular, "aaa bbb")), vec!((Font::Bold, "ccc"))));
+    }
+
+    #[test]
+    fn test_to_pdf() {
+        let pdf = text(&to_pdf(&parse("# Title\nHello **World**\n| A | B |\n|-|-|\n| a | b |")));
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/F2 22 Tf (Title) Tj"));
+        assert!(pdf.contains("/F1 11 Tf (Hello) Tj /F2 11 Tf ( World) Tj"));
+        assert!(pdf.contains("re S"));
+        assert_eq!(pdf.matches("/Type /Page ").count(), 1);
+
+        let xref: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with("xref\n"));
+        let first: usize = pdf[xref..].lines().nth(3).unwrap()[..10].parse().unwrap();
+        assert!(pdf[first..].starts_with("1 0 obj"));
+    }
+
+    #[test]
+    fn test_page_breaks() {
+        let text = "line\n".repeat(200);
+        let pdf = self::text(&to_pdf(&parse(&text)));
+        assert_eq!(pdf.matches("/Type /Page ").count(), 5);
+        assert!(pdf.contains("/Count 5 >>"));
+    }
+}
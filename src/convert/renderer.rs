@@ -0,0 +1,228 @@
+use crate::parser::parser::{Align, ImageSize, ListKind};
+use super::options::Direction;
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+pub(crate) fn escape_attribute(text: &str) -> String {
+    escape_html(text).replace('"', "&quot;")
+}
+
+// The ` class="language-..."` of a code block, from the fence's info string.
+pub(crate) fn language_class(lang: &str) -> String {
+    if lang.is_empty() { "".to_string() } else { format!(" class=\"language-{}\"", escape_attribute(lang)) }
+}
+
+fn align_to_string<'a>(align: &Align) -> &'a str {
+    match align {
+        Align::Right => "right",
+        Align::Center => "center",
+        Align::Left => "left",
+    }
+}
+
+/// Builds the html for each element from its already rendered content.
+///
+/// Every method has a default that produces the crate's standard output, so an
+/// implementation only overrides the elements it wants to change.
+///
+/// ```
+/// use app::convert::convert::mds_to_html_with_renderer;
+/// use app::convert::options::RenderOptions;
+/// use app::convert::renderer::Renderer;
+/// use app::parser::parser::parse;
+///
+/// struct Classy;
+///
+/// impl Renderer for Classy {
+///     fn heading(&self, level: usize, _id: &str, content: &str) -> String {
+///         format!("<h{} class=\"title\">{}</h{}>", level, content, level)
+///     }
+/// }
+///
+/// let html = mds_to_html_with_renderer(&parse("# Hello"), &RenderOptions::default(), &Classy);
+/// assert_eq!(html, Ok("<h1 class=\"title\">Hello</h1>".to_string()));
+/// ```
+pub trait Renderer {
+    fn text(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn emphasis(&self, tag: &str, content: &str) -> String {
+        format!("<{}>{}</{}>", tag, content, tag)
+    }
+
+    fn kbd(&self, key: &str) -> String {
+        format!("<kbd>{}</kbd>", key)
+    }
+
+    // A `Word::Custom` without a callback in `RenderOptions::custom_words`.
+    fn custom(&self, name: &str, content: &str) -> String {
+        format!("<span class=\"{}\">{}</span>", escape_attribute(name), content)
+    }
+
+    // An `Md::Custom` without a callback in `RenderOptions::custom_blocks`.
+    fn custom_block(&self, name: &str, content: &str) -> String {
+        format!("<div class=\"{}\">{}</div>", escape_attribute(name), content)
+    }
+
+    fn task(&self, checked: bool) -> String {
+        let checked = if checked { " checked" } else { "" };
+        format!("<input type=\"checkbox\" disabled{} />", checked)
+    }
+
+    fn link(&self, url: &str, content: &str) -> String {
+        format!("<a href=\"{}\">{}</a>", escape_attribute(url), content)
+    }
+
+    // Sizes reserve the image's space before it loads, so the page does not shift.
+    fn image(&self, src: &str, alt: &str, size: &ImageSize) -> String {
+        let width = size.width.as_ref().map_or("".to_string(), |width| format!(" width=\"{}\"", escape_attribute(width)));
+        let height = size.height.as_ref().map_or("".to_string(), |height| format!(" height=\"{}\"", escape_attribute(height)));
+        format!("<img src=\"{}\" alt=\"{}\"{}{} />", escape_attribute(src), escape_attribute(alt), width, height)
+    }
+
+    // `id` is empty when the heading text has no sluggable characters.
+    fn heading(&self, level: usize, id: &str, content: &str) -> String {
+        if id.is_empty() {
+            return format!("<h{}>{}</h{}>", level, content, level)
+        }
+        format!("<h{} id=\"{}\">{}</h{}>", level, id, content, level)
+    }
+
+    fn sentence(&self, content: &str) -> String {
+        format!("{}<br />", content)
+    }
+
+    // A paragraph, with the `dir` it needs when its text runs right-to-left. Bare text has no
+    // element to carry the attribute, so it goes in a `<span>`; a renderer that wraps
+    // paragraphs, say in `<p>`, puts it on the wrapper instead.
+    fn paragraph(&self, dir: Option<Direction>, content: &str) -> String {
+        match dir {
+            Some(dir) => self.sentence(&format!("<span dir=\"{}\">{}</span>", dir.as_str(), content)),
+            None => self.sentence(content),
+        }
+    }
+
+    fn table(&self, caption: Option<&str>, header: &str, rows: &[String]) -> String {
+        let caption = caption.map_or("".to_string(), |caption| format!("<caption>{}</caption>\n", caption));
+        let rows: String = rows.iter().map(|row| format!("{}\n", row)).collect();
+        format!("<table>\n{}{}\n{}</table>\n", caption, header, rows)
+    }
+
+    // The same with the header row in `<thead>` and the others in `<tbody>`.
+    fn table_sections(&self, caption: Option<&str>, header: &str, rows: &[String]) -> String {
+        let caption = caption.map_or("".to_string(), |caption| format!("<caption>{}</caption>\n", caption));
+        let body = if rows.is_empty() { "".to_string() } else { format!("<tbody>\n{}\n</tbody>\n", rows.join("\n")) };
+        format!("<table>\n{}<thead>\n{}\n</thead>\n{}</table>\n", caption, header, body)
+    }
+
+    fn table_row(&self, cells: &str) -> String {
+        format!("<tr>{}</tr>", cells)
+    }
+
+    // `span` is the (columns, rows) a cell covers; header cells have no alignment.
+    fn table_cell(&self, header: bool, align: Option<&Align>, span: (usize, usize), content: &str) -> String {
+        let tag = if header { "th scope=\"col\"" } else { "td" };
+        let align = align.map_or("".to_string(), |align| format!(" align=\"{}\"", align_to_string(align)));
+        let cols = if span.0 > 1 { format!(" colspan=\"{}\"", span.0) } else { "".to_string() };
+        let rows = if span.1 > 1 { format!(" rowspan=\"{}\"", span.1) } else { "".to_string() };
+        let close = if header { "th" } else { "td" };
+        format!("<{}{}{}{}>{}</{}>", tag, align, cols, rows, content, close)
+    }
+
+    fn list(&self, kind: &ListKind, items: &[String]) -> String {
+        let (tag, start) = match kind {
+            ListKind::Bullet(_) => ("ul", "".to_string()),
+            ListKind::Ordered(1) => ("ol", "".to_string()),
+            ListKind::Ordered(n) => ("ol", format!(" start=\"{}\"", n)),
+        };
+        format!("<{}{}>\n{}\n</{}>\n", tag, start, items.join("\n"), tag)
+    }
+
+    fn list_item(&self, content: &str, children: Option<&str>) -> String {
+        let children = children.map_or("".to_string(), |children| format!("\n{}", children));
+        format!("<li>{}{}</li>", content, children)
+    }
+
+    // `code` is the raw source and still needs escaping.
+    fn code(&self, lang: &str, code: &str) -> String {
+        let class = language_class(lang);
+        format!("<pre><code{}>{}</code></pre>", class, escape_html(code))
+    }
+
+    // Wraps each line in a numbered `<span class="line">`, adding `highlight` to lines
+    // inside one of the inclusive `highlight` ranges.
+    fn code_lines(&self, lang: &str, code: &str, highlight: &[(usize, usize)]) -> String {
+        let class = language_class(lang);
+        let lines: Vec<String> = code.split('\n').enumerate().map(|(i, line)| {
+            let n = i + 1;
+            let marked = highlight.iter().any(|(from, to)| (*from..=*to).contains(&n));
+            let class = if marked { "line highlight" } else { "line" };
+            format!("<span class=\"{}\" data-line=\"{}\">{}</span>", class, n, escape_html(line))
+        })
+        .collect();
+        format!("<pre><code{}>{}</code></pre>", class, lines.join("\n"))
+    }
+
+    fn container(&self, name: &str, content: &str) -> String {
+        format!("<div class=\"{}\">\n{}\n</div>", escape_attribute(name), content)
+    }
+
+    fn details(&self, open: bool, summary: &str, content: &str) -> String {
+        let open = if open { " open" } else { "" };
+        format!("<details{}>\n<summary>{}</summary>\n{}\n</details>", open, summary, content)
+    }
+
+    fn quote(&self, content: &str) -> String {
+        format!("<blockquote>\n{}\n</blockquote>", content)
+    }
+
+    // `nth` counts the references to the same note from 1, so each one gets its own id.
+    fn footnote_ref(&self, number: usize, nth: usize) -> String {
+        format!("<sup class=\"footnote-ref\"><a href=\"#fn-{}\" id=\"{}\">{}</a></sup>", number, footnote_ref_id(number, nth), number)
+    }
+
+    // One note with a backlink to each of its `references`.
+    fn footnote(&self, number: usize, content: &str, references: usize) -> String {
+        let backlinks: Vec<String> = (1..=references).map(|nth| {
+            let mark = if nth == 1 { "".to_string() } else { format!("<sup>{}</sup>", nth) };
+            format!("<a href=\"#{}\" class=\"footnote-backref\">\u{21a9}{}</a>", footnote_ref_id(number, nth), mark)
+        })
+        .collect();
+        format!("<li id=\"fn-{}\">{} {}</li>", number, content, backlinks.join(" "))
+    }
+
+    fn footnotes(&self, heading: Option<&str>, notes: &[String]) -> String {
+        let heading = heading.map_or("".to_string(), |heading| format!("<h2 class=\"footnotes-heading\">{}</h2>\n", heading));
+        format!("<section class=\"footnotes\">\n{}<ol>\n{}\n</ol>\n</section>", heading, notes.join("\n"))
+    }
+}
+
+fn footnote_ref_id(number: usize, nth: usize) -> String {
+    if nth == 1 { format!("fnref-{}", number) } else { format!("fnref-{}-{}", number, nth) }
+}
+
+/// The crate's standard html output.
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_cell() {
+        let renderer = HtmlRenderer;
+        assert_eq!(renderer.table_cell(true, None, (2, 1), "A"), "<th scope=\"col\" colspan=\"2\">A</th>");
+        assert_eq!(renderer.table_cell(false, Some(&Align::Center), (1, 3), "a"), "<td align=\"center\" rowspan=\"3\">a</td>");
+        assert_eq!(renderer.table_cell(false, None, (1, 1), "a"), "<td>a</td>");
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html("<a href=\"x\">&</a>"), "&lt;a href=\"x\"&gt;&amp;&lt;/a&gt;");
+    }
+}
@@ -0,0 +1,209 @@
+use crate::parser::parser::*;
+use super::text::{display_width as width, word_to_text, words_to_text};
+
+pub fn escape_rst(text: &str) -> String {
+    let mut rst = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '`' | '|') { rst.push('\\') }
+        rst.push(c);
+    }
+    rst
+}
+
+// reStructuredText inline markup does not nest, so only the outer emphasis is kept.
+fn word_to_rst(word: &Word) -> String {
+    match word {
+        Word::Normal(_) | Word::Entity(..) | Word::Emoji(_) | Word::Custom(..) => escape_rst(&word_to_text(word)),
+        Word::FootnoteRef(label) => format!(" [#{}]_", label),
+        Word::Task(checked) => if *checked { "[x]" } else { "[ ]" }.to_string(),
+        Word::Bold(words) => format!("**{}**", escape_rst(&words_to_text(words))),
+        Word::Italic(words) => format!("*{}*", escape_rst(&words_to_text(words))),
+        Word::Subscript(words) => format!(":sub:`{}`", escape_rst(&words_to_text(words))),
+        Word::Superscript(words) => format!(":sup:`{}`", escape_rst(&words_to_text(words))),
+        Word::StrikeThough(words)
+        | Word::Underline(words)
+        | Word::Highlight(words)
+        | Word::Insert(words) => words_to_rst(words),
+        Word::Kbd(key) => format!(":kbd:`{}`", escape_rst(key)),
+        Word::Image(alt, ..) => escape_rst(alt),
+        Word::Link(words, url) => format!("`{} <{}>`__", escape_rst(&words_to_text(words)), url),
+    }
+}
+
+fn words_to_rst(words: &Words) -> String {
+    words.0.iter().map(word_to_rst).collect()
+}
+
+fn indent(text: &str, width: usize) -> String {
+    let lines: Vec<String> = text.lines().map(|line| {
+        if line.is_empty() { "".to_string() } else { format!("{}{}", " ".repeat(width), line) }
+    })
+    .collect();
+    lines.join("\n")
+}
+
+fn grid_row(cells: &[String], widths: &[usize]) -> String {
+    let cells: Vec<String> = widths.iter().enumerate().map(|(i, width)| {
+        let cell = cells.get(i).map(String::as_str).unwrap_or("");
+        format!(" {}{} ", cell, " ".repeat(width - self::width(cell)))
+    })
+    .collect();
+    format!("|{}|", cells.join("|"))
+}
+
+fn grid_rule(widths: &[usize], line: &str) -> String {
+    let lines: Vec<String> = widths.iter().map(|width| line.repeat(width + 2)).collect();
+    format!("+{}+", lines.join("+"))
+}
+
+// Grid tables have no column alignment or spans, so covered cells are left empty.
+fn table_to_rst(header: &Record, records: &[Record], caption: Option<&Words>) -> String {
+    let cells = |record: &Record| -> Vec<String> {
+        record.0.iter().enumerate().map(|(i, words)| match record.1.get(i) {
+            Some(Span::Merged) => "".to_string(),
+            _ => words_to_rst(words),
+        })
+        .collect()
+    };
+    let header = cells(header);
+    let records: Vec<Vec<String>> = records.iter().map(cells).collect();
+    let columns = records.iter().map(Vec::len).chain([header.len()]).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns).map(|i| {
+        records.iter().chain([&header]).filter_map(|row| row.get(i)).map(|cell| width(cell)).max().unwrap_or(0)
+    })
+    .collect();
+    let mut lines = vec!(grid_rule(&widths, "-"), grid_row(&header, &widths), grid_rule(&widths, "="));
+    for record in &records {
+        lines.push(grid_row(record, &widths));
+        lines.push(grid_rule(&widths, "-"));
+    }
+    let table = lines.join("\n");
+    match caption {
+        Some(caption) => format!(".. table:: {}\n\n{}", words_to_rst(caption), indent(&table, 3)),
+        None => table,
+    }
+}
+
+fn items_to_rst(items: &Items) -> String {
+    let lines: Vec<String> = items.0.iter().enumerate().map(|(i, item)| {
+        let marker = match items.1 {
+            ListKind::Bullet(_) => "-".to_string(),
+            ListKind::Ordered(start) => format!("{}.", start.saturating_add(i)),
+        };
+        let mut rst = format!("{} {}", marker, words_to_rst(&item.0));
+        if !item.1.is_empty() {
+            rst.push_str("\n\n");
+            rst.push_str(&indent(&to_rst(&item.1), marker.len() + 1));
+        }
+        rst
+    })
+    .collect();
+    lines.join("\n")
+}
+
+fn ranges_to_rst(ranges: &[(usize, usize)]) -> String {
+    let ranges: Vec<String> = ranges.iter().map(|(start, end)| {
+        if start == end { start.to_string() } else { format!("{}-{}", start, end) }
+    })
+    .collect();
+    ranges.join(",")
+}
+
+const ADMONITIONS: [&str; 9] = ["attention", "caution", "danger", "error", "hint", "important", "note", "tip", "warning"];
+
+fn md_to_rst(md: &Md) -> String {
+    match md {
+        Md::Heading(size, words) => {
+            let text = words_to_rst(words);
+            let line = ["=", "-", "~", "^", "\""][(size - 1).min(4)];
+            format!("{}\n{}", text, line.repeat(width(&text)))
+        },
+        Md::Sentence(words) => words_to_rst(words),
+        Md::Footnote(label, words) => format!(".. [#{}] {}", label, words_to_rst(words)),
+        Md::Table(table) => table_to_rst(&table.header, &table.records, table.caption.as_ref()),
+        Md::LooseTable(table) => table_to_rst(&table.header, &table.rows, None),
+        Md::List(items) => items_to_rst(items),
+        Md::Container(name, mds) if ADMONITIONS.contains(&name.as_str()) => {
+            format!(".. {}::\n\n{}", name, indent(&to_rst(mds), 3))
+        },
+        Md::Container(name, mds) => format!(".. container:: {}\n\n{}", name, indent(&to_rst(mds), 3)),
+        Md::Details(_, summary, mds) => format!(".. rubric:: {}\n\n{}", words_to_rst(summary), to_rst(mds)),
+        Md::Quote(mds) => indent(&to_rst(mds), 3),
+        Md::Code(lang, code, ranges) => {
+            let mut rst = if lang.is_empty() { "::".to_string() } else { format!(".. code-block:: {}", lang) };
+            if !lang.is_empty() && !ranges.is_empty() {
+                rst.push_str(&format!("\n   :emphasize-lines: {}", ranges_to_rst(ranges)));
+            }
+            format!("{}\n\n{}", rst, indent(code, 3))
+        },
+        Md::Toc => ".. contents::".to_string(),
+        Md::Abbreviation(..) | Md::FrontMatter(..) => "".to_string(),
+        Md::Raw(text) => format!(".. raw:: html\n\n{}", indent(text, 3)),
+        Md::Custom(_, source) => escape_rst(source),
+    }
+}
+
+// Consecutive lines become a `| ` line block so their breaks survive.
+fn paragraph(lines: &mut Vec<String>, rst: &mut Vec<String>) {
+    match lines.len() {
+        0 => {},
+        1 => rst.push(lines.remove(0)),
+        _ => rst.push(lines.drain(..).map(|line| format!("| {}", line)).collect::<Vec<String>>().join("\n")),
+    }
+}
+
+/// Renders markdown as reStructuredText for Sphinx and docutils.
+pub fn to_rst(mds: &[Md]) -> String {
+    let mut rst = vec!();
+    let mut lines = vec!();
+    for md in mds {
+        match md {
+            Md::Sentence(words) if words_to_text(words).is_empty() => paragraph(&mut lines, &mut rst),
+            Md::Sentence(_) => lines.push(md_to_rst(md)),
+            _ => {
+                paragraph(&mut lines, &mut rst);
+                let block = md_to_rst(md);
+                if !block.is_empty() { rst.push(block) }
+            },
+        }
+    }
+    paragraph(&mut lines, &mut rst);
+    rst.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_rst() {
+        assert_eq!(escape_rst("a*b `c` |d| \\"), "a\\*b \\`c\\` \\|d\\| \\\\");
+    }
+
+    #[test]
+    fn test_to_rst() {
+        let mds = parse("# Title\n**bold** *it*\nnext\n\n## 見出し\nalone");
+        let expect = "Title\n=====\n\n| **bold** *it*\n| next\n\n見出し\n------\n\nalone";
+        assert_eq!(to_rst(&mds), expect);
+    }
+
+    #[test]
+    fn test_table_to_rst() {
+        let mds = parse("| A | Long |\n|-|-|\n| abc | x |");
+        let expect = [
+            "+-----+------+",
+            "| A   | Long |",
+            "+=====+======+",
+            "| abc | x    |",
+            "+-----+------+",
+        ].join("\n");
+        assert_eq!(to_rst(&mds), expect);
+    }
+
+    #[test]
+    fn test_blocks_to_rst() {
+        let mds = parse("- a\n  - b\n::: note\nHi\n:::\n```rust {2}\nlet a;\n```");
+        let expect = "- a\n\n  - b\n\n.. note::\n\n   Hi\n\n.. code-block:: rust\n   :emphasize-lines: 2\n\n   let a;";
+        assert_eq!(to_rst(&mds), expect);
+    }
+}
@@ -0,0 +1,138 @@
+use super::renderer::{escape_attribute, escape_html};
+use super::tree::decode;
+use super::options::TRUNCATED;
+
+const TAGS: [&str; 49] = [
+    "h1", "h2", "h3", "h4", "h5", "h6", "p", "br", "hr", "b", "i", "s", "u", "strong", "em",
+    "del", "ins", "mark", "sub", "sup", "kbd", "span", "abbr", "a", "img", "table", "caption",
+    "tr", "th", "td", "ul", "ol", "li", "pre", "code", "blockquote", "div", "details",
+    "summary", "nav", "section", "html", "head", "body", "meta", "title", "input",
+    "thead", "tbody",
+];
+
+const ATTRIBUTES: [&str; 24] = [
+    "class", "id", "href", "src", "alt", "title", "align", "colspan", "rowspan", "start",
+    "open", "lang", "charset", "aria-hidden", "data-line", "data-sourcepos", "width", "height", "target", "rel",
+    "type", "checked", "disabled", "scope",
+];
+
+// Rejects script-running schemes, ignoring case and the whitespace and control
+// characters browsers skip over.
+pub fn is_safe_url(url: &str) -> bool {
+    let url: String = url.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    !["javascript:", "vbscript:", "data:"].iter().any(|scheme| url.starts_with(scheme))
+}
+
+fn attributes(mut text: &str) -> Vec<(String, Option<String>)> {
+    let mut attributes = vec!();
+    loop {
+        text = text.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if text.is_empty() { break }
+        let end = text.find(|c: char| c.is_whitespace() || c == '=' || c == '/').unwrap_or(text.len());
+        let name = text[..end].to_ascii_lowercase();
+        text = text[end..].trim_start();
+        let Some(value) = text.strip_prefix('=') else {
+            attributes.push((name, None));
+            continue
+        };
+        let value = value.trim_start();
+        let (value, rest) = match value.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => {
+                let value = &value[1..];
+                value.split_once(quote).unwrap_or((value, ""))
+            },
+            _ => value.split_at(value.find(char::is_whitespace).unwrap_or(value.len())),
+        };
+        attributes.push((name, Some(value.to_string())));
+        text = rest;
+    }
+    attributes
+}
+
+fn tag(text: &str) -> Option<String> {
+    let closing = text.starts_with('/');
+    let text = text.trim_start_matches('/');
+    let end = text.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(text.len());
+    let name = text[..end].to_ascii_lowercase();
+    if !TAGS.contains(&name.as_str()) { return None }
+    if closing { return Some(format!("</{}>", name)) }
+    let mut tag = format!("<{}", name);
+    for (attribute, value) in attributes(&text[end..]) {
+        if !ATTRIBUTES.contains(&attribute.as_str()) { continue }
+        // Values come escaped, like `&amp;` in a query string; decoded first so they aren't
+        // escaped twice, and so an encoded `javascript:` is still caught.
+        match value.as_deref().map(decode) {
+            Some(value) if (attribute == "href" || attribute == "src") && !is_safe_url(&value) => {},
+            Some(value) => tag.push_str(&format!(" {}=\"{}\"", attribute, escape_attribute(&value))),
+            None => tag.push_str(&format!(" {}", attribute)),
+        }
+    }
+    if text.trim_end().ends_with('/') { tag.push_str(" /") }
+    tag.push('>');
+    Some(tag)
+}
+
+// Keeps only whitelisted tags and attributes; anything else that looks like a tag is escaped.
+// Comments are dropped, except the truncation marker, since browsers also end them at `--!>`.
+pub fn sanitize_html(html: &str) -> String {
+    let mut sanitized = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        sanitized.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let doctype = rest.as_bytes().get(..15).is_some_and(|b| b.eq_ignore_ascii_case(b"<!doctype html>"));
+        let comment = rest.starts_with("<!--");
+        let end = if comment {
+            [rest.find("-->").map(|i| i + 2), rest.find("--!>").map(|i| i + 3)].into_iter().flatten().min()
+        } else {
+            rest.find('>')
+        };
+        let Some(end) = end else {
+            sanitized.push_str(&escape_html(rest));
+            return sanitized
+        };
+        if comment {
+            if &rest[..=end] == TRUNCATED { sanitized.push_str(TRUNCATED) }
+        } else if doctype {
+            sanitized.push_str(&rest[..=end]);
+        } else if let Some(tag) = tag(&rest[1..end]) {
+            sanitized.push_str(&tag);
+        } else {
+            sanitized.push_str(&escape_html(&rest[..=end]));
+        }
+        rest = &rest[(end + 1)..];
+    }
+    sanitized.push_str(rest);
+    sanitized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_safe_url() {
+        assert!(is_safe_url("https://example.com/a.png"));
+        assert!(is_safe_url("./docs/page.html"));
+        assert!(!is_safe_url("javascript:alert(1)"));
+        assert!(!is_safe_url(" JaVa\tScRiPt:alert(1)"));
+        assert!(!is_safe_url("data:text/html;base64,xx"));
+    }
+
+    #[test]
+    fn test_sanitize_html() {
+        assert_eq!(sanitize_html("<b onclick=\"x()\">a</b>"), "<b>a</b>");
+        assert_eq!(sanitize_html("<script>alert(1)</script>"), "&lt;script&gt;alert(1)&lt;/script&gt;");
+        assert_eq!(sanitize_html("<a href='javascript:x' class=c>a</a>"), "<a class=\"c\">a</a>");
+        assert_eq!(sanitize_html("<img src=\"a.png\" alt=\"a\" onerror=\"x\"/>"), "<img src=\"a.png\" alt=\"a\" />");
+        assert_eq!(sanitize_html("<details open>\n<!-- truncated -->"), "<details open>\n<!-- truncated -->");
+        assert_eq!(sanitize_html("a < b"), "a &lt; b");
+        assert_eq!(sanitize_html("a<!-- note -->b"), "ab");
+        assert_eq!(sanitize_html("<!-- x --!><img src=x onerror=alert(1)> -->"), "<img src=\"x\"> -->");
+        assert_eq!(sanitize_html("<a href=\"/s?a=1&amp;b=2\" title='&quot;x&quot;'>a</a>"), "<a href=\"/s?a=1&amp;b=2\" title=\"&quot;x&quot;\">a</a>");
+        assert_eq!(sanitize_html("<a href=\"jav&#x61;script:x()\">a</a>"), "<a>a</a>");
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+pub fn slugify(text: &str) -> String {
+    text.trim().to_lowercase().chars().filter_map(|c| {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            Some(c)
+        } else if c.is_whitespace() {
+            Some('-')
+        } else {
+            None
+        }
+    })
+    .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct Slugger {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Repeated slugs get `-1`, `-2`, ... suffixes in document order, as on GitHub.
+    pub fn slug(&mut self, text: &str) -> String {
+        let base = slugify(text);
+        if base.is_empty() { return base }
+        let mut slug = base.clone();
+        while self.seen.contains_key(&slug) {
+            let count = self.seen.entry(base.clone()).or_default();
+            *count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+        self.seen.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello World!"), "hello-world".to_string());
+        assert_eq!(slugify("  What's new in v1.2?  "), "whats-new-in-v12".to_string());
+        assert_eq!(slugify("snake_case and-kebab"), "snake_case-and-kebab".to_string());
+        assert_eq!(slugify("日本語 タイトル"), "日本語-タイトル".to_string());
+        assert_eq!(slugify("!!!"), "".to_string());
+    }
+
+    #[test]
+    fn test_slugger() {
+        let mut slugger = Slugger::new();
+        assert_eq!(slugger.slug("Usage"), "usage".to_string());
+        assert_eq!(slugger.slug("Usage"), "usage-1".to_string());
+        assert_eq!(slugger.slug("usage!"), "usage-2".to_string());
+        assert_eq!(slugger.slug("Usage 1"), "usage-1-1".to_string());
+        assert_eq!(slugger.slug("Usage-3"), "usage-3".to_string());
+        assert_eq!(slugger.slug("Usage"), "usage-4".to_string());
+        assert_eq!(slugger.slug("???"), "".to_string());
+    }
+}
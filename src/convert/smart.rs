@@ -0,0 +1,49 @@
+fn quote(c: char, opening: bool) -> char {
+    match (c, opening) {
+        ('"', true) => '“',
+        ('"', false) => '”',
+        (_, true) => '‘',
+        (_, false) => '’',
+    }
+}
+
+// `prev` and `next` are the characters around `text` in its sentence, so quotes
+// next to emphasis still curl the right way.
+pub fn smarten(text: &str, prev: Option<char>, next: Option<char>) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut smart = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let rest = &chars[i..];
+        let (c, len) = match rest[0] {
+            '-' if rest.starts_with(&['-', '-', '-']) => ('—', 3),
+            '-' if rest.starts_with(&['-', '-']) => ('–', 2),
+            '.' if rest.starts_with(&['.', '.', '.']) => ('…', 3),
+            c @ ('"' | '\'') => {
+                let before = if i == 0 { prev } else { Some(chars[i - 1]) };
+                let after = chars.get(i + 1).copied().or(next);
+                let opening = before.is_none_or(|c| c.is_whitespace() || "([{-–—".contains(c))
+                    && after.is_some_and(|c| !c.is_whitespace());
+                (quote(c, opening), 1)
+            },
+            c => (c, 1),
+        };
+        smart.push(c);
+        i += len;
+    }
+    smart
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smarten() {
+        assert_eq!(smarten("\"Hello,\" she said -- 'don't'...", None, None), "“Hello,” she said – ‘don’t’…".to_string());
+        assert_eq!(smarten("1990---2000", None, None), "1990—2000".to_string());
+        assert_eq!(smarten("\"", None, Some('H')), "“".to_string());
+        assert_eq!(smarten("\" end", Some('o'), None), "” end".to_string());
+        assert_eq!(smarten("a .. b", None, None), "a .. b".to_string());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::parser::options::ParserOptions;
+use crate::parser::reader::BlockReader;
+use super::convert::HtmlWriter;
+use super::options::RenderOptions;
+use std::io::{self, BufRead, Write};
+use std::mem::take;
+
+// The html of markdown read from `reader`, handed out a chunk at a time as blocks complete,
+// e.g. as the body of a chunked http response. Joined, the chunks are the `HtmlWriter`
+// output; blocks it holds back come out together in the last chunk.
+pub struct HtmlChunks<R> {
+    blocks: BlockReader<R>,
+    writer: Option<HtmlWriter<Vec<u8>>>,
+}
+
+impl<R: BufRead> HtmlChunks<R> {
+    pub fn new(reader: R, parser: &ParserOptions, options: &RenderOptions) -> Self {
+        HtmlChunks{ blocks: BlockReader::new(reader, parser), writer: Some(HtmlWriter::new(vec!(), options)) }
+    }
+
+    fn chunk(html: Vec<u8>) -> String {
+        String::from_utf8(html).expect("rendered html is utf-8")
+    }
+}
+
+impl<R: BufRead> Iterator for HtmlChunks<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        loop {
+            let writer = self.writer.as_mut()?;
+            let written = match self.blocks.next() {
+                Some(Ok(md)) => writer.write(&md),
+                Some(Err(err)) => Err(err),
+                None => {
+                    let html = self.writer.take()?.finish();
+                    return html.map(Self::chunk).map(|html| (!html.is_empty()).then_some(html)).transpose()
+                },
+            };
+            if let Err(err) = written {
+                self.writer = None;
+                return Some(Err(err))
+            }
+            let html = take(writer.get_mut());
+            if !html.is_empty() { return Some(Ok(Self::chunk(html))) }
+        }
+    }
+}
+
+// Reads markdown from `reader` and writes its html to `out` block by block, so the start of
+// the page can go out before the rest of the input arrives. Returns `out` once done.
+pub fn stream_html<R: BufRead, W: Write>(reader: R, out: W, parser: &ParserOptions, options: &RenderOptions) -> io::Result<W> {
+    let mut writer = HtmlWriter::new(out, options);
+    for md in BlockReader::new(reader, parser) {
+        writer.write(&md?)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::convert::mds_to_html;
+    use crate::parser::parser::parse;
+    use std::io::{BufReader, Read};
+
+    // Gives out its bytes, then fails like a dropped connection.
+    struct Broken<'a>(&'a [u8]);
+
+    impl Read for Broken<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() { return Err(io::Error::other("connection reset")) }
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_html_chunks() {
+        let text = "# Title\n\nA paragraph\n\n- item\n\nSee[^1]\n\n[^1]: note\n";
+        let options = (ParserOptions::default(), RenderOptions::default());
+        let chunks: Vec<String> = HtmlChunks::new(text.as_bytes(), &options.0, &options.1).collect::<io::Result<_>>().unwrap();
+        assert_eq!(chunks[0], "<h1 id=\"title\">Title</h1>");
+        assert!(chunks.len() > 2);
+        assert_eq!(chunks.concat(), mds_to_html(&parse(text)));
+        assert_eq!(HtmlChunks::new(&b""[..], &options.0, &options.1).count(), 0);
+
+        // The html read before the input broke off is already out.
+        let chunks: Vec<io::Result<String>> = HtmlChunks::new(BufReader::new(Broken(b"# Title\nOne\nTwo\nThree\n")), &options.0, &options.1).collect();
+        assert_eq!(chunks[0].as_ref().unwrap(), "<h1 id=\"title\">Title</h1>");
+        assert_eq!(chunks.last().unwrap().as_ref().unwrap_err().to_string(), "connection reset");
+    }
+
+    #[test]
+    fn test_stream_html() {
+        let text = "# Title\n| A |\n| - |\n| a |\nEnd";
+        let html = stream_html(text.as_bytes(), vec!(), &ParserOptions::default(), &RenderOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(html).unwrap(), mds_to_html(&parse(text)));
+        let broken = BufReader::new(Broken(b"# Title\n"));
+        assert!(stream_html(broken, vec!(), &ParserOptions::default(), &RenderOptions::default()).is_err());
+    }
+}
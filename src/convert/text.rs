@@ -0,0 +1,171 @@
+use crate::parser::parser::*;
+#[cfg(feature = "emoji")]
+use crate::emoji::shortcode;
+
+pub fn emoji_to_text(name: &str) -> String {
+    #[cfg(feature = "emoji")]
+    if let Some(emoji) = shortcode(name) {
+        return emoji.to_string()
+    }
+    format!(":{}:", name)
+}
+
+pub fn word_to_text(word: &Word) -> String {
+    match word {
+        Word::Normal(val) => val.to_string(),
+        Word::Entity(_, text) => text.clone(),
+        Word::Italic(words)
+        | Word::Bold(words)
+        | Word::StrikeThough(words)
+        | Word::Underline(words)
+        | Word::Highlight(words)
+        | Word::Subscript(words)
+        | Word::Superscript(words)
+        | Word::Insert(words)
+        | Word::Link(words, _) => words_to_text(words),
+        Word::Kbd(key) => key.clone(),
+        Word::Custom(_, source) => source.clone(),
+        Word::Emoji(name) => emoji_to_text(name),
+        Word::FootnoteRef(_) | Word::Task(_) => "".to_string(),
+        Word::Image(alt, ..) => alt.clone(),
+    }
+}
+
+pub fn words_to_text(words: &Words) -> String {
+    words.0.iter().map(word_to_text).collect()
+}
+
+// Cells are separated by tabs.
+pub fn record_to_text(record: &Record) -> String {
+    record.0.iter().map(words_to_text).collect::<Vec<String>>().join("\t")
+}
+
+fn join_lines(lines: impl IntoIterator<Item = String>) -> String {
+    lines.into_iter().filter(|line| !line.is_empty()).collect::<Vec<String>>().join("\n")
+}
+
+pub fn item_to_text(item: &Item) -> String {
+    join_lines(std::iter::once(words_to_text(&item.0)).chain(item.1.iter().map(md_to_text)))
+}
+
+// The readable text of a block, one line per row, item or child block. Front matter,
+// abbreviation definitions and table of contents markers have none.
+pub fn md_to_text(md: &Md) -> String {
+    match md {
+        Md::Heading(_, words) | Md::Sentence(words) | Md::Footnote(_, words) => words_to_text(words),
+        Md::Table(table) => {
+            let records = std::iter::once(&table.header).chain(&table.records).map(record_to_text);
+            join_lines(table.caption.iter().map(words_to_text).chain(records))
+        },
+        Md::LooseTable(table) => join_lines(std::iter::once(&table.header).chain(&table.rows).map(record_to_text)),
+        Md::List(items) => join_lines(items.0.iter().map(item_to_text)),
+        Md::Container(_, mds) | Md::Quote(mds) => mds_to_text(mds),
+        Md::Details(_, summary, mds) => join_lines(std::iter::once(words_to_text(summary)).chain(mds.iter().map(md_to_text))),
+        Md::Code(_, code, _) => code.clone(),
+        Md::Raw(text) | Md::Custom(_, text) => text.clone(),
+        Md::Toc | Md::Abbreviation(..) | Md::FrontMatter(..) => "".to_string(),
+    }
+}
+
+pub fn mds_to_text(mds: &[Md]) -> String {
+    join_lines(mds.iter().map(md_to_text))
+}
+
+// Markup stripped down to the text a reader sees, as for slugs, alt text or search indexes.
+pub trait ToPlainText {
+    fn to_plain_text(&self) -> String;
+}
+
+impl ToPlainText for Word<'_> {
+    fn to_plain_text(&self) -> String {
+        word_to_text(self)
+    }
+}
+
+impl ToPlainText for Words<'_> {
+    fn to_plain_text(&self) -> String {
+        words_to_text(self)
+    }
+}
+
+impl ToPlainText for Record<'_> {
+    fn to_plain_text(&self) -> String {
+        record_to_text(self)
+    }
+}
+
+impl ToPlainText for Item<'_> {
+    fn to_plain_text(&self) -> String {
+        item_to_text(self)
+    }
+}
+
+impl ToPlainText for Md<'_> {
+    fn to_plain_text(&self) -> String {
+        md_to_text(self)
+    }
+}
+
+impl ToPlainText for [Md<'_>] {
+    fn to_plain_text(&self) -> String {
+        mds_to_text(self)
+    }
+}
+
+// Columns the text takes up in a monospace font, counting CJK and other wide characters twice.
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c as u32 >= 0x1100 { 2 } else { 1 }).sum()
+}
+
+// Hebrew, Arabic, Syriac, Thaana, N'Ko and their presentation forms.
+fn is_rtl_char(c: char) -> bool {
+    matches!(c as u32, 0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF)
+}
+
+// Whether most of the letters in the text are written right to left.
+pub fn is_rtl(text: &str) -> bool {
+    let (rtl, ltr) = text.chars().filter(|c| c.is_alphabetic()).fold((0, 0), |(rtl, ltr), c| {
+        if is_rtl_char(c) { (rtl + 1, ltr) } else { (rtl, ltr + 1) }
+    });
+    rtl > ltr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{normal_word,words};
+
+    #[test]
+    fn test_words_to_text() {
+        let hello = normal_word!("Hello ");
+        let world = Word::Bold(words!(Word::Italic(words!(normal_word!("World")))));
+        let kbd = Word::Kbd("Ctrl".to_string());
+        let words = words!(hello, world, normal_word!(" "), kbd);
+        assert_eq!(words_to_text(&words), "Hello World Ctrl".to_string());
+        assert_eq!(mds_to_text(&parse("&copy; 2024 &#x1F600;")), "© 2024 😀");
+    }
+
+    #[test]
+    fn test_to_plain_text() {
+        let mds = parse("---\ntitle: x\n---\n# **Title**\n- one [link](/a)\n- two\n\n| A | *B* |\n| - | - |\n| 1 | 2 |\n> quoted *text*\n```\nlet x;\n```");
+        assert_eq!(mds.to_plain_text(), "Title\none link\ntwo\nA\tB\n1\t2\nquoted text\nlet x;");
+        assert_eq!(mds[1].to_plain_text(), "Title");
+        let Md::Table(table) = &mds[4] else { panic!("{:?}", mds[4]) };
+        assert_eq!(table.header.to_plain_text(), "A\tB");
+        assert_eq!(mds[0].to_plain_text(), "");
+    }
+
+    #[test]
+    fn test_is_rtl() {
+        assert!(is_rtl("שלום עולם"));
+        assert!(is_rtl("مرحبا بالعالم (Hello)"));
+        assert!(!is_rtl("Hello שלום world"));
+        assert!(!is_rtl("123 !?"));
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("見出し"), 6);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::parser::parser::*;
+use super::slug::Slugger;
+use super::text::words_to_text;
+use super::renderer::escape_html;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TocEntry {
+    pub level: usize,
+    pub text: String,
+    pub slug: String,
+    // Section number, e.g. `[1, 2]` for 1.2.
+    pub number: Vec<usize>,
+}
+
+impl TocEntry {
+    pub fn section(&self) -> String {
+        section(&self.number)
+    }
+}
+
+pub fn section(number: &[usize]) -> String {
+    number.iter().map(usize::to_string).collect::<Vec<String>>().join(".")
+}
+
+fn headings<'a>(mds: &'a [Md<'a>], found: &mut Vec<(usize, &'a Words<'a>)>) {
+    for md in mds {
+        match md {
+            Md::Heading(level, words) => found.push((*level, words)),
+            Md::Container(_, mds) | Md::Details(_, _, mds) | Md::Quote(mds) => headings(mds, found),
+            Md::List(items) => items.0.iter().for_each(|item| headings(&item.1, found)),
+            _ => {},
+        }
+    }
+}
+
+// Hierarchical section numbers counted from the document's shallowest heading, so a document
+// made of `##` headings still starts at 1. A skipped level counts as 0, as in 1.0.1.
+#[derive(Debug, Default)]
+pub struct Numbering {
+    top: usize,
+    counters: Vec<usize>,
+}
+
+impl Numbering {
+    pub fn new(mds: &[Md]) -> Self {
+        let mut found = vec!();
+        headings(mds, &mut found);
+        let top = found.iter().map(|(level, _)| *level).min().unwrap_or(1);
+        Numbering{ top, counters: vec!() }
+    }
+
+    pub fn next(&mut self, level: usize) -> Vec<usize> {
+        let depth = level.saturating_sub(self.top) + 1;
+        self.counters.resize(depth, 0);
+        self.counters[depth - 1] += 1;
+        self.counters.clone()
+    }
+}
+
+// Walks headings in the same order as rendering so the slugs and numbers match the headings.
+pub fn toc(mds: &[Md]) -> Vec<TocEntry> {
+    let mut found = vec!();
+    headings(mds, &mut found);
+    let (mut slugger, mut numbering) = (Slugger::new(), Numbering::new(mds));
+    found.into_iter().filter_map(|(level, words)| {
+        let text = words_to_text(words);
+        let slug = slugger.slug(&text);
+        let number = numbering.next(level);
+        if slug.is_empty() { return None }
+        Some(TocEntry{ level, text, slug, number })
+    })
+    .collect()
+}
+
+fn entries_to_html(entries: &[TocEntry], numbered: bool) -> String {
+    let mut items: Vec<String> = vec!();
+    let mut rest = entries;
+    while let Some((entry, tail)) = rest.split_first() {
+        let children = tail.iter().take_while(|child| child.level > entry.level).count();
+        // The text is decoded, so it's escaped again here.
+        let text = if numbered { format!("{} {}", entry.section(), entry.text) } else { entry.text.clone() };
+        let link = format!("<a href=\"#{}\">{}</a>", entry.slug, escape_html(&text));
+        let item = if children == 0 {
+            format!("<li>{}</li>", link)
+        } else {
+            format!("<li>{}\n{}\n</li>", link, entries_to_html(&tail[..children], numbered))
+        };
+        items.push(item);
+        rest = &tail[children..];
+    }
+    format!("<ul>\n{}\n</ul>", items.join("\n"))
+}
+
+// The elements `toc_to_html` renders for `entries`: the `<nav>`, a `<ul>` for the top level
+// and for each entry with children, and an `<li>` and `<a>` per entry.
+pub fn toc_elements(entries: &[TocEntry]) -> usize {
+    if entries.is_empty() { return 0 }
+    let parents = entries.windows(2).filter(|pair| pair[1].level > pair[0].level).count();
+    2 + parents + 2 * entries.len()
+}
+
+// `numbered` puts each entry's section number in front of its text.
+pub fn toc_to_html(entries: &[TocEntry], numbered: bool) -> String {
+    if entries.is_empty() { return "".to_string() }
+    format!("<nav class=\"toc\">\n{}\n</nav>", entries_to_html(entries, numbered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toc() {
+        let mds = parse("# Title\n## Usage\n::: note\n## Usage\n:::\n# ???");
+        let entries: Vec<(usize, String)> = toc(&mds).into_iter()
+            .map(|entry| (entry.level, entry.slug))
+            .collect();
+        let expect = vec!((1, "title".to_string()), (2, "usage".to_string()), (2, "usage-1".to_string()));
+        assert_eq!(entries, expect);
+    }
+
+    #[test]
+    fn test_toc_to_html() {
+        let mds = parse("## Intro\n# Title\n## Install\n### Cargo\n## Usage");
+        let expect = "<nav class=\"toc\">\n<ul>\n<li><a href=\"#intro\">Intro</a></li>\n<li><a href=\"#title\">Title</a>\n<ul>\n<li><a href=\"#install\">Install</a>\n<ul>\n<li><a href=\"#cargo\">Cargo</a></li>\n</ul>\n</li>\n<li><a href=\"#usage\">Usage</a></li>\n</ul>\n</li>\n</ul>\n</nav>";
+        assert_eq!(toc_to_html(&toc(&mds), false), expect.to_string());
+        assert_eq!(toc_to_html(&[], false), "".to_string());
+        assert_eq!(toc_elements(&toc(&mds)), expect.matches("<").count() - expect.matches("</").count());
+        assert_eq!(toc_elements(&[]), 0);
+
+        let mds = parse("# &lt;img src=x onerror=alert(1)&gt;");
+        let expect = "<nav class=\"toc\">\n<ul>\n<li><a href=\"#img-srcx-onerroralert1\">1 &lt;img src=x onerror=alert(1)&gt;</a></li>\n</ul>\n</nav>";
+        assert_eq!(toc_to_html(&toc(&mds), true), expect.to_string());
+    }
+
+    #[test]
+    fn test_numbering() {
+        let mds = parse("## Intro\n### ???\n### Setup\n## Usage\n## Notes");
+        let sections: Vec<String> = toc(&mds).iter().map(TocEntry::section).collect();
+        assert_eq!(sections, vec!("1", "1.2", "2", "3"));
+
+        let mds = parse("# Title\n### Deep\n# Next");
+        let sections: Vec<String> = toc(&mds).iter().map(TocEntry::section).collect();
+        assert_eq!(sections, vec!("1", "1.0.1", "2"));
+
+        let mds = parse("# A\n## B");
+        let expect = "<nav class=\"toc\">\n<ul>\n<li><a href=\"#a\">1 A</a>\n<ul>\n<li><a href=\"#b\">1.1 B</a></li>\n</ul>\n</li>\n</ul>\n</nav>";
+        assert_eq!(toc_to_html(&toc(&mds), true), expect.to_string());
+    }
+}
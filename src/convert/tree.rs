@@ -0,0 +1,245 @@
+use crate::parser::parser::Md;
+use super::convert::mds_to_html_with;
+use super::options::{RenderError, RenderOptions};
+
+// An element or a run of text, with entities decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HtmlNode {
+    Element { tag: String, attrs: Vec<(String, String)>, children: Vec<HtmlNode> },
+    Text(String),
+}
+
+// An element still waiting for its end tag.
+type Open = (String, Vec<(String, String)>, Vec<HtmlNode>);
+
+const VOID: [&str; 8] = ["br", "hr", "img", "input", "meta", "link", "wbr", "col"];
+const RAW: [&str; 2] = ["script", "style"];
+
+pub fn decode(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').filter(|end| *end <= 10).map(|end| &rest[1..end]);
+        let c = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                };
+                code.and_then(char::from_u32)
+            },
+        });
+        match (c, entity) {
+            (Some(c), Some(entity)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            },
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn attributes(text: &str) -> Vec<(String, String)> {
+    let mut attributes = vec!();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let name = rest[..end].to_ascii_lowercase();
+        rest = rest[end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, next) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = value[1..].find(quote).map(|end| end + 1).unwrap_or(value.len());
+                        (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                    },
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    },
+                };
+                rest = next.trim_start();
+                decode(value)
+            },
+            None => "".to_string(),
+        };
+        if !name.is_empty() { attributes.push((name, value)) }
+    }
+    attributes
+}
+
+// Tags whose end tag may be left out, with the tags that close them and the containers bounding the search.
+fn implied(name: &str) -> Option<(&'static [&'static str], &'static [&'static str])> {
+    match name {
+        "li" => Some((&["li"], &["ul", "ol"])),
+        "tr" => Some((&["tr", "td", "th"], &["table", "thead", "tbody", "tfoot"])),
+        "td" | "th" => Some((&["td", "th"], &["tr", "table"])),
+        "thead" | "tbody" | "tfoot" => Some((&["thead", "tbody", "tfoot", "tr", "td", "th"], &["table"])),
+        "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "table" | "pre" | "div" | "blockquote" => {
+            Some((&["p"], &["div", "li", "td", "th", "blockquote", "body", "details"]))
+        },
+        _ => None,
+    }
+}
+
+fn close_implied(stack: &mut Vec<Open>, name: &str) {
+    let Some((closes, bounds)) = implied(name) else { return };
+    let bound = stack.iter().rposition(|(open, _, _)| bounds.contains(&open.as_str())).unwrap_or(0);
+    let Some(open) = stack.iter().skip(bound + 1).position(|(open, _, _)| closes.contains(&open.as_str())) else { return };
+    while stack.len() > bound + 1 + open {
+        let (name, attributes, children) = stack.pop().unwrap();
+        stack.last_mut().unwrap().2.push(HtmlNode::Element{ tag: name, attrs: attributes, children });
+    }
+}
+
+// A forgiving tree builder: unknown closing tags are ignored and unclosed ones end with their parent.
+pub fn parse_html(html: &str) -> Vec<HtmlNode> {
+    let mut stack: Vec<Open> = vec!(("".to_string(), vec!(), vec!()));
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            stack.last_mut().unwrap().2.push(HtmlNode::Text(decode(rest)));
+            break;
+        };
+        if start > 0 {
+            stack.last_mut().unwrap().2.push(HtmlNode::Text(decode(&rest[..start])));
+        }
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or("");
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            stack.last_mut().unwrap().2.push(HtmlNode::Text(decode(rest)));
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('!') || tag.starts_with('?') { continue }
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim().to_ascii_lowercase();
+            if let Some(open) = stack.iter().rposition(|(open, _, _)| open == &name).filter(|open| *open > 0) {
+                while stack.len() > open {
+                    let (name, attributes, children) = stack.pop().unwrap();
+                    stack.last_mut().unwrap().2.push(HtmlNode::Element{ tag: name, attrs: attributes, children });
+                }
+            }
+            continue;
+        }
+        let closed = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let name = tag[..end].to_ascii_lowercase();
+        let attributes = attributes(&tag[end..]);
+        if RAW.contains(&name.as_str()) {
+            let close = format!("</{}", name);
+            let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+            let text = HtmlNode::Text(rest[..end].to_string());
+            rest = rest[end..].find('>').map(|close| &rest[end + close + 1..]).unwrap_or("");
+            stack.last_mut().unwrap().2.push(HtmlNode::Element{ tag: name, attrs: attributes, children: vec!(text) });
+        } else if closed || VOID.contains(&name.as_str()) {
+            stack.last_mut().unwrap().2.push(HtmlNode::Element{ tag: name, attrs: attributes, children: vec!() });
+        } else {
+            close_implied(&mut stack, &name);
+            stack.push((name, attributes, vec!()));
+        }
+    }
+    while stack.len() > 1 {
+        let (name, attributes, children) = stack.pop().unwrap();
+        stack.last_mut().unwrap().2.push(HtmlNode::Element{ tag: name, attrs: attributes, children });
+    }
+    stack.pop().unwrap().2
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+impl HtmlNode {
+    // Serializes the node the way the converter writes it: void elements as `<br />`,
+    // script and style contents untouched.
+    pub fn to_html(&self) -> String {
+        match self {
+            HtmlNode::Text(text) => escape_text(text),
+            HtmlNode::Element{ tag, attrs, children } => {
+                let attrs: String = attrs.iter()
+                    .map(|(key, value)| format!(" {}=\"{}\"", key, escape_text(value).replace('"', "&quot;")))
+                    .collect();
+                if VOID.contains(&tag.as_str()) {
+                    return format!("<{}{} />", tag, attrs);
+                }
+                let inner: String = if RAW.contains(&tag.as_str()) {
+                    children.iter().map(|child| match child {
+                        HtmlNode::Text(text) => text.clone(),
+                        other => other.to_html(),
+                    }).collect()
+                } else {
+                    tree_to_html(children)
+                };
+                format!("<{}{}>{}</{}>", tag, attrs, inner, tag)
+            },
+        }
+    }
+}
+
+pub fn tree_to_html(nodes: &[HtmlNode]) -> String {
+    nodes.iter().map(HtmlNode::to_html).collect()
+}
+
+/// Renders `mds` into a tree of elements instead of a string, for consumers that inspect or
+/// rewrite the output before serializing it with `tree_to_html`.
+pub fn mds_to_tree(mds: &[Md], options: &RenderOptions) -> Result<Vec<HtmlNode>, RenderError> {
+    Ok(parse_html(&mds_to_html_with(mds, options)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::parse;
+    use crate::convert::convert::mds_to_html;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("a &amp; b &lt;c&gt; &#65;&#x42; &unknown; & x"), "a & b <c> AB &unknown; & x");
+    }
+
+    #[test]
+    fn test_tree_round_trip() {
+        let mds = parse("# Title\n*a* and **b**\n| A | B |\n|:-|-:|\n| 1 | 2 |\n- x\n- y\n> quote");
+        let tree = mds_to_tree(&mds, &RenderOptions::default()).unwrap();
+        assert_eq!(tree_to_html(&tree), mds_to_html(&mds));
+    }
+
+    #[test]
+    fn test_tree_structure() {
+        let mds = parse("# Title\nHello");
+        let tree = mds_to_tree(&mds, &RenderOptions::default()).unwrap();
+        let tags: Vec<&str> = tree.iter().filter_map(|node| match node {
+            HtmlNode::Element{ tag, .. } => Some(tag.as_str()),
+            HtmlNode::Text(_) => None,
+        }).collect();
+        assert_eq!(tags[0], "h1");
+        assert_eq!(
+            HtmlNode::Element{
+                tag: "a".to_string(),
+                attrs: vec!(("title".to_string(), "\"x\" & y".to_string())),
+                children: vec!(HtmlNode::Text("<b>".to_string())),
+            }.to_html(),
+            "<a title=\"&quot;x&quot; &amp; y\">&lt;b&gt;</a>"
+        );
+    }
+}
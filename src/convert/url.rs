@@ -0,0 +1,100 @@
+// The host of an absolute or protocol-relative url, without user info or port.
+pub fn host(url: &str) -> Option<&str> {
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        None => url.strip_prefix("//")?,
+    };
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    Some(host.split(':').next().unwrap_or(host))
+}
+
+fn has_scheme(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else { return false };
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+}
+
+// Resolves a relative url against `base`, which is treated as a directory whether or not it
+// ends in `/`. Absolute urls and fragments are left alone; root-relative ones keep only the
+// base's scheme and host.
+pub fn resolve(base: &str, url: &str) -> String {
+    if url.is_empty() || url.starts_with('#') || url.starts_with("//") || has_scheme(url) {
+        return url.to_string()
+    }
+    let origin = match base.find("://") {
+        Some(i) => base[i + 3..].find('/').map_or(base.len(), |end| i + 3 + end),
+        None => 0,
+    };
+    if url.starts_with('/') {
+        return if origin == 0 { url.to_string() } else { format!("{}{}", &base[..origin], url) }
+    }
+    let mut segments: Vec<&str> = base[origin..].split('/').filter(|segment| !segment.is_empty()).collect();
+    let mut rest = url;
+    loop {
+        if let Some(after) = rest.strip_prefix("./") {
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("../") {
+            segments.pop();
+            rest = after;
+        } else {
+            break
+        }
+    }
+    let path: String = segments.iter().map(|segment| format!("{}/", segment)).collect();
+    if origin == 0 && !base.starts_with('/') {
+        return format!("{}{}", path, rest)
+    }
+    format!("{}/{}{}", &base[..origin], path, rest)
+}
+
+// A relative link to a markdown file pointing at its html output instead, e.g.
+// `guide/setup.md#install` becomes `guide/setup.html#install`.
+pub fn md_to_html_link(url: &str) -> Option<String> {
+    if url.starts_with("//") || has_scheme(url) { return None }
+    let end = url.find(['?', '#']).unwrap_or(url.len());
+    let stem = url[..end].strip_suffix(".md")?;
+    if stem.is_empty() || stem.ends_with('/') { return None }
+    Some(format!("{}.html{}", stem, &url[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host() {
+        assert_eq!(host("https://example.com:8080/x"), Some("example.com"));
+        assert_eq!(host("//cdn.example.org/a.js"), Some("cdn.example.org"));
+        assert_eq!(host("https://user@example.com"), Some("example.com"));
+        assert_eq!(host("/about"), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = "https://example.com/docs/v1";
+        assert_eq!(resolve(base, "img/a.png"), "https://example.com/docs/v1/img/a.png");
+        assert_eq!(resolve(base, "./a.md"), "https://example.com/docs/v1/a.md");
+        assert_eq!(resolve(base, "../v2/a.md"), "https://example.com/docs/v2/a.md");
+        assert_eq!(resolve(base, "/about"), "https://example.com/about");
+        assert_eq!(resolve(base, "#usage"), "#usage");
+        assert_eq!(resolve(base, "mailto:a@b.c"), "mailto:a@b.c");
+        assert_eq!(resolve(base, "//cdn.example.org/a.js"), "//cdn.example.org/a.js");
+        assert_eq!(resolve("https://example.com", "a.png"), "https://example.com/a.png");
+        assert_eq!(resolve("/blog/", "a.png"), "/blog/a.png");
+        assert_eq!(resolve("/blog/", "/a.png"), "/a.png");
+        assert_eq!(resolve("assets", "a.png"), "assets/a.png");
+    }
+
+    #[test]
+    fn test_md_to_html_link() {
+        assert_eq!(md_to_html_link("setup.md"), Some("setup.html".to_string()));
+        assert_eq!(md_to_html_link("../guide/setup.md#install"), Some("../guide/setup.html#install".to_string()));
+        assert_eq!(md_to_html_link("/docs/a.md?raw=1"), Some("/docs/a.html?raw=1".to_string()));
+        assert_eq!(md_to_html_link("https://example.com/a.md"), None);
+        assert_eq!(md_to_html_link("//example.com/a.md"), None);
+        assert_eq!(md_to_html_link("notes.mdx"), None);
+        assert_eq!(md_to_html_link("dir/.md"), None);
+        assert_eq!(md_to_html_link("#a.md"), None);
+    }
+}
@@ -0,0 +1,50 @@
+use super::options::VoidStyle;
+
+const VOID: [&str; 7] = ["br", "hr", "img", "meta", "link", "input", "wbr"];
+
+fn void_tag(tag: &str, style: VoidStyle) -> Option<String> {
+    let name_end = tag.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(tag.len());
+    if !VOID.contains(&tag[..name_end].to_ascii_lowercase().as_str()) { return None }
+    let tag = tag.trim_end().trim_end_matches('/').trim_end();
+    Some(match style {
+        VoidStyle::Keep => return None,
+        VoidStyle::Spaced => format!("<{} />", tag),
+        VoidStyle::Html => format!("<{}>", tag),
+        VoidStyle::Xhtml => format!("<{}/>", tag),
+    })
+}
+
+// Rewrites void elements like `<br />` to the chosen style. Xhtml also spells out the
+// `open` attribute of `<details>`, as XML has no bare attributes.
+pub fn void_elements(html: &str, style: VoidStyle) -> String {
+    if style == VoidStyle::Keep { return html.to_string() }
+    let mut rewritten = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rewritten.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('>') else { break };
+        let tag = &rest[1..end];
+        match void_tag(tag, style) {
+            Some(tag) => rewritten.push_str(&tag),
+            None if style == VoidStyle::Xhtml && tag == "details open" => rewritten.push_str("<details open=\"open\">"),
+            None => rewritten.push_str(&rest[..=end]),
+        }
+        rest = &rest[(end + 1)..];
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_void_elements() {
+        let html = "a<br />\n<meta charset=\"utf-8\">\n<details open>\n<hr/><b>x</b>";
+        assert_eq!(void_elements(html, VoidStyle::Spaced), "a<br />\n<meta charset=\"utf-8\" />\n<details open>\n<hr /><b>x</b>");
+        assert_eq!(void_elements(html, VoidStyle::Html), "a<br>\n<meta charset=\"utf-8\">\n<details open>\n<hr><b>x</b>");
+        assert_eq!(void_elements(html, VoidStyle::Xhtml), "a<br/>\n<meta charset=\"utf-8\"/>\n<details open=\"open\">\n<hr/><b>x</b>");
+    }
+}
@@ -0,0 +1,321 @@
+use crate::parser::parser::*;
+use crate::convert::text::words_to_text;
+use crate::format::format::md_to_markdown;
+use crate::format::options::FormatOptions;
+use std::fmt;
+use std::mem::discriminant;
+
+#[derive(Debug, PartialEq)]
+pub enum Change<'a> {
+    Added(usize, &'a Md<'a>),
+    Removed(usize, &'a Md<'a>),
+    Modified(usize, usize, &'a Md<'a>, &'a Md<'a>),
+}
+
+fn kind(md: &Md) -> &'static str {
+    match md {
+        Md::Heading(..) => "heading",
+        Md::Sentence(_) => "sentence",
+        Md::Table(_) | Md::LooseTable(_) => "table",
+        Md::List(_) => "list",
+        Md::Container(..) => "container",
+        Md::Details(..) => "details",
+        Md::Toc => "toc",
+        Md::Abbreviation(..) => "abbreviation",
+        Md::Footnote(..) => "footnote",
+        Md::FrontMatter(..) => "front matter",
+        Md::Code(..) => "code",
+        Md::Quote(_) => "quote",
+        Md::Raw(_) => "raw",
+        Md::Custom(..) => "custom",
+    }
+}
+
+fn snippet(md: &Md) -> String {
+    let markdown = md_to_markdown(md, &FormatOptions::default());
+    let line = markdown.lines().next().unwrap_or("");
+    if line.chars().count() > 40 {
+        format!("{}...", line.chars().take(40).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn rows(md: &Md) -> Option<usize> {
+    match md {
+        Md::Table(table) => Some(table.records.len()),
+        Md::LooseTable(table) => Some(table.rows.len()),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Added(_, md) => write!(f, "+ {} added: {}", kind(md), snippet(md)),
+            Change::Removed(_, md) => write!(f, "- {} removed: {}", kind(md), snippet(md)),
+            Change::Modified(_, _, old, new) => {
+                write!(f, "~ {} modified: {}", kind(new), snippet(new))?;
+                match (rows(old), rows(new)) {
+                    (Some(before), Some(after)) if before != after => {
+                        write!(f, " ({} rows -> {} rows)", before, after)
+                    },
+                    _ if snippet(old) != snippet(new) => write!(f, " (was: {})", snippet(old)),
+                    _ => Ok(()),
+                }
+            },
+        }
+    }
+}
+
+// What changed inside a modified block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InlineChange {
+    // The old and new heading level.
+    Level(usize, usize),
+    // Words of a heading, sentence or footnote, in order, that were removed and added.
+    Words{ removed: Vec<String>, added: Vec<String> },
+    // A table cell by row and column, with row 0 the header; a missing cell is empty.
+    Cell{ row: usize, column: usize, old: String, new: String },
+    // A list item's text by index; a missing item is empty.
+    Item{ index: usize, old: String, new: String },
+}
+
+impl fmt::Display for InlineChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InlineChange::Level(old, new) => write!(f, "level {} -> {}", old, new),
+            InlineChange::Words{ removed, added } => {
+                let removed = removed.iter().map(|word| format!("-{}", word));
+                let added = added.iter().map(|word| format!("+{}", word));
+                write!(f, "words {}", removed.chain(added).collect::<Vec<String>>().join(" "))
+            },
+            InlineChange::Cell{ row, column, old, new } => write!(f, "cell {},{}: {} -> {}", row, column, old, new),
+            InlineChange::Item{ index, old, new } => write!(f, "item {}: {} -> {}", index + 1, old, new),
+        }
+    }
+}
+
+// The lengths of the common prefix and suffix of `old` and `new`, which never overlap. They are
+// matched without building the LCS table, keeping it small when only part of a document changed.
+fn common_ends<T: PartialEq>(old: &[T], new: &[T]) -> (usize, usize) {
+    let prefix = old.iter().zip(new).take_while(|(old, new)| old == new).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(old, new)| old == new).count();
+    (prefix, suffix)
+}
+
+// The elements of `old` and of `new` that are not part of their longest common subsequence.
+fn unmatched<'t, T: PartialEq>(old: &'t [T], new: &'t [T]) -> (Vec<&'t T>, Vec<&'t T>) {
+    let (prefix, suffix) = common_ends(old, new);
+    let (old, new) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec!(vec!(0usize; m + 1); n + 1);
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let (mut removed, mut added) = (vec!(), vec!());
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(&new[j]);
+            j += 1;
+        } else {
+            removed.push(&old[i]);
+            i += 1;
+        }
+    }
+    (removed, added)
+}
+
+fn word_changes(old: &Words, new: &Words) -> Option<InlineChange> {
+    let (old, new) = (words_to_text(old), words_to_text(new));
+    let (old, new): (Vec<&str>, Vec<&str>) = (old.split_whitespace().collect(), new.split_whitespace().collect());
+    let (removed, added) = unmatched(&old, &new);
+    if removed.is_empty() && added.is_empty() { return None }
+    let strings = |words: Vec<&&str>| words.into_iter().map(|word| word.to_string()).collect();
+    Some(InlineChange::Words{ removed: strings(removed), added: strings(added) })
+}
+
+fn records<'r, 'a>(md: &'r Md<'a>) -> Option<Vec<&'r Record<'a>>> {
+    match md {
+        Md::Table(table) => Some(std::iter::once(&table.header).chain(&table.records).collect()),
+        Md::LooseTable(table) => Some(std::iter::once(&table.header).chain(&table.rows).collect()),
+        _ => None,
+    }
+}
+
+// Pairs up the texts at each index of `old` and `new`, which may differ in length.
+fn zip_texts(old: Vec<String>, new: Vec<String>) -> Vec<(usize, String, String)> {
+    (0..old.len().max(new.len())).filter_map(|index| {
+        let (old, new) = (old.get(index).cloned().unwrap_or_default(), new.get(index).cloned().unwrap_or_default());
+        (old != new).then_some((index, old, new))
+    })
+    .collect()
+}
+
+// The changes within two blocks of the same kind: heading levels and words, sentence words,
+// table cells and list items. Other blocks report nothing finer than the block itself.
+pub fn inline_changes(old: &Md, new: &Md) -> Vec<InlineChange> {
+    match (old, new) {
+        (Md::Heading(old_level, old), Md::Heading(new_level, new)) => {
+            let level = (old_level != new_level).then_some(InlineChange::Level(*old_level, *new_level));
+            level.into_iter().chain(word_changes(old, new)).collect()
+        },
+        (Md::Sentence(old), Md::Sentence(new)) | (Md::Footnote(_, old), Md::Footnote(_, new)) => {
+            word_changes(old, new).into_iter().collect()
+        },
+        (Md::List(old), Md::List(new)) => {
+            let texts = |items: &Items| items.0.iter().map(|item| words_to_text(&item.0)).collect();
+            zip_texts(texts(old), texts(new)).into_iter()
+                .map(|(index, old, new)| InlineChange::Item{ index, old, new })
+                .collect()
+        },
+        _ => match (records(old), records(new)) {
+            (Some(old), Some(new)) => {
+                let texts = |records: &[&Record], row: usize| records.get(row)
+                    .map_or(vec!(), |record| record.0.iter().map(words_to_text).collect());
+                (0..old.len().max(new.len())).flat_map(|row| {
+                    zip_texts(texts(&old, row), texts(&new, row)).into_iter()
+                        .map(move |(column, old, new)| InlineChange::Cell{ row, column, old, new })
+                })
+                .collect()
+            },
+            _ => vec!(),
+        },
+    }
+}
+
+impl Change<'_> {
+    pub fn inline(&self) -> Vec<InlineChange> {
+        match self {
+            Change::Modified(_, _, old, new) => inline_changes(old, new),
+            _ => vec!(),
+        }
+    }
+}
+
+fn flush<'a>(
+    changes: &mut Vec<Change<'a>>,
+    removed: &mut Vec<(usize, &'a Md)>,
+    added: &mut Vec<(usize, &'a Md)>
+) {
+    let mut unpaired: Vec<Option<(usize, &Md)>> = added.drain(..).map(Some).collect();
+    for (i, old) in removed.drain(..) {
+        let pair = unpaired.iter_mut()
+            .find(|new| new.is_some_and(|(_, new)| discriminant(old) == discriminant(new)))
+            .and_then(|new| new.take());
+        match pair {
+            Some((j, new)) => changes.push(Change::Modified(i, j, old, new)),
+            None => changes.push(Change::Removed(i, old)),
+        }
+    }
+    changes.extend(unpaired.into_iter().flatten().map(|(j, new)| Change::Added(j, new)));
+}
+
+pub fn diff<'a>(old: &'a [Md], new: &'a [Md]) -> Vec<Change<'a>> {
+    let (prefix, suffix) = common_ends(old, new);
+    let (old, new) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec!(vec!(0usize; m + 1); n + 1);
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes: Vec<Change> = vec!();
+    let mut removed = vec!();
+    let mut added = vec!();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            flush(&mut changes, &mut removed, &mut added);
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push((prefix + j, &new[j]));
+            j += 1;
+        } else {
+            removed.push((prefix + i, &old[i]));
+            i += 1;
+        }
+    }
+    flush(&mut changes, &mut removed, &mut added);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_same() {
+        let old = parse("# Title\nHello");
+        let new = parse("# Title\nHello");
+        assert_eq!(diff(&old, &new), vec!());
+    }
+
+    #[test]
+    fn test_diff_added_removed() {
+        let old = parse("# Title\nHello\nBye");
+        let new = parse("# Title\n## Install\nHello");
+        let changes = diff(&old, &new);
+        assert_eq!(changes, vec!(Change::Added(1, &new[1]), Change::Removed(2, &old[2])));
+        assert_eq!(changes[0].to_string(), "+ heading added: ## Install");
+        assert_eq!(changes[1].to_string(), "- sentence removed: Bye");
+    }
+
+    #[test]
+    fn test_diff_modified() {
+        let old = parse("# Title\n| A |\n| - |\n| a |\nEnd");
+        let new = parse("# Title\n| A |\n| - |\n| a |\n| b |\nEnd");
+        let changes = diff(&old, &new);
+        assert_eq!(changes, vec!(Change::Modified(1, 1, &old[1], &new[1])));
+        assert_eq!(changes[0].to_string(), "~ table modified: | A | (1 rows -> 2 rows)");
+
+        let old = parse("# Title");
+        let new = parse("# New title");
+        let changes = diff(&old, &new);
+        assert_eq!(changes[0].to_string(), "~ heading modified: # New title (was: # Title)");
+    }
+
+    #[test]
+    fn test_diff_common_ends() {
+        let old = parse("# Title\nA\nB\nC\nD");
+        let new = parse("# Title\nA\nX\nC\nD");
+        assert_eq!(common_ends(&old, &new), (2, 2));
+        assert_eq!(diff(&old, &new), vec!(Change::Modified(2, 2, &old[2], &new[2])));
+
+        let old = parse("A\nA");
+        let new = parse("A");
+        assert_eq!(common_ends(&old, &new), (1, 0));
+        assert_eq!(diff(&old, &new), vec!(Change::Removed(1, &old[1])));
+    }
+
+    #[test]
+    fn test_inline_changes() {
+        let old = parse("# Old title\nThe quick brown fox\n- one\n- two\n\n| A | B |\n| - | - |\n| a | b |");
+        let new = parse("## New title\nThe slow brown fox jumps\n- one\n- three\n- four\n\n| A | C |\n| - | - |\n| a | b |\n| c | d |");
+        let changes = diff(&old, &new);
+        let inline: Vec<Vec<String>> = changes.iter()
+            .map(|change| change.inline().iter().map(InlineChange::to_string).collect())
+            .collect();
+        assert_eq!(inline, vec!(
+            vec!("level 1 -> 2".to_string(), "words -Old +New".to_string()),
+            vec!("words -quick +slow +jumps".to_string()),
+            vec!("item 2: two -> three".to_string(), "item 3:  -> four".to_string()),
+            vec!("cell 0,1: B -> C".to_string(), "cell 2,0:  -> c".to_string(), "cell 2,1:  -> d".to_string()),
+        ));
+        assert_eq!(inline_changes(&old[0], &old[0]), vec!());
+        assert_eq!(Change::Added(0, &new[0]).inline(), vec!());
+    }
+}
@@ -0,0 +1,367 @@
+use crate::parser::parser::*;
+use crate::convert::text::words_to_text;
+use crate::parser::options::ParserOptions;
+use crate::parser::span::{spans, NodeRef, SourceSpan, Spanned};
+use crate::parser::visit::{walk, walk_item, walk_md, walk_record, walk_word, Visitor};
+use crate::query::kind;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// The text of the first `#` heading. Unlike the page title of a standalone document, this
+// ignores the front matter and lower headings.
+pub fn title(mds: &[Md]) -> Option<String> {
+    mds.iter().find_map(|md| match md {
+        Md::Heading(1, words) => Some(words_to_text(words)),
+        _ => None,
+    })
+}
+
+// The first paragraph as plain text, cut after `n_words` words with an ellipsis, for index
+// pages and feeds. A paragraph is a run of top-level lines up to a blank line or another block.
+pub fn summary(mds: &[Md], n_words: usize) -> Option<String> {
+    let mut lines = vec!();
+    for md in mds {
+        match md {
+            Md::Sentence(words) => {
+                let line = words_to_text(words);
+                if !line.trim().is_empty() { lines.push(line) } else if !lines.is_empty() { break }
+            },
+            _ if !lines.is_empty() => break,
+            _ => {},
+        }
+    }
+    let text = lines.join(" ");
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() { return None }
+    let cut = words.len() > n_words;
+    let summary = words[..words.len().min(n_words)].join(" ");
+    Some(if cut { summary + "…" } else { summary })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatsOptions {
+    // Counts the text of code blocks too.
+    pub code: bool,
+    pub words_per_minute: usize,
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        StatsOptions{ code: true, words_per_minute: 200 }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DocStats {
+    pub words: usize,
+    // Characters other than whitespace.
+    pub characters: usize,
+    pub reading_time: Duration,
+}
+
+// The readable text of the document, with a space between blocks, cells and items.
+struct Text {
+    text: String,
+    code: bool,
+}
+
+impl<'a> Visitor<'a> for Text {
+    fn visit_md(&mut self, md: &'a Md) {
+        self.text.push(' ');
+        walk_md(self, md)
+    }
+
+    fn visit_table_cell(&mut self, words: &'a Words) {
+        self.text.push(' ');
+        self.visit_words(words)
+    }
+
+    fn visit_item(&mut self, item: &'a Item) {
+        self.text.push(' ');
+        walk_item(self, item)
+    }
+
+    fn visit_text(&mut self, text: &'a str) {
+        self.text.push_str(text)
+    }
+
+    fn visit_code(&mut self, _lang: &'a str, code: &'a str) {
+        if self.code { self.text.push_str(code) }
+    }
+}
+
+// Words are separated by whitespace, except that each CJK or other wide character counts as
+// a word of its own, since those scripts do not put spaces between words.
+fn count_words(text: &str) -> usize {
+    text.split_whitespace().map(|word| {
+        let wide = word.chars().filter(|c| *c as u32 >= 0x1100).count();
+        wide + usize::from(word.chars().any(|c| (c as u32) < 0x1100))
+    })
+    .sum()
+}
+
+pub fn stats_with(mds: &[Md], options: &StatsOptions) -> DocStats {
+    let mut text = Text{ text: String::new(), code: options.code };
+    walk(&mut text, mds);
+    let words = count_words(&text.text);
+    let characters = text.text.chars().filter(|c| !c.is_whitespace()).count();
+    let seconds = (words * 60).div_ceil(options.words_per_minute.max(1));
+    DocStats{ words, characters, reading_time: Duration::from_secs(seconds as u64) }
+}
+
+// Word and character counts over headings, sentences, list items, table cells and code, and
+// the reading time at 200 words a minute.
+pub fn stats(mds: &[Md]) -> DocStats {
+    stats_with(mds, &StatsOptions::default())
+}
+
+// An image in the document: `block` is the index of the top-level block it sits in, and
+// `span` is where it is in the input, when the text was given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageRef {
+    pub alt: String,
+    pub src: String,
+    pub block: usize,
+    pub span: Option<SourceSpan>,
+}
+
+struct Images {
+    images: Vec<ImageRef>,
+    block: usize,
+}
+
+impl<'a> Visitor<'a> for Images {
+    fn visit_image(&mut self, alt: &'a str, src: &'a str, _size: &'a ImageSize) {
+        self.images.push(ImageRef{ alt: alt.to_string(), src: src.to_string(), block: self.block, span: None })
+    }
+}
+
+// Every image in document order, including those in tables, lists and containers.
+pub fn extract_images(mds: &[Md]) -> Vec<ImageRef> {
+    let mut images = Images{ images: vec!(), block: 0 };
+    for (block, md) in mds.iter().enumerate() {
+        images.block = block;
+        images.visit_md(md);
+    }
+    images.images
+}
+
+fn spanned_images(spanned: &Spanned, block: usize, images: &mut Vec<ImageRef>) {
+    if let NodeRef::Word(Word::Image(alt, src, _)) = spanned.node {
+        images.push(ImageRef{ alt: alt.clone(), src: src.clone(), block, span: Some(spanned.span) });
+    }
+    for child in &spanned.children {
+        spanned_images(child, block, images)
+    }
+}
+
+// Like `extract_images`, with the span of each image in `text`, which `mds` was parsed from.
+pub fn extract_images_located(text: &str, mds: &[Md], options: &ParserOptions) -> Vec<ImageRef> {
+    let mut images = vec!();
+    for (block, spanned) in spans(text, mds, options).iter().enumerate() {
+        spanned_images(spanned, block, &mut images)
+    }
+    images
+}
+
+// A heading for a document outline; `span` is set as for `ImageRef`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    pub level: usize,
+    pub text: String,
+    pub span: Option<SourceSpan>,
+}
+
+struct Headings {
+    headings: Vec<Heading>,
+}
+
+impl<'a> Visitor<'a> for Headings {
+    fn visit_md(&mut self, md: &'a Md) {
+        if let Md::Heading(level, words) = md {
+            self.headings.push(Heading{ level: *level, text: words_to_text(words), span: None });
+        }
+        walk_md(self, md)
+    }
+}
+
+// The headings in document order, including those in lists, quotes and containers.
+pub fn outline(mds: &[Md]) -> Vec<Heading> {
+    let mut headings = Headings{ headings: vec!() };
+    walk(&mut headings, mds);
+    headings.headings
+}
+
+fn spanned_headings(spanned: &Spanned, headings: &mut Vec<Heading>) {
+    if let NodeRef::Md(Md::Heading(level, words)) = spanned.node {
+        headings.push(Heading{ level: *level, text: words_to_text(words), span: Some(spanned.span) });
+    }
+    for child in &spanned.children {
+        spanned_headings(child, headings)
+    }
+}
+
+pub fn outline_located(text: &str, mds: &[Md], options: &ParserOptions) -> Vec<Heading> {
+    let mut headings = vec!();
+    for spanned in spans(text, mds, options).iter() {
+        spanned_headings(spanned, &mut headings)
+    }
+    headings
+}
+
+// How much of each kind of node a document has.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Profile {
+    // Counts by the node kinds of `query`, like `heading`, `item` or `link`.
+    pub kinds: BTreeMap<&'static str, usize>,
+    pub headings: BTreeMap<usize, usize>,
+    pub tables: usize,
+    pub cells: usize,
+    // Emphasis, strong, strikethrough, underline and highlight spans by how deeply they are
+    // nested: index 0 counts the outermost ones.
+    pub emphasis_depth: Vec<usize>,
+}
+
+#[derive(Default)]
+struct Profiler {
+    profile: Profile,
+    depth: usize,
+}
+
+impl Profiler {
+    fn count(&mut self, node: NodeRef) {
+        *self.profile.kinds.entry(kind(&node)).or_default() += 1;
+    }
+}
+
+impl<'a> Visitor<'a> for Profiler {
+    fn visit_md(&mut self, md: &'a Md) {
+        self.count(NodeRef::Md(md));
+        match md {
+            Md::Heading(level, _) => *self.profile.headings.entry(*level).or_default() += 1,
+            Md::Table(_) | Md::LooseTable(_) => self.profile.tables += 1,
+            _ => {},
+        }
+        walk_md(self, md)
+    }
+
+    fn visit_record(&mut self, record: &'a Record) {
+        self.count(NodeRef::Record(record));
+        walk_record(self, record)
+    }
+
+    fn visit_table_cell(&mut self, words: &'a Words) {
+        self.count(NodeRef::Cell(words));
+        self.profile.cells += 1;
+        self.visit_words(words)
+    }
+
+    fn visit_item(&mut self, item: &'a Item) {
+        self.count(NodeRef::Item(item));
+        walk_item(self, item)
+    }
+
+    fn visit_word(&mut self, word: &'a Word) {
+        self.count(NodeRef::Word(word));
+        let emphasis = matches!(word, Word::Italic(_) | Word::Bold(_) | Word::StrikeThough(_) | Word::Underline(_) | Word::Highlight(_));
+        if !emphasis { return walk_word(self, word) }
+        if self.profile.emphasis_depth.len() <= self.depth { self.profile.emphasis_depth.push(0) }
+        self.profile.emphasis_depth[self.depth] += 1;
+        self.depth += 1;
+        walk_word(self, word);
+        self.depth -= 1;
+    }
+}
+
+pub fn profile(mds: &[Md]) -> Profile {
+    let mut profiler = Profiler::default();
+    walk(&mut profiler, mds);
+    profiler.profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title() {
+        assert_eq!(title(&parse("---\ntitle: Meta\n---\n## Sub\n# Main *title*\n# Later")), Some("Main title".to_string()));
+        assert_eq!(title(&parse("## Only a subheading")), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mds = parse("# Title\n\nThe **first** [paragraph](/p)\nruns on.\n\nSecond paragraph.");
+        assert_eq!(summary(&mds, 10), Some("The first paragraph runs on.".to_string()));
+        assert_eq!(summary(&mds, 3), Some("The first paragraph…".to_string()));
+        assert_eq!(summary(&parse("- only\n- a list"), 10), None);
+        assert_eq!(summary(&parse("Intro line\n## Heading\nMore"), 10), Some("Intro line".to_string()));
+    }
+
+    #[test]
+    fn test_stats() {
+        let mds = parse("# Two words
+- one
+- *two* items
+
+| A | B |
+| - | - |
+| c | d |
+```
+let x;
+```");
+        assert_eq!(stats(&mds), DocStats{ words: 11, characters: 28, reading_time: Duration::from_secs(4) });
+        let options = StatsOptions{ code: false, words_per_minute: 60 };
+        assert_eq!(stats_with(&mds, &options), DocStats{ words: 9, characters: 23, reading_time: Duration::from_secs(9) });
+        assert_eq!(stats(&parse("日本語の text")).words, 5);
+        assert_eq!(stats(&[]), DocStats::default());
+    }
+
+    #[test]
+    fn test_extract_images() {
+        let text = "# ![logo](logo.png)\n\nText ![a *b*](img/a.png){width=10}\n- ![badge](b.svg)\n\n| ![c](c.jpg) |\n| - |";
+        let mds = parse(text);
+        let images = extract_images(&mds);
+        let sources: Vec<(&str, &str, usize)> = images.iter().map(|image| (image.alt.as_str(), image.src.as_str(), image.block)).collect();
+        assert_eq!(sources, vec!(("logo", "logo.png", 0), ("a *b*", "img/a.png", 2), ("badge", "b.svg", 3), ("c", "c.jpg", 5)));
+        assert!(images.iter().all(|image| image.span.is_none()));
+
+        let located = extract_images_located(text, &mds, &ParserOptions::default());
+        assert_eq!(located.len(), 4);
+        let span = located[1].span.unwrap();
+        assert_eq!((&text[span.start..span.end], span.line, span.column), ("![a *b*](img/a.png){width=10}", 3, 6));
+        assert_eq!(located[2].span.map(|span| span.line), Some(4));
+        assert_eq!(extract_images(&parse("No images")), vec!());
+    }
+
+    #[test]
+    fn test_outline() {
+        let text = "# Title\nIntro\n## *Usage*\n> ### Quoted\n\n## Last";
+        let mds = parse(text);
+        let headings = outline(&mds);
+        let headings: Vec<(usize, &str)> = headings.iter().map(|heading| (heading.level, heading.text.as_str())).collect();
+        assert_eq!(headings, vec!((1, "Title"), (2, "Usage"), (3, "Quoted"), (2, "Last")));
+
+        let located = outline_located(text, &mds, &ParserOptions::default());
+        let lines: Vec<(usize, &str)> = located.iter().map(|heading| {
+            let span = heading.span.unwrap();
+            (span.line, &text[span.start..span.end])
+        })
+        .collect();
+        assert_eq!(lines, vec!((1, "# Title"), (3, "## *Usage*"), (4, "### Quoted"), (6, "## Last")));
+        assert_eq!(outline(&parse("No headings")), vec!());
+    }
+
+    #[test]
+    fn test_profile() {
+        let mds = parse("# Title\n## A\n## B\nSome *em **strong ~~deep~~** again* and **more**\n\n| A | B |\n| - | - |\n| 1 | *2* |\n- item");
+        let profile = profile(&mds);
+        assert_eq!(profile.headings, BTreeMap::from([(1, 1), (2, 2)]));
+        assert_eq!((profile.tables, profile.cells), (1, 4));
+        assert_eq!(profile.emphasis_depth, vec!(3, 1, 1));
+        assert_eq!(profile.kinds["heading"], 3);
+        assert_eq!(profile.kinds["row"], 2);
+        assert_eq!(profile.kinds["item"], 1);
+        assert_eq!(super::profile(&[]), Profile::default());
+    }
+}
@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod format;
+pub mod options;
+pub mod roundtrip;
@@ -0,0 +1,460 @@
+use crate::parser::parser::*;
+use super::options::*;
+use crate::convert::text::display_width;
+
+fn word_to_markdown(word: &Word) -> String {
+    match word {
+        Word::Normal(val) => val.to_string(),
+        Word::Italic(words) => format!("*{}*", words_to_markdown(words)),
+        Word::Bold(words) => format!("**{}**", words_to_markdown(words)),
+        Word::StrikeThough(words) => format!("~~{}~~", words_to_markdown(words)),
+        Word::Underline(words) => format!("__{}__", words_to_markdown(words)),
+        Word::Highlight(words) => format!("=={}==", words_to_markdown(words)),
+        Word::Subscript(words) => format!("~{}~", words_to_markdown(words)),
+        Word::Superscript(words) => format!("^{}^", words_to_markdown(words)),
+        Word::Insert(words) => format!("++{}++", words_to_markdown(words)),
+        Word::Kbd(key) => format!("[[{}]]", key),
+        Word::Custom(_, source) => source.clone(),
+        Word::Emoji(name) => format!(":{}:", name),
+        Word::FootnoteRef(label) => format!("[^{}]", label),
+        Word::Task(checked) => if *checked { "[x]" } else { "[ ]" }.to_string(),
+        Word::Entity(reference, _) => reference.clone(),
+        Word::Image(alt, src, size) => image_to_markdown(alt, src, size),
+        Word::Link(words, url) => format!("[{}]({})", words_to_markdown(words), url),
+    }
+}
+
+// `=640x480` when both sides are plain pixel counts, the attribute form otherwise.
+fn image_to_markdown(alt: &str, src: &str, size: &ImageSize) -> String {
+    let pixels = |value: &Option<String>| value.as_ref().is_none_or(|value| value.chars().all(|c| c.is_ascii_digit()));
+    if *size == ImageSize::default() {
+        return format!("![{}]({})", alt, src)
+    }
+    if pixels(&size.width) && pixels(&size.height) {
+        let side = |value: &Option<String>| value.clone().unwrap_or_default();
+        return format!("![{}]({} ={}x{})", alt, src, side(&size.width), side(&size.height))
+    }
+    let pairs: Vec<String> = [("width", &size.width), ("height", &size.height)].iter()
+        .filter_map(|(key, value)| value.as_ref().map(|value| format!("{}={}", key, value)))
+        .collect();
+    format!("![{}]({}){{{}}}", alt, src, pairs.join(" "))
+}
+
+fn words_to_markdown(words: &Words) -> String {
+    let words = &words.0;
+    words.iter().map(word_to_markdown)
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn cells_to_markdown(record: &Record) -> Vec<String> {
+    record.0.iter().map(|words| words_to_markdown(words).replace('|', "\\|")).collect()
+}
+
+fn pad(cell: &str, width: usize, align: Option<&Align>) -> String {
+    let space = width.saturating_sub(display_width(cell));
+    match align {
+        Some(Align::Right) => format!("{}{}", " ".repeat(space), cell),
+        Some(Align::Center) => format!("{}{}{}", " ".repeat(space / 2), cell, " ".repeat(space - space / 2)),
+        _ => format!("{}{}", cell, " ".repeat(space)),
+    }
+}
+
+// Cells are padded to `widths` when given, which is empty unless tables are aligned.
+fn record_to_markdown(cells: &[String], widths: &[usize], aligns: &[Align]) -> String {
+    let cells: Vec<String> = cells.iter().enumerate().map(|(i, cell)| match widths.get(i) {
+        Some(width) => pad(cell, *width, aligns.get(i)),
+        None => cell.clone(),
+    })
+    .collect();
+    format!("| {} |", cells.join(" | "))
+}
+
+fn column_widths(rows: &[Vec<String>], options: &FormatOptions) -> Vec<usize> {
+    if !options.align_tables { return vec!() }
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    (0..columns).map(|i| {
+        rows.iter().filter_map(|row| row.get(i)).map(|cell| display_width(cell)).max().unwrap_or(0).max(3)
+    })
+    .collect()
+}
+
+fn align_to_markdown(align: &Align, width: usize) -> String {
+    match align {
+        Align::Right => format!("{}:", "-".repeat(width.max(3) - 1)),
+        Align::Center => format!(":{}:", "-".repeat(width.max(3) - 2)),
+        Align::Left => "-".repeat(width.max(3)),
+    }
+}
+
+fn table_to_markdown(table: &Table, options: &FormatOptions) -> String {
+    let rows: Vec<Vec<String>> = [&table.header].into_iter().chain(&table.records).map(cells_to_markdown).collect();
+    let widths = column_widths(&rows, options);
+    let aligns: Vec<String> = table.align.iter().enumerate()
+        .map(|(i, align)| align_to_markdown(align, widths.get(i).copied().unwrap_or(3)))
+        .collect();
+    let mut lines = vec!(record_to_markdown(&rows[0], &widths, &table.align), format!("| {} |", aligns.join(" | ")));
+    lines.extend(rows[1..].iter().map(|row| record_to_markdown(row, &widths, &table.align)));
+    if let Some(caption) = &table.caption {
+        lines.push(format!("Table: {}", words_to_markdown(caption)));
+    }
+    lines.join("\n")
+}
+
+fn loose_table_to_markdown(table: &LooseTable, options: &FormatOptions) -> String {
+    let rows: Vec<Vec<String>> = [&table.header].into_iter().chain(&table.rows).map(cells_to_markdown).collect();
+    let widths = column_widths(&rows, options);
+    let lines: Vec<String> = rows.iter().map(|row| record_to_markdown(row, &widths, &[])).collect();
+    lines.join("\n")
+}
+
+// A line starting with one of these could be read back as a different block.
+fn can_start_line(atom: &str) -> bool {
+    let Some(first) = atom.chars().next() else { return false };
+    let digits = atom.trim_start_matches(|c: char| c.is_ascii_digit());
+    let ordered = digits.len() < atom.len() && (digits.starts_with('.') || digits.starts_with(')'));
+    let bullet = atom.chars().all(|c| "-+*".contains(c));
+    !"#>|:`~?[=!<^\\".contains(first) && !ordered && !bullet
+}
+
+// Breaks only at spaces in plain text, so emphasis and other markup stay on one line.
+fn wrap_words(words: &Words, width: usize) -> Vec<String> {
+    let mut atoms = vec!();
+    let mut atom = String::new();
+    for word in &words.0 {
+        match word {
+            Word::Normal(text) => for (i, part) in text.split(' ').enumerate() {
+                if i > 0 { atoms.push(std::mem::take(&mut atom)) }
+                atom.push_str(part);
+            },
+            word => atom.push_str(&word_to_markdown(word)),
+        }
+    }
+    atoms.push(atom);
+
+    let mut lines = vec!();
+    let mut line = String::new();
+    for (i, atom) in atoms.into_iter().enumerate() {
+        let fits = display_width(&line) + 1 + display_width(&atom) <= width;
+        if i == 0 {
+            line = atom;
+        } else if !line.is_empty() && !fits && can_start_line(&atom) {
+            lines.push(std::mem::take(&mut line));
+            line = atom;
+        } else {
+            line.push(' ');
+            line.push_str(&atom);
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+fn wrapped(words: &Words, width: Option<usize>) -> String {
+    match width {
+        Some(width) => wrap_words(words, width).join("\n"),
+        None => words_to_markdown(words),
+    }
+}
+
+fn marker(kind: &ListKind, index: usize, options: &FormatOptions) -> String {
+    match kind {
+        ListKind::Bullet(c) => match options.bullet {
+            BulletStyle::Preserve => c.to_string(),
+            BulletStyle::Dash => "-".to_string(),
+            BulletStyle::Star => "*".to_string(),
+            BulletStyle::Plus => "+".to_string(),
+        },
+        ListKind::Ordered(start) => match options.numbering {
+            Numbering::Incrementing => format!("{}.", start.saturating_add(index)),
+            Numbering::Repeat => format!("{}.", start),
+        },
+    }
+}
+
+fn indent_lines(text: &str, indent: &str) -> String {
+    text.split('\n')
+        .map(|line| if line.is_empty() { "".to_string() } else { format!("{}{}", indent, line) })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// A blank line keeps a leading child paragraph from being read back as item text.
+fn children_to_markdown(children: &[Md], depth: usize, options: &FormatOptions) -> String {
+    let indent = " ".repeat(depth * options.indent);
+    children.iter().enumerate().map(|(i, md)| match md {
+        Md::List(items) => items_to_markdown(items, depth, options),
+        md if i == 0 => format!("\n{}", indent_lines(&md_to_markdown(md, options), &indent)),
+        md => indent_lines(&md_to_markdown(md, options), &indent),
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+fn items_to_markdown(items: &Items, depth: usize, options: &FormatOptions) -> String {
+    let indent = " ".repeat(depth * options.indent);
+    let text_indent = format!("\n{}", " ".repeat((depth + 1) * options.indent));
+    items.0.iter().enumerate().map(|(index, item)| {
+        let marker = marker(&items.1, index, options);
+        let width = options.wrap.map(|width| width.saturating_sub(indent.len() + marker.len() + 1));
+        let text = wrapped(&item.0, width).replace('\n', &text_indent);
+        let line = format!("{}{} {}", indent, marker, text);
+        if item.1.is_empty() {
+            line
+        } else {
+            format!("{}\n{}", line, children_to_markdown(&item.1, depth + 1, options))
+        }
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+fn quote_to_markdown(mds: &[Md], options: &FormatOptions) -> String {
+    mds_to_markdown(mds, options).split('\n')
+        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn code_to_markdown(lang: &str, code: &str, lines: &[(usize, usize)]) -> String {
+    let fence = if code.contains("```") { "~~~" } else { "```" };
+    let ranges = if lines.is_empty() {
+        "".to_string()
+    } else {
+        let ranges: Vec<String> = lines.iter().map(|(from, to)| {
+            if from == to { from.to_string() } else { format!("{}-{}", from, to) }
+        }).collect();
+        format!(" {{{}}}", ranges.join(","))
+    };
+    format!("{}{}{}\n{}\n{}", fence, lang, ranges, code, fence)
+}
+
+fn heading_to_markdown(size: &usize, words: &Words) -> String {
+    format!("{} {}", "#".repeat(*size), words_to_markdown(words))
+}
+
+fn container_to_markdown(name: &str, mds: &[Md], options: &FormatOptions) -> String {
+    if mds.is_empty() { return format!("::: {}\n:::", name) }
+    format!("::: {}\n{}\n:::", name, mds_to_markdown(mds, options))
+}
+
+fn details_to_markdown(open: &bool, summary: &Words, mds: &[Md], options: &FormatOptions) -> String {
+    let open = if *open { "+" } else { "" };
+    let summary = format!("???{} {}", open, words_to_markdown(summary));
+    if mds.is_empty() { return summary }
+    let body = mds_to_markdown(mds, options);
+    let body: Vec<String> = body.split('\n').map(|line| {
+        if line.is_empty() { "".to_string() } else { format!("    {}", line) }
+    }).collect();
+    format!("{}\n{}", summary, body.join("\n"))
+}
+
+pub fn md_to_markdown(md: &Md, options: &FormatOptions) -> String {
+    match md {
+        Md::Heading(size, words) => heading_to_markdown(size, words),
+        Md::Sentence(words) => wrapped(words, options.wrap),
+        Md::Table(table) => table_to_markdown(table, options),
+        Md::LooseTable(table) => loose_table_to_markdown(table, options),
+        Md::List(items) => items_to_markdown(items, 0, options),
+        Md::Container(name, mds) => container_to_markdown(name, mds, options),
+        Md::Details(open, summary, mds) => details_to_markdown(open, summary, mds, options),
+        Md::Toc => "[TOC]".to_string(),
+        Md::Abbreviation(name, title) => format!("*[{}]: {}", name, title),
+        Md::Footnote(label, words) => format!("[^{}]: {}", label, words_to_markdown(words)),
+        Md::FrontMatter(format, text) => format!("{}\n{}\n{}", format.fence(), text, format.fence()),
+        Md::Raw(text) => format!("<!-- md:off -->\n{}\n<!-- md:on -->", text),
+        Md::Custom(_, source) => source.clone(),
+        Md::Code(lang, code, lines) => code_to_markdown(lang, code, lines),
+        Md::Quote(mds) => quote_to_markdown(mds, options),
+    }
+}
+
+fn is_blank(md: &Md) -> bool {
+    matches!(md, Md::Sentence(words) if words.0.iter().all(|word| word == &Word::Normal("".into())))
+}
+
+// Drops the blank lines around headings and puts back exactly one on each side.
+fn space_headings<'a>(mds: &'a [Md<'a>]) -> Vec<Option<&'a Md<'a>>> {
+    let is_heading = |md: Option<&Md>| matches!(md, Some(Md::Heading(..)));
+    let mut spaced: Vec<Option<&Md>> = vec!();
+    for (i, md) in mds.iter().enumerate() {
+        if is_blank(md) {
+            let before = mds[..i].iter().rev().find(|md| !is_blank(md));
+            let after = mds[i..].iter().find(|md| !is_blank(md));
+            if !is_heading(before) && !is_heading(after) { spaced.push(Some(md)) }
+            continue;
+        }
+        if is_heading(Some(md)) && !spaced.is_empty() { spaced.push(None) }
+        if is_heading(spaced.last().copied().flatten()) { spaced.push(None) }
+        spaced.push(Some(md));
+    }
+    spaced
+}
+
+pub fn mds_to_markdown(mds: &[Md], options: &FormatOptions) -> String {
+    let strings: Vec<String> = if options.heading_spacing {
+        space_headings(mds).into_iter()
+            .map(|md| md.map(|md| md_to_markdown(md, options)).unwrap_or_default())
+            .collect()
+    } else {
+        mds.iter().map(|md| md_to_markdown(md, options)).collect()
+    };
+    strings.join("\n")
+}
+
+// Canonical markdown for the ast, so a parsed document can be edited and written back.
+pub fn to_markdown(mds: &[Md]) -> String {
+    mds_to_markdown(mds, &FormatOptions::default())
+}
+
+// Parses and reprints a document, normalizing it as far as the options ask.
+pub fn format_markdown(text: &str, options: &FormatOptions) -> String {
+    mds_to_markdown(&parse(text), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::options::ParserOptions;
+    use crate::{normal_word,words,items};
+
+    fn format_with(text: &str, options: &FormatOptions) -> String {
+        mds_to_markdown(&parse(text), options)
+    }
+
+    #[test]
+    fn test_words_to_markdown() {
+        let hello = normal_word!("Hello ");
+        let world = Word::Bold(words!(Word::Underline(words!(normal_word!("World")))));
+        let words = words!(hello, world, Word::Emoji("smile".to_string()));
+        assert_eq!(words_to_markdown(&words), "Hello **__World__**:smile:".to_string());
+        assert_eq!(to_markdown(&parse("&lt;b&gt; &#169;")), "&lt;b&gt; &#169;");
+    }
+
+    #[test]
+    fn test_image_to_markdown() {
+        let text = "![a](a.png) ![b](b.png =640x) ![c](c.png){width=50% height=20}";
+        let mds = parse(text);
+        assert_eq!(to_markdown(&mds), text);
+    }
+
+    #[test]
+    fn test_table_to_markdown() {
+        let a = words!(normal_word!("A"));
+        let b = words!(normal_word!("B"));
+        let header = Record(vec!(a, b), vec!());
+        let c = words!(normal_word!("c"));
+        let d = words!(normal_word!("d"));
+        let records = vec!(Record(vec!(c, d), vec!()));
+        let table = Table{header, align: vec!(Align::Right, Align::Center), records, caption: None};
+        assert_eq!(table_to_markdown(&table, &FormatOptions::default()), "| A | B |\n| --: | :-: |\n| c | d |".to_string());
+    }
+
+    #[test]
+    fn test_loose_table_to_markdown() {
+        let text = "| A | B |\n| -: | :-b: |\n| a |";
+        let options = ParserOptions{ loose_tables: true, ..Default::default() };
+        let mds = parse_with(text, &options);
+        assert_eq!(mds_to_markdown(&mds, &FormatOptions::default()), text.to_string());
+    }
+
+    #[test]
+    fn test_items_to_markdown() {
+        let child = Item(words!(normal_word!("child")), vec!());
+        let parent = Item(words!(normal_word!("parent")), vec!(Md::List(items!(child))));
+        let items = Items(vec!(parent), ListKind::Bullet('*'));
+        let options = FormatOptions::default();
+        assert_eq!(items_to_markdown(&items, 0, &options), "* parent\n  - child".to_string());
+
+        let options = FormatOptions{ bullet: BulletStyle::Plus, indent: 4, ..Default::default() };
+        assert_eq!(items_to_markdown(&items, 0, &options), "+ parent\n    + child".to_string());
+    }
+
+    #[test]
+    fn test_item_blocks_to_markdown() {
+        let text = "- Hello\n  World\n\n  ```rust\n  let a = 1;\n  ```\n  - child\n- End";
+        let options = FormatOptions::default();
+        assert_eq!(format_with(text, &options), text.to_string());
+        assert_eq!(parse(&format_with(text, &options)), parse(text));
+
+        let text = "+++\ntitle = \"Doc\"\n+++\n# Title";
+        assert_eq!(format_with(text, &options), text.to_string());
+    }
+
+    #[test]
+    fn test_numbering() {
+        let text = "3. a\n7. b\n1. c";
+        let options = FormatOptions::default();
+        assert_eq!(format_with(text, &options), "3. a\n4. b\n5. c".to_string());
+
+        let text = "1. a\n2. b\n3. c";
+        let options = FormatOptions{ numbering: Numbering::Repeat, ..Default::default() };
+        assert_eq!(format_with(text, &options), "1. a\n1. b\n1. c".to_string());
+
+        let items = Items(vec!(Item(words!(normal_word!("a")), vec!()), Item(words!(normal_word!("b")), vec!())), ListKind::Ordered(usize::MAX));
+        let last = format!("{}. b", usize::MAX);
+        assert!(to_markdown(&[Md::List(items)]).ends_with(&last));
+    }
+
+    #[test]
+    fn test_normalize_markers() {
+        let text = "* a\n  + b\n    - c\n* d";
+        let options = FormatOptions{ bullet: BulletStyle::Dash, ..Default::default() };
+        assert_eq!(format_with(text, &options), "- a\n  - b\n    - c\n- d".to_string());
+
+        let options = FormatOptions::default();
+        assert_eq!(format_with(text, &options), text.to_string());
+    }
+
+    #[test]
+    fn test_mds_to_markdown() {
+        let text = "```rust {1,3-5}\nx\n```";
+        assert_eq!(format_with(text, &FormatOptions::default()), text.to_string());
+
+        let text = "---\ntitle: Doc\n---\n# Title\n[TOC]\n> - quoted\n>\n> > nested\n*[HTML]: HyperText Markup Language\n**Hello** __World__\n\n::: warning\n## Inner\n:::\n???+ FAQ\n    Answer\n\n    - item\n| A | B |\n| --- | :-: |\n| c\\|e | d |\nTable: Results";
+        let options = FormatOptions::default();
+        assert_eq!(format_with(text, &options), text.to_string());
+        assert_eq!(parse(&format_with(text, &options)), parse(text));
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let mut mds = parse("|A|B|\n|:-|-:|\n|a|**b**|\n#  Title");
+        assert_eq!(to_markdown(&mds), "| A | B |\n| --- | --: |\n| a | **b** |\n# Title");
+
+        mds.push(Md::Heading(2, words!(Word::Italic(words!(normal_word!("Added"))))));
+        let text = to_markdown(&mds);
+        assert_eq!(text, "| A | B |\n| --- | --: |\n| a | **b** |\n# Title\n## *Added*");
+        assert_eq!(parse(&text), mds);
+    }
+
+    #[test]
+    fn test_align_tables() {
+        let text = "| A | Long header |\n|-:|:-:|\n| wide cell | x |\n| 見出し | **b** |";
+        let options = FormatOptions{ align_tables: true, ..Default::default() };
+        let expect = [
+            "|         A | Long header |",
+            "| --------: | :---------: |",
+            "| wide cell |      x      |",
+            "|    見出し |    **b**    |",
+        ].join("\n");
+        assert_eq!(format_markdown(text, &options), expect);
+        assert_eq!(parse(&expect), parse(text));
+    }
+
+    #[test]
+    fn test_wrap() {
+        let text = "one two three **four five** six - seven\n- item with some words";
+        let options = FormatOptions{ wrap: Some(14), ..Default::default() };
+        let expect = "one two three\n**four five**\nsix - seven\n- item with\n  some words";
+        assert_eq!(format_markdown(text, &options), expect);
+    }
+
+    #[test]
+    fn test_heading_spacing() {
+        let text = "Intro\n# Title\n\n\nBody\n\nMore\n## Sub";
+        let options = FormatOptions{ heading_spacing: true, ..Default::default() };
+        assert_eq!(format_markdown(text, &options), "Intro\n\n# Title\n\nBody\n\nMore\n\n## Sub");
+        assert_eq!(format_markdown("# A\n# B", &options), "# A\n\n# B");
+    }
+}
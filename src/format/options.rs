@@ -0,0 +1,42 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BulletStyle {
+    #[default]
+    Preserve,
+    Dash,
+    Star,
+    Plus,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Numbering {
+    #[default]
+    Incrementing,
+    Repeat,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    pub bullet: BulletStyle,
+    pub numbering: Numbering,
+    pub indent: usize,
+    // Pads table cells so the pipes line up in columns.
+    pub align_tables: bool,
+    // Breaks sentence and list item lines longer than this at spaces outside emphasis.
+    // Each sentence line renders with its own line break, so wrapping sentences adds breaks.
+    pub wrap: Option<usize>,
+    // Exactly one blank line before and after every heading.
+    pub heading_spacing: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            bullet: BulletStyle::Preserve,
+            numbering: Numbering::Incrementing,
+            indent: 2,
+            align_tables: false,
+            wrap: None,
+            heading_spacing: false,
+        }
+    }
+}
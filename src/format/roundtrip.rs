@@ -0,0 +1,163 @@
+use crate::parser::parser::*;
+use super::format::to_markdown;
+use std::fmt;
+use std::mem::discriminant;
+
+// Where two trees first differ: the steps down to the node, e.g. `block 2 > item 1 > word 3`,
+// and that node on each side, or `None` where one side has nothing there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub path: Vec<String>,
+    pub original: Option<String>,
+    pub reparsed: Option<String>,
+}
+
+// The markdown a document was written back as, and where reparsing it gave a different tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundtripReport {
+    pub markdown: String,
+    pub divergence: Option<Divergence>,
+}
+
+impl RoundtripReport {
+    pub fn is_ok(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+impl fmt::Display for RoundtripReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(divergence) = &self.divergence else { return write!(f, "round trip ok") };
+        let side = |node: &Option<String>| node.clone().unwrap_or_else(|| "nothing".to_string());
+        write!(
+            f, "round trip diverges at {}\n  original: {}\n  reparsed: {}",
+            divergence.path.join(" > "), side(&divergence.original), side(&divergence.reparsed),
+        )
+    }
+}
+
+type Found = (Option<String>, Option<String>);
+
+fn whole<T: fmt::Debug>(original: &T, reparsed: &T) -> Found {
+    (Some(format!("{:?}", original)), Some(format!("{:?}", reparsed)))
+}
+
+// The first index where `original` and `reparsed` differ, looked into with `within`.
+fn first<T: PartialEq + fmt::Debug>(
+    original: &[T], reparsed: &[T], step: &str, path: &mut Vec<String>, within: fn(&T, &T, &mut Vec<String>) -> Found,
+) -> Option<Found> {
+    let i = (0..original.len().max(reparsed.len())).find(|&i| original.get(i) != reparsed.get(i))?;
+    path.push(format!("{} {}", step, i + 1));
+    Some(match (original.get(i), reparsed.get(i)) {
+        (Some(original), Some(reparsed)) => within(original, reparsed, path),
+        (original, reparsed) => (original.map(|node| format!("{:?}", node)), reparsed.map(|node| format!("{:?}", node))),
+    })
+}
+
+fn children<'b, 'a>(word: &'b Word<'a>) -> Option<&'b Words<'a>> {
+    match word {
+        Word::Italic(words)
+        | Word::Bold(words)
+        | Word::StrikeThough(words)
+        | Word::Underline(words)
+        | Word::Highlight(words)
+        | Word::Subscript(words)
+        | Word::Superscript(words)
+        | Word::Insert(words)
+        | Word::Link(words, _) => Some(words),
+        _ => None,
+    }
+}
+
+fn within_word(original: &Word, reparsed: &Word, path: &mut Vec<String>) -> Found {
+    let same = match (original, reparsed) {
+        (Word::Link(_, a), Word::Link(_, b)) => a == b,
+        (a, b) => discriminant(a) == discriminant(b),
+    };
+    match (children(original), children(reparsed)) {
+        (Some(a), Some(b)) if same => within_words(a, b, path),
+        _ => whole(original, reparsed),
+    }
+}
+
+fn within_words(original: &Words, reparsed: &Words, path: &mut Vec<String>) -> Found {
+    first(&original.0, &reparsed.0, "word", path, within_word).unwrap_or_else(|| whole(original, reparsed))
+}
+
+fn within_item(original: &Item, reparsed: &Item, path: &mut Vec<String>) -> Found {
+    if original.0 != reparsed.0 { return within_words(&original.0, &reparsed.0, path) }
+    first(&original.1, &reparsed.1, "block", path, within_md).unwrap_or_else(|| whole(original, reparsed))
+}
+
+fn within_mds(original: &[Md], reparsed: &[Md], path: &mut Vec<String>) -> Found {
+    first(original, reparsed, "block", path, within_md).unwrap_or_else(|| whole(&original, &reparsed))
+}
+
+fn within_md(original: &Md, reparsed: &Md, path: &mut Vec<String>) -> Found {
+    match (original, reparsed) {
+        (Md::Heading(a, words), Md::Heading(b, other)) if a == b => within_words(words, other, path),
+        (Md::Sentence(words), Md::Sentence(other)) => within_words(words, other, path),
+        (Md::Container(a, mds), Md::Container(b, other)) if a == b => within_mds(mds, other, path),
+        (Md::Quote(mds), Md::Quote(other)) => within_mds(mds, other, path),
+        (Md::Details(a, summary, mds), Md::Details(b, other_summary, other)) if a == b => {
+            if summary != other_summary { within_words(summary, other_summary, path) } else { within_mds(mds, other, path) }
+        },
+        (Md::List(Items(items, a)), Md::List(Items(other, b))) if a == b => {
+            first(items, other, "item", path, within_item).unwrap_or_else(|| whole(original, reparsed))
+        },
+        _ => whole(original, reparsed),
+    }
+}
+
+// The first place `reparsed` differs from `original`, descending through blocks, list items
+// and inline emphasis so the report points at the smallest node that changed.
+pub fn divergence(original: &[Md], reparsed: &[Md]) -> Option<Divergence> {
+    let mut path = vec!();
+    let (original, reparsed) = first(original, reparsed, "block", &mut path, within_md)?;
+    Some(Divergence{ path, original, reparsed })
+}
+
+// Parses `text`, writes it back with `to_markdown` and parses that again, reporting the first
+// place the two trees differ. A guard for changes to the parser or the formatter.
+pub fn verify_roundtrip(text: &str) -> RoundtripReport {
+    let original = parse(text);
+    let markdown = to_markdown(&original);
+    let divergence = divergence(&original, &parse(&markdown));
+    RoundtripReport{ markdown, divergence }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{normal_word,words,items};
+
+    #[test]
+    fn test_verify_roundtrip() {
+        let text = "# Title\n- **bold** and [link](/a)\n  > quoted\n| A | B |\n| --- | --: |\n| a | b |\n```rust\nfn main() {}\n```";
+        let report = verify_roundtrip(text);
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.markdown, text);
+        assert_eq!(report.to_string(), "round trip ok");
+    }
+
+    #[test]
+    fn test_divergence() {
+        let original = vec!(
+            Md::Heading(1, words!(normal_word!("Title"))),
+            Md::List(items!(Item(words!(Word::Bold(words!(normal_word!("a"), normal_word!("b")))), vec!()))),
+        );
+        let mut reparsed = original.clone();
+        reparsed[1] = Md::List(items!(Item(words!(Word::Bold(words!(normal_word!("a"), normal_word!("c")))), vec!())));
+        let found = divergence(&original, &reparsed).unwrap();
+        assert_eq!(found.path, vec!("block 2", "item 1", "word 1", "word 2"));
+        assert_eq!((found.original.as_deref(), found.reparsed.as_deref()), (Some("Normal(\"b\")"), Some("Normal(\"c\")")));
+
+        reparsed[1] = Md::Sentence(words!(normal_word!("a")));
+        assert_eq!(divergence(&original, &reparsed).unwrap().path, vec!("block 2"));
+
+        let report = RoundtripReport{ markdown: "# Title".to_string(), divergence: divergence(&original, &original[..1]) };
+        assert!(report.to_string().starts_with("round trip diverges at block 2\n  original: List("));
+        assert!(report.to_string().ends_with("\n  reparsed: nothing"));
+        assert_eq!(divergence(&original, &original), None);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::parser::parser::*;
+use crate::convert::tree::{parse_html, HtmlNode};
+
+const SKIPPED: [&str; 5] = ["head", "script", "style", "title", "hr"];
+const BLOCKS: [&str; 24] = [
+    "h1", "h2", "h3", "h4", "h5", "h6", "p", "ul", "ol", "table", "pre", "blockquote",
+    "details", "div", "section", "article", "main", "header", "footer", "nav", "aside",
+    "body", "html", "figure",
+];
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+}
+
+fn text_content(nodes: &[HtmlNode]) -> String {
+    nodes.iter().map(|node| match node {
+        HtmlNode::Text(text) => text.clone(),
+        HtmlNode::Element{ tag: name, .. } if name == "br" => "\n".to_string(),
+        HtmlNode::Element{ children, .. } => text_content(children),
+    })
+    .collect()
+}
+
+fn collapse(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            space = true;
+            continue;
+        }
+        if space { collapsed.push(' ') }
+        space = false;
+        collapsed.push(c);
+    }
+    if space { collapsed.push(' ') }
+    collapsed
+}
+
+// Inline content split into lines at `<br>`.
+fn inline(nodes: &[HtmlNode], lines: &mut Vec<Vec<Word<'static>>>) {
+    for node in nodes {
+        let (name, attributes, children) = match node {
+            HtmlNode::Text(text) => {
+                lines.last_mut().unwrap().push(Word::Normal(collapse(text).into()));
+                continue;
+            },
+            HtmlNode::Element{ tag: name, attrs: attributes, children } => (name.as_str(), attributes, children),
+        };
+        let wrap: fn(Words<'static>) -> Word<'static> = match name {
+            "br" => {
+                lines.push(vec!());
+                continue;
+            },
+            "kbd" => {
+                lines.last_mut().unwrap().push(Word::Kbd(collapse(&text_content(children)).trim().to_string()));
+                continue;
+            },
+            "a" if attribute(attributes, "href").is_some() => {
+                let url = attribute(attributes, "href").unwrap_or("").to_string();
+                wrapped(children, lines, |words| Word::Link(words, url.clone()));
+                continue;
+            },
+            "img" => {
+                let alt = attribute(attributes, "alt").unwrap_or("").to_string();
+                let word = match attribute(attributes, "src") {
+                    Some(src) => {
+                        let size = |name| attribute(attributes, name).map(str::to_string);
+                        Word::Image(alt, src.to_string(), ImageSize{ width: size("width"), height: size("height") })
+                    },
+                    None => Word::Normal(alt.into()),
+                };
+                lines.last_mut().unwrap().push(word);
+                continue;
+            },
+            "b" | "strong" => Word::Bold,
+            "i" | "em" => Word::Italic,
+            "s" | "del" | "strike" => Word::StrikeThough,
+            "u" => Word::Underline,
+            "ins" => Word::Insert,
+            "mark" => Word::Highlight,
+            "sub" => Word::Subscript,
+            "sup" => Word::Superscript,
+            name if SKIPPED.contains(&name) => continue,
+            _ => {
+                inline(children, lines);
+                continue;
+            },
+        };
+        wrapped(children, lines, wrap);
+    }
+}
+
+// Wraps each line of the children's content, so a `<br>` inside emphasis splits it in two.
+fn wrapped(children: &[HtmlNode], lines: &mut Vec<Vec<Word<'static>>>, wrap: impl Fn(Words<'static>) -> Word<'static>) {
+    let mut inner = vec!(vec!());
+    inline(children, &mut inner);
+    for (i, line) in inner.into_iter().enumerate() {
+        if i > 0 { lines.push(vec!()) }
+        lines.last_mut().unwrap().push(wrap(tidy(line, false)));
+    }
+}
+
+// Joins neighbouring text and trims the line ends, as the markdown parser would have read them.
+fn tidy(words: Vec<Word<'static>>, trim: bool) -> Words<'static> {
+    let mut tidied: Vec<Word<'static>> = vec!();
+    for word in words {
+        match (tidied.last_mut(), word) {
+            (Some(Word::Normal(last)), Word::Normal(text)) => {
+                if last.ends_with(' ') { last.to_mut().push_str(text.trim_start()) } else { last.to_mut().push_str(&text) }
+            },
+            (_, word) => tidied.push(word),
+        }
+    }
+    if trim {
+        if let Some(Word::Normal(first)) = tidied.first_mut() { *first = first.trim_start().to_string().into() }
+        if let Some(Word::Normal(last)) = tidied.last_mut() { *last = last.trim_end().to_string().into() }
+    }
+    tidied.retain(|word| word != &Word::Normal("".into()));
+    if tidied.is_empty() { tidied.push(Word::Normal("".into())) }
+    Words(tidied)
+}
+
+fn inline_lines(nodes: &[&HtmlNode]) -> Vec<Words<'static>> {
+    let mut lines = vec!(vec!());
+    for node in nodes {
+        inline(std::slice::from_ref(*node), &mut lines);
+    }
+    let mut lines: Vec<Words<'static>> = lines.into_iter().map(|line| tidy(line, true)).collect();
+    if lines.last().is_some_and(|line| line.0 == vec!(Word::Normal("".into()))) {
+        lines.pop();
+    }
+    lines
+}
+
+fn inline_words(nodes: &[HtmlNode]) -> Words<'static> {
+    let nodes: Vec<&HtmlNode> = nodes.iter().collect();
+    let mut words = vec!();
+    for (i, line) in inline_lines(&nodes).into_iter().enumerate() {
+        if i > 0 { words.push(Word::Normal(" ".into())) }
+        words.extend(line.0);
+    }
+    tidy(words, true)
+}
+
+fn is_block(node: &HtmlNode) -> bool {
+    matches!(node, HtmlNode::Element{ tag: name, .. } if BLOCKS.contains(&name.as_str()) || SKIPPED.contains(&name.as_str()))
+}
+
+fn elements<'a>(nodes: &'a [HtmlNode], names: &'a [&str]) -> impl Iterator<Item = (&'a [(String, String)], &'a [HtmlNode])> + 'a {
+    nodes.iter().filter_map(move |node| match node {
+        HtmlNode::Element{ tag: name, attrs: attributes, children } if names.contains(&name.as_str()) => {
+            Some((attributes.as_slice(), children.as_slice()))
+        },
+        _ => None,
+    })
+}
+
+fn align(attributes: &[(String, String)]) -> Option<Align> {
+    let style = attribute(attributes, "style").and_then(|style| {
+        let (_, value) = style.split_once("text-align")?;
+        Some(value.trim_start_matches([':', ' ']).split(';').next()?.trim().to_string())
+    });
+    match attribute(attributes, "align").map(str::to_string).or(style)?.as_str() {
+        "right" => Some(Align::Right),
+        "center" => Some(Align::Center),
+        "left" => Some(Align::Left),
+        _ => None,
+    }
+}
+
+fn table(nodes: &[HtmlNode]) -> Option<Md<'static>> {
+    let mut rows: Vec<&[HtmlNode]> = elements(nodes, &["tr"]).map(|(_, row)| row).collect();
+    for (_, section) in elements(nodes, &["thead", "tbody", "tfoot"]) {
+        rows.extend(elements(section, &["tr"]).map(|(_, row)| row));
+    }
+    let cells = |row: &[HtmlNode]| -> Vec<Words<'static>> {
+        elements(row, &["th", "td"]).map(|(_, cell)| inline_words(cell)).collect()
+    };
+    let (first, rest) = rows.split_first()?;
+    let header = Record(cells(first), vec!());
+    let aligns = |row: &[HtmlNode]| -> Vec<Option<Align>> { elements(row, &["th", "td"]).map(|(attributes, _)| align(attributes)).collect() };
+    let mut body_aligns = rest.first().map(|row| aligns(row)).unwrap_or_default().into_iter();
+    let align = aligns(first).into_iter()
+        .map(|align| align.or(body_aligns.next().flatten()).unwrap_or(Align::Left))
+        .collect();
+    let records = rest.iter().map(|row| Record(cells(row), vec!())).collect();
+    let caption = elements(nodes, &["caption"]).next().map(|(_, caption)| inline_words(caption));
+    Some(Md::Table(Box::new(Table{header, align, records, caption})))
+}
+
+fn list(nodes: &[HtmlNode], kind: ListKind) -> Md<'static> {
+    let items = elements(nodes, &["li"]).map(|(_, children)| {
+        let split = children.iter().position(is_block).unwrap_or(children.len());
+        let words = inline_words(&children[..split]);
+        Item(words, blocks(&children[split..]))
+    })
+    .collect();
+    Md::List(Items(items, kind))
+}
+
+fn code(nodes: &[HtmlNode]) -> Md<'static> {
+    let (lang, text) = match elements(nodes, &["code"]).next() {
+        Some((attributes, children)) => {
+            let lang = attribute(attributes, "class")
+                .and_then(|class| class.split_whitespace().find_map(|class| class.strip_prefix("language-")))
+                .unwrap_or("");
+            (lang.to_string(), text_content(children))
+        },
+        None => ("".to_string(), text_content(nodes)),
+    };
+    let text = text.strip_suffix('\n').unwrap_or(&text).to_string();
+    Md::Code(lang, text, vec!())
+}
+
+fn block(name: &str, attributes: &[(String, String)], children: &[HtmlNode], mds: &mut Vec<Md<'static>>) {
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse().unwrap_or(1);
+            mds.push(Md::Heading(level, inline_words(children)));
+        },
+        "ul" => mds.push(list(children, ListKind::Bullet('-'))),
+        "ol" => {
+            let start = attribute(attributes, "start").and_then(|start| start.parse().ok()).unwrap_or(1);
+            mds.push(list(children, ListKind::Ordered(start)));
+        },
+        "table" => mds.extend(table(children)),
+        "pre" => mds.push(code(children)),
+        "blockquote" => mds.push(Md::Quote(blocks(children))),
+        "details" => {
+            let summary = elements(children, &["summary"]).next().map(|(_, summary)| inline_words(summary));
+            let rest: Vec<&HtmlNode> = children.iter()
+                .filter(|node| !matches!(node, HtmlNode::Element{ tag: name, .. } if name == "summary"))
+                .collect();
+            let open = attribute(attributes, "open").is_some();
+            mds.push(Md::Details(open, summary.unwrap_or(Words(vec!())), blocks_of(&rest)));
+        },
+        "div" => match attribute(attributes, "class").and_then(|class| class.split_whitespace().next()) {
+            Some(class) => mds.push(Md::Container(class.to_string(), blocks(children))),
+            None => mds.extend(blocks(children)),
+        },
+        name if SKIPPED.contains(&name) => {},
+        _ => mds.extend(blocks(children)),
+    }
+}
+
+fn blocks_of(nodes: &[&HtmlNode]) -> Vec<Md<'static>> {
+    let mut mds = vec!();
+    let mut inline: Vec<&HtmlNode> = vec!();
+    for node in nodes {
+        match node {
+            HtmlNode::Element{ tag: name, attrs: attributes, children } if is_block(node) => {
+                mds.extend(inline_lines(&inline).into_iter().map(Md::Sentence));
+                inline.clear();
+                block(name, attributes, children, &mut mds);
+            },
+            node => inline.push(node),
+        }
+    }
+    mds.extend(inline_lines(&inline).into_iter().map(Md::Sentence));
+    mds
+}
+
+fn blocks(nodes: &[HtmlNode]) -> Vec<Md<'static>> {
+    blocks_of(&nodes.iter().collect::<Vec<&HtmlNode>>())
+}
+
+/// Reads html made of headings, paragraphs, emphasis, tables, lists, code and quotes back into markdown blocks.
+///
+/// Links keep only their text, since the markdown has no link syntax yet; other tags are unwrapped.
+pub fn html_to_mds(html: &str) -> Vec<Md<'static>> {
+    blocks(&parse_html(html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::convert::mds_to_html;
+    use crate::{normal_word,words};
+
+    #[test]
+    fn test_inline_to_md() {
+        let mds = html_to_mds("<p>Hello <strong>bold <em>both</em></strong>\n  and <a href=\"/x\">link</a><br>next</p>");
+        let bold = Word::Bold(words!(normal_word!("bold "), Word::Italic(words!(normal_word!("both")))));
+        let link = Word::Link(words!(normal_word!("link")), "/x".to_string());
+        let first = Md::Sentence(words!(normal_word!("Hello "), bold, normal_word!(" and "), link));
+        let second = Md::Sentence(words!(normal_word!("next")));
+        assert_eq!(mds, vec!(first, second));
+    }
+
+    #[test]
+    fn test_table_to_md() {
+        let html = "<table><thead><tr><th>A</th><th style=\"text-align: right\">B</th></tr></thead>\
+                    <tbody><tr><td>a</td><td>b</td></tr></tbody></table>";
+        assert_eq!(html_to_mds(html), parse("| A | B |\n| - | -: |\n| a | b |"));
+    }
+
+    #[test]
+    fn test_unclosed_tags() {
+        let mds = html_to_mds("<ul><li>one<li>two</ul><h2>Title");
+        assert_eq!(mds, parse("- one\n- two\n## Title"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = "# Title\n**b** *i* ~~s~~ __u__ [[Ctrl]] [a *b*](/c?d=1&e) ![x \"y\" & z](a.png =20x10)\n\nnext\n| A | B |\n| --- | :-: |\n| a | b |\nTable: cap\n3. x\n  - y\n4. z\n> q\n```rust\nlet a = 1 < 2;\n```\n??? Sum\n    body\n::: note\nhi\n:::";
+        let mds = parse(text);
+        assert_eq!(html_to_mds(&mds_to_html(&mds)), mds);
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum IncludeError {
+    Io(PathBuf, io::Error),
+    // The file resolves to somewhere outside the root directory.
+    OutsideRoot(PathBuf),
+    // The chain of files that includes itself, ending with the file included again.
+    Cycle(Vec<PathBuf>),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IncludeError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            IncludeError::OutsideRoot(path) => write!(f, "{} is outside the include root", path.display()),
+            IncludeError::Cycle(chain) => {
+                let chain: Vec<String> = chain.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "include cycle: {}", chain.join(" -> "))
+            },
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+// The path of a `<!-- include: path.md -->` line.
+fn directive(line: &str) -> Option<&str> {
+    let inner = line.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    let path = inner.trim().strip_prefix("include:")?.trim();
+    (!path.is_empty()).then_some(path)
+}
+
+fn expand(text: &str, dir: &Path, root: &Path, stack: &mut Vec<PathBuf>) -> Result<String, IncludeError> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut fence: Option<&str> = None;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"].into_iter().find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            _ => {},
+        }
+        let Some(path) = directive(line).filter(|_| fence.is_none()) else {
+            expanded.push_str(line);
+            continue
+        };
+        let joined = dir.join(path);
+        let file = joined.canonicalize().map_err(|err| IncludeError::Io(joined.clone(), err))?;
+        if !file.starts_with(root) { return Err(IncludeError::OutsideRoot(joined)) }
+        if stack.contains(&file) {
+            let mut chain = stack.clone();
+            chain.push(file);
+            return Err(IncludeError::Cycle(chain))
+        }
+        let included = fs::read_to_string(&file).map_err(|err| IncludeError::Io(joined, err))?;
+        let parent = file.parent().unwrap_or(root).to_path_buf();
+        stack.push(file);
+        let included = expand(&included, &parent, root, stack)?;
+        stack.pop();
+        expanded.push_str(included.trim_end_matches('\n'));
+        if line.ends_with('\n') { expanded.push('\n') }
+    }
+    Ok(expanded)
+}
+
+// Replaces each line that is only `<!-- include: path.md -->`, outside fenced code, with the
+// text of that file, expanding the files it includes in turn. Paths are relative to the
+// including file, `dir` for `text` itself, and must stay under `root`.
+pub fn expand_includes(text: &str, dir: &Path, root: &Path) -> Result<String, IncludeError> {
+    let root = root.canonicalize().map_err(|err| IncludeError::Io(root.to_path_buf(), err))?;
+    expand(text, dir, &root, &mut vec!())
+}
+
+// Like `expand_includes` for the text of `path`, which counts as part of any cycle.
+pub fn expand_file(path: &Path, root: &Path) -> Result<String, IncludeError> {
+    let root = root.canonicalize().map_err(|err| IncludeError::Io(root.to_path_buf(), err))?;
+    let file = path.canonicalize().map_err(|err| IncludeError::Io(path.to_path_buf(), err))?;
+    let text = fs::read_to_string(&file).map_err(|err| IncludeError::Io(path.to_path_buf(), err))?;
+    let dir = file.parent().unwrap_or(&root).to_path_buf();
+    expand(&text, &dir, &root, &mut vec!(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("app-include-{}-{}", std::process::id(), name));
+        fs::create_dir_all(dir.join("parts")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_expand_includes() {
+        let dir = temp_dir("expand");
+        fs::write(dir.join("parts/intro.md"), "## Intro\n<!-- include: detail.md -->\n").unwrap();
+        fs::write(dir.join("parts/detail.md"), "Details").unwrap();
+        let text = "# Book\n<!-- include: parts/intro.md -->\n```\n<!-- include: parts/intro.md -->\n```\nEnd";
+        let expect = "# Book\n## Intro\nDetails\n```\n<!-- include: parts/intro.md -->\n```\nEnd";
+        assert_eq!(expand_includes(text, &dir, &dir).unwrap(), expect);
+        assert_eq!(expand_file(&dir.join("parts/intro.md"), &dir).unwrap(), "## Intro\nDetails\n");
+
+        let outside = expand_includes("<!-- include: ../x.md -->", &dir.join("parts"), &dir.join("parts"));
+        assert!(matches!(outside, Err(IncludeError::Io(..))));
+        fs::write(dir.join("secret.md"), "secret").unwrap();
+        let outside = expand_includes("<!-- include: ../secret.md -->", &dir.join("parts"), &dir.join("parts"));
+        assert!(matches!(outside, Err(IncludeError::OutsideRoot(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = temp_dir("cycle");
+        fs::write(dir.join("a.md"), "<!-- include: parts/b.md -->").unwrap();
+        fs::write(dir.join("parts/b.md"), "<!-- include: ../a.md -->").unwrap();
+        let err = expand_file(&dir.join("a.md"), &dir).unwrap_err();
+        let root = dir.canonicalize().unwrap();
+        assert!(matches!(&err, IncludeError::Cycle(chain) if chain.len() == 3 && chain[0] == root.join("a.md")));
+        assert!(err.to_string().starts_with("include cycle: "));
+
+        fs::write(dir.join("self.md"), "<!-- include: self.md -->").unwrap();
+        assert!(matches!(expand_includes("<!-- include: self.md -->", &dir, &dir), Err(IncludeError::Cycle(chain)) if chain.len() == 2));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
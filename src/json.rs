@@ -0,0 +1,58 @@
+use crate::parser::parser::Md;
+
+// Each block is an object keyed by its variant name, e.g. `{"Heading":[1,[{"Normal":"Title"}]]}`.
+pub fn to_json(mds: &[Md]) -> String {
+    serde_json::to_string(mds).expect("the markdown ast always serializes")
+}
+
+pub fn from_json(json: &str) -> Result<Vec<Md<'static>>, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::parse;
+    use crate::convert::convert::mds_to_html;
+
+    #[test]
+    fn test_to_json() {
+        let mds = parse("# Title\n**Hi**\n| A |\n|:-:|\n| a |");
+        let expect = concat!(
+            "[{\"Heading\":[1,[{\"Normal\":\"Title\"}]]},",
+            "{\"Sentence\":[{\"Bold\":[{\"Normal\":\"Hi\"}]}]},",
+            "{\"Table\":{\"header\":[[[{\"Normal\":\"A\"}]],[]],\"align\":[\"Center\"],",
+            "\"records\":[[[[{\"Normal\":\"a\"}]],[]]],\"caption\":null}}]",
+        );
+        assert_eq!(to_json(&mds), expect);
+        assert_eq!(to_json(&[]), "[]");
+    }
+
+    #[test]
+    fn test_from_json() {
+        let mds = parse("# Title\n- a\n  - b\n```rust {2}\nlet a;\nlet b;\n```\n> quoted");
+        assert_eq!(from_json(&to_json(&mds)).unwrap(), mds);
+
+        let mds = from_json("[{\"Heading\":[2,[{\"Normal\":\"Hi\"}]]}]").unwrap();
+        assert_eq!(mds_to_html(&mds), "<h2 id=\"hi\">Hi</h2>");
+
+        assert!(from_json("[{\"Heading\":[2]}]").is_err());
+        assert!(from_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_results_serialize() {
+        use crate::parser::diagnostics::{parse_with_diagnostics, Diagnostics};
+        use crate::parser::error::{try_parse, ParseError};
+        use crate::parser::options::ParserOptions;
+
+        let (_, diagnostics) = parse_with_diagnostics("# A\n### B **open", &ParserOptions::default());
+        let json = serde_json::to_string(&diagnostics).unwrap();
+        assert_eq!(serde_json::from_str::<Diagnostics>(&json).unwrap(), diagnostics);
+
+        let err = try_parse("```\nunclosed").unwrap_err();
+        let json = serde_json::to_string(&err).unwrap();
+        assert_eq!(json, "{\"kind\":\"UnclosedCodeFence\",\"line\":1,\"column\":1,\"snippet\":\"```\"}");
+        assert_eq!(serde_json::from_str::<ParseError>(&json).unwrap(), err);
+    }
+}
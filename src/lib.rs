@@ -1,2 +1,109 @@
+//! Markdown to html, with table extensions, containers, footnotes and other backends.
+//!
+//! The items re-exported here are the crate's supported surface and follow semver: the
+//! markdown AST, the parser entry points, and the html converter with its options. The
+//! modules they come from stay public for the less common helpers, but their layout may
+//! change between minor versions.
+//!
+//! ```
+//! use app::{parse, mds_to_html, mds_to_html_with, Md, RenderOptions};
+//!
+//! let mds = parse("# Title\n| A | B |\n| - | - |\n| 1 | 2 |");
+//! assert!(matches!(mds[0], Md::Heading(1, _)));
+//! assert!(mds_to_html(&mds).starts_with("<h1 id=\"title\">Title</h1>"));
+//!
+//! let options = RenderOptions{ heading_anchors: true, ..Default::default() };
+//! assert!(mds_to_html_with(&mds, &options).is_ok());
+//! ```
+
+// Enters a `tracing` span, e.g. `span!(debug_span, "parse", bytes = text.len())`, until the
+// end of the scope; without the `tracing` feature it expands to nothing.
+macro_rules! span {
+    ($level:ident, $($fields:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::$level!($($fields)*).entered();
+    };
+}
+
 pub mod parser;
-pub mod convert;
\ No newline at end of file
+pub mod convert;
+pub mod format;
+pub mod diff;
+pub mod pipeline;
+pub mod cache;
+pub mod pass;
+pub mod preview;
+pub mod config;
+pub mod include;
+pub mod extract;
+pub mod links;
+pub mod query;
+pub mod merge;
+pub mod prose;
+pub mod report;
+pub mod cli;
+pub mod commonmark;
+pub mod import;
+#[cfg(feature = "emoji")]
+pub mod emoji;
+#[cfg(any(feature = "tera", feature = "askama"))]
+pub mod template;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "pyo3")]
+pub mod python;
+#[cfg(feature = "metadata")]
+pub mod metadata;
+#[cfg(feature = "tokio")]
+pub mod nonblocking;
+
+pub use parser::parser::{
+    parse, parse_with, parse_with_positions, try_parse_with, normalize_newlines,
+    Md, Word, Words, Item, Items, ListKind, Table, LooseTable, TableIssue, Record, Span, Align,
+    ImageSize, SourcePos, FrontMatterFormat,
+};
+pub use parser::tidy::SortOrder;
+pub use parser::options::{ParserOptions, InlineExtension, BlockExtension, LimitExceeded};
+pub use parser::builder::{Parser, ParserBuilder};
+pub use parser::doc::Doc;
+pub use parser::error::{try_parse, try_parse_strict, ParseError, ParseErrorKind};
+pub use parser::span::{spans, SourceSpan, Spanned, NodeRef};
+pub use parser::visit::{walk, Visitor};
+pub use parser::dump::dump;
+pub use parser::events::{events, Events, Event, Tag};
+pub use parser::reader::{parse_reader, parse_reader_with, BlockReader};
+pub use parser::intern::{parse_interned, Interner};
+#[cfg(feature = "rayon")]
+pub use parser::parallel::parse_parallel;
+#[cfg(feature = "mmap")]
+pub use parser::mapped::{convert_mapped, MappedFile};
+pub use parser::diagnostics::{parse_with_accessibility_diagnostics, parse_with_diagnostics, Diagnostic, DiagnosticKind, Diagnostics};
+pub use convert::convert::{
+    mds_to_html, mds_to_html_with, mds_to_html_with_renderer, mds_to_html_with_positions,
+    mds_to_html_with_source_map, md_to_html_with, words_to_html_with, table_to_html_with,
+    footnotes_to_html_with, write_html, HtmlWriter, SourceMapping,
+};
+pub use convert::options::{
+    RenderOptions, HtmlOptions, RenderError, LimitAction, VoidStyle, FootnoteOptions, FootnotePlacement,
+};
+pub use convert::document::DocumentOptions;
+pub use convert::renderer::{Renderer, HtmlRenderer};
+pub use convert::toc::{toc, TocEntry};
+pub use convert::text::ToPlainText;
+pub use convert::tree::{mds_to_tree, tree_to_html, HtmlNode};
+pub use convert::stream::{stream_html, HtmlChunks};
+pub use convert::ansi::to_ansi;
+pub use convert::latex::to_latex;
+pub use convert::man::to_man;
+pub use convert::asciidoc::to_asciidoc;
+pub use convert::rst::to_rst;
+#[cfg(feature = "docx")]
+pub use convert::docx::to_docx;
+#[cfg(feature = "pdf")]
+pub use convert::pdf::to_pdf;
+pub use format::format::{to_markdown, format_markdown};
+pub use format::options::FormatOptions;
+pub use format::roundtrip::{divergence, verify_roundtrip, Divergence, RoundtripReport};
+pub use import::html::html_to_mds;
+#[cfg(feature = "json")]
+pub use json::{to_json, from_json};
@@ -0,0 +1,178 @@
+use crate::convert::toc::toc;
+use crate::parser::options::ParserOptions;
+use crate::parser::parser::*;
+use crate::parser::span::{spans, NodeRef, Spanned};
+use crate::preview::percent_decode;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkProblem {
+    MissingFile,
+    // The file exists but has no heading with that slug.
+    MissingAnchor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    pub path: PathBuf,
+    pub line: usize,
+    pub target: String,
+    pub problem: LinkProblem,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let problem = match self.problem {
+            LinkProblem::MissingFile => "no such file",
+            LinkProblem::MissingAnchor => "no such anchor",
+        };
+        write!(f, "{}:{}: {}: {}", self.path.display(), self.line, self.target, problem)
+    }
+}
+
+// Urls with a scheme, like `https:` or `mailto:`, and site-absolute paths are left alone.
+fn is_relative(target: &str) -> bool {
+    let scheme = target.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    });
+    !scheme && !target.starts_with('/')
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md" || ext == "markdown")
+}
+
+fn links<'a>(spanned: &Spanned<'a>, found: &mut Vec<(usize, &'a str)>) {
+    if let NodeRef::Word(Word::Link(_, url)) = spanned.node {
+        found.push((spanned.span.line, url));
+    }
+    for child in &spanned.children {
+        links(child, found)
+    }
+}
+
+fn with_path(path: &Path) -> impl Fn(io::Error) -> io::Error + '_ {
+    move |err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+}
+
+fn markdown_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(())
+    }
+    let entries = fs::read_dir(path).map_err(with_path(path))?;
+    let mut entries = entries.map(|entry| entry.map(|entry| entry.path())).collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() || is_markdown(&entry) { markdown_files(&entry, files)? }
+    }
+    Ok(())
+}
+
+// The heading slugs of each markdown file, read once; `None` when it cannot be read.
+#[derive(Debug, Default)]
+struct Anchors {
+    files: HashMap<PathBuf, Option<HashSet<String>>>,
+}
+
+impl Anchors {
+    fn get(&mut self, path: &Path) -> Option<&HashSet<String>> {
+        let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.files.entry(key).or_insert_with(|| {
+            let text = fs::read_to_string(path).ok()?;
+            Some(toc(&parse(&text)).into_iter().map(|entry| entry.slug).collect())
+        })
+        .as_ref()
+    }
+}
+
+fn check(file: &Path, target: &str, anchors: &mut Anchors) -> Option<LinkProblem> {
+    let (path, anchor) = target.split_once('#').unwrap_or((target, ""));
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    let resolved = if path.is_empty() {
+        file.to_path_buf()
+    } else {
+        let path = percent_decode(path).unwrap_or_else(|| path.to_string());
+        let resolved = file.parent().unwrap_or(Path::new("")).join(path);
+        // A link to the page a markdown file turns into.
+        let source = resolved.with_extension("md");
+        match resolved.exists() {
+            true => resolved,
+            false if resolved.extension().is_some_and(|ext| ext == "html") && source.exists() => source,
+            false => return Some(LinkProblem::MissingFile),
+        }
+    };
+    if anchor.is_empty() || !is_markdown(&resolved) { return None }
+    let anchor = percent_decode(anchor).unwrap_or_else(|| anchor.to_string());
+    match anchors.get(&resolved) {
+        Some(slugs) if !slugs.contains(&anchor) => Some(LinkProblem::MissingAnchor),
+        _ => None,
+    }
+}
+
+// Checks the relative links in markdown files, and in the markdown files under directories,
+// against the filesystem and the heading slugs of the files they point to. An `.html` link
+// may also be satisfied by the `.md` file it is built from.
+pub fn check_links<P: AsRef<Path>>(paths: &[P]) -> io::Result<Vec<BrokenLink>> {
+    let mut files = vec!();
+    for path in paths {
+        markdown_files(path.as_ref(), &mut files)?;
+    }
+    let (mut anchors, mut broken) = (Anchors::default(), vec!());
+    let options = ParserOptions::default();
+    for file in files {
+        let text = fs::read_to_string(&file).map_err(with_path(&file))?;
+        let mds = parse_with(&text, &options);
+        let mut found = vec!();
+        spans(&text, &mds, &options).iter().for_each(|spanned| links(spanned, &mut found));
+        for (line, target) in found.into_iter().filter(|(_, target)| is_relative(target)) {
+            if let Some(problem) = check(&file, target, &mut anchors) {
+                broken.push(BrokenLink{ path: file.clone(), line, target: target.to_string(), problem });
+            }
+        }
+    }
+    Ok(broken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_relative() {
+        assert!(is_relative("guide/setup.md"));
+        assert!(is_relative("#usage"));
+        assert!(is_relative("../a.md#b"));
+        assert!(!is_relative("https://example.com"));
+        assert!(!is_relative("mailto:me@example.com"));
+        assert!(!is_relative("/docs/a.md"));
+    }
+
+    #[test]
+    fn test_check_links() {
+        let dir = std::env::temp_dir().join(format!("app-links-{}", std::process::id()));
+        fs::create_dir_all(dir.join("guide")).unwrap();
+        fs::write(dir.join("index.md"), "# Home\n[Setup](guide/setup.md#install)\n[Gone](guide/gone.md)\n\n[Web](https://example.com) [Page](guide/setup.html)\n- [Here](#home) [There](#nowhere)").unwrap();
+        fs::write(dir.join("guide/setup.md"), "# Setup\n## Install\n[Up](../index.md#setup)\n[Space](my%20notes.txt)").unwrap();
+        fs::write(dir.join("guide/my notes.txt"), "notes").unwrap();
+
+        let broken = check_links(&[&dir]).unwrap();
+        let found: Vec<(PathBuf, usize, &str, LinkProblem)> = broken.iter()
+            .map(|link| (link.path.strip_prefix(&dir).unwrap().to_path_buf(), link.line, link.target.as_str(), link.problem))
+            .collect();
+        assert_eq!(found, vec!(
+            (PathBuf::from("guide/setup.md"), 3, "../index.md#setup", LinkProblem::MissingAnchor),
+            (PathBuf::from("index.md"), 3, "guide/gone.md", LinkProblem::MissingFile),
+            (PathBuf::from("index.md"), 6, "#nowhere", LinkProblem::MissingAnchor),
+        ));
+        assert!(broken[1].to_string().ends_with("index.md:3: guide/gone.md: no such file"));
+        let err = check_links(&[dir.join("missing.md")]).unwrap_err();
+        assert!(err.to_string().starts_with(&format!("{}: ", dir.join("missing.md").display())));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
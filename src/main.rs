@@ -1,6 +1,4 @@
-mod parser;
-mod convert;
-
+use app::{parse, mds_to_html};
 use std::fs;
 use std::io;
 
@@ -17,8 +15,8 @@ fn read_file_to_string(path: &str) -> io::Result<String> {
 fn main()  -> io::Result<()> {
     let contents = read_file_to_string("./test.md")?;
     let contents: &str = &contents;
-    let mds = parser::parser::parse(&contents);
-    let output = convert::convert::mds_to_html(&mds);
+    let mds = parse(contents);
+    let output = mds_to_html(&mds);
     let output: &str = &output;
     write_to_file("./test.html", output)?;
     Ok(())
@@ -0,0 +1,70 @@
+use crate::parser::parser::*;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    // Levels added to the headings of each document after the first, up to `######`.
+    pub demote: usize,
+    // Keeps the first front matter at the top and drops the rest, rather than leaving each
+    // document's front matter where it was.
+    pub dedupe_front_matter: bool,
+}
+
+fn demote(mds: &mut [Md], levels: usize) {
+    for md in mds {
+        match md {
+            Md::Heading(level, _) => *level = (*level + levels).min(6),
+            Md::Container(_, mds) | Md::Details(_, _, mds) | Md::Quote(mds) => demote(mds, levels),
+            Md::List(items) => items.0.iter_mut().for_each(|item| demote(&mut item.1, levels)),
+            _ => {},
+        }
+    }
+}
+
+// Joins documents in order into one, as for a handbook built from chapter files.
+pub fn merge<'a>(docs: Vec<Vec<Md<'a>>>, options: &MergeOptions) -> Vec<Md<'a>> {
+    let mut merged = vec!();
+    let mut front_matter = None;
+    for (index, mut mds) in docs.into_iter().enumerate() {
+        if index > 0 && options.demote > 0 { demote(&mut mds, options.demote) }
+        if options.dedupe_front_matter {
+            if let Some(Md::FrontMatter(..)) = mds.first() {
+                let first = mds.remove(0);
+                front_matter.get_or_insert(first);
+            }
+        }
+        merged.extend(mds);
+    }
+    if let Some(front_matter) = front_matter { merged.insert(0, front_matter) }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::format::to_markdown;
+
+    #[test]
+    fn test_merge() {
+        let chapters = || vec!(
+            parse("---\ntitle: Handbook\n---\n# Handbook"),
+            parse("---\ntitle: Setup\n---\n# Setup\n> ## Note\n\n### Deep"),
+            parse("# Usage"),
+        );
+
+        let merged = merge(chapters(), &MergeOptions{ demote: 1, dedupe_front_matter: true });
+        assert_eq!(to_markdown(&merged), "---\ntitle: Handbook\n---\n# Handbook\n## Setup\n> ### Note\n\n#### Deep\n## Usage");
+
+        let merged = merge(chapters(), &MergeOptions{ demote: 4, dedupe_front_matter: true });
+        let levels: Vec<usize> = merged.iter().filter_map(|md| match md {
+            Md::Heading(level, _) => Some(*level),
+            _ => None,
+        })
+        .collect();
+        assert_eq!(levels, vec!(1, 5, 6, 5));
+
+        let merged = merge(chapters(), &MergeOptions::default());
+        assert_eq!(merged.iter().filter(|md| matches!(md, Md::FrontMatter(..))).count(), 2);
+        assert!(matches!(merged[merged.len() - 1], Md::Heading(1, _)));
+        assert_eq!(merge(vec!(), &MergeOptions::default()), vec!());
+    }
+}
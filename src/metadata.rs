@@ -0,0 +1,100 @@
+use crate::parser::parser::*;
+use crate::parser::options::ParserOptions;
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataError {
+    // The document has no front matter.
+    Missing,
+    // The front matter does not fit the type; `line` is 1-based in the document, where the
+    // deserializer knows it.
+    Invalid{ format: FrontMatterFormat, line: Option<usize>, message: String },
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetadataError::Missing => write!(f, "the document has no front matter"),
+            MetadataError::Invalid{ format, line: Some(line), message } => write!(f, "{:?} front matter, line {}: {}", format, line, message),
+            MetadataError::Invalid{ format, line: None, message } => write!(f, "{:?} front matter: {}", format, message),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {}
+
+// A parsed document together with typed access to its front matter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document<'a> {
+    pub mds: Vec<Md<'a>>,
+}
+
+impl<'a> Document<'a> {
+    pub fn parse(text: &'a str) -> Self {
+        Document{ mds: parse(text) }
+    }
+
+    pub fn parse_with(text: &'a str, options: &ParserOptions) -> Self {
+        Document{ mds: parse_with(text, options) }
+    }
+
+    pub fn front_matter(&self) -> Option<(FrontMatterFormat, &str)> {
+        match self.mds.first() {
+            Some(Md::FrontMatter(format, text)) => Some((*format, text)),
+            _ => None,
+        }
+    }
+
+    // Deserializes the `---` YAML or `+++` TOML front matter into `T`.
+    pub fn metadata<T: DeserializeOwned>(&self) -> Result<T, MetadataError> {
+        let (format, text) = self.front_matter().ok_or(MetadataError::Missing)?;
+        // The fence takes up the document's first line.
+        let invalid = |line: Option<usize>, message: String| MetadataError::Invalid{ format, line: line.map(|line| line + 1), message };
+        match format {
+            FrontMatterFormat::Yaml => serde_yaml::from_str(text).map_err(|err| {
+                invalid(err.location().map(|location| location.line()), err.to_string())
+            }),
+            FrontMatterFormat::Toml => toml::from_str(text).map_err(|err| {
+                let line = err.span().map(|span| text[..span.start].matches('\n').count() + 1);
+                invalid(line, err.message().to_string())
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Post {
+        title: String,
+        tags: Vec<String>,
+        #[serde(default)]
+        draft: bool,
+    }
+
+    #[test]
+    fn test_metadata() {
+        let post = Post{ title: "Hello".to_string(), tags: vec!("a".to_string(), "b".to_string()), draft: false };
+        let yaml = Document::parse("---\ntitle: Hello\ntags: [a, b]\n---\n# Body");
+        assert_eq!(yaml.metadata::<Post>(), Ok(post));
+        assert_eq!(yaml.mds.len(), 2);
+
+        let toml = Document::parse("+++\ntitle = \"Hello\"\ntags = [\"a\"]\ndraft = true\n+++\n# Body");
+        let post = Post{ title: "Hello".to_string(), tags: vec!("a".to_string()), draft: true };
+        assert_eq!(toml.metadata::<Post>(), Ok(post));
+        assert_eq!(Document::parse("# Body").metadata::<Post>(), Err(MetadataError::Missing));
+    }
+
+    #[test]
+    fn test_invalid_metadata() {
+        let err = Document::parse("---\ntitle: Hello\ntags: 3\n---").metadata::<Post>().unwrap_err();
+        assert!(matches!(&err, MetadataError::Invalid{ format: FrontMatterFormat::Yaml, line: Some(3), .. }), "{:?}", err);
+
+        let err = Document::parse("+++\ntitle = \"Hello\"\n\ntags = 3\n+++").metadata::<Post>().unwrap_err();
+        assert!(matches!(&err, MetadataError::Invalid{ format: FrontMatterFormat::Toml, line: Some(4), .. }), "{:?}", err);
+        assert!(err.to_string().starts_with("Toml front matter, line 4: "));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::pipeline::{Pipeline, PipelineError};
+use std::io;
+use std::panic::resume_unwind;
+use std::path::PathBuf;
+use tokio::task::spawn_blocking;
+
+// Runs `f` on tokio's blocking threads so parsing and rendering don't hold up the executor,
+// passing on a panic in it.
+async fn blocking<T, F>(f: F) -> Result<T, PipelineError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, PipelineError> + Send + 'static,
+{
+    match spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => resume_unwind(err.into_panic()),
+        Err(err) => Err(PipelineError::Io(io::Error::other(err))),
+    }
+}
+
+// The async versions of `render`, `convert` and `convert_dir`, for calling from async
+// handlers. Files are read on the blocking threads too, as `tokio::fs` does.
+impl Pipeline {
+    pub async fn render_async(&self, text: String) -> Result<String, PipelineError> {
+        let pipeline = self.clone();
+        blocking(move || Ok(pipeline.render(&text)?)).await
+    }
+
+    pub async fn convert_async(&self, path: impl Into<PathBuf>) -> Result<String, PipelineError> {
+        let (pipeline, path) = (self.clone(), path.into());
+        blocking(move || pipeline.convert(&path)).await
+    }
+
+    // Converts the markdown file at `input` and writes the html to `output`.
+    pub async fn convert_file_async(&self, input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Result<(), PipelineError> {
+        let html = self.convert_async(input).await?;
+        Ok(tokio::fs::write(output.into(), html).await?)
+    }
+
+    pub async fn convert_dir_async(&self, input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Result<Vec<PathBuf>, PipelineError> {
+        let (pipeline, input, output) = (self.clone(), input.into(), output.into());
+        blocking(move || pipeline.convert_dir(&input, &output)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::options::{RenderError, RenderOptions};
+    use std::fs;
+    use std::future::Future;
+
+    fn run<T>(future: impl Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn test_render_async() {
+        let pipeline = Pipeline::new(RenderOptions::default());
+        assert_eq!(run(pipeline.render_async("**a**".to_string())).unwrap(), "<b>a</b><br />");
+        let pipeline = Pipeline::new(RenderOptions{ max_bytes: Some(1), ..Default::default() });
+        let result = run(pipeline.render_async("**a**".to_string()));
+        assert!(matches!(result, Err(PipelineError::Render(RenderError::TooManyBytes(1)))));
+    }
+
+    #[test]
+    fn test_convert_async() {
+        let root = std::env::temp_dir().join(format!("app-nonblocking-{}", std::process::id()));
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/index.md"), "# Home").unwrap();
+        let pipeline = Pipeline::new(RenderOptions::default());
+
+        run(pipeline.convert_file_async(root.join("docs/index.md"), root.join("index.html"))).unwrap();
+        assert_eq!(fs::read_to_string(root.join("index.html")).unwrap(), "<h1 id=\"home\">Home</h1>");
+        let written = run(pipeline.convert_dir_async(root.join("docs"), root.join("site"))).unwrap();
+        assert_eq!(written, vec!(root.join("site/index.html")));
+        assert!(matches!(run(pipeline.convert_async(root.join("none.md"))), Err(PipelineError::Io(_))));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
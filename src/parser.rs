@@ -1,5 +1,35 @@
+#[allow(clippy::module_inception)]
 pub mod parser;
 mod heading;
 mod sentence;
 mod table;
-mod list;
\ No newline at end of file
+mod list;
+mod container;
+mod details;
+mod toc;
+mod abbr;
+mod footnote;
+mod code;
+mod quote;
+mod tabs;
+mod entity;
+mod limits;
+mod front_matter;
+mod raw;
+pub mod tidy;
+pub mod options;
+pub mod builder;
+pub mod doc;
+pub mod error;
+pub mod diagnostics;
+pub mod span;
+pub mod visit;
+pub mod events;
+pub mod reader;
+pub mod intern;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "mmap")]
+pub mod mapped;
+pub mod combinator;
+pub mod dump;
@@ -0,0 +1,29 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+
+pub fn abbreviation<'a>(texts: &'a str, _options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let (line, rest) = split_first_pattern(texts, "\n");
+    let line = consume(line, "*[")?;
+    let (name, title) = line.split_once("]:")?;
+    let name = name.trim();
+    if name.is_empty() || name.contains(']') { return None }
+    let token = Md::Abbreviation(name.to_string(), title.trim().to_string());
+    Some(ParsedResult::new(token, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abbreviation() {
+        let options = ParserOptions::default();
+        let token = Md::Abbreviation("HTML".to_string(), "HyperText Markup Language".to_string());
+        let rest = "Hello";
+        assert_eq!(abbreviation("*[HTML]: HyperText Markup Language\nHello", &options), Some(ParsedResult{token, rest}));
+
+        assert_eq!(abbreviation("*[]: Empty", &options), None);
+        assert_eq!(abbreviation("*[HTML] HyperText", &options), None);
+        assert_eq!(abbreviation("*HTML*", &options), None);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::parser::parser::{parse_with, parse_with_positions, Md, SourcePos};
+use super::options::{BlockExtension, InlineExtension, ParserOptions};
+use super::error::{try_parse_strict, ParseError};
+use super::diagnostics::{parse_with_diagnostics, Diagnostics};
+use super::span::{spans, Spanned};
+use super::events::{events, Events};
+use super::reader::parse_reader_with;
+use super::intern::{parse_interned, Interner};
+#[cfg(feature = "rayon")]
+use super::parallel::parse_parallel;
+use std::io::{self, BufRead};
+
+// A parser configured once and reused, instead of passing `ParserOptions` to each call.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Parser {
+    options: ParserOptions,
+}
+
+impl Parser {
+    pub fn new(options: ParserOptions) -> Self {
+        Parser{ options }
+    }
+
+    pub fn builder() -> ParserBuilder {
+        ParserBuilder::default()
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    pub fn parse<'a>(&self, text: &'a str) -> Vec<Md<'a>> {
+        parse_with(text, &self.options)
+    }
+
+    pub fn parse_with_positions<'a>(&self, text: &'a str) -> (Vec<Md<'a>>, Vec<SourcePos>) {
+        parse_with_positions(text, &self.options)
+    }
+
+    // Fails on an unclosed fence, a broken table or a cap in the options the input exceeds.
+    pub fn parse_with_diagnostics<'a>(&self, text: &'a str) -> (Vec<Md<'a>>, Diagnostics) {
+        parse_with_diagnostics(text, &self.options)
+    }
+
+    pub fn parse_reader<R: BufRead>(&self, reader: R) -> io::Result<Vec<Md<'static>>> {
+        parse_reader_with(reader, &self.options)
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn parse_parallel<'a>(&self, text: &'a str) -> Vec<Md<'a>> {
+        parse_parallel(text, &self.options)
+    }
+
+    pub fn parse_interned<'a>(&self, text: &'a str, interner: &'a mut Interner) -> Vec<Md<'a>> {
+        parse_interned(text, interner, &self.options)
+    }
+
+    pub fn events<'a>(&self, text: &'a str) -> Events<'a> {
+        events(text, &self.options)
+    }
+
+    // Spans of `mds`, which this parser must have parsed from `text`.
+    pub fn spans<'a>(&self, text: &str, mds: &'a [Md<'a>]) -> Vec<Spanned<'a>> {
+        spans(text, mds, &self.options)
+    }
+
+    pub fn try_parse<'a>(&self, text: &'a str) -> Result<Vec<Md<'a>>, ParseError> {
+        try_parse_strict(text, &self.options)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ParserBuilder {
+    options: ParserOptions,
+}
+
+impl ParserBuilder {
+    pub fn loose_tables(mut self, enabled: bool) -> Self {
+        self.options.loose_tables = enabled;
+        self
+    }
+
+    pub fn ragged_rows(mut self, enabled: bool) -> Self {
+        self.options.ragged_rows = enabled;
+        self
+    }
+
+    pub fn table_spans(mut self, enabled: bool) -> Self {
+        self.options.table_spans = enabled;
+        self
+    }
+
+    // `__a__` is bold and `_a_` italic, instead of `__a__` being an underline.
+    pub fn commonmark_underscores(mut self, enabled: bool) -> Self {
+        self.options.commonmark_underscores = enabled;
+        self
+    }
+
+    pub fn autolinks(mut self, enabled: bool) -> Self {
+        self.options.autolinks = enabled;
+        self
+    }
+
+    pub fn task_lists(mut self, enabled: bool) -> Self {
+        self.options.task_lists = enabled;
+        self
+    }
+
+    pub fn single_tilde_strikethrough(mut self, enabled: bool) -> Self {
+        self.options.single_tilde_strikethrough = enabled;
+        self
+    }
+
+    pub fn tab_width(mut self, width: usize) -> Self {
+        self.options.tab_width = width;
+        self
+    }
+
+    pub fn strip_zero_width(mut self, enabled: bool) -> Self {
+        self.options.strip_zero_width = enabled;
+        self
+    }
+
+    pub fn max_nesting(mut self, depth: usize) -> Self {
+        self.options.max_nesting = depth;
+        self
+    }
+
+    pub fn max_input_bytes(mut self, max: usize) -> Self {
+        self.options.max_input_bytes = Some(max);
+        self
+    }
+
+    pub fn max_table_rows(mut self, max: usize) -> Self {
+        self.options.max_table_rows = Some(max);
+        self
+    }
+
+    pub fn max_table_columns(mut self, max: usize) -> Self {
+        self.options.max_table_columns = Some(max);
+        self
+    }
+
+    pub fn max_nodes(mut self, max: usize) -> Self {
+        self.options.max_nodes = Some(max);
+        self
+    }
+
+    // Registers inline syntax that parses to `Word::Custom(name, matched text)`.
+    pub fn inline_extension(mut self, name: &str, delimiter: char, parse: fn(&str) -> Option<usize>) -> Self {
+        self.options.inline_extensions.push(InlineExtension{ name: name.to_string(), delimiter, parse });
+        self
+    }
+
+    // Registers block syntax that parses to `Md::Custom(name, matched text)`.
+    pub fn block_extension(mut self, name: &str, priority: i32, parse: fn(&str) -> Option<usize>) -> Self {
+        self.options.block_extensions.push(BlockExtension{ name: name.to_string(), priority, parse });
+        self
+    }
+
+    pub fn build(self) -> Parser {
+        Parser{ options: self.options }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::{parse, Word, Words};
+    use crate::parser::options::LimitExceeded;
+    use crate::parser::error::ParseErrorKind;
+    use crate::normal_word;
+
+    #[test]
+    fn test_builder() {
+        let parser = Parser::builder().commonmark_underscores(true).tab_width(2).max_nodes(3).build();
+        let expect = ParserOptions{ commonmark_underscores: true, tab_width: 2, max_nodes: Some(3), ..Default::default() };
+        assert_eq!(parser.options(), &expect);
+        assert_eq!(parser, Parser::new(expect));
+
+        let bold = Md::Sentence(Words(vec!(Word::Bold(Words(vec!(normal_word!("a")))))));
+        assert_eq!(parser.parse("__a__"), vec!(bold));
+        assert_eq!(parser.try_parse("a\nb\nc\nd").unwrap_err().kind, ParseErrorKind::Limit(LimitExceeded::Nodes(3)));
+        assert_eq!(Parser::default().parse("__a__"), parse("__a__"));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+
+pub(super) fn fence(text: &str) -> Option<(char, usize, &str)> {
+    let text = text.trim();
+    let c = text.chars().next().filter(|c| c == &'`' || c == &'~')?;
+    let n = text.chars().take_while(|d| d == &c).count();
+    if n < 3 { return None }
+    let info = text[n..].trim();
+    if c == '`' && info.contains('`') { return None }
+    Some((c, n, info))
+}
+
+pub(super) fn is_close(text: &str, c: char, n: usize) -> bool {
+    let text = text.trim();
+    text.len() >= n && text.chars().all(|d| d == c)
+}
+
+// Whether a fenced block's source ends with a fence that closes its opening one.
+pub(super) fn is_closed(block: &str) -> bool {
+    let mut lines = block.lines();
+    let Some((c, n, _)) = lines.next().and_then(fence) else { return false };
+    lines.next_back().is_some_and(|last| is_close(last, c, n))
+}
+
+fn line_ranges(info: &str) -> Vec<(usize, usize)> {
+    let Some(start) = info.find('{') else { return vec!() };
+    let Some(end) = info[start..].find('}') else { return vec!() };
+    info[(start + 1)..(start + end)].split(',').filter_map(|range| {
+        let (from, to) = range.split_once('-').unwrap_or((range, range));
+        let (from, to) = (from.trim().parse().ok()?, to.trim().parse().ok()?);
+        (from <= to).then_some((from, to))
+    })
+    .collect()
+}
+
+pub fn code<'a>(texts: &'a str, _options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let (line, mut rest) = split_first_pattern(texts, "\n");
+    let (c, n, info) = fence(line)?;
+    let lang = info.split(|c: char| c.is_whitespace() || c == '{').next().unwrap_or("");
+    let mut lines: Vec<&str> = vec!();
+    while !rest.is_empty() {
+        let (line, next) = split_first_pattern(rest, "\n");
+        rest = next;
+        if is_close(line, c, n) { break }
+        lines.push(line);
+    }
+    let token = Md::Code(lang.to_string(), lines.join("\n"), line_ranges(info));
+    Some(ParsedResult::new(token, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code() {
+        let options = ParserOptions::default();
+        let token = Md::Code("rust".to_string(), "fn main() {\n\n}".to_string(), vec!());
+        let rest = "End";
+        assert_eq!(code("```rust ignore\nfn main() {\n\n}\n```\nEnd", &options), Some(ParsedResult{token, rest}));
+
+        let token = Md::Code("".to_string(), "```\n~~~".to_string(), vec!());
+        let rest = "";
+        assert_eq!(code("~~~~\n```\n~~~\n~~~~", &options), Some(ParsedResult{token, rest}));
+
+        let token = Md::Code("".to_string(), "unclosed".to_string(), vec!());
+        let rest = "";
+        assert_eq!(code("```\nunclosed", &options), Some(ParsedResult{token, rest}));
+
+        assert_eq!(code("``not code``", &options), None);
+        assert_eq!(code("``` a`b", &options), None);
+
+        let token = Md::Code("rust".to_string(), "a".to_string(), vec!((1, 1), (3, 5)));
+        let rest = "";
+        assert_eq!(code("```rust {1,3-5, 6-2}\na\n```", &options), Some(ParsedResult{token, rest}));
+
+        let token = Md::Code("js".to_string(), "a".to_string(), vec!((2, 2)));
+        assert_eq!(code("```js{2}\na\n```", &options), Some(ParsedResult{token, rest}));
+    }
+}
@@ -0,0 +1,91 @@
+//! Small building blocks shared by the block and inline parsers.
+//!
+//! Every parser in this crate takes the remaining input and, on success,
+//! returns the parsed token together with the unconsumed rest of the input.
+//! Extension authors can build their own parsers from the same pieces:
+//!
+//! ```
+//! use app::parser::combinator::*;
+//!
+//! fn mention(text: &str) -> Option<ParsedResult<'_, String>> {
+//!     let text = consume(text, "@")?;
+//!     let (name, rest) = split_first_pattern(text, " ");
+//!     Some(ParsedResult::new(name.to_string(), rest))
+//! }
+//!
+//! assert_eq!(mention("@alice hello"), Some(ParsedResult::new("alice".to_string(), "hello")));
+//! assert_eq!(mention("alice"), None);
+//! ```
+
+/// A parsed `token` and the `rest` of the input that follows it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParsedResult<'a, T> {
+    pub token: T,
+    pub rest: &'a str,
+}
+
+impl<'a, T> ParsedResult<'a, T> {
+    pub fn new(token: T, rest: &'a str) -> ParsedResult<'a, T> {
+        ParsedResult { token, rest }
+    }
+
+    /// Transforms the token, keeping the rest of the input.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> ParsedResult<'a, U> {
+        ParsedResult::new(f(self.token), self.rest)
+    }
+}
+
+/// Requires at least one leading space and skips all following whitespace.
+///
+/// ```
+/// use app::parser::combinator::space;
+///
+/// assert_eq!(space("   text"), Some("text"));
+/// assert_eq!(space("text"), None);
+/// ```
+pub fn space(text: &str) -> Option<&str> {
+    let text = consume(text, " ")?;
+    Some(text.trim_start())
+}
+
+/// Strips `pattern` from the start of `text`, or returns `None` if it is not there.
+///
+/// ```
+/// use app::parser::combinator::consume;
+///
+/// assert_eq!(consume("## Title", "##"), Some(" Title"));
+/// assert_eq!(consume("Title", "#"), None);
+/// ```
+pub fn consume<'a>(text: &'a str, pattern: &str) -> Option<&'a str> {
+    text.strip_prefix(pattern)
+}
+
+/// Splits `texts` around the first `pattern`, which is dropped.
+/// Without a match the whole input is returned with an empty rest.
+///
+/// ```
+/// use app::parser::combinator::split_first_pattern;
+///
+/// assert_eq!(split_first_pattern("line\nrest", "\n"), ("line", "rest"));
+/// assert_eq!(split_first_pattern("line", "\n"), ("line", ""));
+/// ```
+pub fn split_first_pattern<'a>(texts: &'a str, pattern: &str) -> (&'a str, &'a str) {
+    texts.split_once(pattern).unwrap_or((texts, ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        let result = ParsedResult::new(1, "rest").map(|n| n + 1);
+        assert_eq!(result, ParsedResult::new(2, "rest"));
+    }
+
+    #[test]
+    fn test_split_first_pattern() {
+        assert_eq!(split_first_pattern("a | b | c", " | "), ("a", "b | c"));
+        assert_eq!(split_first_pattern("", "\n"), ("", ""));
+    }
+}
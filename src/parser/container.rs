@@ -0,0 +1,92 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+
+fn fence(text: &str) -> Option<(usize, &str)> {
+    let text = text.trim_end();
+    let colons = text.chars().take_while(|c| c == &':').count();
+    if colons < 3 { return None }
+    Some((colons, text[colons..].trim()))
+}
+
+fn open(text: &str) -> Option<(usize, &str)> {
+    let (colons, name) = fence(text)?;
+    let name = name.split_whitespace().next()?;
+    Some((colons, name))
+}
+
+fn is_close(text: &str, colons: usize) -> bool {
+    matches!(fence(text), Some((n, "")) if n == colons)
+}
+
+// Whether a container's source ends with the fence that closes it.
+pub(super) fn is_closed(block: &str) -> bool {
+    let mut lines = block.lines();
+    let Some((colons, _)) = lines.next().and_then(open) else { return false };
+    lines.next_back().is_some_and(|last| is_close(last, colons))
+}
+
+pub fn container<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let (line, mut rest) = split_first_pattern(texts, "\n");
+    let (colons, name) = open(line)?;
+    let nested = options.nested()?;
+    let body = rest;
+    let mut depth = 1;
+    let mut end = body.len();
+    while !rest.is_empty() {
+        let (line, next) = split_first_pattern(rest, "\n");
+        if open(line).is_some_and(|(n, _)| n == colons) {
+            depth += 1;
+        } else if is_close(line, colons) {
+            depth -= 1;
+            if depth == 0 {
+                end = body.len() - rest.len();
+                rest = next;
+                break;
+            }
+        }
+        rest = next;
+    }
+    let inner = body[..end].trim_end_matches('\n');
+    let token = Md::Container(name.to_string(), parse_blocks(inner, &nested));
+    Some(ParsedResult::new(token, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{words,normal_word};
+
+    #[test]
+    fn test_container() {
+        let hello = Md::Sentence(words!(normal_word!("Hello")));
+        let token = Md::Container("warning".to_string(), vec!(hello));
+        let rest = "World";
+        assert_eq!(container("::: warning\nHello\n:::\nWorld", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let heading = Md::Heading(1, words!(normal_word!("Title")));
+        let token = Md::Container("note".to_string(), vec!(heading));
+        let rest = "";
+        assert_eq!(container(":::: note \n# Title\n::::", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        assert_eq!(container(":::\nHello\n:::", &ParserOptions::default()), None);
+        assert_eq!(container(":: warning\nHello\n::", &ParserOptions::default()), None);
+    }
+
+    #[test]
+    fn test_nest_container() {
+        let hello = Md::Sentence(words!(normal_word!("Hello")));
+        let inner = Md::Container("tip".to_string(), vec!(hello));
+        let world = Md::Sentence(words!(normal_word!("World")));
+        let token = Md::Container("warning".to_string(), vec!(inner, world));
+        let rest = "";
+        assert_eq!(container("::: warning\n::: tip\nHello\n:::\nWorld\n:::", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_unclosed_container() {
+        let hello = Md::Sentence(words!(normal_word!("Hello")));
+        let token = Md::Container("warning".to_string(), vec!(hello));
+        let rest = "";
+        assert_eq!(container("::: warning\nHello", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+use super::sentence::words;
+
+const INDENT: &str = "    ";
+
+fn summary(text: &str) -> Option<(bool, &str)> {
+    let text = consume(text, "???")?;
+    let (open, text) = match consume(text, "+") {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let text = space(text)?.trim_end();
+    if text.is_empty() { return None }
+    Some((open, text))
+}
+
+fn body(mut texts: &str) -> (String, &str) {
+    let mut lines: Vec<&str> = vec!();
+    let mut rest = texts;
+    while !texts.is_empty() {
+        let (line, next) = split_first_pattern(texts, "\n");
+        if let Some(line) = consume(line, INDENT) {
+            lines.push(line);
+            rest = next;
+        } else if line.trim().is_empty() {
+            lines.push("");
+        } else {
+            break;
+        }
+        texts = next;
+    }
+    let body = lines.join("\n");
+    (body.trim_end_matches('\n').to_string(), rest)
+}
+
+pub fn details<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let (line, rest) = split_first_pattern(texts, "\n");
+    let (open, text) = summary(line)?;
+    let nested = options.nested()?;
+    let (inner, rest) = body(rest);
+    let token = Md::Details(open, words(text, options), owned_blocks(parse_blocks(&inner, &nested)));
+    Some(ParsedResult::new(token, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{words,normal_word};
+
+    #[test]
+    fn test_details() {
+        let summary = words!(normal_word!("Question?"));
+        let answer = Md::Sentence(words!(normal_word!("Answer")));
+        let token = Md::Details(false, summary, vec!(answer));
+        let rest = "Next";
+        assert_eq!(details("??? Question?\n    Answer\nNext", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let summary = words!(normal_word!("Question?"));
+        let token = Md::Details(true, summary, vec!());
+        let rest = "";
+        assert_eq!(details("???+ Question?", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        assert_eq!(details("???", &ParserOptions::default()), None);
+        assert_eq!(details("???Question?", &ParserOptions::default()), None);
+    }
+
+    #[test]
+    fn test_details_blocks() {
+        let summary = words!(normal_word!("FAQ"));
+        let heading = Md::Heading(2, words!(normal_word!("Title")));
+        let blank = Md::Sentence(words!(normal_word!("")));
+        let answer = Md::Sentence(words!(normal_word!("Answer")));
+        let token = Md::Details(false, summary, vec!(heading, blank, answer));
+        let rest = "\nNext";
+        assert_eq!(details("??? FAQ\n    ## Title\n\n    Answer\n\nNext", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+use super::table::ragged_row;
+use super::span::{spans, NodeRef, SourceSpan, Spanned};
+use super::visit::{walk, walk_loose_table, walk_word, Visitor};
+use std::fmt;
+
+// Markers that are left as text when nothing closes them.
+const EMPHASIS: [&str; 7] = ["***", "___", "**", "__", "~~", "==", "++"];
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DiagnosticKind {
+    // The marker, e.g. `**`.
+    UnclosedEmphasis(String),
+    RaggedTable(TableIssue),
+    UnknownFootnote(String),
+    // The previous heading's level and this one's.
+    SkippedHeadingLevel(usize, usize),
+    // The image's source; only reported by `parse_with_accessibility_diagnostics`.
+    MissingAltText(String),
+    // The link's url, when its text is a url too, which screen readers spell out.
+    BareUrlLink(String),
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticKind::UnclosedEmphasis(marker) => write!(f, "`{}` is never closed", marker),
+            DiagnosticKind::RaggedTable(issue) => write!(f, "{}", issue),
+            DiagnosticKind::UnknownFootnote(label) => write!(f, "footnote `{}` is never defined", label),
+            DiagnosticKind::SkippedHeadingLevel(previous, level) =>
+                write!(f, "heading level {} follows level {}", level, previous),
+            DiagnosticKind::MissingAltText(src) => write!(f, "image `{}` has no alt text", src),
+            DiagnosticKind::BareUrlLink(url) => write!(f, "link text is the bare url `{}`", url),
+        }
+    }
+}
+
+// A soft problem in the top-level block starting at the 1-based line and column; the
+// snippet is the block's first line. Accessibility diagnostics point at the node itself,
+// and `span` covers the block or node for editors to underline.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub line: usize,
+    pub column: usize,
+    pub snippet: String,
+    pub span: SourceSpan,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}: `{}`", self.line, self.column, self.kind, self.snippet)
+    }
+}
+
+// The problems found in one parse, in document order.
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.items.iter()
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+#[derive(Default)]
+struct Definitions<'a>(Vec<&'a str>);
+
+impl<'a> Visitor<'a> for Definitions<'a> {
+    fn visit_footnote(&mut self, label: &'a str, _words: &'a Words) {
+        self.0.push(label);
+    }
+}
+
+// Walks the document collecting the problems of each block, and remembers the last heading level.
+struct Collector<'a> {
+    labels: Vec<&'a str>,
+    heading: Option<usize>,
+    kinds: Vec<DiagnosticKind>,
+}
+
+impl<'a> Visitor<'a> for Collector<'_> {
+    fn visit_heading(&mut self, level: usize, words: &'a Words) {
+        if let Some(previous) = self.heading.filter(|previous| level > previous + 1) {
+            self.kinds.push(DiagnosticKind::SkippedHeadingLevel(previous, level));
+        }
+        self.heading = Some(level);
+        self.visit_words(words)
+    }
+
+    fn visit_loose_table(&mut self, table: &'a LooseTable) {
+        self.kinds.push(DiagnosticKind::RaggedTable(table.issue.clone()));
+        walk_loose_table(self, table)
+    }
+
+    fn visit_word(&mut self, word: &'a Word) {
+        match word {
+            Word::Normal(text) if EMPHASIS.contains(&text.as_ref()) =>
+                self.kinds.push(DiagnosticKind::UnclosedEmphasis(text.to_string())),
+            word => walk_word(self, word),
+        }
+    }
+
+    fn visit_footnote_ref(&mut self, label: &'a str) {
+        if !self.labels.contains(&label) {
+            self.kinds.push(DiagnosticKind::UnknownFootnote(label.to_string()));
+        }
+    }
+}
+
+// Parses like `parse_with`, and also reports the soft problems the parser worked around.
+// Nested problems are reported at their top-level block.
+pub fn parse_with_diagnostics<'a>(text: &'a str, options: &ParserOptions) -> (Vec<Md<'a>>, Diagnostics) {
+    let (mds, positions) = parse_with_positions(text, options);
+    let mut definitions = Definitions::default();
+    walk(&mut definitions, &mds);
+    let mut collector = Collector{ labels: definitions.0, heading: None, kinds: vec!() };
+    let mut items = vec!();
+    for (md, position) in mds.iter().zip(&positions) {
+        let source = &text[position.bytes.0..position.bytes.1];
+        collector.visit_md(md);
+        if let (Md::Table(_), true) = (md, options.ragged_rows) {
+            collector.kinds.extend(ragged_row(source).map(DiagnosticKind::RaggedTable));
+        }
+        let snippet = source.lines().next().unwrap_or("");
+        let (line, column) = position.start;
+        let span = SourceSpan{ start: position.bytes.0, end: position.bytes.1, line, column };
+        items.extend(collector.kinds.drain(..).map(|kind| {
+            Diagnostic{ kind, line, column, snippet: snippet.to_string(), span }
+        }));
+    }
+    (mds, Diagnostics{ items })
+}
+
+fn is_url(text: &str) -> bool {
+    !text.contains(char::is_whitespace) && ["https://", "http://", "www."].iter().any(|prefix| text.starts_with(prefix))
+}
+
+// Finds accessibility problems in a node and below it, in document order.
+fn accessibility(text: &str, spanned: &Spanned, heading: &mut Option<usize>, items: &mut Vec<Diagnostic>) {
+    let kind = match spanned.node {
+        NodeRef::Md(Md::Heading(level, _)) => {
+            let skipped = heading.filter(|previous| *level > previous + 1);
+            *heading = Some(*level);
+            skipped.map(|previous| DiagnosticKind::SkippedHeadingLevel(previous, *level))
+        },
+        NodeRef::Word(Word::Image(alt, src, _)) if alt.trim().is_empty() => Some(DiagnosticKind::MissingAltText(src.clone())),
+        NodeRef::Word(Word::Link(words, url)) if is_url(crate::convert::text::words_to_text(words).trim()) => {
+            Some(DiagnosticKind::BareUrlLink(url.clone()))
+        },
+        _ => None,
+    };
+    if let Some(kind) = kind {
+        let SourceSpan{ start, end, line, column } = spanned.span;
+        let snippet = text[start..end].lines().next().unwrap_or("").to_string();
+        items.push(Diagnostic{ kind, line, column, snippet, span: spanned.span });
+    }
+    for child in &spanned.children {
+        accessibility(text, child, heading, items);
+    }
+}
+
+// An accessibility lint: parses like `parse_with`, and reports images without alt text,
+// links whose text is a bare url and skipped heading levels, each at the node's own span.
+pub fn parse_with_accessibility_diagnostics<'a>(text: &'a str, options: &ParserOptions) -> (Vec<Md<'a>>, Diagnostics) {
+    let mds = parse_with(text, options);
+    let mut items = vec!();
+    let mut heading = None;
+    for spanned in spans(text, &mds, options) {
+        accessibility(text, &spanned, &mut heading, &mut items);
+    }
+    (mds, Diagnostics{ items })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str, options: &ParserOptions) -> Vec<DiagnosticKind> {
+        parse_with_diagnostics(text, options).1.into_iter().map(|diagnostic| diagnostic.kind).collect()
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let options = ParserOptions::default();
+        let (mds, diagnostics) = parse_with_diagnostics("# Title\n## Section\nPlain **bold** text", &options);
+        assert_eq!(mds, parse("# Title\n## Section\nPlain **bold** text"));
+        assert!(diagnostics.is_empty());
+
+        let (_, diagnostics) = parse_with_diagnostics("# Title\n\nSome **bold text", &options);
+        let expect = Diagnostic{
+            kind: DiagnosticKind::UnclosedEmphasis("**".to_string()),
+            line: 3, column: 1, snippet: "Some **bold text".to_string(),
+            span: SourceSpan{ start: 9, end: 25, line: 3, column: 1 },
+        };
+        assert_eq!(diagnostics.iter().collect::<Vec<_>>(), vec!(&expect));
+        assert_eq!(expect.to_string(), "3:1: `**` is never closed: `Some **bold text`");
+
+        assert_eq!(kinds("# A\n### C\n## B", &options), vec!(DiagnosticKind::SkippedHeadingLevel(1, 3)));
+        assert_eq!(kinds("Note[^a] and[^b]\n[^a]: defined", &options), vec!(DiagnosticKind::UnknownFootnote("b".to_string())));
+        assert_eq!(kinds("> quoted ~~strike", &options), vec!(DiagnosticKind::UnclosedEmphasis("~~".to_string())));
+        assert_eq!(kinds("***both", &options), vec!(DiagnosticKind::UnclosedEmphasis("***".to_string())));
+    }
+
+    #[test]
+    fn test_accessibility_diagnostics() {
+        let text = "# Title\n### Deep\nSee ![](a.png) and ![logo](b.png)\n- [https://example.com](https://example.com) or [docs](/docs)";
+        let options = ParserOptions::default();
+        let (mds, diagnostics) = parse_with_accessibility_diagnostics(text, &options);
+        assert_eq!(mds, parse(text));
+        let found: Vec<String> = diagnostics.iter().map(Diagnostic::to_string).collect();
+        assert_eq!(found, vec!(
+            "2:1: heading level 3 follows level 1: `### Deep`",
+            "3:5: image `a.png` has no alt text: `![](a.png)`",
+            "4:3: link text is the bare url `https://example.com`: `[https://example.com](https://example.com)`",
+        ));
+        let image = diagnostics.iter().nth(1).unwrap();
+        assert_eq!(&text[image.span.start..image.span.end], "![](a.png)");
+
+        let options = ParserOptions::gfm();
+        let kinds: Vec<DiagnosticKind> = parse_with_accessibility_diagnostics("Visit www.rust-lang.org", &options).1.into_iter()
+            .map(|diagnostic| diagnostic.kind)
+            .collect();
+        assert_eq!(kinds, vec!(DiagnosticKind::BareUrlLink("http://www.rust-lang.org".to_string())));
+        assert!(parse_with_accessibility_diagnostics("# A\n## B\n![a](a.png)", &ParserOptions::default()).1.is_empty());
+    }
+
+    #[test]
+    fn test_table_diagnostics() {
+        let text = "| A | B |\n| - | - |\n| a |";
+        let options = ParserOptions{ ragged_rows: true, ..Default::default() };
+        assert_eq!(kinds(text, &options), vec!(DiagnosticKind::RaggedTable(TableIssue::RowCells(0, 2, 1))));
+        let options = ParserOptions{ loose_tables: true, ..Default::default() };
+        assert_eq!(kinds(text, &options), vec!(DiagnosticKind::RaggedTable(TableIssue::RowCells(0, 2, 1))));
+        assert!(kinds(text, &ParserOptions::default()).is_empty());
+    }
+}
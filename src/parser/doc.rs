@@ -0,0 +1,133 @@
+use crate::parser::parser::*;
+use crate::convert::convert::mds_to_html;
+use crate::format::format::to_markdown;
+use super::options::ParserOptions;
+use super::sentence::words;
+
+fn inline(text: &str) -> Words<'static> {
+    words(text, &ParserOptions::default()).into_owned()
+}
+
+// Builds a document in code rather than parsing one. Text arguments take inline markdown,
+// like `**bold**` or `[a link](/url)`, and block arguments take another `Doc`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Doc {
+    mds: Vec<Md<'static>>,
+}
+
+impl Doc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn block(mut self, md: Md<'static>) -> Self {
+        self.mds.push(md);
+        self
+    }
+
+    pub fn heading(self, level: usize, text: &str) -> Self {
+        self.block(Md::Heading(level, inline(text)))
+    }
+
+    pub fn h1(self, text: &str) -> Self {
+        self.heading(1, text)
+    }
+
+    pub fn h2(self, text: &str) -> Self {
+        self.heading(2, text)
+    }
+
+    pub fn h3(self, text: &str) -> Self {
+        self.heading(3, text)
+    }
+
+    // One sentence per line, set apart from a paragraph just before it by a blank line.
+    pub fn para(mut self, text: &str) -> Self {
+        if matches!(self.mds.last(), Some(Md::Sentence(_))) {
+            self.mds.push(Md::Sentence(inline("")));
+        }
+        for line in text.lines() {
+            self.mds.push(Md::Sentence(inline(line)));
+        }
+        self
+    }
+
+    pub fn list<S: AsRef<str>>(self, items: impl IntoIterator<Item = S>) -> Self {
+        self.list_of(ListKind::Bullet('-'), items)
+    }
+
+    pub fn ordered_list<S: AsRef<str>>(self, items: impl IntoIterator<Item = S>) -> Self {
+        self.list_of(ListKind::Ordered(1), items)
+    }
+
+    fn list_of<S: AsRef<str>>(self, kind: ListKind, items: impl IntoIterator<Item = S>) -> Self {
+        let items = items.into_iter().map(|item| Item(inline(item.as_ref()), vec!())).collect();
+        self.block(Md::List(Items(items, kind)))
+    }
+
+    // Every column is left aligned; rows shorter than the header get empty cells.
+    pub fn table<S: AsRef<str>, R: AsRef<[S]>>(self, header: &[S], rows: &[R]) -> Self {
+        let record = |cells: &[S]| {
+            let mut cells: Vec<Words<'static>> = cells.iter().map(|cell| inline(cell.as_ref())).collect();
+            while cells.len() < header.len() { cells.push(inline("")) }
+            Record(cells, vec!())
+        };
+        let records = rows.iter().map(|row| record(row.as_ref())).collect();
+        let align = vec!(Align::Left; header.len());
+        self.block(Md::Table(Box::new(Table{ header: record(header), align, records, caption: None })))
+    }
+
+    pub fn code(self, lang: &str, code: &str) -> Self {
+        self.block(Md::Code(lang.to_string(), code.to_string(), vec!()))
+    }
+
+    pub fn quote(self, doc: Doc) -> Self {
+        self.block(Md::Quote(doc.mds))
+    }
+
+    pub fn container(self, name: &str, doc: Doc) -> Self {
+        self.block(Md::Container(name.to_string(), doc.mds))
+    }
+
+    pub fn toc(self) -> Self {
+        self.block(Md::Toc)
+    }
+
+    pub fn mds(&self) -> &[Md<'static>] {
+        &self.mds
+    }
+
+    pub fn build(self) -> Vec<Md<'static>> {
+        self.mds
+    }
+
+    pub fn to_html(&self) -> String {
+        mds_to_html(&self.mds)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        to_markdown(&self.mds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc() {
+        let doc = Doc::new()
+            .h1("Report")
+            .para("Generated **weekly**.\nSee [the wiki](/wiki).")
+            .para("Second paragraph.")
+            .table(&["Name", "Count"], &[vec!("a", "1"), vec!("b")])
+            .list(["one", "*two*"])
+            .quote(Doc::new().para("Quoted"))
+            .code("rust", "fn main() {}");
+        let markdown = doc.to_markdown();
+        assert_eq!(parse(&markdown), doc.clone().build());
+        assert!(markdown.starts_with("# Report\nGenerated **weekly**.\nSee [the wiki](/wiki).\n\nSecond paragraph.\n| Name | Count |"), "{}", markdown);
+        assert!(doc.to_html().contains("<tr><td align=\"left\">b</td><td align=\"left\"></td></tr>"));
+        assert_eq!(Doc::new().ordered_list(vec!("x".to_string())).mds(), parse("1. x").as_slice());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::parser::parser::*;
+use crate::parser::span::NodeRef;
+use crate::query::kind;
+use std::fmt::Write;
+
+fn line(out: &mut String, depth: usize, text: &str) {
+    writeln!(out, "{}{}", "  ".repeat(depth), text).expect("writing to a String never fails");
+}
+
+fn dump_words(out: &mut String, depth: usize, words: &Words) {
+    for word in &words.0 {
+        let name = kind(&NodeRef::Word(word));
+        match word {
+            Word::Italic(words)
+            | Word::Bold(words)
+            | Word::StrikeThough(words)
+            | Word::Underline(words)
+            | Word::Highlight(words)
+            | Word::Subscript(words)
+            | Word::Superscript(words)
+            | Word::Insert(words) => {
+                line(out, depth, name);
+                dump_words(out, depth + 1, words);
+            },
+            Word::Link(words, url) => {
+                line(out, depth, &format!("{} {:?}", name, url));
+                dump_words(out, depth + 1, words);
+            },
+            Word::Normal(text) => line(out, depth, &format!("{} {:?}", name, text)),
+            Word::Kbd(text) | Word::Emoji(text) | Word::FootnoteRef(text) => line(out, depth, &format!("{} {:?}", name, text)),
+            Word::Task(checked) => line(out, depth, if *checked { "task checked" } else { "task" }),
+            Word::Entity(reference, text) => line(out, depth, &format!("{} {:?} {:?}", name, reference, text)),
+            Word::Image(alt, src, size) => {
+                let mut text = format!("{} {:?} alt={:?}", name, src, alt);
+                if let Some(width) = &size.width { text += &format!(" width={:?}", width) }
+                if let Some(height) = &size.height { text += &format!(" height={:?}", height) }
+                line(out, depth, &text)
+            },
+            Word::Custom(custom, text) => line(out, depth, &format!("{} {:?} {:?}", name, custom, text)),
+        }
+    }
+}
+
+fn dump_record(out: &mut String, depth: usize, name: &str, record: &Record) {
+    line(out, depth, name);
+    for cell in &record.0 {
+        line(out, depth + 1, "cell");
+        dump_words(out, depth + 2, cell);
+    }
+}
+
+fn dump_mds(out: &mut String, depth: usize, mds: &[Md]) {
+    for md in mds {
+        match md {
+            Md::Heading(level, words) => {
+                line(out, depth, &format!("heading {}", level));
+                dump_words(out, depth + 1, words);
+            },
+            Md::Sentence(words) => {
+                line(out, depth, "sentence");
+                dump_words(out, depth + 1, words);
+            },
+            Md::Table(table) => {
+                let align: Vec<String> = table.align.iter().map(|align| format!("{:?}", align).to_lowercase()).collect();
+                line(out, depth, &format!("table align=[{}]", align.join(", ")));
+                if let Some(caption) = &table.caption {
+                    line(out, depth + 1, "caption");
+                    dump_words(out, depth + 2, caption);
+                }
+                dump_record(out, depth + 1, "header", &table.header);
+                table.records.iter().for_each(|record| dump_record(out, depth + 1, "row", record));
+            },
+            Md::LooseTable(table) => {
+                line(out, depth, &format!("loose table ({})", table.issue));
+                dump_record(out, depth + 1, "header", &table.header);
+                table.rows.iter().for_each(|record| dump_record(out, depth + 1, "row", record));
+            },
+            Md::List(Items(items, list)) => {
+                match list {
+                    ListKind::Bullet(bullet) => line(out, depth, &format!("list bullet {:?}", bullet)),
+                    ListKind::Ordered(start) => line(out, depth, &format!("list ordered from {}", start)),
+                }
+                for Item(words, mds) in items {
+                    line(out, depth + 1, "item");
+                    dump_words(out, depth + 2, words);
+                    dump_mds(out, depth + 2, mds);
+                }
+            },
+            Md::Container(name, mds) => {
+                line(out, depth, &format!("container {:?}", name));
+                dump_mds(out, depth + 1, mds);
+            },
+            Md::Details(open, summary, mds) => {
+                line(out, depth, if *open { "details open" } else { "details" });
+                line(out, depth + 1, "summary");
+                dump_words(out, depth + 2, summary);
+                dump_mds(out, depth + 1, mds);
+            },
+            Md::Toc => line(out, depth, "toc"),
+            Md::Abbreviation(abbr, title) => line(out, depth, &format!("abbreviation {:?} {:?}", abbr, title)),
+            Md::Footnote(label, words) => {
+                line(out, depth, &format!("footnote {:?}", label));
+                dump_words(out, depth + 1, words);
+            },
+            Md::Code(lang, code, ranges) => {
+                let mut text = format!("code {:?}", lang);
+                if !ranges.is_empty() {
+                    let ranges: Vec<String> = ranges.iter().map(|(start, end)| format!("{}-{}", start, end)).collect();
+                    text += &format!(" highlight={}", ranges.join(","));
+                }
+                line(out, depth, &format!("{} {:?}", text, code));
+            },
+            Md::Quote(mds) => {
+                line(out, depth, "quote");
+                dump_mds(out, depth + 1, mds);
+            },
+            Md::FrontMatter(format, text) => line(out, depth, &format!("front matter {:?} {:?}", format, text)),
+            Md::Raw(text) => line(out, depth, &format!("raw {:?}", text)),
+            Md::Custom(name, text) => line(out, depth, &format!("custom {:?} {:?}", name, text)),
+        }
+    }
+}
+
+// The document as an indented tree, one node per line with its children two spaces further
+// in; easier to read than `{:?}` when looking into why something parsed the way it did.
+pub fn dump(mds: &[Md]) -> String {
+    let mut out = String::new();
+    dump_mds(&mut out, 0, mds);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump() {
+        let text = "# Title\nSome **bold [link](/a)** &amp; ![logo](l.png)\n| A | B |\n| :- | -: |\n| a | b |\n1. one\n   - [x] nested\n```rust {2}\nfn main() {}\n```";
+        let expect = r#"heading 1
+  text "Title"
+sentence
+  text "Some "
+  bold
+    text "bold "
+    link "/a"
+      text "link"
+  text " "
+  entity "&amp;" "&"
+  text " "
+  image "l.png" alt="logo"
+table align=[left, right]
+  header
+    cell
+      text "A"
+    cell
+      text "B"
+  row
+    cell
+      text "a"
+    cell
+      text "b"
+list ordered from 1
+  item
+    text "one"
+    list bullet '-'
+      item
+        text "[x] nested"
+code "rust" highlight=2-2 "fn main() {}"
+"#;
+        assert_eq!(dump(&parse(text)), expect);
+        assert_eq!(dump(&[]), "");
+    }
+}
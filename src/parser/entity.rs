@@ -0,0 +1,55 @@
+// Named references the parser decodes, sorted by name. Others stay literal text.
+const NAMED: [(&str, &str); 48] = [
+    ("AElig", "Æ"), ("Eacute", "É"), ("aacute", "á"), ("amp", "&"), ("apos", "'"), ("bull", "•"),
+    ("cent", "¢"), ("copy", "©"), ("darr", "↓"), ("deg", "°"), ("divide", "÷"), ("eacute", "é"),
+    ("egrave", "è"), ("euro", "€"), ("frac12", "½"), ("gt", ">"), ("harr", "↔"), ("hearts", "♥"),
+    ("hellip", "…"), ("iexcl", "¡"), ("iquest", "¿"), ("laquo", "«"), ("larr", "←"), ("ldquo", "“"),
+    ("lsquo", "‘"), ("lt", "<"), ("mdash", "—"), ("micro", "µ"), ("middot", "·"), ("nbsp", "\u{a0}"),
+    ("ndash", "–"), ("ntilde", "ñ"), ("ouml", "ö"), ("para", "¶"), ("plusmn", "±"), ("pound", "£"),
+    ("quot", "\""), ("raquo", "»"), ("rarr", "→"), ("rdquo", "”"), ("reg", "®"), ("rsquo", "’"),
+    ("sect", "§"), ("szlig", "ß"), ("times", "×"), ("trade", "™"), ("uarr", "↑"), ("uuml", "ü"),
+];
+
+// `&#169;` or `&#x1F600;`; like CommonMark, zero and invalid code points become U+FFFD.
+fn numeric(reference: &str) -> Option<char> {
+    let (digits, radix, max) = match reference.strip_prefix(['x', 'X']) {
+        Some(hex) => (hex, 16, 6),
+        None => (reference, 10, 7),
+    };
+    if digits.is_empty() || digits.len() > max || !digits.chars().all(|c| c.is_digit(radix)) { return None }
+    let code = u32::from_str_radix(digits, radix).ok()?;
+    Some(char::from_u32(code).filter(|c| *c != '\0').unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+// The entity reference at the start of `text`, as written, and the text it stands for.
+pub fn entity(text: &str) -> Option<(&str, String)> {
+    let inner = text.strip_prefix('&')?;
+    let end = inner.find(';')?;
+    let name = &inner[..end];
+    let decoded = match name.strip_prefix('#') {
+        Some(number) => numeric(number)?.to_string(),
+        None => NAMED.binary_search_by_key(&name, |(name, _)| name).ok().map(|i| NAMED[i].1.to_string())?,
+    };
+    Some((&text[..(end + 2)], decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity() {
+        assert_eq!(entity("&amp; rest"), Some(("&amp;", "&".to_string())));
+        assert_eq!(entity("&copy;"), Some(("&copy;", "©".to_string())));
+        assert_eq!(entity("&#169;"), Some(("&#169;", "©".to_string())));
+        assert_eq!(entity("&#x1F600;"), Some(("&#x1F600;", "😀".to_string())));
+        assert_eq!(entity("&#X41;"), Some(("&#X41;", "A".to_string())));
+        assert_eq!(entity("&#0;"), Some(("&#0;", "\u{fffd}".to_string())));
+        assert_eq!(entity("&#xD800;"), Some(("&#xD800;", "\u{fffd}".to_string())));
+
+        for text in ["&", "&amp", "&unknown;", "&#;", "&#x;", "&#12345678;", "&#xZZ;", "& amp;", "amp;"] {
+            assert_eq!(entity(text), None, "{}", text);
+        }
+        assert!(NAMED.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
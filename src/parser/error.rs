@@ -0,0 +1,125 @@
+use crate::parser::parser::*;
+use super::options::{LimitExceeded, ParserOptions};
+use super::limits::check;
+use super::code;
+use super::container;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseErrorKind {
+    UnclosedCodeFence,
+    UnclosedContainer,
+    // Consecutive `| ... |` lines that did not make a table, or a recovered `LooseTable`.
+    MalformedTable,
+    Limit(LimitExceeded),
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnclosedCodeFence => write!(f, "code fence is never closed"),
+            ParseErrorKind::UnclosedContainer => write!(f, "container is never closed"),
+            ParseErrorKind::MalformedTable => write!(f, "table rows that do not match the header and alignment row"),
+            ParseErrorKind::Limit(limit) => write!(f, "{}", limit),
+        }
+    }
+}
+
+// Where parsing went wrong: the 1-based line and column of the block, and its first line.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub line: usize,
+    pub column: usize,
+    pub snippet: String,
+}
+
+impl ParseError {
+    fn at(kind: ParseErrorKind, text: &str, position: &SourcePos) -> Self {
+        let source = &text[position.bytes.0..position.bytes.1];
+        let snippet = source.lines().next().unwrap_or("").to_string();
+        ParseError{ kind, line: position.start.0, column: position.start.1, snippet }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}: `{}`", self.line, self.column, self.kind, self.snippet)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn is_row(md: &Md, source: &str) -> bool {
+    let line = source.trim();
+    matches!(md, Md::Sentence(_)) && line.len() > 1 && line.starts_with('|') && line.ends_with('|')
+}
+
+// Parses like `parse_with`, but fails on the first top-level block the lenient parser had to
+// guess at, or that breaks one of the options' caps.
+pub fn try_parse_strict<'a>(text: &'a str, options: &ParserOptions) -> Result<Vec<Md<'a>>, ParseError> {
+    if let Some(max) = options.max_input_bytes.filter(|max| text.len() > *max) {
+        let kind = ParseErrorKind::Limit(LimitExceeded::InputBytes(max));
+        return Err(ParseError{ kind, line: 1, column: 1, snippet: "".to_string() })
+    }
+    let (mds, positions) = parse_with_positions(text, options);
+    let mut nodes = 0;
+    for (i, (md, position)) in mds.iter().zip(&positions).enumerate() {
+        let source = &text[position.bytes.0..position.bytes.1];
+        if let Err(limit) = check(md, options, &mut nodes) {
+            return Err(ParseError::at(ParseErrorKind::Limit(limit), text, position))
+        }
+        let kind = match md {
+            Md::Code(..) if !code::is_closed(source) => Some(ParseErrorKind::UnclosedCodeFence),
+            Md::Container(..) if !container::is_closed(source) => Some(ParseErrorKind::UnclosedContainer),
+            Md::LooseTable(_) => Some(ParseErrorKind::MalformedTable),
+            _ if is_row(md, source) => {
+                let next = mds.get(i + 1).zip(positions.get(i + 1));
+                next.filter(|(md, next)| is_row(md, &text[next.bytes.0..next.bytes.1]))
+                    .map(|_| ParseErrorKind::MalformedTable)
+            },
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            return Err(ParseError::at(kind, text, position))
+        }
+    }
+    Ok(mds)
+}
+
+pub fn try_parse(text: &str) -> Result<Vec<Md<'_>>, ParseError> {
+    try_parse_strict(text, &ParserOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_parse() {
+        assert_eq!(try_parse("# Title\n```\ncode\n```\n::: note\nhi\n:::"), Ok(parse("# Title\n```\ncode\n```\n::: note\nhi\n:::")));
+
+        let err = try_parse("# Title\n\n  ```rust\nfn main() {}").unwrap_err();
+        assert_eq!(err, ParseError{ kind: ParseErrorKind::UnclosedCodeFence, line: 3, column: 1, snippet: "  ```rust".to_string() });
+        assert_eq!(err.to_string(), "3:1: code fence is never closed: `  ```rust`");
+
+        let err = try_parse("Intro\n::: warning\nCareful").unwrap_err();
+        assert_eq!((err.kind, err.line), (ParseErrorKind::UnclosedContainer, 2));
+
+        let err = try_parse("| A | B |\n| a | b |").unwrap_err();
+        assert_eq!((err.kind, err.line, err.snippet.as_str()), (ParseErrorKind::MalformedTable, 1, "| A | B |"));
+        assert!(try_parse("| just pipes |").is_ok());
+    }
+
+    #[test]
+    fn test_try_parse_strict_limits() {
+        let options = ParserOptions{ max_table_rows: Some(1), ..Default::default() };
+        let err = try_parse_strict("Hello\n| A |\n| - |\n| a |\n| b |", &options).unwrap_err();
+        assert_eq!((err.kind, err.line), (ParseErrorKind::Limit(LimitExceeded::TableRows(1)), 2));
+
+        let options = ParserOptions{ max_input_bytes: Some(2), ..Default::default() };
+        assert_eq!(try_parse_strict("Hello", &options).unwrap_err().kind, ParseErrorKind::Limit(LimitExceeded::InputBytes(2)));
+    }
+}
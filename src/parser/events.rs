@@ -0,0 +1,256 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+use super::front_matter::front_matter;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+// A node with children, opened by `Event::Start` and closed by a matching `Event::End`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Tag {
+    Heading(usize),
+    Sentence,
+    Table(Vec<Align>),
+    // Rows that did not make a table, with the reason.
+    LooseTable(TableIssue),
+    TableHead,
+    TableRow,
+    TableCell(Span),
+    Caption,
+    List(ListKind),
+    Item,
+    Container(String),
+    Details(bool),
+    Summary,
+    Quote,
+    Footnote(String),
+    Italic,
+    Bold,
+    StrikeThough,
+    Underline,
+    Highlight,
+    Subscript,
+    Superscript,
+    Insert,
+    Link(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Event {
+    Start(Tag),
+    End(Tag),
+    Text(String),
+    // Language, code and highlighted line ranges.
+    Code(String, String, Vec<(usize, usize)>),
+    Kbd(String),
+    // The extension's name and the text it matched, for `Word::Custom`.
+    Custom(String, String),
+    Emoji(String),
+    FootnoteRef(String),
+    Task(bool),
+    // The reference as written and its decoded text, for `Word::Entity`.
+    Entity(String, String),
+    Image(String, String, ImageSize),
+    Toc,
+    Abbreviation(String, String),
+    FrontMatter(String),
+    Raw(String),
+    // The same for `Md::Custom`.
+    CustomBlock(String, String),
+}
+
+fn wrapped(tag: Tag, words: Words, queue: &mut VecDeque<Event>) {
+    queue.push_back(Event::Start(tag.clone()));
+    words_events(words, queue);
+    queue.push_back(Event::End(tag));
+}
+
+fn word_events(word: Word, queue: &mut VecDeque<Event>) {
+    match word {
+        Word::Normal(text) => queue.push_back(Event::Text(text.into_owned())),
+        Word::Italic(words) => wrapped(Tag::Italic, words, queue),
+        Word::Bold(words) => wrapped(Tag::Bold, words, queue),
+        Word::StrikeThough(words) => wrapped(Tag::StrikeThough, words, queue),
+        Word::Underline(words) => wrapped(Tag::Underline, words, queue),
+        Word::Highlight(words) => wrapped(Tag::Highlight, words, queue),
+        Word::Subscript(words) => wrapped(Tag::Subscript, words, queue),
+        Word::Superscript(words) => wrapped(Tag::Superscript, words, queue),
+        Word::Insert(words) => wrapped(Tag::Insert, words, queue),
+        Word::Link(words, url) => wrapped(Tag::Link(url), words, queue),
+        Word::Kbd(key) => queue.push_back(Event::Kbd(key)),
+        Word::Custom(name, source) => queue.push_back(Event::Custom(name, source)),
+        Word::Emoji(name) => queue.push_back(Event::Emoji(name)),
+        Word::FootnoteRef(label) => queue.push_back(Event::FootnoteRef(label)),
+        Word::Task(checked) => queue.push_back(Event::Task(checked)),
+        Word::Entity(reference, text) => queue.push_back(Event::Entity(reference, text)),
+        Word::Image(alt, src, size) => queue.push_back(Event::Image(alt, src, size)),
+    }
+}
+
+fn words_events(words: Words, queue: &mut VecDeque<Event>) {
+    words.0.into_iter().for_each(|word| word_events(word, queue));
+}
+
+fn record_events(record: Record, row: Tag, queue: &mut VecDeque<Event>) {
+    let Record(cells, spans) = record;
+    queue.push_back(Event::Start(row.clone()));
+    for (i, words) in cells.into_iter().enumerate() {
+        let span = spans.get(i).copied().unwrap_or(Span::Cell(1, 1));
+        wrapped(Tag::TableCell(span), words, queue);
+    }
+    queue.push_back(Event::End(row));
+}
+
+fn blocks_events(mds: Vec<Md>, queue: &mut VecDeque<Event>) {
+    mds.into_iter().for_each(|md| md_events(md, queue));
+}
+
+fn md_events(md: Md, queue: &mut VecDeque<Event>) {
+    let (tag, children) = match md {
+        Md::Heading(level, words) => return wrapped(Tag::Heading(level), words, queue),
+        Md::Sentence(words) => return wrapped(Tag::Sentence, words, queue),
+        Md::Footnote(label, words) => return wrapped(Tag::Footnote(label), words, queue),
+        Md::Table(table) => {
+            let Table{ header, align, records, caption } = *table;
+            let tag = Tag::Table(align);
+            queue.push_back(Event::Start(tag.clone()));
+            record_events(header, Tag::TableHead, queue);
+            records.into_iter().for_each(|record| record_events(record, Tag::TableRow, queue));
+            if let Some(caption) = caption {
+                wrapped(Tag::Caption, caption, queue);
+            }
+            queue.push_back(Event::End(tag));
+            return
+        },
+        Md::LooseTable(table) => {
+            let LooseTable{ header, rows, issue } = *table;
+            let tag = Tag::LooseTable(issue);
+            queue.push_back(Event::Start(tag.clone()));
+            record_events(header, Tag::TableHead, queue);
+            rows.into_iter().for_each(|record| record_events(record, Tag::TableRow, queue));
+            queue.push_back(Event::End(tag));
+            return
+        },
+        Md::List(Items(items, kind)) => {
+            queue.push_back(Event::Start(Tag::List(kind)));
+            for Item(words, children) in items {
+                queue.push_back(Event::Start(Tag::Item));
+                words_events(words, queue);
+                blocks_events(children, queue);
+                queue.push_back(Event::End(Tag::Item));
+            }
+            queue.push_back(Event::End(Tag::List(kind)));
+            return
+        },
+        Md::Details(open, summary, mds) => {
+            queue.push_back(Event::Start(Tag::Details(open)));
+            wrapped(Tag::Summary, summary, queue);
+            blocks_events(mds, queue);
+            queue.push_back(Event::End(Tag::Details(open)));
+            return
+        },
+        Md::Container(name, mds) => (Tag::Container(name), mds),
+        Md::Quote(mds) => (Tag::Quote, mds),
+        Md::Code(lang, code, ranges) => return queue.push_back(Event::Code(lang, code, ranges)),
+        Md::Toc => return queue.push_back(Event::Toc),
+        Md::Abbreviation(abbr, title) => return queue.push_back(Event::Abbreviation(abbr, title)),
+        Md::FrontMatter(_, matter) => return queue.push_back(Event::FrontMatter(matter)),
+        Md::Raw(text) => return queue.push_back(Event::Raw(text)),
+        Md::Custom(name, source) => return queue.push_back(Event::CustomBlock(name, source)),
+    };
+    queue.push_back(Event::Start(tag.clone()));
+    blocks_events(children, queue);
+    queue.push_back(Event::End(tag));
+}
+
+// Parses one top-level block at a time as the events are pulled, so only the block being
+// read is ever held as a tree.
+pub struct Events<'a> {
+    text: Cow<'a, str>,
+    offset: usize,
+    options: ParserOptions,
+    queue: VecDeque<Event>,
+}
+
+impl<'a> Events<'a> {
+    pub fn new(text: &'a str, options: &ParserOptions) -> Self {
+        Events{ text: normalize_borrowed(text, options), offset: 0, options: options.clone(), queue: VecDeque::new() }
+    }
+}
+
+impl Iterator for Events<'_> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        if let Some(event) = self.queue.pop_front() {
+            return Some(event)
+        }
+        let rest = &self.text[self.offset..];
+        let matter = if self.offset == 0 { front_matter(rest) } else { None };
+        let result = matter.or_else(|| block(rest, &self.options))?;
+        self.offset = self.text.len() - result.rest.len();
+        md_events(result.token, &mut self.queue);
+        self.queue.pop_front()
+    }
+}
+
+pub fn events<'a>(text: &'a str, options: &ParserOptions) -> Events<'a> {
+    Events::new(text, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let options = ParserOptions::default();
+        let events: Vec<Event> = events("# Title\nSome **bold** [link](a.html)\n- one\n  - two", &options).collect();
+        let expect = vec!(
+            Event::Start(Tag::Heading(1)), Event::Text("Title".to_string()), Event::End(Tag::Heading(1)),
+            Event::Start(Tag::Sentence),
+            Event::Text("Some ".to_string()),
+            Event::Start(Tag::Bold), Event::Text("bold".to_string()), Event::End(Tag::Bold),
+            Event::Text(" ".to_string()),
+            Event::Start(Tag::Link("a.html".to_string())), Event::Text("link".to_string()), Event::End(Tag::Link("a.html".to_string())),
+            Event::End(Tag::Sentence),
+            Event::Start(Tag::List(ListKind::Bullet('-'))),
+            Event::Start(Tag::Item), Event::Text("one".to_string()),
+            Event::Start(Tag::List(ListKind::Bullet('-'))),
+            Event::Start(Tag::Item), Event::Text("two".to_string()), Event::End(Tag::Item),
+            Event::End(Tag::List(ListKind::Bullet('-'))),
+            Event::End(Tag::Item),
+            Event::End(Tag::List(ListKind::Bullet('-'))),
+        );
+        assert_eq!(events, expect);
+    }
+
+    #[test]
+    fn test_table_events() {
+        let options = ParserOptions::default();
+        let events: Vec<Event> = events("---\ntitle: T\n---\n| A |\n| -: |\n| a |\nTable: Caption", &options).collect();
+        let cell = |text: &str| vec!(
+            Event::Start(Tag::TableCell(Span::Cell(1, 1))), Event::Text(text.to_string()), Event::End(Tag::TableCell(Span::Cell(1, 1))),
+        );
+        let mut expect = vec!(Event::FrontMatter("title: T".to_string()), Event::Start(Tag::Table(vec!(Align::Right))), Event::Start(Tag::TableHead));
+        expect.extend(cell("A"));
+        expect.extend([Event::End(Tag::TableHead), Event::Start(Tag::TableRow)]);
+        expect.extend(cell("a"));
+        expect.extend([
+            Event::End(Tag::TableRow),
+            Event::Start(Tag::Caption), Event::Text("Caption".to_string()), Event::End(Tag::Caption),
+            Event::End(Tag::Table(vec!(Align::Right))),
+        ]);
+        assert_eq!(events, expect);
+    }
+
+    #[test]
+    fn test_events_are_lazy() {
+        let options = ParserOptions::default();
+        let text = "# Title\n".repeat(100_000);
+        let mut events = events(&text, &options);
+        assert_eq!(events.next(), Some(Event::Start(Tag::Heading(1))));
+        assert_eq!(events.offset, "# Title\n".len());
+        assert!(matches!(events.text, Cow::Borrowed(_)));
+        assert_eq!(events.count(), 100_000 * 3 - 1);
+    }
+}
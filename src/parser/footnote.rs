@@ -0,0 +1,30 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+use super::sentence::words;
+
+pub fn footnote<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let (line, rest) = split_first_pattern(texts, "\n");
+    let line = consume(line, "[^")?;
+    let (label, text) = line.split_once("]:")?;
+    if label.is_empty() || label.contains(char::is_whitespace) || label.contains(']') { return None }
+    let token = Md::Footnote(label.to_string(), words(text.trim(), options));
+    Some(ParsedResult::new(token, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{normal_word,words};
+
+    #[test]
+    fn test_footnote() {
+        let options = ParserOptions::default();
+        let token = Md::Footnote("note".to_string(), words!(normal_word!("A "), Word::Bold(words!(normal_word!("note"))), normal_word!(".")));
+        let rest = "Hello";
+        assert_eq!(footnote("[^note]: A **note**.\nHello", &options), Some(ParsedResult{token, rest}));
+
+        assert_eq!(footnote("[^]: Empty", &options), None);
+        assert_eq!(footnote("[^a b]: Spaced", &options), None);
+        assert_eq!(footnote("[^note] text", &options), None);
+    }
+}
@@ -0,0 +1,42 @@
+use crate::parser::parser::*;
+
+// The format of front matter opened by `line`.
+pub fn front_matter_fence(line: &str) -> Option<FrontMatterFormat> {
+    [FrontMatterFormat::Yaml, FrontMatterFormat::Toml].into_iter().find(|format| line.trim_end() == format.fence())
+}
+
+// Fenced metadata, only recognised on the first line of a document.
+pub fn front_matter(texts: &str) -> Option<ParsedResult<'_, Md<'_>>> {
+    let (line, mut rest) = split_first_pattern(texts, "\n");
+    let format = front_matter_fence(line)?;
+    let mut lines: Vec<&str> = vec!();
+    while !rest.is_empty() {
+        let (line, next) = split_first_pattern(rest, "\n");
+        rest = next;
+        if line.trim_end() == format.fence() {
+            let token = Md::FrontMatter(format, lines.join("\n"));
+            return Some(ParsedResult::new(token, rest))
+        }
+        lines.push(line);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter() {
+        let token = Md::FrontMatter(FrontMatterFormat::Yaml, "title: Hello\ntags: [a]".to_string());
+        let rest = "# Body";
+        assert_eq!(front_matter("---\ntitle: Hello\ntags: [a]\n---\n# Body"), Some(ParsedResult{token, rest}));
+
+        let token = Md::FrontMatter(FrontMatterFormat::Toml, "title = \"Hello\"".to_string());
+        assert_eq!(front_matter("+++\ntitle = \"Hello\"\n+++\n# Body"), Some(ParsedResult{token, rest}));
+
+        assert_eq!(front_matter("---\ntitle: Hello"), None);
+        assert_eq!(front_matter("+++\ntitle: Hello\n---"), None);
+        assert_eq!(front_matter("# Title\n---\n---"), None);
+    }
+}
@@ -1,16 +1,13 @@
 use crate::parser::parser::*;
+use super::options::ParserOptions;
 use super::sentence::words;
 
-pub fn heading(texts: &str) -> Option<ParsedResult<Md>> {
+pub fn heading<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
     ["#", "##", "###"].iter().find_map(|p| {
-        let (text, rest) = if let Some(n) = texts.find("\n") {
-            (&texts[..n], &texts[(n+1)..])
-        } else {
-            (texts, "")
-        };
+        let (text, rest) = split_first_pattern(texts, "\n");
         let text = consume(text, p)?;
         let text = space(text)?;
-        let tokens = words(&text);
+        let tokens = words(text, options);
         let token = Md::Heading(p.len(), tokens);
         Some(ParsedResult::new(token, rest))
     })
@@ -25,26 +22,26 @@ mod tests {
     fn test_heading() {
         let words = words!(normal_word!("Hello World!"));
         let token = Md::Heading(1, words);
-        assert_eq!(heading(&"# Hello World!"), Some(ParsedResult{token, rest: ""}));
+        assert_eq!(heading("# Hello World!", &ParserOptions::default()), Some(ParsedResult{token, rest: ""}));
 
         let words = words!(normal_word!("Hello World!"));
         let token = Md::Heading(1, words);
-        assert_eq!(heading(&"#    Hello World!"), Some(ParsedResult{token, rest: ""}));
+        assert_eq!(heading("#    Hello World!", &ParserOptions::default()), Some(ParsedResult{token, rest: ""}));
 
         let words = words!(normal_word!("Hello World!"));
         let token = Md::Heading(2, words);
-        assert_eq!(heading(&"## Hello World!"), Some(ParsedResult{token, rest: ""}));
+        assert_eq!(heading("## Hello World!", &ParserOptions::default()), Some(ParsedResult{token, rest: ""}));
 
         let words = words!(normal_word!("Hello World!"));
         let token = Md::Heading(3, words);
-        assert_eq!(heading(&"### Hello World!"), Some(ParsedResult{token, rest: ""}));
+        assert_eq!(heading("### Hello World!", &ParserOptions::default()), Some(ParsedResult{token, rest: ""}));
     }
 
     #[test]
     fn test_heading_multiline() {
         let words = words!(normal_word!("Hello "));
         let token = Md::Heading(1, words);
-        assert_eq!(heading(&"# Hello \nWorld!"), Some(ParsedResult{token, rest: "World!"}));
+        assert_eq!(heading("# Hello \nWorld!", &ParserOptions::default()), Some(ParsedResult{token, rest: "World!"}));
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,146 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+use super::visit::{walk, walk_word, Visitor};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+// One copy of each distinct text the parser could not borrow from the input, e.g. table
+// cells, which are rewritten before they are parsed. Words with the same text then borrow
+// that copy instead of each owning their own, so large tables of repeated values stay small.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Box<str>>,
+}
+
+struct Texts<'s>(&'s mut HashSet<Box<str>>);
+
+impl Visitor<'_> for Texts<'_> {
+    fn visit_word(&mut self, word: &Word) {
+        if let Word::Normal(Cow::Owned(text)) = word {
+            if !self.0.contains(text.as_str()) { self.0.insert(text.as_str().into()); }
+        }
+        walk_word(self, word)
+    }
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    // Keeps a copy of every text in `mds` that does not borrow from its input.
+    pub fn add(&mut self, mds: &[Md]) {
+        walk(&mut Texts(&mut self.strings), mds)
+    }
+
+    // Makes the owned texts of `mds` borrow the interned copies. Texts that were never
+    // added stay owned.
+    pub fn share<'a>(&'a self, mut mds: Vec<Md<'a>>) -> Vec<Md<'a>> {
+        mds.iter_mut().for_each(|md| self.share_md(md));
+        mds
+    }
+
+    fn share_md<'a>(&'a self, md: &mut Md<'a>) {
+        match md {
+            Md::Heading(_, words) | Md::Sentence(words) | Md::Footnote(_, words) => self.share_words(words),
+            Md::Table(table) => {
+                let Table{ header, records, caption, .. } = table.as_mut();
+                self.share_records(std::iter::once(header).chain(records));
+                caption.iter_mut().for_each(|caption| self.share_words(caption));
+            },
+            Md::LooseTable(table) => {
+                let LooseTable{ header, rows, .. } = table.as_mut();
+                self.share_records(std::iter::once(header).chain(rows));
+            },
+            Md::List(items) => for Item(words, mds) in &mut items.0 {
+                self.share_words(words);
+                mds.iter_mut().for_each(|md| self.share_md(md));
+            },
+            Md::Details(_, summary, mds) => {
+                self.share_words(summary);
+                mds.iter_mut().for_each(|md| self.share_md(md));
+            },
+            Md::Container(_, mds) | Md::Quote(mds) => mds.iter_mut().for_each(|md| self.share_md(md)),
+            Md::Toc | Md::Abbreviation(..) | Md::Code(..) | Md::FrontMatter(..) | Md::Raw(_) | Md::Custom(..) => {},
+        }
+    }
+
+    fn share_records<'a, 'r>(&'a self, records: impl Iterator<Item = &'r mut Record<'a>>) where 'a: 'r {
+        records.flat_map(|record| record.0.iter_mut()).for_each(|words| self.share_words(words))
+    }
+
+    fn share_words<'a>(&'a self, words: &mut Words<'a>) {
+        for word in &mut words.0 {
+            match word {
+                Word::Normal(text @ Cow::Owned(_)) => if let Some(shared) = self.strings.get(text.as_ref()) {
+                    *text = Cow::Borrowed(shared);
+                },
+                Word::Italic(words)
+                | Word::Bold(words)
+                | Word::StrikeThough(words)
+                | Word::Underline(words)
+                | Word::Highlight(words)
+                | Word::Subscript(words)
+                | Word::Superscript(words)
+                | Word::Insert(words)
+                | Word::Link(words, _) => self.share_words(words),
+                _ => {},
+            }
+        }
+    }
+}
+
+// Parses like `parse_with`, with repeated texts sharing one copy in `interner`. The interner
+// can take more documents once the trees borrowing from it are dropped.
+pub fn parse_interned<'a>(text: &'a str, interner: &'a mut Interner, options: &ParserOptions) -> Vec<Md<'a>> {
+    let mds = parse_with(text, options);
+    interner.add(&mds);
+    let interner: &'a Interner = interner;
+    interner.share(mds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells<'a>(md: &'a Md) -> Vec<&'a str> {
+        let Md::Table(table) = md else { panic!("not a table: {:?}", md) };
+        table.records.iter().flat_map(|record| &record.0).map(|words| match &words.0[0] {
+            Word::Normal(text) => text.as_ref(),
+            word => panic!("not text: {:?}", word),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_parse_interned() {
+        let text = format!("| Name | State |\n| - | - |\n{}", "| a | on |\n| b | on |\n".repeat(100));
+        let options = ParserOptions::default();
+        let mut interner = Interner::new();
+        let mds = parse_interned(&text, &mut interner, &options);
+        assert_eq!(mds, parse(&text));
+
+        let cells = cells(&mds[0]);
+        assert_eq!(cells.len(), 400);
+        assert!(cells.iter().filter(|cell| **cell == "on").all(|cell| cell.as_ptr() == cells[1].as_ptr()));
+        assert!(cells.iter().filter(|cell| **cell == "a").all(|cell| cell.as_ptr() == cells[0].as_ptr()));
+    }
+
+    #[test]
+    fn test_borrowed_text_is_not_copied() {
+        let mut interner = Interner::new();
+        let mds = parse("Plain **text** and text\n> - **quoted** text");
+        interner.add(&mds);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.share(mds.clone()), mds);
+        assert!(Interner::new().is_empty());
+    }
+}
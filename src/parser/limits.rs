@@ -0,0 +1,75 @@
+use crate::parser::parser::*;
+use super::options::{LimitExceeded, ParserOptions};
+
+fn exceeds(value: usize, max: Option<usize>) -> Option<usize> {
+    max.filter(|max| value > *max)
+}
+
+fn word_nodes(word: &Word) -> usize {
+    match word {
+        Word::Normal(_) | Word::Kbd(_) | Word::Custom(..) | Word::Emoji(_) | Word::FootnoteRef(_) | Word::Task(_) | Word::Entity(..) | Word::Image(..) => 1,
+        Word::Italic(words) | Word::Bold(words) | Word::StrikeThough(words) | Word::Underline(words)
+        | Word::Highlight(words) | Word::Subscript(words) | Word::Superscript(words)
+        | Word::Insert(words) | Word::Link(words, _) => 1 + words_nodes(words),
+    }
+}
+
+fn words_nodes(words: &Words) -> usize {
+    words.0.iter().map(word_nodes).sum()
+}
+
+fn records_nodes<'a>(records: impl Iterator<Item = &'a Record<'a>>) -> usize {
+    records.map(|record| 1 + record.0.iter().map(words_nodes).sum::<usize>()).sum()
+}
+
+fn check_table(rows: usize, columns: usize, options: &ParserOptions) -> Result<(), LimitExceeded> {
+    if let Some(max) = exceeds(rows, options.max_table_rows) {
+        return Err(LimitExceeded::TableRows(max))
+    }
+    if let Some(max) = exceeds(columns, options.max_table_columns) {
+        return Err(LimitExceeded::TableColumns(max))
+    }
+    Ok(())
+}
+
+// Checks table dimensions in `md` and adds its node count to `nodes`.
+pub fn check(md: &Md, options: &ParserOptions, nodes: &mut usize) -> Result<(), LimitExceeded> {
+    *nodes += 1 + match md {
+        Md::Heading(_, words) | Md::Sentence(words) | Md::Footnote(_, words) => words_nodes(words),
+        Md::Table(table) => {
+            check_table(table.records.len(), table.header.0.len(), options)?;
+            let caption = table.caption.as_ref().map_or(0, words_nodes);
+            caption + records_nodes(std::iter::once(&table.header).chain(&table.records))
+        },
+        Md::LooseTable(table) => {
+            let columns = table.rows.iter().map(|row| row.0.len()).chain([table.header.0.len()]).max();
+            check_table(table.rows.len(), columns.unwrap_or(0), options)?;
+            records_nodes(std::iter::once(&table.header).chain(&table.rows))
+        },
+        Md::List(Items(items, _)) => {
+            for Item(_, children) in items {
+                for child in children {
+                    check(child, options, nodes)?;
+                }
+            }
+            items.iter().map(|Item(words, _)| 1 + words_nodes(words)).sum()
+        },
+        Md::Container(_, mds) | Md::Quote(mds) => {
+            for child in mds {
+                check(child, options, nodes)?;
+            }
+            0
+        },
+        Md::Details(_, summary, mds) => {
+            for child in mds {
+                check(child, options, nodes)?;
+            }
+            words_nodes(summary)
+        },
+        Md::Toc | Md::Abbreviation(..) | Md::Code(..) | Md::FrontMatter(..) | Md::Raw(_) | Md::Custom(..) => 0,
+    };
+    if let Some(max) = exceeds(*nodes, options.max_nodes) {
+        return Err(LimitExceeded::Nodes(max))
+    }
+    Ok(())
+}
@@ -1,43 +1,117 @@
 use crate::parser::parser::*;
+use super::options::ParserOptions;
 use super::sentence::words;
-use crate::items;
 
 fn count_tab(texts: &str) -> usize {
     texts.chars().take_while(|c| c ==&' ' ).count()/2
 }
 
-fn item(texts: &str, tab_num: usize) -> Option<ParsedResult<Item>> {
-    let (text, rest) = split_first_pattern(texts, "\n");
+fn marker(text: &str) -> Option<(ListKind, &str)> {
     let text = text.trim_start();
-    let text = consume(text, "-")?;
+    if let Some((bullet, text)) = ['-', '*', '+'].into_iter().find_map(|c| Some((c, text.strip_prefix(c)?))) {
+        let text = space(text)?;
+        return Some((ListKind::Bullet(bullet), text))
+    }
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 || digits > 9 { return None }
+    let number = text[..digits].parse().ok()?;
+    let text = consume(&text[digits..], ".")?;
     let text = space(text)?;
-    let words = words(&text);
-    let space_num = count_tab(&rest);
-    let (i, rest) = if space_num <= tab_num {
-        (items!(), rest)
-    } else {
-        let c = items(&rest, space_num);
-        (c.token, c.rest)
-    };
-    let item = Item(words, i);
+    Some((ListKind::Ordered(number), text))
+}
+
+fn same_list(kind: &ListKind, other: &ListKind) -> bool {
+    match (kind, other) {
+        (ListKind::Bullet(c), ListKind::Bullet(d)) => c == d,
+        (ListKind::Ordered(_), ListKind::Ordered(_)) => true,
+        _ => false,
+    }
+}
+
+fn is_child(line: &str, tab_num: usize) -> bool {
+    !line.trim().is_empty() && count_tab(line) > tab_num
+}
+
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = lines.iter()
+        .map(|line| {
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            &line[spaces.min(indent)..]
+        })
+        .collect();
+    lines.join("\n")
+}
+
+// Indented lines right after the marker continue the item's text; further indented
+// lines, possibly after blank lines, are parsed as the item's child blocks.
+fn item<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Item<'a>>> {
+    let (line, mut rest) = split_first_pattern(texts, "\n");
+    let tab_num = count_tab(line);
+    let (_, text) = marker(line)?;
+    let nested = options.nested()?;
+    let mut text = text.to_string();
+    while !rest.is_empty() {
+        let (line, next) = split_first_pattern(rest, "\n");
+        if !is_child(line, tab_num) || marker(line).is_some() { break }
+        text.push('\n');
+        text.push_str(line.trim());
+        rest = next;
+    }
+
+    let mut body: Vec<&str> = vec!();
+    while !rest.is_empty() {
+        let (line, next) = split_first_pattern(rest, "\n");
+        if line.trim().is_empty() {
+            let following = next.split('\n').find(|line| !line.trim().is_empty());
+            if !following.is_some_and(|line| is_child(line, tab_num)) { break }
+        } else if !is_child(line, tab_num) {
+            break
+        }
+        body.push(line);
+        rest = next;
+    }
+    let body = dedent(&body);
+    let children = parse_blocks(body.trim_start_matches('\n'), &nested);
+    let item = Item(item_words(&text, options), children).into_owned();
     Some(ParsedResult::new(item, rest))
 }
 
-fn items(mut texts: &str, tab_num: usize) -> ParsedResult<Items> {
+// The item's text, led by a checkbox when task lists are on and it starts with `[ ]` or `[x]`.
+fn item_words<'a>(text: &'a str, options: &ParserOptions) -> Words<'a> {
+    let task = ["[ ]", "[x]", "[X]"].iter().find(|task| text.starts_with(**task) && text[3..].starts_with(char::is_whitespace));
+    match task.filter(|_| options.task_lists) {
+        Some(task) => {
+            let mut words = words(&text[3..], options);
+            words.0.insert(0, Word::Task(*task != "[ ]"));
+            words
+        },
+        None => words(text, options),
+    }
+}
+
+fn items<'a>(mut texts: &'a str, options: &ParserOptions) -> ParsedResult<'a, Items<'a>> {
     let mut items: Vec<Item> = vec!();
-    while let Some(i) = item(texts, tab_num) {
-        if count_tab(texts) < tab_num { break; }
+    let mut kind: Option<ListKind> = None;
+    while let Some(i) = item(texts, options) {
+        let Some((k, _)) = marker(texts) else { break };
+        if kind.is_some_and(|kind| !same_list(&kind, &k)) { break; }
+        kind.get_or_insert(k);
         items.push(i.token);
         texts = i.rest;
     }
-    let items = Items(items);
+    let items = Items(items, kind.unwrap_or(ListKind::Bullet('-')));
     ParsedResult::new(items, texts)
 }
 
-pub fn list(texts: &str) -> Option<ParsedResult<Md>> {
-    let l = items(texts, 0);
+pub fn list<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let l = items(texts, options);
     match l.token {
-        Items(item) if item.is_empty() => None,
+        Items(item, _) if item.is_empty() => None,
         _ => Some(ParsedResult{token: Md::List(l.token), rest: l.rest})
     }
 }
@@ -50,91 +124,145 @@ mod tests {
     #[test]
     fn test_item() {
         let words = words!(normal_word!("Hello World!"));
-        let token = Item(words, items!());
+        let token = Item(words, vec!());
         let rest = "";
-        assert_eq!(item(&"- Hello World!\n", 0), Some(ParsedResult{token, rest}));
+        assert_eq!(item("- Hello World!\n", &ParserOptions::default()), Some(ParsedResult{token, rest}));
 
         let words = words!(normal_word!("Hello World!"));
-        let token = Item(words, items!());
+        let token = Item(words, vec!());
         let rest = "";
-        assert_eq!(item(&"- Hello World!", 0), Some(ParsedResult{token, rest}));
-        assert_eq!(item(&"Hello World!", 0), None);
-        assert_eq!(item(&"-Hello World!", 0), None);
+        assert_eq!(item("- Hello World!", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+        assert_eq!(item("Hello World!", &ParserOptions::default()), None);
+        assert_eq!(item("-Hello World!", &ParserOptions::default()), None);
     }
 
     #[test]
     fn test_items() {
         let words = words!(normal_word!("Hello"));
-        let item0 = Item(words, items!());
+        let item0 = Item(words, vec!());
         let words = words!(normal_word!("World"));
-        let item1 = Item(words, items!());
+        let item1 = Item(words, vec!());
         let words = words!(normal_word!("Rust"));
-        let item2 = Item(words, items!());
+        let item2 = Item(words, vec!());
         let token = items!(item0, item1, item2);
         let rest = "";
-        assert_eq!(items(&"- Hello\n- World\n- Rust", 0), ParsedResult{token, rest});
-        assert_eq!(items(&"Rust", 0), ParsedResult{token: items!(), rest: "Rust"});
+        assert_eq!(items("- Hello\n- World\n- Rust", &ParserOptions::default()), ParsedResult{token, rest});
+        assert_eq!(items("Rust", &ParserOptions::default()), ParsedResult{token: items!(), rest: "Rust"});
     }
 
     #[test]
     fn test_nest_items() {
         let words = words!(normal_word!("World"));
-        let children = items!(Item(words, items!()));
+        let children = items!(Item(words, vec!()));
         let words = words!(normal_word!("Hello"));
-        let token = items!(Item(words, children));
+        let token = items!(Item(words, vec!(Md::List(children))));
         let rest = "";
-        assert_eq!(items(&"- Hello\n  - World", 0), ParsedResult{token, rest});
+        assert_eq!(items("- Hello\n  - World", &ParserOptions::default()), ParsedResult{token, rest});
 
         let world = words!(normal_word!("World"));
-        let item0 = Item(world, items!());
+        let item0 = Item(world, vec!());
         let world = words!(normal_word!("End"));
-        let item1 = Item(world, items!());
+        let item1 = Item(world, vec!());
         let children = items!(item0, item1);
         let words = words!(normal_word!("Hello"));
-        let token = items!(Item(words, children));
+        let token = items!(Item(words, vec!(Md::List(children))));
         let rest = "";
-        assert_eq!(items(&"- Hello\n  - World\n  - End", 0), ParsedResult{token, rest});
+        assert_eq!(items("- Hello\n  - World\n  - End", &ParserOptions::default()), ParsedResult{token, rest});
 
         let words = words!(normal_word!("World"));
-        let children = items!(Item(words, items!()));
+        let children = items!(Item(words, vec!()));
         let words = words!(normal_word!("Hello"));
-        let item0 = Item(words, children);
+        let item0 = Item(words, vec!(Md::List(children)));
         let end = words!(normal_word!("End"));
-        let item1 = Item(end, items!());
+        let item1 = Item(end, vec!());
         let token = items!(item0, item1);
         let rest = "";
-        assert_eq!(items(&"- Hello\n  - World\n- End", 0), ParsedResult{token, rest});
+        assert_eq!(items("- Hello\n  - World\n- End", &ParserOptions::default()), ParsedResult{token, rest});
 
         let words = words!(normal_word!("World"));
-        let item0 = Item(words, items!());
+        let item0 = Item(words, vec!());
         let words = words!(normal_word!("End"));
-        let item1 = Item(words, items!());
+        let item1 = Item(words, vec!());
         let children = items!(item0, item1);
 
         let words = words!(normal_word!("Hello"));
-        let item0 = Item(words, children);
+        let item0 = Item(words, vec!(Md::List(children)));
         let words = words!(normal_word!("Reboot"));
-        let item1 = Item(words, items!());
+        let item1 = Item(words, vec!());
         let token = items!(item0, item1);
         let rest = "";
-        assert_eq!(items(&"- Hello\n  - World\n  - End\n- Reboot", 0), ParsedResult{token, rest});
+        assert_eq!(items("- Hello\n  - World\n  - End\n- Reboot", &ParserOptions::default()), ParsedResult{token, rest});
 
 
         let words = words!(normal_word!("End"));
-        let children = items!(Item(words, items!()));
+        let children = items!(Item(words, vec!()));
 
         let words = words!(normal_word!("World"));
-        let children = items!(Item(words, children));
+        let children = items!(Item(words, vec!(Md::List(children))));
 
         let words = words!(normal_word!("Hello"));
-        let item0 = Item(words, children);
+        let item0 = Item(words, vec!(Md::List(children)));
 
         let words = words!(normal_word!("Reboot"));
-        let item1 = Item(words, items!());
+        let item1 = Item(words, vec!());
 
         let token = items!(item0, item1);
         let rest = "";
-        assert_eq!(items(&"- Hello\n  - World\n    - End\n- Reboot", 0), ParsedResult{token, rest});
+        assert_eq!(items("- Hello\n  - World\n    - End\n- Reboot", &ParserOptions::default()), ParsedResult{token, rest});
+    }
+
+    #[test]
+    fn test_item_blocks() {
+        let words = words!(normal_word!("Hello\nWorld"));
+        let token = Item(words, vec!());
+        let rest = "Next";
+        assert_eq!(item("- Hello\n  World\nNext", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let words = words!(normal_word!("Hello"));
+        let para = Md::Sentence(words!(normal_word!("Para")));
+        let code = Md::Code("".to_string(), "code".to_string(), vec!());
+        let token = Item(words, vec!(para, Md::Sentence(words!(normal_word!(""))), code));
+        let rest = "\nNext";
+        assert_eq!(item("- Hello\n\n  Para\n\n  ```\n  code\n  ```\n\nNext", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_markers() {
+        let words = words!(normal_word!("Hello"));
+        let item0 = Item(words, vec!());
+        let words = words!(normal_word!("World"));
+        let item1 = Item(words, vec!());
+        let token = Items(vec!(item0, item1), ListKind::Bullet('*'));
+        let rest = "";
+        assert_eq!(items("* Hello\n* World", &ParserOptions::default()), ParsedResult{token, rest});
+
+        let words = words!(normal_word!("Hello"));
+        let item0 = Item(words, vec!());
+        let token = Items(vec!(item0), ListKind::Bullet('+'));
+        let rest = "- World";
+        assert_eq!(items("+ Hello\n- World", &ParserOptions::default()), ParsedResult{token, rest});
+        assert_eq!(items("*Hello*", &ParserOptions::default()), ParsedResult{token: items!(), rest: "*Hello*"});
+    }
+
+    #[test]
+    fn test_ordered_items() {
+        let words = words!(normal_word!("Hello"));
+        let item0 = Item(words, vec!());
+        let words = words!(normal_word!("World"));
+        let item1 = Item(words, vec!());
+        let token = Items(vec!(item0, item1), ListKind::Ordered(3));
+        let rest = "";
+        assert_eq!(items("3. Hello\n1. World", &ParserOptions::default()), ParsedResult{token, rest});
+
+        let words = words!(normal_word!("World"));
+        let children = Items(vec!(Item(words, vec!())), ListKind::Ordered(1));
+        let words = words!(normal_word!("Hello"));
+        let token = items!(Item(words, vec!(Md::List(children))));
+        let rest = "";
+        assert_eq!(items("- Hello\n  1. World", &ParserOptions::default()), ParsedResult{token, rest});
+
+        assert_eq!(items("1.Hello", &ParserOptions::default()), ParsedResult{token: items!(), rest: "1.Hello"});
+        assert_eq!(items("2024 was good", &ParserOptions::default()), ParsedResult{token: items!(), rest: "2024 was good"});
     }
 
     #[test]
@@ -147,11 +275,26 @@ mod tests {
     #[test]
     fn test_list() {
         let words = words!(normal_word!("World"));
-        let children = items!(Item(words, items!()));
+        let children = items!(Item(words, vec!()));
         let words = words!(normal_word!("Hello"));
-        let item = Item(words, children);
+        let item = Item(words, vec!(Md::List(children)));
         let token = Md::List(items!(item));
         let rest = "";
-        assert_eq!(list(&"- Hello\n  - World"), Some(ParsedResult{token, rest}));
+        assert_eq!(list("- Hello\n  - World", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_task_list() {
+        let options = ParserOptions{ task_lists: true, ..Default::default() };
+        let done = Item(words!(Word::Task(true), normal_word!(" Done")), vec!());
+        let todo = Item(words!(Word::Task(false), normal_word!(" Todo")), vec!());
+        let plain = Item(words!(normal_word!("[] no")), vec!());
+        let token = Md::List(items!(done, todo, plain));
+        let rest = "";
+        assert_eq!(list("- [x] Done\n- [ ] Todo\n- [] no", &options), Some(ParsedResult{token, rest}));
+
+        let item = Item(words!(normal_word!("[x] Done")), vec!());
+        let token = Md::List(items!(item));
+        assert_eq!(list("- [x] Done", &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
 }
\ No newline at end of file
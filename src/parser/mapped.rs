@@ -0,0 +1,88 @@
+use crate::parser::parser::*;
+use crate::convert::convert::HtmlWriter;
+use crate::convert::options::RenderOptions;
+use super::options::ParserOptions;
+use super::front_matter::front_matter;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+// A markdown file mapped into memory instead of read into a string. The parsed tree
+// borrows its text from the mapping, so a large file isn't held twice.
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    // Fails with `InvalidData` when the file isn't utf-8.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is only read, and only as the `&str` checked below. Like every
+        // memory map it assumes no other process truncates the file while it is open.
+        let map = unsafe { Mmap::map(&file)? };
+        std::str::from_utf8(&map).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(MappedFile{ map })
+    }
+
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.map).expect("checked to be utf-8 in open")
+    }
+
+    pub fn parse(&self, options: &ParserOptions) -> Vec<Md<'_>> {
+        parse_with(self.text(), options)
+    }
+}
+
+// Converts the file at `path` one block at a time through an `HtmlWriter`, so besides the
+// mapping only the current block, and those the writer holds back, are in memory.
+pub fn convert_mapped<W: io::Write>(path: impl AsRef<Path>, out: W, parser: &ParserOptions, options: &RenderOptions) -> io::Result<W> {
+    let file = MappedFile::open(path)?;
+    let text = normalize_borrowed(file.text(), parser);
+    let mut writer = HtmlWriter::new(out, options);
+    let mut rest: &str = &text;
+    if let Some(ret) = front_matter(rest) {
+        writer.write(&ret.token)?;
+        rest = ret.rest;
+    }
+    while let Some(ret) = block(rest, parser) {
+        writer.write(&ret.token)?;
+        rest = ret.rest;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::convert::mds_to_html;
+    use std::fs;
+
+    fn temp_file(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("app-mapped-{}-{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_mapped_file() {
+        let text = "---\ntitle: Log\n---\n# Log\r\n| A |\n| - |\n| a |\nSee[^1]\n\n[^1]: note";
+        let path = temp_file("log.md", text.as_bytes());
+        let options = ParserOptions::default();
+        let file = MappedFile::open(&path).unwrap();
+        assert_eq!(file.text(), text);
+        assert_eq!(file.parse(&options), parse(text));
+
+        let html = convert_mapped(&path, vec!(), &options, &RenderOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(html).unwrap(), mds_to_html(&parse(text)));
+        fs::remove_file(&path).unwrap();
+
+        let path = temp_file("empty.md", b"");
+        assert_eq!(MappedFile::open(&path).unwrap().parse(&options), vec!());
+        fs::remove_file(&path).unwrap();
+
+        let path = temp_file("latin1.md", b"caf\xe9");
+        assert_eq!(MappedFile::open(&path).err().map(|err| err.kind()), Some(io::ErrorKind::InvalidData));
+        fs::remove_file(&path).unwrap();
+    }
+}
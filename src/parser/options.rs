@@ -0,0 +1,144 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserOptions {
+    // Keeps pipe rows with a malformed alignment row or a wrong cell count together as a
+    // `LooseTable` that records the issue, instead of leaving them as sentences.
+    pub loose_tables: bool,
+    pub ragged_rows: bool,
+    pub table_spans: bool,
+    pub commonmark_underscores: bool,
+    // Links bare `https://`, `http://` and `www.` urls and `<https://...>`, as GitHub does.
+    pub autolinks: bool,
+    // Reads a list item starting with `[ ]` or `[x]` as a task with a checkbox.
+    pub task_lists: bool,
+    // Reads `~text~` as strikethrough like `~~text~~`, as GitHub does, instead of subscript.
+    pub single_tilde_strikethrough: bool,
+    // Tabs outside code blocks expand to this many columns; 0 keeps them as-is.
+    pub tab_width: usize,
+    // Removes zero-width spaces and joiners anywhere in the input.
+    pub strip_zero_width: bool,
+    // Emphasis, quotes, containers and list children nested deeper than this stay literal text.
+    pub max_nesting: usize,
+    // Caps checked by `try_parse_with`; `None` means unlimited.
+    pub max_input_bytes: Option<usize>,
+    pub max_table_rows: Option<usize>,
+    pub max_table_columns: Option<usize>,
+    pub max_nodes: Option<usize>,
+    pub inline_extensions: Vec<InlineExtension>,
+    pub block_extensions: Vec<BlockExtension>,
+}
+
+// Inline syntax added by the caller, e.g. `#123` issue references or `@name` mentions. Where
+// `delimiter` starts a word, `parse` gets the text from the delimiter on and returns the byte
+// length of the syntax there, or `None` to leave it as text. The matched text becomes a
+// `Word::Custom` named `name`.
+#[derive(Debug, Clone)]
+pub struct InlineExtension {
+    pub name: String,
+    pub delimiter: char,
+    pub parse: fn(&str) -> Option<usize>,
+}
+
+impl PartialEq for InlineExtension {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.delimiter == other.delimiter && std::ptr::fn_addr_eq(self.parse, other.parse)
+    }
+}
+
+// Block syntax added by the caller, e.g. `@@@ diagram` fences. `parse` gets the text from the
+// start of a block on and returns how many bytes the block takes up, or `None` to let the
+// next parser try. The matched text, without its final newline, becomes an `Md::Custom`
+// named `name`. Extensions with a `priority` of 0 or more are tried before the built-in
+// blocks, negative ones after them but before plain text; higher priorities go first.
+#[derive(Debug, Clone)]
+pub struct BlockExtension {
+    pub name: String,
+    pub priority: i32,
+    pub parse: fn(&str) -> Option<usize>,
+}
+
+impl PartialEq for BlockExtension {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.priority == other.priority && std::ptr::fn_addr_eq(self.parse, other.parse)
+    }
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            loose_tables: false,
+            ragged_rows: false,
+            table_spans: false,
+            commonmark_underscores: false,
+            autolinks: false,
+            task_lists: false,
+            single_tilde_strikethrough: false,
+            tab_width: 4,
+            strip_zero_width: false,
+            max_nesting: 64,
+            max_input_bytes: None,
+            max_table_rows: None,
+            max_table_columns: None,
+            max_nodes: None,
+            inline_extensions: vec!(),
+            block_extensions: vec!(),
+        }
+    }
+}
+
+impl ParserOptions {
+    // GitHub Flavored Markdown: `_` emphasis, autolinks, task lists and single-tilde
+    // strikethrough on top of the tables, strikethrough and footnotes that are always read.
+    pub fn gfm() -> Self {
+        ParserOptions{
+            commonmark_underscores: true,
+            autolinks: true,
+            task_lists: true,
+            single_tilde_strikethrough: true,
+            ..Default::default()
+        }
+    }
+
+    // As close to CommonMark as the parser gets: `_` emphasis, four-column tabs and none of
+    // the table extensions. Tables, containers and the other extra syntax stay on, which
+    // `commonmark::run_spec` reports as divergences.
+    pub fn commonmark() -> Self {
+        ParserOptions{
+            commonmark_underscores: true,
+            tab_width: 4,
+            loose_tables: false,
+            ragged_rows: false,
+            table_spans: false,
+            ..Default::default()
+        }
+    }
+
+    // Options for parsing one level deeper, or `None` once `max_nesting` is used up.
+    pub fn nested(&self) -> Option<ParserOptions> {
+        let max_nesting = self.max_nesting.checked_sub(1)?;
+        Some(ParserOptions{ max_nesting, ..self.clone() })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LimitExceeded {
+    InputBytes(usize),
+    TableRows(usize),
+    TableColumns(usize),
+    Nodes(usize),
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitExceeded::InputBytes(max) => write!(f, "input exceeds {} bytes", max),
+            LimitExceeded::TableRows(max) => write!(f, "table exceeds {} rows", max),
+            LimitExceeded::TableColumns(max) => write!(f, "table exceeds {} columns", max),
+            LimitExceeded::Nodes(max) => write!(f, "document exceeds {} nodes", max),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
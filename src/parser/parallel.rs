@@ -0,0 +1,115 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+use super::front_matter::front_matter;
+use memchr::memmem;
+use rayon::prelude::*;
+use std::borrow::Cow;
+
+// Chunks are cut at the first blank line after this many bytes.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+// Where each chunk starts and ends: just after a blank line, roughly every `CHUNK_BYTES`.
+fn chunks(text: &str, start: usize) -> Vec<(usize, usize)> {
+    let mut starts = vec!(start);
+    let mut from = start + CHUNK_BYTES;
+    while from < text.len() {
+        let Some(blank) = memmem::find(&text.as_bytes()[from..], b"\n\n") else { break };
+        let at = from + blank + 2;
+        if at >= text.len() { break }
+        starts.push(at);
+        from = at + CHUNK_BYTES;
+    }
+    let ends = starts.iter().skip(1).copied().chain([text.len()]);
+    starts.iter().copied().zip(ends).collect()
+}
+
+// The blocks of one chunk, parsed as if the text ended with it, and where each starts.
+fn chunk_blocks<'a>(text: &'a str, (start, end): (usize, usize), options: &ParserOptions) -> Vec<(usize, Md<'a>)> {
+    let mut rest = &text[start..end];
+    let mut blocks = vec!();
+    while let Some(ret) = block(rest, options) {
+        blocks.push((end - rest.len(), ret.token));
+        rest = ret.rest;
+    }
+    blocks
+}
+
+fn is_blank(md: &Md) -> bool {
+    matches!(md, Md::Sentence(Words(words)) if matches!(words.as_slice(), [Word::Normal(text)] if text.is_empty()))
+}
+
+fn parse_chunks<'a>(text: &'a str, options: &ParserOptions) -> Vec<Md<'a>> {
+    let mut mds = vec!();
+    let mut pos = 0;
+    if let Some(ret) = front_matter(text) {
+        mds.push(ret.token);
+        pos = text.len() - ret.rest.len();
+    }
+    let chunks = chunks(text, pos);
+    let parsed: Vec<Vec<(usize, Md)>> = chunks.par_iter().map(|chunk| chunk_blocks(text, *chunk, options)).collect();
+    for (&(start, end), blocks) in chunks.iter().zip(parsed) {
+        // A block only looks ahead as far as the next non-blank line, so every block before
+        // the chunk's last non-blank one reads the same as in the whole text, and the last
+        // chunk saw the whole rest of it. The rest is parsed again against the whole text,
+        // as is every chunk the parse did not land on the start of, until the parse lands
+        // on the next chunk's start.
+        if pos == start {
+            let last = end == text.len();
+            let keep = if last { blocks.len() } else { blocks.iter().rposition(|(_, md)| !is_blank(md)).unwrap_or(0) };
+            pos = blocks.get(keep).map_or(end, |(at, _)| *at);
+            mds.extend(blocks.into_iter().take(keep).map(|(_, md)| md));
+        }
+        while pos < end {
+            let Some(ret) = block(&text[pos..], options) else { break };
+            mds.push(ret.token);
+            pos = text.len() - ret.rest.len();
+        }
+    }
+    mds
+}
+
+// Parses like `parse_with`, but splits a large document at blank lines and parses the
+// pieces on rayon's thread pool. The blocks come out the same and in the same order.
+pub fn parse_parallel<'a>(text: &'a str, options: &ParserOptions) -> Vec<Md<'a>> {
+    match normalize_borrowed(text, options) {
+        Cow::Borrowed(text) => parse_chunks(text, options),
+        Cow::Owned(text) => owned_blocks(parse_chunks(&text, options)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(sections: usize) -> String {
+        let section = "# Section\n\nSome **bold** text\n\n\
+            ```\ncode\n\n\nmore code\n```\n\n\
+            - item\n\n  child paragraph\n\n- next\n\n\
+            ::: note\nInside\n\nStill inside\n:::\n\n\
+            [Caption]\n| A | B |\n| - | - |\n| a | b |\n\n\
+            > quoted\n\n??? Details\n    body\n\n    more body\n\n";
+        section.repeat(sections)
+    }
+
+    #[test]
+    fn test_parse_parallel() {
+        let options = ParserOptions::default();
+        let text = document(1_500);
+        assert!(chunks(&text, 0).len() > 2);
+        assert_eq!(parse_parallel(&text, &options), parse_with(&text, &options));
+
+        let text = format!("---\ntitle: T\n---\n{}", document(800).replace('\n', "\r\n"));
+        assert_eq!(parse_parallel(&text, &options), parse_with(&text, &options));
+    }
+
+    #[test]
+    fn test_block_across_chunks() {
+        let options = ParserOptions::default();
+        let text = format!("Intro\n\n```\n{}", "code\n\n".repeat(40_000));
+        assert_eq!(parse_parallel(&text, &options), parse_with(&text, &options));
+
+        let text = format!("{}\n\n- item\n\n{}\n\n", "a".repeat(CHUNK_BYTES), "  child\n\n".repeat(20_000));
+        assert_eq!(parse_parallel(&text, &options), parse_with(&text, &options));
+        assert_eq!(parse_parallel("", &options), vec!());
+    }
+}
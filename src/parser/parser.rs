@@ -2,52 +2,156 @@ use super::heading::heading;
 use super::sentence::sentence;
 use super::table::table;
 use super::list::list;
+use super::container::container;
+use super::details::details;
+use super::toc::toc;
+use super::abbr::abbreviation;
+use super::footnote::footnote;
+use super::code::code;
+use super::quote::quote;
+use super::raw::raw;
+use super::tabs::expand_tabs;
+use super::front_matter::front_matter;
+use super::limits::check;
+use super::options::{BlockExtension, LimitExceeded, ParserOptions};
+use std::borrow::Cow;
+pub use super::combinator::*;
 
-#[derive(Debug, PartialEq)]
-pub enum Md {
-    Heading(usize, Words),
-    Sentence(Words),
-    Table(Box<Table>),
-    List(Items),
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub enum Md<'a> {
+    Heading(#[cfg_attr(feature = "testing", arbitrary(with = heading_level))] usize, Words<'a>),
+    Sentence(Words<'a>),
+    Table(Box<Table<'a>>),
+    LooseTable(Box<LooseTable<'a>>),
+    List(Items<'a>),
+    Container(String, Vec<Md<'a>>),
+    Details(bool, Words<'a>, Vec<Md<'a>>),
+    Toc,
+    Abbreviation(String, String),
+    // A `[^label]: text` definition, rendered with the other notes at the end.
+    Footnote(String, Words<'a>),
+    // Language, code and the inclusive line ranges to highlight, from `{3-5,8}` in the info string.
+    Code(String, String, Vec<(usize, usize)>),
+    Quote(Vec<Md<'a>>),
+    FrontMatter(FrontMatterFormat, String),
+    // Text between `<!-- md:off -->` and `<!-- md:on -->`, left unparsed.
+    Raw(String),
+    // The extension's name and the text it matched, see `BlockExtension`.
+    Custom(String, String),
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Word {
-    Normal(String),
-    Italic(Words),
-    Bold(Words),
-    StrikeThough(Words),
-    Underline(Words),
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub enum Word<'a> {
+    // Borrowed from the input where the parser could, see `into_owned`.
+    Normal(Cow<'a, str>),
+    Italic(Words<'a>),
+    Bold(Words<'a>),
+    StrikeThough(Words<'a>),
+    Underline(Words<'a>),
+    Highlight(Words<'a>),
+    Subscript(Words<'a>),
+    Superscript(Words<'a>),
+    Insert(Words<'a>),
+    Kbd(String),
+    Emoji(String),
+    FootnoteRef(String),
+    // The `[ ]` or `[x]` checkbox starting a task list item; `true` when checked.
+    Task(bool),
+    // An entity reference as written, e.g. `&copy;` or `&#169;`, and the text it stands for.
+    Entity(String, String),
+    // Link text and url.
+    Link(Words<'a>, String),
+    // Alt text, source and the size given with `=640x480` or `{width=50%}`.
+    Image(String, String, ImageSize),
+    // The extension's name and the text it matched, see `InlineExtension`.
+    Custom(String, String),
 }
+// Sizes as written, e.g. `640` or `50%`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub struct ImageSize {
+    pub width: Option<String>,
+    pub height: Option<String>,
+}
+
+/// A plain text word, owning a copy of anything that implements `Display`.
+///
+/// ```
+/// use app::{normal_word, words, Word, Words};
+///
+/// let hello = words!(normal_word!("Hello "), Word::Bold(words!(normal_word!("world"))));
+/// assert_eq!(hello.0.len(), 2);
+/// ```
 #[macro_export]
 macro_rules! normal_word {
     ($text:expr) => {{
-        Word::Normal($text.to_string())
+        $crate::Word::Normal(std::borrow::Cow::Owned($text.to_string()))
     }};
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Item(pub Words, pub Items);
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub struct Item<'a>(pub Words<'a>, pub Vec<Md<'a>>);
+
+// Which fence the front matter is between: `---` for YAML or `+++` for TOML.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub enum FrontMatterFormat {
+    Yaml,
+    Toml,
+}
+
+impl FrontMatterFormat {
+    pub fn fence(&self) -> &'static str {
+        match self {
+            FrontMatterFormat::Yaml => "---",
+            FrontMatterFormat::Toml => "+++",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub enum ListKind {
+    Bullet(char),
+    Ordered(#[cfg_attr(feature = "testing", arbitrary(with = list_start))] usize),
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub struct Items<'a>(pub Vec<Item<'a>>, pub ListKind);
 
-#[derive(Debug, PartialEq)]
-pub struct Items(pub Vec<Item>);
+impl Default for ListKind {
+    fn default() -> Self {
+        ListKind::Bullet('-')
+    }
+}
+/// A `-` bullet list's items.
 #[macro_export]
 macro_rules! items {
     () => {{
-        Items(vec!()) 
+        $crate::Items(vec!(), $crate::ListKind::Bullet('-'))
     }};
 
     ( $( $item:expr), *) => {{
-        let mut is = vec!();
-        $(
-            is.push($item);
-        )*
-        Items(is) 
+        $crate::Items(vec!($($item),*), $crate::ListKind::Bullet('-'))
     }};
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Words(pub Vec<Word>);
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub struct Words<'a>(pub Vec<Word<'a>>);
+/// A run of words; it needs at least one.
 #[macro_export]
 macro_rules! words {
     () => {{
@@ -55,16 +159,32 @@ macro_rules! words {
     }};
 
     ( $( $word:expr), *) => {{
-        let mut ws = vec!();
-        $(
-            ws.push($word);
-        )*
-        Words(ws) 
+        $crate::Words(vec!($($word),*))
     }}
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Record(pub Vec<Words>);
+// Column and row span of a cell; `Merged` cells are covered by a spanning neighbour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub enum Span {
+    Cell(usize, usize),
+    Merged,
+}
+
+// Spans are empty unless the table uses span syntax.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub struct Record<'a>(pub Vec<Words<'a>>, pub Vec<Span>);
+/// A table row from its cells, without spans; it needs at least one cell.
+///
+/// ```
+/// use app::{normal_word, record, words};
+///
+/// let row = record!(words!(normal_word!("a")), words!(normal_word!(1)));
+/// assert_eq!(row.0.len(), 2);
+/// ```
 #[macro_export]
 macro_rules! record {
     () => {{
@@ -72,66 +192,425 @@ macro_rules! record {
     }};
 
     ( $( $words:expr), *) => {{
-        let mut rd = vec!();
-        $(
-            rd.push($words);
-        )*
-        Record(rd) 
+        $crate::Record(vec!($($words),*), vec!())
     }}
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Table {
-    pub header: Record,
+/// A block of plain text: `h1:`, `h2:` or `h3:` for a heading, `p:` for a sentence,
+/// `list: [..]` for a `-` list, `code "lang": ..` for a fenced block and `quote: [..]` for
+/// a quote of other blocks.
+///
+/// ```
+/// use app::{md, parse};
+///
+/// let mds = vec!(md!(h1: "Title"), md!(p: "Text"), md!(list: ["a", "b"]), md!(code "rust": "let x;"));
+/// assert_eq!(mds, parse("# Title\nText\n- a\n- b\n```rust\nlet x;\n```"));
+/// ```
+#[macro_export]
+macro_rules! md {
+    (h1: $text:expr) => {{
+        $crate::Md::Heading(1, $crate::words!($crate::normal_word!($text)))
+    }};
+
+    (h2: $text:expr) => {{
+        $crate::Md::Heading(2, $crate::words!($crate::normal_word!($text)))
+    }};
+
+    (h3: $text:expr) => {{
+        $crate::Md::Heading(3, $crate::words!($crate::normal_word!($text)))
+    }};
+
+    (p: $text:expr) => {{
+        $crate::Md::Sentence($crate::words!($crate::normal_word!($text)))
+    }};
+
+    (list: [$( $item:expr ),* $(,)?]) => {{
+        $crate::Md::List($crate::items!($( $crate::Item($crate::words!($crate::normal_word!($item)), vec!()) ),*))
+    }};
+
+    (code $lang:literal: $code:expr) => {{
+        $crate::Md::Code($lang.to_string(), $code.to_string(), vec!())
+    }};
+
+    (quote: [$( $md:expr ),* $(,)?]) => {{
+        $crate::Md::Quote(vec!($($md),*))
+    }};
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub struct Table<'a> {
+    pub header: Record<'a>,
     pub align: Vec<Align>,
-    pub records: Vec<Record>,
+    pub records: Vec<Record<'a>>,
+    pub caption: Option<Words<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub enum TableIssue {
+    InvalidAlign,
+    // Columns in the header and in the alignment row.
+    ColumnCount(usize, usize),
+    // The 0-based body row whose cell count differs, the header's count and the row's.
+    RowCells(usize, usize, usize),
+}
+
+impl std::fmt::Display for TableIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TableIssue::InvalidAlign => write!(f, "alignment row is malformed"),
+            TableIssue::ColumnCount(header, found) =>
+                write!(f, "alignment row has {} columns, header has {}", found, header),
+            TableIssue::RowCells(row, header, found) =>
+                write!(f, "row {} has {} cells, header has {}", row + 1, found, header),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
+pub struct LooseTable<'a> {
+    pub header: Record<'a>,
+    pub rows: Vec<Record<'a>>,
+    pub issue: TableIssue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "testing", derive(arbitrary::Arbitrary))]
 pub enum Align {
     Right,
     Center,
     Left
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct ParsedResult<'a, T> {
-    pub token: T,
-    pub rest: &'a str,
+pub(super) fn owned_blocks(mds: Vec<Md>) -> Vec<Md<'static>> {
+    mds.into_iter().map(Md::into_owned).collect()
+}
+
+// Only the levels markdown can write, as a deeper heading isn't html.
+#[cfg(feature = "testing")]
+fn heading_level(u: &mut arbitrary::Unstructured) -> arbitrary::Result<usize> {
+    u.int_in_range(1..=6)
+}
+
+// Only the starts markdown can write, which the parser reads up to nine digits of.
+#[cfg(feature = "testing")]
+fn list_start(u: &mut arbitrary::Unstructured) -> arbitrary::Result<usize> {
+    u.int_in_range(0..=999_999_999)
+}
+
+fn owned_records(records: Vec<Record>) -> Vec<Record<'static>> {
+    records.into_iter().map(Record::into_owned).collect()
+}
+
+// Copies whatever the nodes borrow from the input, so the tree can outlive it.
+impl Md<'_> {
+    pub fn into_owned(self) -> Md<'static> {
+        match self {
+            Md::Heading(level, words) => Md::Heading(level, words.into_owned()),
+            Md::Sentence(words) => Md::Sentence(words.into_owned()),
+            Md::Table(table) => Md::Table(Box::new(table.into_owned())),
+            Md::LooseTable(table) => {
+                let LooseTable{ header, rows, issue } = *table;
+                Md::LooseTable(Box::new(LooseTable{ header: header.into_owned(), rows: owned_records(rows), issue }))
+            },
+            Md::List(items) => Md::List(items.into_owned()),
+            Md::Container(name, mds) => Md::Container(name, owned_blocks(mds)),
+            Md::Details(open, summary, mds) => Md::Details(open, summary.into_owned(), owned_blocks(mds)),
+            Md::Toc => Md::Toc,
+            Md::Abbreviation(abbr, title) => Md::Abbreviation(abbr, title),
+            Md::Footnote(label, words) => Md::Footnote(label, words.into_owned()),
+            Md::Code(lang, code, ranges) => Md::Code(lang, code, ranges),
+            Md::Quote(mds) => Md::Quote(owned_blocks(mds)),
+            Md::FrontMatter(format, matter) => Md::FrontMatter(format, matter),
+            Md::Raw(text) => Md::Raw(text),
+            Md::Custom(name, source) => Md::Custom(name, source),
+        }
+    }
+}
+
+impl Word<'_> {
+    pub fn into_owned(self) -> Word<'static> {
+        match self {
+            Word::Normal(text) => Word::Normal(Cow::Owned(text.into_owned())),
+            Word::Italic(words) => Word::Italic(words.into_owned()),
+            Word::Bold(words) => Word::Bold(words.into_owned()),
+            Word::StrikeThough(words) => Word::StrikeThough(words.into_owned()),
+            Word::Underline(words) => Word::Underline(words.into_owned()),
+            Word::Highlight(words) => Word::Highlight(words.into_owned()),
+            Word::Subscript(words) => Word::Subscript(words.into_owned()),
+            Word::Superscript(words) => Word::Superscript(words.into_owned()),
+            Word::Insert(words) => Word::Insert(words.into_owned()),
+            Word::Kbd(key) => Word::Kbd(key),
+            Word::Custom(name, source) => Word::Custom(name, source),
+            Word::Emoji(name) => Word::Emoji(name),
+            Word::FootnoteRef(label) => Word::FootnoteRef(label),
+            Word::Task(checked) => Word::Task(checked),
+            Word::Entity(reference, text) => Word::Entity(reference, text),
+            Word::Link(words, url) => Word::Link(words.into_owned(), url),
+            Word::Image(alt, src, size) => Word::Image(alt, src, size),
+        }
+    }
+}
+
+impl Words<'_> {
+    pub fn into_owned(self) -> Words<'static> {
+        Words(self.0.into_iter().map(Word::into_owned).collect())
+    }
+}
+
+impl Item<'_> {
+    pub fn into_owned(self) -> Item<'static> {
+        Item(self.0.into_owned(), owned_blocks(self.1))
+    }
+}
+
+impl Items<'_> {
+    pub fn into_owned(self) -> Items<'static> {
+        Items(self.0.into_iter().map(Item::into_owned).collect(), self.1)
+    }
 }
 
-impl<'a, T> ParsedResult<'a, T> {
-    pub fn new(token: T, rest: &'a str) -> ParsedResult<'a, T> {
-        ParsedResult { token: token, rest: rest }
+impl Record<'_> {
+    pub fn into_owned(self) -> Record<'static> {
+        Record(self.0.into_iter().map(Words::into_owned).collect(), self.1)
     }
 }
 
-pub fn space(text: &str) -> Option<&str> {
-    let text = consume(text, " ")?;
-    Some(text.trim_start())
+impl Table<'_> {
+    pub fn into_owned(self) -> Table<'static> {
+        let Table{ header, align, records, caption } = self;
+        Table{ header: header.into_owned(), align, records: owned_records(records), caption: caption.map(Words::into_owned) }
+    }
+}
+
+/// Parses `text` into blocks. Any input is valid markdown: malformed markup comes out as
+/// plain text, and `parse` never panics. `fuzz/` holds a cargo-fuzz target checking this.
+pub fn parse(text: &str) -> Vec<Md<'_>> {
+    parse_with(text, &ParserOptions::default())
+}
+
+// Windows (`\r\n`) and classic Mac (`\r`) line endings become `\n`.
+pub fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
 }
 
-pub fn consume<'a>(text: &'a str, pattern: &'a str) -> Option<&'a str> {
-    if !text.starts_with(pattern) { return None }
-    let length = pattern.len();
-    Some(&text[length..])
+pub(super) const ZERO_WIDTH: [char; 5] = ['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}'];
+
+fn strip_bom<'a>(text: &'a str, options: &ParserOptions) -> Cow<'a, str> {
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    if options.strip_zero_width && text.contains(ZERO_WIDTH) {
+        text.replace(ZERO_WIDTH, "").into()
+    } else {
+        text.into()
+    }
 }
 
-pub fn split_first_pattern<'a>(texts: &'a str, pattern: &str) -> (&'a str, &'a str) {
-    if let Some(n) = texts.find(pattern) {
-        let len = pattern.len();
-        (&texts[..n], &texts[(n+len)..])
+pub(super) fn normalize(text: &str, options: &ParserOptions) -> String {
+    expand_tabs(&normalize_newlines(&strip_bom(text, options)), options.tab_width)
+}
+
+// Like `normalize`, but borrows the text when there is nothing to change.
+pub(super) fn normalize_borrowed<'a>(text: &'a str, options: &ParserOptions) -> Cow<'a, str> {
+    let tabs = options.tab_width > 0 && text.contains('\t');
+    let zero_width = options.strip_zero_width && text.contains(ZERO_WIDTH);
+    if tabs || zero_width || text.contains('\r') || text.starts_with('\u{FEFF}') {
+        normalize(text, options).into()
     } else {
-        (texts, "")
+        text.into()
+    }
+}
+
+fn custom_block<'a>(text: &'a str, extension: &BlockExtension) -> Option<ParsedResult<'a, Md<'a>>> {
+    // Lengths past the end or inside a character are ignored rather than trusted.
+    let len = (extension.parse)(text).filter(|len| *len > 0 && text.is_char_boundary(*len))?;
+    let (source, rest) = text.split_at(len);
+    let token = Md::Custom(extension.name.clone(), source.trim_end_matches('\n').to_string());
+    Some(ParsedResult::new(token, rest.strip_prefix('\n').unwrap_or(rest)))
+}
+
+pub(super) fn block<'a>(text: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let parsers = [raw, code, quote, container, details, toc, abbreviation, footnote, table, list, heading];
+    if options.block_extensions.is_empty() {
+        return parsers.iter().find_map(|f| f(text, options)).or_else(|| sentence(text, options))
     }
+    let mut extensions: Vec<&BlockExtension> = options.block_extensions.iter().collect();
+    extensions.sort_by_key(|extension| std::cmp::Reverse(extension.priority));
+    let (before, after) = extensions.split_at(extensions.partition_point(|extension| extension.priority >= 0));
+    before.iter().find_map(|extension| custom_block(text, extension))
+        .or_else(|| parsers.iter().find_map(|f| f(text, options)))
+        .or_else(|| after.iter().find_map(|extension| custom_block(text, extension)))
+        .or_else(|| sentence(text, options))
 }
 
-pub fn parse(mut text: &str) -> Vec<Md> {
-    let parsers = vec!(table, list, heading, sentence);
+// Parses the blocks nested inside another block; front matter is only looked for
+// at the start of the whole document. The loop leaves room for the `block` span.
+pub fn parse_blocks<'a>(text: &'a str, options: &ParserOptions) -> Vec<Md<'a>> {
     let mut md: Vec<Md> = vec!();
-    while let Some(ret) = parsers.iter().find_map(|f| f(text)) {
+    let mut rest = text;
+    while let Some(ret) = {
+        span!(trace_span, "block", offset = text.len() - rest.len());
+        block(rest, options)
+    } {
         md.push(ret.token);
-        text = ret.rest;
+        rest = ret.rest;
     }
     md
 }
+
+fn parse_normalized<'a>(text: &'a str, options: &ParserOptions) -> Vec<Md<'a>> {
+    let Some(ret) = front_matter(text) else { return parse_blocks(text, options) };
+    let mut md = vec!(ret.token);
+    md.extend(parse_blocks(ret.rest, options));
+    md
+}
+
+// The text borrows from the input unless newlines, tabs or zero-width characters had to
+// be normalized first; then the tree owns its text.
+pub fn parse_with<'a>(text: &'a str, options: &ParserOptions) -> Vec<Md<'a>> {
+    span!(debug_span, "parse", bytes = text.len());
+    match normalize_borrowed(text, options) {
+        Cow::Borrowed(text) => parse_normalized(text, options),
+        Cow::Owned(text) => owned_blocks(parse_normalized(&text, options)),
+    }
+}
+
+// 1-based (line, column) of the first and last character of a top-level block, and the
+// block's byte range in the input as given, before newlines and tabs were normalized.
+// Ordered by where the block starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourcePos {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub bytes: (usize, usize),
+}
+
+impl std::fmt::Display for SourcePos {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}-{}:{}", self.start.0, self.start.1, self.end.0, self.end.1)
+    }
+}
+
+// Turns increasing byte offsets into line and column numbers in one pass over the text.
+struct LineCounter<'a> {
+    text: &'a str,
+    offset: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl LineCounter<'_> {
+    fn position(&mut self, offset: usize) -> (usize, usize) {
+        for (i, c) in self.text[self.offset..offset].char_indices() {
+            if c == '\n' {
+                self.line += 1;
+                self.line_start = self.offset + i + 1;
+            }
+        }
+        self.offset = offset;
+        (self.line, self.text[self.line_start..offset].chars().count() + 1)
+    }
+}
+
+// Byte range of each line's content in the raw input. Normalizing keeps the lines, so a
+// line number finds the same line here; the ranges leave out the BOM and line breaks.
+pub(super) fn raw_lines(text: &str) -> Vec<(usize, usize)> {
+    let mut lines = vec!();
+    let mut start = if text.starts_with('\u{FEFF}') { '\u{FEFF}'.len_utf8() } else { 0 };
+    let bytes = text.as_bytes();
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                lines.push((start, i));
+                i += 2;
+                start = i;
+            },
+            b'\r' | b'\n' => {
+                lines.push((start, i));
+                i += 1;
+                start = i;
+            },
+            _ => i += 1,
+        }
+    }
+    lines.push((start, bytes.len()));
+    lines
+}
+
+// The top-level blocks of normalized text, with the byte range each one takes up in it,
+// leaving out the line breaks that end it.
+pub(super) fn blocks_with_offsets<'a>(text: &'a str, options: &ParserOptions) -> (Vec<Md<'a>>, Vec<(usize, usize)>) {
+    let mut rest = text;
+    let (mut md, mut offsets) = (vec!(), vec!());
+    let mut push = |token: Md<'a>, before: &str, after: &str| {
+        let start = text.len() - before.len();
+        let consumed = before[..before.len() - after.len()].trim_end_matches('\n');
+        md.push(token);
+        offsets.push((start, start + consumed.len()));
+    };
+    if let Some(ret) = front_matter(rest) {
+        push(ret.token, rest, ret.rest);
+        rest = ret.rest;
+    }
+    while let Some(ret) = block(rest, options) {
+        push(ret.token, rest, ret.rest);
+        rest = ret.rest;
+    }
+    (md, offsets)
+}
+
+// Like `parse_with`, but also returns where each top-level block sits in the input.
+pub fn parse_with_positions<'a>(text: &'a str, options: &ParserOptions) -> (Vec<Md<'a>>, Vec<SourcePos>) {
+    let lines = raw_lines(text);
+    let text = normalize_borrowed(text, options);
+    let (md, offsets) = match &text {
+        Cow::Borrowed(text) => blocks_with_offsets(text, options),
+        Cow::Owned(text) => {
+            let (md, offsets) = blocks_with_offsets(text, options);
+            (owned_blocks(md), offsets)
+        },
+    };
+    let mut counter = LineCounter{ text: &text, offset: 0, line: 1, line_start: 0 };
+    let positions = offsets.into_iter().map(|(start, end)| {
+        let last = text[start..end].chars().last().map_or(0, |c| c.len_utf8());
+        let start_pos = counter.position(start);
+        let end_pos = counter.position(end - last);
+        let bytes = (lines[start_pos.0 - 1].0, lines[end_pos.0 - 1].1);
+        SourcePos{ start: start_pos, end: end_pos, bytes }
+    })
+    .collect();
+    (md, positions)
+}
+
+// Like `parse_with`, but stops at the first block that breaks one of the options' caps.
+pub fn try_parse_with<'a>(text: &'a str, options: &ParserOptions) -> Result<Vec<Md<'a>>, LimitExceeded> {
+    if let Some(max) = options.max_input_bytes.filter(|max| text.len() > *max) {
+        return Err(LimitExceeded::InputBytes(max))
+    }
+    match normalize_borrowed(text, options) {
+        Cow::Borrowed(text) => try_parse_normalized(text, options),
+        Cow::Owned(text) => try_parse_normalized(&text, options).map(owned_blocks),
+    }
+}
+
+fn try_parse_normalized<'a>(mut text: &'a str, options: &ParserOptions) -> Result<Vec<Md<'a>>, LimitExceeded> {
+    let mut md: Vec<Md> = vec!();
+    if let Some(ret) = front_matter(text) {
+        md.push(ret.token);
+        text = ret.rest;
+    }
+    let mut nodes = 0;
+    while let Some(ret) = block(text, options) {
+        check(&ret.token, options, &mut nodes)?;
+        md.push(ret.token);
+        text = ret.rest;
+    }
+    Ok(md)
+}
@@ -0,0 +1,51 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+
+fn quote_line(text: &str) -> Option<&str> {
+    let text = consume(text.trim_start(), ">")?;
+    Some(text.strip_prefix(' ').unwrap_or(text))
+}
+
+pub fn quote<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let (line, _) = split_first_pattern(texts, "\n");
+    quote_line(line)?;
+    let mut lines: Vec<&str> = vec!();
+    let mut rest = texts;
+    while !rest.is_empty() {
+        let (line, next) = split_first_pattern(rest, "\n");
+        let Some(line) = quote_line(line) else { break };
+        lines.push(line);
+        rest = next;
+    }
+    let token = Md::Quote(owned_blocks(parse_blocks(&lines.join("\n"), &options.nested()?)));
+    Some(ParsedResult::new(token, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{words,items,normal_word};
+
+    #[test]
+    fn test_quote() {
+        let options = ParserOptions::default();
+        let heading = Md::Heading(1, words!(normal_word!("Title")));
+        let list = Md::List(items!(Item(words!(normal_word!("item")), vec!())));
+        let token = Md::Quote(vec!(heading, list));
+        let rest = "End";
+        assert_eq!(quote("> # Title\n>- item\nEnd", &options), Some(ParsedResult{token, rest}));
+
+        let inner = Md::Quote(vec!(Md::Sentence(words!(normal_word!("nested")))));
+        let token = Md::Quote(vec!(inner));
+        let rest = "";
+        assert_eq!(quote("> > nested", &options), Some(ParsedResult{token, rest}));
+
+        assert_eq!(quote("a > b", &options), None);
+
+        let options = ParserOptions{ max_nesting: 1, ..Default::default() };
+        let inner = Md::Sentence(words!(normal_word!("> nested")));
+        let token = Md::Quote(vec!(inner));
+        let rest = "";
+        assert_eq!(quote("> > nested", &options), Some(ParsedResult{token, rest}));
+    }
+}
@@ -0,0 +1,46 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+
+fn is_marker(line: &str, name: &str) -> bool {
+    let inner = line.trim().strip_prefix("<!--").and_then(|line| line.strip_suffix("-->"));
+    inner.is_some_and(|inner| inner.trim() == name)
+}
+
+// Lines between `<!-- md:off -->` and `<!-- md:on -->`, or the end of the text, kept as they
+// are instead of being parsed.
+pub fn raw<'a>(texts: &'a str, _options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let (line, mut rest) = split_first_pattern(texts, "\n");
+    if !is_marker(line, "md:off") { return None }
+    let body = rest;
+    let mut end = body.len();
+    while !rest.is_empty() {
+        let (line, after) = split_first_pattern(rest, "\n");
+        if is_marker(line, "md:on") {
+            end = body.len() - rest.len();
+            rest = after;
+            break
+        }
+        rest = after;
+    }
+    let content = body[..end].strip_suffix('\n').unwrap_or(&body[..end]);
+    Some(ParsedResult::new(Md::Raw(content.to_string()), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw() {
+        let options = ParserOptions::default();
+        let text = "<!-- md:off -->\n# Not a heading\n<b>*as is*</b>\n<!--md:on-->\nafter";
+        let token = Md::Raw("# Not a heading\n<b>*as is*</b>".to_string());
+        assert_eq!(raw(text, &options), Some(ParsedResult{token, rest: "after"}));
+
+        let token = Md::Raw("open\nto the end".to_string());
+        assert_eq!(raw(" <!-- md:off --> \nopen\nto the end", &options), Some(ParsedResult{token, rest: ""}));
+        assert_eq!(raw("<!-- md:off -->\n<!-- md:on -->", &options), Some(ParsedResult{token: Md::Raw("".to_string()), rest: ""}));
+        assert_eq!(raw("<!-- md:on -->\ntext", &options), None);
+        assert_eq!(raw("<!-- md:off --> text", &options), None);
+    }
+}
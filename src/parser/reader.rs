@@ -0,0 +1,159 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+use super::front_matter::{front_matter, front_matter_fence};
+use super::tabs::expand_tabs;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+// Blocks read from a `BufRead` one line at a time. Only the last two blocks of the text
+// read so far can still change as more lines arrive (a caption line only becomes part of
+// a table once the alignment row follows), so everything before them is handed out and dropped.
+pub struct BlockReader<R> {
+    reader: R,
+    options: ParserOptions,
+    buffer: String,
+    lines: usize,
+    // Line count at which the buffer is parsed again, doubling so long blocks stay linear.
+    next_parse: usize,
+    at_start: bool,
+    done: bool,
+    ready: VecDeque<Md<'static>>,
+}
+
+impl<R: BufRead> BlockReader<R> {
+    pub fn new(reader: R, options: &ParserOptions) -> Self {
+        BlockReader{
+            reader, options: options.clone(), buffer: String::new(), lines: 0, next_parse: 1,
+            at_start: true, done: false, ready: VecDeque::new(),
+        }
+    }
+
+    fn push_line(&mut self, mut line: String) {
+        if self.at_start && self.buffer.is_empty() {
+            if let Some(stripped) = line.strip_prefix('\u{FEFF}') { line = stripped.to_string() }
+        }
+        if self.options.strip_zero_width {
+            line.retain(|c| !ZERO_WIDTH.contains(&c));
+        }
+        self.buffer.push_str(&normalize_newlines(&line));
+        self.lines += 1;
+    }
+
+    // Parses the buffer and hands out the blocks that can no longer change, or all of
+    // them at the end of the input.
+    fn parse(&mut self, all: bool) {
+        let text = expand_tabs(&self.buffer, self.options.tab_width);
+        let mut rest = text.as_str();
+        let mut blocks: Vec<(Md, usize)> = vec!();
+        if self.at_start {
+            match front_matter(rest) {
+                Some(ret) => {
+                    blocks.push((ret.token, text.len() - ret.rest.len()));
+                    rest = ret.rest;
+                },
+                // Unclosed front matter could still be closed by a later line.
+                None if !all && rest.lines().next().and_then(front_matter_fence).is_some() => return,
+                None => {},
+            }
+        }
+        while let Some(ret) = block(rest, &self.options) {
+            blocks.push((ret.token, text.len() - ret.rest.len()));
+            rest = ret.rest;
+        }
+        let keep = if all { 0 } else { 2.min(blocks.len()) };
+        let done = blocks.len() - keep;
+        if done > 0 {
+            let cut = blocks[done - 1].1;
+            self.ready.extend(blocks.into_iter().take(done).map(|(md, _)| md.into_owned()));
+            self.buffer = text[cut..].to_string();
+            self.lines = self.buffer.lines().count();
+            self.at_start = false;
+        }
+        self.next_parse = self.lines.max(1) * 2;
+    }
+}
+
+impl<R: BufRead> Iterator for BlockReader<R> {
+    type Item = io::Result<Md<'static>>;
+
+    fn next(&mut self) -> Option<io::Result<Md<'static>>> {
+        loop {
+            if let Some(md) = self.ready.pop_front() {
+                return Some(Ok(md))
+            }
+            if self.done { return None }
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Err(err) => return Some(Err(err)),
+                Ok(0) => {
+                    self.done = true;
+                    self.parse(true);
+                },
+                Ok(_) => {
+                    let blank = line.trim().is_empty();
+                    self.push_line(line);
+                    if blank || self.lines >= self.next_parse { self.parse(false) }
+                },
+            }
+        }
+    }
+}
+
+pub fn parse_reader_with<R: BufRead>(reader: R, options: &ParserOptions) -> io::Result<Vec<Md<'static>>> {
+    BlockReader::new(reader, options).collect()
+}
+
+// Parses text as it is read, e.g. from piped stdin, giving the same blocks as `parse`.
+pub fn parse_reader<R: BufRead>(reader: R) -> io::Result<Vec<Md<'static>>> {
+    parse_reader_with(reader, &ParserOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    fn read(text: &str, options: &ParserOptions) -> Vec<Md<'static>> {
+        parse_reader_with(BufReader::with_capacity(3, text.as_bytes()), options).unwrap()
+    }
+
+    #[test]
+    fn test_parse_reader() {
+        let texts = [
+            "",
+            "# Title\nHello **world**\n\n- a\n  - b\n- c\n\nEnd",
+            "---\ntitle: T\n---\n# Body",
+            "---\nnot closed\n# Body",
+            "Intro\n[Caption]\n| A | B |\n| - | - |\n| a | b |\nTable: Below\nAfter",
+            "```rust\nfn main() {\n\n\tlet a;\n}\n```\n\tindented\n::: note\nA\n\nB\n:::\n> quoted\n> more\n\n??? Summary\n    body\n\n    more",
+            "\u{FEFF}Line one\r\nLine two\r\n\r\n| A |\r\n| - |\r\n| a |\r\n",
+            "[^1]: Note\nRef[^1]\n*[HTML]: Hyper Text\n[TOC]\n```\nunclosed",
+        ];
+        let options = ParserOptions::default();
+        for text in texts {
+            assert_eq!(read(text, &options), parse(text), "{:?}", text);
+        }
+        let options = ParserOptions{ loose_tables: true, strip_zero_width: true, ..Default::default() };
+        let text = "| A | B |\n| - | x |\n| a |\nZero\u{200B}width";
+        assert_eq!(read(text, &options), parse_with(text, &options));
+    }
+
+    #[test]
+    fn test_long_input() {
+        let text = "Paragraph with *emphasis*\n\n".repeat(2_000);
+        let blocks = BlockReader::new(text.as_bytes(), &ParserOptions::default());
+        assert_eq!(blocks.map(|md| md.unwrap()).collect::<Vec<_>>(), parse(&text));
+    }
+
+    #[test]
+    fn test_read_error() {
+        struct Failing;
+        impl io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("closed"))
+            }
+        }
+        let err = parse_reader(BufReader::new(Failing)).unwrap_err();
+        assert_eq!(err.to_string(), "closed");
+    }
+}
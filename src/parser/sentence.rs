@@ -1,85 +1,520 @@
 use crate::parser::parser::*;
+use super::options::ParserOptions;
+use super::entity::entity;
 use crate::{normal_word, words};
+use std::borrow::Cow;
+use memchr::{memchr_iter, memchr3_iter};
 
-fn emphasis<'a>(
+// Where the brackets of a line close, found in one pass: each `]`, `]]` and `](`, the `)`
+// ending the destination after each `](`, each `}` and each whitespace character. Looking
+// for the end of a bracket is then a search in these rather than in the rest of the line,
+// which on a line of `[` would make reading it quadratic.
+struct Closers<'a> {
     text: &'a str,
-    pattern: &'a str,
-    em: &dyn Fn(Words)->Word
-) -> Option<ParsedResult<'a, Word>> {
-    let text = consume(text, pattern)?;
-    let index = text.find(pattern)?;
-    if index == 0 { return  None }
-    let start = pattern.len() + index;
-    let (text, rest) = (&text[..index], &text[start..]);
-    let token = em(words(text));
-    Some(ParsedResult::new(token, rest))
+    brackets: Vec<usize>,
+    doubles: Vec<usize>,
+    links: Vec<usize>,
+    parens: Vec<Option<usize>>,
+    braces: Vec<usize>,
+    spaces: Vec<usize>,
 }
 
-fn italic(text: &str) -> Option<ParsedResult<Word>> {
-    let em = |token| Word::Italic(token);
-    emphasis(&text, "*", &em)
+// The first of the sorted `offsets` at or after `from`, if it is before `end`.
+fn next_in(offsets: &[usize], from: usize, end: usize) -> Option<usize> {
+    offsets.get(offsets.partition_point(|i| *i < from)).copied().filter(|i| *i < end)
 }
 
-fn bold(text: &str) -> Option<ParsedResult<Word>> {
-    let em = |token| Word::Bold(token);
-    emphasis(&text, "**", &em)
+impl<'a> Closers<'a> {
+    fn new(text: &'a str) -> Self {
+        let bytes = text.as_bytes();
+        let brackets: Vec<usize> = memchr_iter(b']', bytes).collect();
+        let followed = |by: u8| brackets.iter().copied().filter(|i| bytes.get(i + 1) == Some(&by)).collect();
+        let (doubles, links): (Vec<usize>, Vec<usize>) = (followed(b']'), followed(b'('));
+        // A destination ends at the first `)` that takes the depth of parentheses below where
+        // it started, past balanced pairs as in `/wiki/Rust_(programming_language)`. Those
+        // still waiting are stacked by depth, so a `)` only ever ends the ones on top.
+        let mut parens = vec!(None; links.len());
+        let mut waiting: Vec<(isize, usize)> = vec!();
+        let (mut depth, mut next) = (0isize, 0);
+        for (i, byte) in bytes.iter().enumerate() {
+            while links.get(next).is_some_and(|link| link + 2 == i) {
+                waiting.push((depth, next));
+                next += 1;
+            }
+            match byte {
+                b'(' => depth += 1,
+                b')' => {
+                    while let Some((_, link)) = waiting.pop_if(|(start, _)| *start == depth) {
+                        parens[link] = Some(i);
+                    }
+                    depth -= 1;
+                },
+                _ => {},
+            }
+        }
+        let braces = memchr_iter(b'}', bytes).collect();
+        let spaces = text.char_indices().filter(|(_, c)| c.is_whitespace()).map(|(i, _)| i).collect();
+        Closers{ text, brackets, doubles, links, parens, braces, spaces }
+    }
+
+    fn spaced(&self, start: usize, end: usize) -> bool {
+        next_in(&self.spaces, start, end).is_some()
+    }
+
+    // `[[key]]` at `at`, read no further than `end`.
+    fn kbd(&self, at: usize, end: usize) -> Option<ParsedResult<'a, Word<'a>>> {
+        consume(&self.text[at..end], "[[")?;
+        let index = next_in(&self.doubles, at + 2, end - 1)?;
+        let key = self.text[(at+2)..index].trim();
+        if key.is_empty() { return None }
+        let token = Word::Kbd(key.to_string());
+        Some(ParsedResult::new(token, &self.text[(index+2)..end]))
+    }
+
+    fn footnote_ref(&self, at: usize, end: usize) -> Option<ParsedResult<'a, Word<'a>>> {
+        consume(&self.text[at..end], "[^")?;
+        let index = next_in(&self.brackets, at + 2, end)?;
+        let label = &self.text[(at+2)..index];
+        if label.is_empty() || self.spaced(at + 2, index) { return None }
+        let token = Word::FootnoteRef(label.to_string());
+        Some(ParsedResult::new(token, &self.text[(index+1)..end]))
+    }
+
+    // `[text](target)` at `at`, where the text may be empty, as an image's alt text may.
+    fn brackets(&self, at: usize, end: usize) -> Option<Bracketed<'a>> {
+        consume(&self.text[at..end], "[")?;
+        let link = self.links.partition_point(|i| *i < at + 1);
+        let close = self.links.get(link).copied().filter(|close| close + 2 <= end)?;
+        if next_in(&self.brackets, at + 1, end) != Some(close) { return None }
+        let after = close + 2;
+        let paren = self.parens[link].filter(|paren| *paren < end)?;
+        let raw = &self.text[after..paren];
+        let target = raw.trim();
+        if target.is_empty() { return None }
+        let start = after + raw.len() - raw.trim_start().len();
+        let spaced = self.spaced(start, start + target.len());
+        Some(Bracketed{ label: &self.text[(at+1)..close], target, spaced, rest: &self.text[(paren+1)..end] })
+    }
+
+    // The same where the text must not be empty.
+    fn bracketed(&self, at: usize, end: usize) -> Option<Bracketed<'a>> {
+        self.brackets(at, end).filter(|bracketed| !bracketed.label.is_empty())
+    }
+
+    // A trailing `{width=50% height=200}` at `at`; `None` unless every pair is a width or height.
+    fn size_attributes(&self, at: usize, end: usize) -> Option<(ImageSize, usize)> {
+        consume(&self.text[at..end], "{")?;
+        let close = next_in(&self.braces, at + 1, end)?;
+        let inner = &self.text[(at+1)..close];
+        // Checked first, so a `{` that is not attributes is given up on by the next image.
+        if inner.contains(|c: char| !(c.is_ascii_alphanumeric() || c == '=' || c == '%' || c.is_whitespace())) { return None }
+        let mut size = ImageSize::default();
+        for pair in inner.split_whitespace() {
+            let (key, value) = pair.split_once('=')?;
+            if !is_dimension(value) { return None }
+            match key {
+                "width" => size.width = Some(value.to_string()),
+                "height" => size.height = Some(value.to_string()),
+                _ => return None,
+            }
+        }
+        Some((size, close + 1))
+    }
+
+    fn image(&self, at: usize, end: usize) -> Option<ParsedResult<'a, Word<'a>>> {
+        consume(&self.text[at..end], "!")?;
+        let Bracketed{ label: alt, target, rest, .. } = self.brackets(at + 1, end)?;
+        let (src, mut size) = image_target(target)?;
+        let mut rest = rest;
+        if let Some((attributes, after)) = self.size_attributes(end - rest.len(), end) {
+            size = ImageSize{ width: attributes.width.or(size.width), height: attributes.height.or(size.height) };
+            rest = &self.text[after..end];
+        }
+        let token = Word::Image(alt.to_string(), src.to_string(), size);
+        Some(ParsedResult::new(token, rest))
+    }
+}
+
+// A `[text](target)`: the text, the trimmed target, whether whitespace is left inside the
+// target, and the rest.
+struct Bracketed<'a> {
+    label: &'a str,
+    target: &'a str,
+    spaced: bool,
+    rest: &'a str,
+}
+
+fn link_target<'a>(bracketed: &Bracketed<'a>) -> Option<&'a str> {
+    if bracketed.spaced { return None }
+    Some(bracketed.target)
+}
+
+// A number with an optional unit, e.g. `640`, `50%` or `20em`.
+fn is_dimension(value: &str) -> bool {
+    let unit = value.trim_start_matches(|c: char| c.is_ascii_digit());
+    unit.len() < value.len() && ["", "%", "px", "em", "rem"].contains(&unit)
 }
 
-fn underline(text: &str) -> Option<ParsedResult<Word>> {
-    let em = |token| Word::Underline(token);
-    emphasis(&text, "__", &em)
+// `src` or `src =640x480`, where either side of the `x` may be left out.
+fn image_target(target: &str) -> Option<(&str, ImageSize)> {
+    let Some((src, size)) = target.split_once(char::is_whitespace) else { return Some((target, ImageSize::default())) };
+    let (width, height) = size.trim().strip_prefix('=')?.split_once('x')?;
+    let dimension = |value: &str| match value {
+        "" => Some(None),
+        value if value.chars().all(|c| c.is_ascii_digit()) => Some(Some(value.to_string())),
+        _ => None,
+    };
+    let size = ImageSize{ width: dimension(width)?, height: dimension(height)? };
+    if size == ImageSize::default() { return None }
+    Some((src, size))
 }
 
-fn strike_though(text: &str) -> Option<ParsedResult<Word>> {
-    let em = |token| Word::StrikeThough(token);
-    emphasis(&text, "~~", &em)
+fn emoji<'a>(text: &'a str, _options: &ParserOptions) -> Option<ParsedResult<'a, Word<'a>>> {
+    if !cfg!(feature = "emoji") { return None }
+    let text = consume(text, ":")?;
+    let index = text.find(':')?;
+    let name = &text[..index];
+    let is_code = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || "_+-".contains(c);
+    if name.is_empty() || !name.chars().all(is_code) { return None }
+    if name.chars().all(|c| c.is_ascii_digit()) { return None }
+    let token = Word::Emoji(name.to_string());
+    Some(ParsedResult::new(token, &text[(index+1)..]))
 }
 
-fn normal(text: &str) -> Option<ParsedResult<Word>> {
-    let keywords = ["~~", "__", "**", "*"];
+fn entity_reference<'a>(text: &'a str, _options: &ParserOptions) -> Option<ParsedResult<'a, Word<'a>>> {
+    let (reference, decoded) = entity(text)?;
+    let token = Word::Entity(reference.to_string(), decoded);
+    Some(ParsedResult::new(token, &text[reference.len()..]))
+}
+
+// An extension's syntax at the start of `rest`, unless `before` ends in a letter or digit,
+// so that `a@b.c` is not read as a mention.
+fn custom<'a>(before: &str, rest: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Word<'a>>> {
+    if before.chars().next_back().is_some_and(char::is_alphanumeric) { return None }
+    options.inline_extensions.iter().filter(|extension| rest.starts_with(extension.delimiter)).find_map(|extension| {
+        // Lengths past the end or inside a character are ignored rather than trusted.
+        let len = (extension.parse)(rest).filter(|len| *len > 0 && rest.is_char_boundary(*len))?;
+        let token = Word::Custom(extension.name.clone(), rest[..len].to_string());
+        Some(ParsedResult::new(token, &rest[len..]))
+    })
+}
+
+// A bare `https://`, `http://` or `www.` url up to whitespace or `<`, leaving out trailing
+// punctuation and unbalanced `)`, or a url in `<` and `>`, as GitHub links them. Like an
+// extension, a bare url must not follow a letter or digit.
+fn autolink<'a>(before: &str, rest: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Word<'a>>> {
+    if !options.autolinks { return None }
+    const SCHEMES: [&str; 2] = ["https://", "http://"];
+    if let Some(inner) = rest.strip_prefix('<') {
+        let end = inner.find(['>', '<', ' ', '\n'])?;
+        let url = &inner[..end];
+        if !inner[end..].starts_with('>') || !SCHEMES.iter().any(|scheme| url.len() > scheme.len() && url.starts_with(scheme)) { return None }
+        let token = Word::Link(words!(Word::Normal(Cow::Borrowed(url))), url.to_string());
+        return Some(ParsedResult::new(token, &inner[(end+1)..]))
+    }
+    if before.chars().next_back().is_some_and(char::is_alphanumeric) { return None }
+    let prefix = SCHEMES.iter().chain(&["www."]).find(|prefix| rest.starts_with(**prefix))?;
+    let mut url = &rest[..rest.find(|c: char| c.is_whitespace() || c == '<').unwrap_or(rest.len())];
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ':', ';', '!', '?', '"', '\'', '*', '_', '~']);
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(inner) if trimmed.matches(')').count() > trimmed.matches('(').count() => inner,
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() { break }
+        url = trimmed;
+    }
+    if url.len() <= prefix.len() || !url[prefix.len()..].contains(|c: char| c.is_alphanumeric()) { return None }
+    let href = if *prefix == "www." { format!("http://{}", url) } else { url.to_string() };
+    let token = Word::Link(words!(Word::Normal(Cow::Borrowed(url))), href);
+    Some(ParsedResult::new(token, &rest[url.len()..]))
+}
+
+// Byte offsets of every character that can start markup, found with vectorized scans so
+// the plain text between them is skipped rather than read a character at a time.
+fn specials(text: &str, options: &ParserOptions) -> Vec<usize> {
+    let bytes = text.as_bytes();
+    let mut found: Vec<usize> = memchr3_iter(b'*', b'_', b'~', bytes)
+        .chain(memchr3_iter(b'=', b'+', b'^', bytes))
+        .chain(memchr3_iter(b'[', b':', b'&', bytes))
+        .collect();
+    for extension in &options.inline_extensions {
+        found.extend(text.match_indices(extension.delimiter).map(|(i, _)| i));
+    }
+    if options.autolinks {
+        found.extend(["http", "www.", "<"].iter().flat_map(|start| text.match_indices(start).map(|(i, _)| i)));
+    }
+    found.sort_unstable();
+    found
+}
+
+// Plain text up to the next possible markup; always takes at least one character.
+// `specials` are the offsets in `text`, which starts at `pos` in the line, where markup
+// could start, in order.
+fn normal<'a>(
+    text: &'a str,
+    pos: usize,
+    closers: &Closers,
+    mut specials: impl Iterator<Item = usize>,
+    options: &ParserOptions
+) -> ParsedResult<'a, Word<'a>> {
+    let mut keywords = vec!["~~", "__", "**", "==", "++", "*", "~", "^", "[[", "[^"];
+    if cfg!(feature = "emoji") { keywords.push(":") }
+    if options.commonmark_underscores { keywords.push("_") }
     let matched = keywords.iter().find_map(|p| {
         let rest = consume(text, p)?;
-        Some(ParsedResult::new(normal_word!(p), &rest))
+        Some(ParsedResult::new(Word::Normal(Cow::Borrowed(&text[..p.len()])), rest))
     });
-    if matched.is_some() {
+    if let Some(matched) = matched {
         return matched
     }
 
-    let index = keywords.iter().filter_map(|p| text.find(p)).min();
+    let index = specials.find_map(|i| {
+        let at = &text[i..];
+        if i == 0 { return None }
+        if keywords.iter().any(|p| at.starts_with(p)) { return Some(i) }
+        if custom(&text[..i], at, options).is_some() || autolink(&text[..i], at, options).is_some() { return Some(i) }
+        if entity(at).is_some() { return Some(i) }
+        let bracketed = closers.brackets(pos + i, pos + text.len())?;
+        if text[..i].ends_with('!') && image_target(bracketed.target).is_some() { return Some(i - 1).filter(|i| *i > 0) }
+        link_target(&bracketed).filter(|_| !bracketed.label.is_empty()).map(|_| i)
+    });
     if let Some(n) = index {
         let token = &text[..n];
         let rest = &text[n..];
-        return Some(ParsedResult::new(normal_word!(token), rest))
+        return ParsedResult::new(Word::Normal(Cow::Borrowed(token)), rest)
     }
 
-    let token = normal_word!(text);
-    Some(ParsedResult::new(token,  ""))
+    let token = Word::Normal(Cow::Borrowed(text));
+    ParsedResult::new(token,  "")
+}
+
+// Markers in the order they are tried, so `**` is read before `*`. A run of three opens
+// or closes both an italic and a bold (or underline) pair at once.
+const MARKERS: [&str; 11] = ["***", "___", "~~", "__", "**", "==", "++", "*", "_", "~", "^"];
+
+// What a matched pair of markers, or a link's brackets, wraps its contents in.
+enum Wrap<'a> {
+    Emphasis(&'a str),
+    Link(String),
+}
+
+impl<'a> Wrap<'a> {
+    fn word(self, words: Words<'a>, options: &ParserOptions) -> Word<'a> {
+        match self {
+            Wrap::Emphasis("**") => Word::Bold(words),
+            Wrap::Emphasis("__") if options.commonmark_underscores => Word::Bold(words),
+            Wrap::Emphasis("__") => Word::Underline(words),
+            Wrap::Emphasis("~~") => Word::StrikeThough(words),
+            Wrap::Emphasis("==") => Word::Highlight(words),
+            Wrap::Emphasis("++") => Word::Insert(words),
+            Wrap::Emphasis("~") if options.single_tilde_strikethrough => Word::StrikeThough(words),
+            Wrap::Emphasis("~") => Word::Subscript(words),
+            Wrap::Emphasis("^") => Word::Superscript(words),
+            Wrap::Emphasis(_) => Word::Italic(words),
+            Wrap::Link(url) => Word::Link(words, url),
+        }
+    }
+}
+
+// The flat reading of a line: finished words, markers that have not (yet) found a partner,
+// and the two ends of each pair, which carry their source text in case they stay literal.
+enum Token<'a> {
+    Word(Word<'a>),
+    Marker(&'a str),
+    Open(Wrap<'a>, &'a str),
+    Close(&'a str),
+}
+
+// An unmatched marker: its token and where its text ends. A run of three that closed with
+// only part of itself keeps the rest open here.
+struct Opener<'a> {
+    marker: &'a str,
+    token: usize,
+    end: usize,
+}
+
+// A link label being read: where it ends, the `](target)` after it, and how much of the
+// marker stack belongs to the text outside, with the bottoms searched outside it.
+struct Label<'a> {
+    end: usize,
+    close: &'a str,
+    floor: usize,
+    bottoms: [usize; MARKERS.len()],
 }
 
-fn word(text: &str) -> ParsedResult<Word> {
-    let parsers = vec!(underline, strike_though, bold, italic, normal);
-    if let Some(result) = parsers.iter().find_map(|f| f(text)) {
-        result
+// The marker at the start of `text`; a run of three counts only on its own, so longer runs
+// still split into pairs.
+fn marker<'a>(before: &str, text: &'a str, options: &ParserOptions) -> Option<&'a str> {
+    let triple = |marker: &str| marker.len() < 3 || !(before.ends_with(&marker[..1]) || text[3..].starts_with(&marker[..1]));
+    let marker = MARKERS.iter().find(|marker| text.starts_with(**marker) && triple(marker))?;
+    if *marker == "_" && !options.commonmark_underscores { return None }
+    Some(&text[..marker.len()])
+}
+
+// Whether the marker at `start..end` can open and whether it can close a pair, by CommonMark's
+// flanking rules: an opener must not be followed by whitespace, nor by punctuation unless
+// whitespace or punctuation comes before it, and a closer the same mirrored. Underscores
+// must also not sit inside a word, so `snake_case_name` stays plain.
+fn flanking(text: &str, start: usize, end: usize) -> (bool, bool) {
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    let space = |c: Option<char>| c.is_none_or(char::is_whitespace);
+    let punctuation = |c: Option<char>| c.is_some_and(|c| c.is_ascii_punctuation() || (!c.is_alphanumeric() && !c.is_whitespace()));
+    let left = !space(after) && (!punctuation(after) || space(before) || punctuation(before));
+    let right = !space(before) && (!punctuation(before) || space(after) || punctuation(after));
+    if text[start..end].starts_with('_') {
+        (left && (!right || punctuation(before)), right && (!left || punctuation(after)))
     } else {
-        panic!("parse err!")
+        (left, right)
+    }
+}
+
+// Whether a closing marker can pair with an opening one: the same marker, or a run of three
+// with one or two of the same character on the other side.
+fn pairs(opener: &str, closer: &str) -> bool {
+    opener == closer || (opener[..1] == closer[..1] && (opener.len() == 3 || closer.len() == 3))
+}
+
+// Whether `closer`, starting at `start`, may close the opener, given the text between them.
+fn closes(text: &str, opener: &Opener, start: usize, options: &ParserOptions) -> bool {
+    let inner = &text[opener.end..start];
+    match opener.marker {
+        _ if inner.is_empty() => false,
+        "~" if options.single_tilde_strikethrough => true,
+        "~" | "^" => !inner.contains(char::is_whitespace),
+        _ => true,
+    }
+}
+
+// Reads the line left to right, matching each marker against the nearest open one of the
+// same kind, as CommonMark's delimiter stack does. Markers left open in between, or that
+// never close, stay literal text. A link's label is read on its own, so its markers never
+// pair with ones outside it. As in CommonMark, each marker remembers the bottom of the stack
+// worth searching, below which nothing can close it, so unmatched openers are passed over
+// once rather than by every closer.
+fn tokens<'a>(text: &'a str, options: &ParserOptions) -> Vec<Token<'a>> {
+    let atoms = [emoji, entity_reference];
+    let closers = Closers::new(text);
+    let mut tokens: Vec<Token> = vec!();
+    let mut openers: Vec<Opener> = vec!();
+    let mut label: Option<Label> = None;
+    let mut bottoms = [0; MARKERS.len()];
+    let specials = specials(text, options);
+    let mut next = 0;
+    let mut pos = 0;
+    while pos < text.len() {
+        if let Some(Label{ end, close, floor, bottoms: outside }) = label.take_if(|label| label.end == pos) {
+            openers.truncate(floor);
+            bottoms = outside;
+            tokens.push(Token::Close(close));
+            pos = end + close.len();
+            continue;
+        }
+        let end = label.as_ref().map_or(text.len(), |label| label.end);
+        let rest = &text[pos..end];
+        let link = closers.bracketed(pos, end).filter(|bracketed| label.is_none() && link_target(bracketed).is_some());
+        let atom = custom(&text[..pos], rest, options)
+            .or_else(|| autolink(&text[..pos], rest, options).filter(|_| label.is_none()))
+            .or_else(|| closers.kbd(pos, end))
+            .or_else(|| closers.footnote_ref(pos, end))
+            .or_else(|| closers.image(pos, end))
+            .or_else(|| atoms.iter().find_map(|f| f(rest, options)));
+        if let Some(result) = atom {
+            tokens.push(Token::Word(result.token));
+            pos = end - result.rest.len();
+        } else if let Some(Bracketed{ label: inner, target, rest: after, .. }) = link {
+            tokens.push(Token::Open(Wrap::Link(target.to_string()), &rest[..1]));
+            let close = &rest[(1+inner.len())..(rest.len()-after.len())];
+            label = Some(Label{ end: pos + 1 + inner.len(), close, floor: openers.len(), bottoms });
+            pos += 1;
+        } else if let Some(run) = marker(&text[..pos], rest, options) {
+            let floor = label.as_ref().map_or(0, |label| label.floor);
+            let (can_open, can_close) = flanking(text, pos, pos + run.len());
+            let mut marker = run;
+            while can_close && !marker.is_empty() {
+                let kind = MARKERS.iter().position(|kind| *kind == marker).expect("markers split into markers");
+                let bottom = bottoms[kind].max(floor);
+                let nearest = openers[bottom..].iter().rposition(|opener| pairs(opener.marker, marker));
+                let Some(i) = nearest.map(|i| bottom + i) else {
+                    bottoms[kind] = openers.len();
+                    break
+                };
+                if !closes(text, &openers[i], pos, options) {
+                    // With text in between it never closes this marker, as that text only grows.
+                    if openers[i].end < pos { bottoms[kind] = i + 1 }
+                    break
+                }
+                // Runs of three on both sides close the inner bold first, then the italic.
+                let len = match openers[i].marker.len().min(marker.len()) { 3 => 2, len => len };
+                let (outer, inner) = openers[i].marker.split_at(openers[i].marker.len() - len);
+                let token = openers[i].token;
+                if outer.is_empty() {
+                    tokens[token] = Token::Open(Wrap::Emphasis(inner), inner);
+                    openers.truncate(i);
+                } else {
+                    tokens[token] = Token::Marker(outer);
+                    tokens.insert(token + 1, Token::Open(Wrap::Emphasis(inner), inner));
+                    openers[i].marker = outer;
+                    openers.truncate(i + 1);
+                }
+                bottoms.iter_mut().for_each(|bottom| *bottom = (*bottom).min(openers.len()));
+                tokens.push(Token::Close(&marker[..len]));
+                marker = &marker[len..];
+            }
+            if !marker.is_empty() {
+                if can_open {
+                    openers.push(Opener{ marker, token: tokens.len(), end: pos + run.len() });
+                }
+                tokens.push(Token::Marker(marker));
+            }
+            pos += run.len();
+        } else {
+            next += specials[next..].partition_point(|i| *i < pos);
+            let offsets = specials[next..].iter().map(|i| i - pos).take_while(|i| *i < rest.len());
+            let result = normal(rest, pos, &closers, offsets, options);
+            tokens.push(Token::Word(result.token));
+            pos = end - result.rest.len();
+        }
     }
+    tokens
 }
 
-pub fn words(mut text: &str) -> Words {
+pub fn words<'a>(text: &'a str, options: &ParserOptions) -> Words<'a> {
+    span!(trace_span, "inline", bytes = text.len());
     if text.is_empty() { return words!(normal_word!(""))};
-    let mut tokens: Vec<Word> = vec!();
-    while !text.is_empty() {
-        let result = word(&text);
-        tokens.push(result.token);
-        text = result.rest;
+    // The pairs still open, outermost first, with their words so far. Pairs deeper than
+    // `max_nesting` stay literal; `wrapped` remembers which pairs became nodes.
+    let mut root: Vec<Word> = vec!();
+    let mut open: Vec<(Wrap, Vec<Word>)> = vec!();
+    let mut wrapped: Vec<bool> = vec!();
+    for token in tokens(text, options) {
+        let word = match token {
+            Token::Word(word) => word,
+            Token::Marker(marker) => Word::Normal(Cow::Borrowed(marker)),
+            Token::Open(wrap, _) if open.len() < options.max_nesting => {
+                open.push((wrap, vec!()));
+                wrapped.push(true);
+                continue;
+            },
+            Token::Open(_, source) => {
+                wrapped.push(false);
+                Word::Normal(Cow::Borrowed(source))
+            },
+            Token::Close(source) => match wrapped.pop().filter(|wrapped| *wrapped).and_then(|_| open.pop()) {
+                Some((wrap, words)) => wrap.word(Words(words), options),
+                None => Word::Normal(Cow::Borrowed(source)),
+            },
+        };
+        open.last_mut().map_or(&mut root, |(_, words)| words).push(word);
     }
-    Words(tokens)
+    Words(root)
 }
 
-pub fn sentence(texts: &str) -> Option<ParsedResult<Md>> {
+pub fn sentence<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
     if texts.is_empty() { return None }
     let (text, rest) = split_first_pattern(texts, "\n");
-    let tokens = words(&text);
+    let tokens = words(text, options);
     Some(ParsedResult::new(Md::Sentence(tokens), rest))
 }
 
@@ -87,13 +522,14 @@ pub fn sentence(texts: &str) -> Option<ParsedResult<Md>> {
 mod tests {
     use super::*;
     use crate::{words,normal_word};
+    use crate::parser::options::InlineExtension;
 
     #[test]
     fn test_sentence() {
         let words = words!(normal_word!("Hello World!"));
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"Hello World!"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("Hello World!", &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
 
     #[test]
@@ -101,7 +537,7 @@ mod tests {
         let words = words!(normal_word!("Hello World!"));
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"Hello World!"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("Hello World!", &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
 
     #[test]
@@ -110,7 +546,7 @@ mod tests {
         let words = words!(Word::Italic(words));
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"*Hello World!*"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("*Hello World!*", &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
 
     #[test]
@@ -119,7 +555,7 @@ mod tests {
         let words = words!(Word::Bold(words));
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"**Hello World!**"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("**Hello World!**", &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
 
     #[test]
@@ -128,7 +564,21 @@ mod tests {
         let words = words!(Word::StrikeThough(words));
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"~~Hello World!~~"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("~~Hello World!~~", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_single_tilde_strikethrough() {
+        let options = ParserOptions{ single_tilde_strikethrough: true, ..Default::default() };
+        let strike = |text: &'static str| Word::StrikeThough(words!(normal_word!(text)));
+        let token = Md::Sentence(words!(strike("Hello World!"), normal_word!(" "), strike("old")));
+        let rest = "";
+        assert_eq!(sentence("~Hello World!~ ~~old~~", &options), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(normal_word!("H"), Word::Subscript(words!(normal_word!("2"))), normal_word!("O")));
+        assert_eq!(sentence("H~2~O", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(normal_word!("~"), normal_word!("a "), normal_word!("~")));
+        assert_eq!(sentence("~a ~", &options), Some(ParsedResult{token, rest}));
     }
 
     #[test]
@@ -137,7 +587,168 @@ mod tests {
         let words = words!(Word::Underline(words));
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"__Hello World!__"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("__Hello World!__", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_highlight() {
+        let words = words!(normal_word!("Hello World!"));
+        let words = words!(Word::Highlight(words));
+        let token = Md::Sentence(words);
+        let rest = "";
+        assert_eq!(sentence("==Hello World!==", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let hello = normal_word!("Hello ");
+        let world = Word::Highlight(words!(Word::Bold(words!(normal_word!("World!")))));
+        let token = Md::Sentence(words!(hello, world));
+        let rest = "";
+        assert_eq!(sentence("Hello ==**World!**==", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let a = normal_word!("a ");
+        let eq = normal_word!("==");
+        let b = normal_word!(" b");
+        let token = Md::Sentence(words!(a, eq, b));
+        let rest = "";
+        assert_eq!(sentence("a == b", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_insert() {
+        let words = words!(normal_word!("Hello World!"));
+        let words = words!(Word::Insert(words));
+        let token = Md::Sentence(words);
+        let rest = "";
+        assert_eq!(sentence("++Hello World!++", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let c = normal_word!("C");
+        let plus = normal_word!("++");
+        let token = Md::Sentence(words!(c, plus));
+        let rest = "";
+        assert_eq!(sentence("C++", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_script() {
+        let h = normal_word!("H");
+        let two = Word::Subscript(words!(normal_word!("2")));
+        let o = normal_word!("O");
+        let token = Md::Sentence(words!(h, two, o));
+        let rest = "";
+        assert_eq!(sentence("H~2~O", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let e = normal_word!("E=mc");
+        let two = Word::Superscript(words!(normal_word!("2")));
+        let token = Md::Sentence(words!(e, two));
+        let rest = "";
+        assert_eq!(sentence("E=mc^2^", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let strike = Word::StrikeThough(words!(normal_word!("gone")));
+        let token = Md::Sentence(words!(strike));
+        let rest = "";
+        assert_eq!(sentence("~~gone~~", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let word0 = normal_word!("a ");
+        let word1 = normal_word!("~");
+        let word2 = normal_word!("b c");
+        let word3 = normal_word!("~");
+        let token = Md::Sentence(words!(word0, word1, word2, word3));
+        let rest = "";
+        assert_eq!(sentence("a ~b c~", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_entity() {
+        let entity = |reference: &str, text: &str| Word::Entity(reference.to_string(), text.to_string());
+        let token = Md::Sentence(words!(
+            normal_word!("Tom "), entity("&amp;", "&"), normal_word!(" Jerry "), entity("&#169;", "©"), normal_word!(" 2024")
+        ));
+        let rest = "";
+        assert_eq!(sentence("Tom &amp; Jerry &#169; 2024", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(Word::Bold(words!(entity("&lt;", "<"), normal_word!("tag"), entity("&gt;", ">")))));
+        assert_eq!(sentence("**&lt;tag&gt;**", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(normal_word!("R&D &unknown; &amp")));
+        assert_eq!(sentence("R&D &unknown; &amp", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_kbd() {
+        let press = normal_word!("Press ");
+        let ctrl = Word::Kbd("Ctrl+C".to_string());
+        let copy = normal_word!(" to copy");
+        let token = Md::Sentence(words!(press, ctrl, copy));
+        let rest = "";
+        assert_eq!(sentence("Press [[Ctrl+C]] to copy", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let open = normal_word!("[[");
+        let text = normal_word!("]] and [");
+        let token = Md::Sentence(words!(open, text));
+        let rest = "";
+        assert_eq!(sentence("[[]] and [", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_link() {
+        let see = normal_word!("See ");
+        let link = Word::Link(words!(normal_word!("the "), Word::Bold(words!(normal_word!("docs")))), "https://example.com".to_string());
+        let token = Md::Sentence(words!(see, link, normal_word!(".")));
+        let rest = "";
+        assert_eq!(sentence("See [the **docs**](https://example.com).", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(normal_word!("[a] (b) [c](d e)")));
+        assert_eq!(sentence("[a] (b) [c](d e)", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let link = Word::Link(words!(normal_word!("Rust")), "/wiki/Rust_(programming_language)".to_string());
+        let token = Md::Sentence(words!(link, normal_word!(" (lang)")));
+        assert_eq!(sentence("[Rust](/wiki/Rust_(programming_language)) (lang)", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_image() {
+        let logo = Word::Image("The logo".to_string(), "img/logo.png".to_string(), ImageSize::default());
+        let token = Md::Sentence(words!(normal_word!("Logo "), logo, normal_word!("!")));
+        let rest = "";
+        assert_eq!(sentence("Logo ![The logo](img/logo.png)!", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(normal_word!("Wow![a]")));
+        assert_eq!(sentence("Wow![a]", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let spacer = Word::Image("".to_string(), "spacer.gif".to_string(), ImageSize::default());
+        let token = Md::Sentence(words!(normal_word!("a "), spacer, normal_word!(" [](b)")));
+        assert_eq!(sentence("a ![](spacer.gif) [](b)", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_image_size() {
+        let options = ParserOptions::default();
+        let size = |width: Option<&str>, height: Option<&str>| ImageSize{
+            width: width.map(str::to_string),
+            height: height.map(str::to_string),
+        };
+        let image = |size| Md::Sentence(words!(normal_word!("A "), Word::Image("a".to_string(), "a.png".to_string(), size)));
+        let rest = "";
+        assert_eq!(sentence("A ![a](a.png =640x480)", &options), Some(ParsedResult{token: image(size(Some("640"), Some("480"))), rest}));
+        assert_eq!(sentence("A ![a](a.png =x480)", &options), Some(ParsedResult{token: image(size(None, Some("480"))), rest}));
+        assert_eq!(sentence("A ![a](a.png){width=50%}", &options), Some(ParsedResult{token: image(size(Some("50%"), None)), rest}));
+        assert_eq!(sentence("A ![a](a.png =640x){height=20em}", &options), Some(ParsedResult{token: image(size(Some("640"), Some("20em"))), rest}));
+
+        let token = Md::Sentence(words!(normal_word!("A "), Word::Image("a".to_string(), "a.png".to_string(), ImageSize::default()), normal_word!("{alt=b}")));
+        assert_eq!(sentence("A ![a](a.png){alt=b}", &options), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("A ![a](a.png =big)", &options), Some(ParsedResult{token: Md::Sentence(words!(normal_word!("A ![a](a.png =big)"))), rest}));
+    }
+
+    #[test]
+    fn test_footnote_ref() {
+        let text = normal_word!("Text");
+        let note = Word::FootnoteRef("1".to_string());
+        let token = Md::Sentence(words!(text, note, normal_word!(".")));
+        let rest = "";
+        assert_eq!(sentence("Text[^1].", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let open = normal_word!("[^");
+        let text = normal_word!("] and ");
+        let token = Md::Sentence(words!(open, text, normal_word!("[^"), normal_word!("a b]")));
+        assert_eq!(sentence("[^] and [^a b]", &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
 
     #[test]
@@ -147,14 +758,14 @@ mod tests {
         let words = words!(Word::Underline(words));
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"__**Hello World!**__"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("__**Hello World!**__", &ParserOptions::default()), Some(ParsedResult{token, rest}));
 
         let words = words!(normal_word!("Hello World!"));
         let words = words!(Word::Underline(words));
         let words = words!(Word::Bold(words));
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"**__Hello World!__**"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("**__Hello World!__**", &ParserOptions::default()), Some(ParsedResult{token, rest}));
 
         let words = words!(normal_word!("Hello World!"));
         let words = words!(Word::Underline(words));
@@ -162,7 +773,7 @@ mod tests {
         let words = words!(Word::StrikeThough(words));
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"~~**__Hello World!__**~~"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("~~**__Hello World!__**~~", &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
 
     #[test]
@@ -172,7 +783,7 @@ mod tests {
         // let words = words!(word0, word1);
         // let token = Md::Sentence(words);
         // let rest = "";
-        // assert_eq!(sentence(&"Hello **World!**"), Some(ParsedResult{token, rest}));
+        // assert_eq!(sentence(&"Hello **World!**", &ParserOptions::default()), Some(ParsedResult{token, rest}));
 
         let word0 = normal_word!("Hello ");
         let word1 = normal_word!("**");
@@ -180,7 +791,28 @@ mod tests {
         let words = words!(word0, word1, word2);
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"Hello **World!"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("Hello **World!", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    // An opening delimiter that never closes, on its own, after text or after a closed pair,
+    // stays as literal text with nothing else on the line changed.
+    #[test]
+    fn test_unclosed_delimiters() {
+        let options = ParserOptions{ commonmark_underscores: true, ..Default::default() };
+        let openers = [
+            "***", "___", "**", "__", "~~", "==", "++", "*", "_", "~", "^", "[[", "[^", "[", "![", "[a](", "![a](",
+        ];
+        for opener in openers {
+            for text in [format!("{}never closed", opener), format!("a {}b c", opener), format!("**a** {}b", opener)] {
+                let words = words(&text, &options);
+                let literal: String = words.0.iter().filter_map(|word| match word {
+                    Word::Normal(text) => Some(text.as_ref()),
+                    Word::Bold(inner) if inner.0 == vec!(normal_word!("a")) => Some("**a**"),
+                    _ => None,
+                }).collect();
+                assert_eq!(literal, text, "{:?}", words);
+            }
+        }
     }
 
     #[test]
@@ -192,7 +824,7 @@ mod tests {
         let words = words!(word0, word1, word2, word3);
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"Hello ****World!"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("Hello ****World!", &ParserOptions::default()), Some(ParsedResult{token, rest}));
 
         let word0 = normal_word!("Hello ");
         let word1 = normal_word!("**");
@@ -201,7 +833,7 @@ mod tests {
         let words = words!(word0, word1, word2, word3);
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"Hello **~~World!"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("Hello **~~World!", &ParserOptions::default()), Some(ParsedResult{token, rest}));
 
         let word0 = normal_word!("~~");
         let word1 = normal_word!("World!");
@@ -210,10 +842,10 @@ mod tests {
         let words = words!(normal, bold);
         let token = Md::Sentence(words);
         let rest = "";
-        assert_eq!(sentence(&"Hello **~~World!**"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("Hello **~~World!**", &ParserOptions::default()), Some(ParsedResult{token, rest}));
 
         let strike = normal_word!("~~");
-        let wor = normal_word!("Wor");
+        let wor = normal_word!("Wor ");
         let ld = normal_word!("ld!");
         let un = Word::Underline(words!(ld));
         let bold = Word::Bold(words!(strike, wor, un));
@@ -221,7 +853,34 @@ mod tests {
         let token = words!(hello, bold);
         let token = Md::Sentence(token);
         let rest = "";
-        assert_eq!(sentence(&"Hello **~~Wor__ld!__**"), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence("Hello **~~Wor __ld!__**", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    #[cfg(feature = "emoji")]
+    fn test_emoji() {
+        let hello = normal_word!("Hello ");
+        let smile = Word::Emoji("smile".to_string());
+        let token = Md::Sentence(words!(hello, smile));
+        let rest = "";
+        assert_eq!(sentence("Hello :smile:", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let word0 = normal_word!("at 10");
+        let word1 = normal_word!(":");
+        let word2 = normal_word!("30");
+        let word3 = normal_word!(":");
+        let word4 = normal_word!("00");
+        let token = Md::Sentence(words!(word0, word1, word2, word3, word4));
+        let rest = "";
+        assert_eq!(sentence("at 10:30:00", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    #[cfg(not(feature = "emoji"))]
+    fn test_emoji_disabled() {
+        let token = Md::Sentence(words!(normal_word!("Hello :smile:")));
+        let rest = "";
+        assert_eq!(sentence("Hello :smile:", &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
 
     #[test]
@@ -231,7 +890,7 @@ mod tests {
         let token = words!(token);
         let token = Md::Sentence(token);
         let rest = " World!";
-        assert_eq!(sentence(&test_word), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence(test_word, &ParserOptions::default()), Some(ParsedResult{token, rest}));
 
         let test_word = "**Hello**\n World!";
         let token = normal_word!("Hello");
@@ -239,6 +898,182 @@ mod tests {
         let token = words!(token);
         let token = Md::Sentence(token);
         let rest = " World!";
-        assert_eq!(sentence(&test_word), Some(ParsedResult{token, rest}));
+        assert_eq!(sentence(test_word, &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_max_nesting() {
+        let options = ParserOptions{ max_nesting: 1, ..Default::default() };
+        let bold = Word::Bold(words!(
+            normal_word!("a "), normal_word!("=="), normal_word!("b"), normal_word!("==")
+        ));
+        assert_eq!(words("**a ==b==**", &options), words!(bold));
+
+        let options = ParserOptions{ max_nesting: 0, ..Default::default() };
+        let expect = words!(normal_word!("*"), normal_word!("a"), normal_word!("*"));
+        assert_eq!(words("*a*", &options), expect);
+    }
+
+    #[test]
+    fn test_mismatched_markers() {
+        let options = ParserOptions::default();
+        let bold = Word::Bold(words!(normal_word!("b")));
+        let italic = Word::Italic(words!(normal_word!("a "), bold, normal_word!(" c")));
+        assert_eq!(words("*a **b** c*", &options), words!(italic));
+
+        let italic = Word::Italic(words!(normal_word!("a"), normal_word!("**"), normal_word!("b")));
+        assert_eq!(words("*a**b*", &options), words!(italic));
+
+        let link = Word::Link(words!(normal_word!("b"), normal_word!("**")), "c".to_string());
+        assert_eq!(words("**a [b**](c)", &options), words!(normal_word!("**"), normal_word!("a "), link));
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let text = format!("{}a{}", "**".repeat(50_000), "**".repeat(50_000));
+        let words = words(&text, &ParserOptions::default());
+        let mut current = &words;
+        let mut depth = 0;
+        while let [Word::Bold(inner)] = current.0.as_slice() {
+            current = inner;
+            depth += 1;
+        }
+        assert_eq!(depth, 64);
+        assert_eq!(current.0.len(), 2 * (50_000 - 64) + 1);
+    }
+
+    #[test]
+    fn test_commonmark_underscores() {
+        let options = ParserOptions{ commonmark_underscores: true, ..Default::default() };
+        let bold = Word::Bold(words!(normal_word!("Hello")));
+        let space = normal_word!(" ");
+        let italic = Word::Italic(words!(normal_word!("World")));
+        let token = Md::Sentence(words!(bold, space, italic));
+        let rest = "";
+        assert_eq!(sentence("__Hello__ _World_", &options), Some(ParsedResult{token, rest}));
+
+        let snake = normal_word!("snake");
+        let case = normal_word!("_");
+        let name = normal_word!("case");
+        let token = Md::Sentence(words!(snake, case, name, normal_word!("_"), normal_word!("name")));
+        let rest = "";
+        assert_eq!(sentence("snake_case_name", &options), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(normal_word!("snake_case")));
+        let rest = "";
+        assert_eq!(sentence("snake_case", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_flanking() {
+        let options = ParserOptions{ commonmark_underscores: true, ..Default::default() };
+        let token = Md::Sentence(words!(normal_word!("a"), Word::Italic(words!(normal_word!("b"))), normal_word!("c")));
+        let rest = "";
+        assert_eq!(sentence("a*b*c", &options), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(normal_word!("a"), normal_word!("_"), normal_word!("b"), normal_word!("_"), normal_word!("c")));
+        assert_eq!(sentence("a_b_c", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(normal_word!("foo"), normal_word!("__"), normal_word!("bar"), normal_word!("__")));
+        assert_eq!(sentence("foo__bar__", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(normal_word!("*"), normal_word!("text "), normal_word!("*")));
+        assert_eq!(sentence("*text *", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(normal_word!("*"), normal_word!(" text"), normal_word!("*")));
+        assert_eq!(sentence("* text*", &options), Some(ParsedResult{token, rest}));
+
+        let italic = Word::Italic(words!(normal_word!("a "), Word::Italic(words!(normal_word!("b")))));
+        let token = Md::Sentence(words!(italic));
+        assert_eq!(sentence("_a *b*_", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(Word::Italic(words!(normal_word!("\"quoted\""))), normal_word!(".")));
+        assert_eq!(sentence("_\"quoted\"_.", &options), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_triple_markers() {
+        let options = ParserOptions::default();
+        let rest = "";
+        let bold = |words: Words<'static>| Word::Bold(words);
+        let italic = |words: Words<'static>| Word::Italic(words);
+        let token = Md::Sentence(words!(italic(words!(bold(words!(normal_word!("text")))))));
+        assert_eq!(sentence("***text***", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(italic(words!(Word::Underline(words!(normal_word!("text")))))));
+        assert_eq!(sentence("___text___", &options), Some(ParsedResult{token, rest}));
+        let commonmark = ParserOptions{ commonmark_underscores: true, ..Default::default() };
+        let token = Md::Sentence(words!(italic(words!(bold(words!(normal_word!("text")))))));
+        assert_eq!(sentence("___text___", &commonmark), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(italic(words!(bold(words!(normal_word!("a"))), normal_word!(" b")))));
+        assert_eq!(sentence("***a** b*", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(bold(words!(italic(words!(normal_word!("a"))), normal_word!(" b")))));
+        assert_eq!(sentence("***a* b**", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(bold(words!(normal_word!("a "), italic(words!(normal_word!("b")))))));
+        assert_eq!(sentence("**a *b***", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(italic(words!(normal_word!("a "), bold(words!(normal_word!("b")))))));
+        assert_eq!(sentence("*a **b***", &options), Some(ParsedResult{token, rest}));
+
+        let both = |text: &'static str| italic(words!(bold(words!(normal_word!(text)))));
+        let token = Md::Sentence(words!(both("a"), normal_word!(" "), both("b")));
+        assert_eq!(sentence("***a*** ***b***", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(normal_word!("*"), bold(words!(normal_word!("a")))));
+        assert_eq!(sentence("***a**", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(normal_word!("***"), normal_word!("a")));
+        assert_eq!(sentence("***a", &options), Some(ParsedResult{token, rest}));
+    }
+
+    fn issue(text: &str) -> Option<usize> {
+        let digits = text[1..].bytes().take_while(u8::is_ascii_digit).count();
+        (digits > 0).then_some(digits + 1)
+    }
+
+    fn mention(text: &str) -> Option<usize> {
+        let name = text[1..].chars().take_while(|c| c.is_alphanumeric()).map(char::len_utf8).sum::<usize>();
+        (name > 0).then_some(name + 1)
+    }
+
+    #[test]
+    fn test_inline_extensions() {
+        let options = ParserOptions{
+            inline_extensions: vec!(
+                InlineExtension{ name: "issue".to_string(), delimiter: '#', parse: issue },
+                InlineExtension{ name: "mention".to_string(), delimiter: '@', parse: mention },
+            ),
+            ..Default::default()
+        };
+        let issue = Word::Custom("issue".to_string(), "#123".to_string());
+        let mention = Word::Custom("mention".to_string(), "@hitsan".to_string());
+        let token = Md::Sentence(words!(normal_word!("See "), issue, normal_word!(", "), Word::Bold(words!(mention))));
+        let rest = "";
+        assert_eq!(sentence("See #123, **@hitsan**", &options), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(normal_word!("mail a@b.c or # and @")));
+        assert_eq!(sentence("mail a@b.c or # and @", &options), Some(ParsedResult{token, rest}));
+        assert_eq!(
+            sentence("See #123", &ParserOptions::default()),
+            Some(ParsedResult{ token: Md::Sentence(words!(normal_word!("See #123"))), rest })
+        );
+    }
+
+    #[test]
+    fn test_autolinks() {
+        let options = ParserOptions::gfm();
+        let link = |url: &'static str, href: &str| Word::Link(words!(normal_word!(url)), href.to_string());
+        let token = Md::Sentence(words!(
+            normal_word!("See "),
+            link("https://example.com/a_(b)", "https://example.com/a_(b)"),
+            normal_word!(", "),
+            link("www.rust-lang.org", "http://www.rust-lang.org"),
+            normal_word!(". "),
+            link("http://x.y", "http://x.y")
+        ));
+        let rest = "";
+        assert_eq!(sentence("See https://example.com/a_(b), www.rust-lang.org. <http://x.y>", &options), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(normal_word!("("), link("https://a.b", "https://a.b"), normal_word!(")")));
+        assert_eq!(sentence("(https://a.b)", &options), Some(ParsedResult{token, rest}));
+
+        let token = Md::Sentence(words!(normal_word!("xwww.a.b www. <a>")));
+        assert_eq!(sentence("xwww.a.b www. <a>", &options), Some(ParsedResult{token, rest}));
+        let token = Md::Sentence(words!(normal_word!("www.a.b")));
+        assert_eq!(sentence("www.a.b", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+}
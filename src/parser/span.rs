@@ -0,0 +1,434 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+
+// Where a node sits in the input as given: its byte range, and the 1-based line and
+// column of its first character.
+// Ordered by where the node starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceSpan {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeRef<'a> {
+    Md(&'a Md<'a>),
+    Item(&'a Item<'a>),
+    Record(&'a Record<'a>),
+    Cell(&'a Words<'a>),
+    Word(&'a Word<'a>),
+}
+
+// A node with its span and its children in source order: the words of a heading or sentence,
+// the items of a list, the records of a table and their cells, or the blocks in a container.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<'a> {
+    pub node: NodeRef<'a>,
+    pub span: SourceSpan,
+    pub children: Vec<Spanned<'a>>,
+}
+
+impl Spanned<'_> {
+    // The innermost node whose span contains the byte offset.
+    pub fn find(&self, offset: usize) -> Option<&Spanned<'_>> {
+        if offset < self.span.start || offset >= self.span.end { return None }
+        self.children.iter().find_map(|child| child.find(offset)).or(Some(self))
+    }
+}
+
+// Ranges in normalized text until the end, when they are mapped back to the input.
+struct Located<'a> {
+    node: NodeRef<'a>,
+    range: (usize, usize),
+    children: Vec<Located<'a>>,
+}
+
+// Finds nodes in the normalized text in document order; a marker that cannot be found
+// leaves an empty range at the cursor rather than failing.
+struct Locator<'t> {
+    text: &'t str,
+    cursor: usize,
+}
+
+impl Locator<'_> {
+    fn find_any(&mut self, needles: &[&str]) -> (usize, usize) {
+        let rest = &self.text[self.cursor..];
+        let found = needles.iter()
+            .filter_map(|needle| rest.find(needle).map(|index| (index, needle.len())))
+            .min_by_key(|(index, len)| (*index, usize::MAX - len));
+        let Some((index, len)) = found else { return (self.cursor, self.cursor) };
+        let start = self.cursor + index;
+        self.cursor = start + len;
+        (start, self.cursor)
+    }
+
+    fn find(&mut self, needle: &str) -> (usize, usize) {
+        self.find_any(&[needle])
+    }
+
+    // Text the parser joined from several lines, found one line at a time since markers like
+    // `>` or indentation sit between them in the source.
+    fn find_lines(&mut self, text: &str) -> (usize, usize) {
+        let mut lines = text.split('\n');
+        let (start, mut end) = self.find(lines.next().unwrap_or(""));
+        for line in lines {
+            end = self.find(line).1;
+        }
+        (start, end)
+    }
+
+    // Like `find`, but only on the line after the cursor's, for closing fences.
+    fn find_next_line(&mut self, needle: &str) -> Option<(usize, usize)> {
+        let rest = &self.text[self.cursor..];
+        let line_end = rest.match_indices('\n').nth(1).map_or(rest.len(), |(index, _)| index);
+        let index = rest[..line_end].find(needle)?;
+        Some(self.find(&rest[index..index + needle.len()]))
+    }
+
+    // Skips the rest of the cursor's line and the next one, like a table's alignment row.
+    fn skip_line(&mut self) {
+        let rest = &self.text[self.cursor..];
+        self.cursor += rest.match_indices('\n').nth(1).map_or(rest.len(), |(index, _)| index);
+    }
+
+    // Where the content of the line holding `offset` starts, after indentation and quote markers.
+    fn line_start(&self, offset: usize) -> usize {
+        let start = self.text[..offset].rfind('\n').map_or(0, |index| index + 1);
+        let line = &self.text[start..offset];
+        start + line.len() - line.trim_start_matches(|c: char| c.is_whitespace() || c == '>').len()
+    }
+
+    fn located<'a>(&self, node: NodeRef<'a>, start: usize, end: usize, children: Vec<Located<'a>>) -> Located<'a> {
+        Located{ node, range: (start.min(end), end), children }
+    }
+
+    fn word<'a>(&mut self, word: &'a Word) -> Located<'a> {
+        let (markers, words): (&[&str], _) = match word {
+            Word::Normal(text) => {
+                let (start, end) = self.find_lines(text);
+                return self.located(NodeRef::Word(word), start, end, vec!())
+            },
+            Word::Kbd(key) => {
+                let (start, _) = self.find("[[");
+                self.find(key);
+                let (_, end) = self.find("]]");
+                return self.located(NodeRef::Word(word), start, end, vec!())
+            },
+            Word::Custom(_, source) => {
+                let (start, end) = self.find(source);
+                return self.located(NodeRef::Word(word), start, end, vec!())
+            },
+            Word::Emoji(name) => {
+                let (start, end) = self.find(&format!(":{}:", name));
+                return self.located(NodeRef::Word(word), start, end, vec!())
+            },
+            Word::FootnoteRef(label) => {
+                let (start, end) = self.find(&format!("[^{}]", label));
+                return self.located(NodeRef::Word(word), start, end, vec!())
+            },
+            Word::Entity(reference, _) => {
+                let (start, end) = self.find(reference);
+                return self.located(NodeRef::Word(word), start, end, vec!())
+            },
+            Word::Task(_) => {
+                let (start, end) = self.find_any(&["[ ]", "[x]", "[X]"]);
+                return self.located(NodeRef::Word(word), start, end, vec!())
+            },
+            Word::Image(_, _, size) => {
+                let (start, _) = self.find("![");
+                self.find("](");
+                let (_, mut end) = self.find(")");
+                if *size != ImageSize::default() && self.text[self.cursor..].starts_with('{') {
+                    end = self.find("}").1;
+                }
+                return self.located(NodeRef::Word(word), start, end, vec!())
+            },
+            Word::Link(words, _) => {
+                // An autolink is the url itself, with no brackets before it.
+                if let [Word::Normal(url)] = words.0.as_slice() {
+                    let rest = &self.text[self.cursor..];
+                    if rest.find(url.as_ref()).is_some_and(|index| rest.find('[').is_none_or(|bracket| index < bracket)) {
+                        let children = self.words(words);
+                        let (start, end) = self.around(&children);
+                        return self.located(NodeRef::Word(word), start, end, children)
+                    }
+                }
+                let (start, _) = self.find("[");
+                let children = self.words(words);
+                self.find("](");
+                let (_, end) = self.find(")");
+                return self.located(NodeRef::Word(word), start, end, children)
+            },
+            Word::Italic(words) => (&["*", "_"], words),
+            Word::Bold(words) => (&["**", "__"], words),
+            Word::StrikeThough(words) => (&["~~", "~"], words),
+            Word::Underline(words) => (&["__"], words),
+            Word::Highlight(words) => (&["=="], words),
+            Word::Subscript(words) => (&["~"], words),
+            Word::Superscript(words) => (&["^"], words),
+            Word::Insert(words) => (&["++"], words),
+        };
+        let (start, open) = self.find_any(markers);
+        let children = self.words(words);
+        let text = self.text;
+        let (_, end) = self.find(&text[start..open]);
+        self.located(NodeRef::Word(word), start, end, children)
+    }
+
+    fn words<'a>(&mut self, words: &'a Words) -> Vec<Located<'a>> {
+        words.0.iter().map(|word| self.word(word)).collect()
+    }
+
+    // The range covering the children, or an empty one at the cursor without any.
+    fn around(&self, children: &[Located]) -> (usize, usize) {
+        let start = children.first().map_or(self.cursor, |child| child.range.0);
+        let end = children.last().map_or(self.cursor, |child| child.range.1);
+        (start, end)
+    }
+
+    fn cell<'a>(&mut self, words: &'a Words) -> Located<'a> {
+        let children = self.words(words);
+        let (start, end) = self.around(&children);
+        self.located(NodeRef::Cell(words), start, end, children)
+    }
+
+    fn record<'a>(&mut self, record: &'a Record) -> Located<'a> {
+        let (start, _) = self.find_any(&["|", "｜"]);
+        let children: Vec<Located> = record.0.iter().map(|words| self.cell(words)).collect();
+        let (_, end) = self.find_any(&["|", "｜"]);
+        self.located(NodeRef::Record(record), start, end, children)
+    }
+
+    fn records<'a>(&mut self, header: &'a Record, rows: &'a [Record], align: bool) -> Vec<Located<'a>> {
+        let mut located = vec!(self.record(header));
+        if align { self.skip_line() }
+        located.extend(rows.iter().map(|record| self.record(record)));
+        located
+    }
+
+    fn blocks<'a>(&mut self, mds: &'a [Md]) -> Vec<Located<'a>> {
+        mds.iter().map(|md| self.md(md)).collect()
+    }
+
+    fn item<'a>(&mut self, item: &'a Item) -> Located<'a> {
+        let mut children = self.words(&item.0);
+        children.extend(self.blocks(&item.1));
+        let (start, end) = self.around(&children);
+        self.located(NodeRef::Item(item), self.line_start(start), end, children)
+    }
+
+    fn md<'a>(&mut self, md: &'a Md) -> Located<'a> {
+        let node = NodeRef::Md(md);
+        let (start, end, children) = match md {
+            Md::Heading(_, words) => {
+                let (start, _) = self.find("#");
+                let children = self.words(words);
+                (start, self.around(&children).1, children)
+            },
+            Md::Sentence(words) => {
+                let children = self.words(words);
+                let (start, end) = self.around(&children);
+                (start, end, children)
+            },
+            Md::Footnote(label, words) => {
+                let (start, _) = self.find(&format!("[^{}]:", label));
+                let children = self.words(words);
+                (start, self.around(&children).1, children)
+            },
+            Md::Abbreviation(_, title) => {
+                let (start, _) = self.find("*[");
+                (start, self.find(title).1, vec!())
+            },
+            Md::Toc => {
+                let (start, end) = self.find_any(&["[TOC]", "[[_TOC_]]"]);
+                (start, end, vec!())
+            },
+            Md::Table(table) => {
+                let above = table.caption.as_ref().filter(|_| self.text[self.line_start(self.cursor)..].starts_with('['));
+                let mut children = above.map_or(vec!(), |caption| self.words(caption));
+                children.extend(self.records(&table.header, &table.records, true));
+                if above.is_none() {
+                    children.extend(table.caption.iter().flat_map(|caption| self.words(caption)));
+                }
+                let (start, end) = self.around(&children);
+                (start, end, children)
+            },
+            Md::LooseTable(table) => {
+                let align = matches!(table.issue, TableIssue::RowCells(..));
+                let children = self.records(&table.header, &table.rows, align);
+                let (start, end) = self.around(&children);
+                (start, end, children)
+            },
+            Md::List(items) => {
+                let children: Vec<Located> = items.0.iter().map(|item| self.item(item)).collect();
+                let (start, end) = self.around(&children);
+                (start, end, children)
+            },
+            Md::Container(_, mds) => {
+                let (start, _) = self.find(":::");
+                let children = self.blocks(mds);
+                let end = self.find_next_line(":::").map_or(self.around(&children).1, |(_, end)| end);
+                (start, end, children)
+            },
+            Md::Details(_, summary, mds) => {
+                let (start, _) = self.find("???");
+                let mut children = self.words(summary);
+                children.extend(self.blocks(mds));
+                (start, self.around(&children).1, children)
+            },
+            Md::Quote(mds) => {
+                let (start, _) = self.find(">");
+                let children = self.blocks(mds);
+                (start, self.around(&children).1, children)
+            },
+            Md::Code(_, code, _) => {
+                let (start, fence) = self.find_any(&["```", "~~~"]);
+                let fence = &self.text[start..fence];
+                let mut end = self.text[self.cursor..].find('\n').map_or(self.text.len(), |index| self.cursor + index);
+                if !code.is_empty() {
+                    end = self.find_lines(code).1;
+                }
+                let end = self.find_next_line(fence).map_or(end, |(_, end)| end);
+                (start, end, vec!())
+            },
+            Md::FrontMatter(format, matter) => {
+                let (start, _) = self.find(format.fence());
+                self.find_lines(matter);
+                (start, self.find(format.fence()).1, vec!())
+            },
+            Md::Raw(text) => {
+                let (start, mut end) = self.find("<!--");
+                if !text.is_empty() { end = self.find_lines(text).1 }
+                let end = self.find_next_line("<!--").map_or(end, |_| self.find("-->").1);
+                (start, end, vec!())
+            },
+            Md::Custom(_, source) => {
+                let (start, end) = self.find_lines(source);
+                (start, end, vec!())
+            },
+        };
+        self.located(node, self.line_start(start), end, children)
+    }
+}
+
+// Maps ranges in the normalized text back to the input, line by line; where tabs were
+// expanded or zero-width characters stripped, offsets past them are approximate.
+struct Mapper<'t> {
+    text: &'t str,
+    input: &'t str,
+    starts: Vec<usize>,
+    lines: Vec<(usize, usize)>,
+}
+
+impl Mapper<'_> {
+    fn offset(&self, offset: usize) -> (usize, usize, usize) {
+        let line = self.starts.partition_point(|start| *start <= offset) - 1;
+        let (raw_start, raw_end) = self.lines[line];
+        let mut raw = (raw_start + offset - self.starts[line]).min(raw_end);
+        while !self.input.is_char_boundary(raw) { raw -= 1 }
+        let column = self.text[self.starts[line]..offset].chars().count() + 1;
+        (raw, line + 1, column)
+    }
+
+    fn spanned<'a>(&self, located: Located<'a>) -> Spanned<'a> {
+        let (start, line, column) = self.offset(located.range.0);
+        let (end, _, _) = self.offset(located.range.1);
+        let children = located.children.into_iter().map(|child| self.spanned(child)).collect();
+        Spanned{ node: located.node, span: SourceSpan{ start, end, line, column }, children }
+    }
+}
+
+// The spans of `mds`, which must be what `parse_with(text, options)` returned. Top-level
+// blocks are exact; nested nodes are found by searching the block's source in order.
+pub fn spans<'a>(text: &str, mds: &'a [Md], options: &ParserOptions) -> Vec<Spanned<'a>> {
+    let lines = raw_lines(text);
+    let normalized = normalize(text, options);
+    let (_, offsets) = blocks_with_offsets(&normalized, options);
+    let starts = std::iter::once(0).chain(normalized.match_indices('\n').map(|(index, _)| index + 1)).collect();
+    let mapper = Mapper{ text: &normalized, input: text, starts, lines };
+    let mut locator = Locator{ text: &normalized, cursor: 0 };
+    mds.iter().zip(offsets).map(|(md, (start, end))| {
+        locator.cursor = start;
+        let mut located = locator.md(md);
+        located.range = (start, end);
+        mapper.spanned(located)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normal_word;
+
+    fn source<'t>(text: &'t str, spanned: &Spanned) -> &'t str {
+        &text[spanned.span.start..spanned.span.end]
+    }
+
+    #[test]
+    fn test_spans() {
+        let text = "# Title\n\nSome **bold** and [a link](https://example.com).";
+        let options = ParserOptions::default();
+        let mds = parse_with(text, &options);
+        let spans = spans(text, &mds, &options);
+        assert_eq!(spans.len(), mds.len());
+        assert_eq!(spans[0].span, SourceSpan{ start: 0, end: 7, line: 1, column: 1 });
+        assert_eq!(source(text, &spans[0].children[0]), "Title");
+
+        let sentence = spans.last().unwrap();
+        assert_eq!((sentence.span.line, sentence.span.column), (3, 1));
+        let words: Vec<&str> = sentence.children.iter().map(|child| source(text, child)).collect();
+        assert_eq!(words, vec!("Some ", "**bold**", " and ", "[a link](https://example.com)", "."));
+        assert_eq!(source(text, &sentence.children[1].children[0]), "bold");
+
+        let bold = sentence.find(text.find("bold").unwrap()).unwrap();
+        assert_eq!(bold.node, NodeRef::Word(&normal_word!("bold")));
+    }
+
+    #[test]
+    fn test_single_tilde_spans() {
+        let options = ParserOptions::gfm();
+        let text = "a ~b~ ~~c~~";
+        let mds = parse_with(text, &options);
+        let spans = spans(text, &mds, &options);
+        let ranges: Vec<(usize, usize)> = spans[0].children.iter().map(|child| (child.span.start, child.span.end)).collect();
+        assert_eq!(ranges, vec!((0, 2), (2, 5), (5, 6), (6, 11)));
+    }
+
+    #[test]
+    fn test_nested_spans() {
+        let text = "> - item\n>   with ~~strike~~\n\n| A | B |\n| - | - |\n| a | b |";
+        let options = ParserOptions::default();
+        let mds = parse_with(text, &options);
+        let spans = spans(text, &mds, &options);
+        let item = &spans[0].children[0].children[0];
+        assert!(matches!(item.node, NodeRef::Item(_)));
+        assert_eq!(source(text, item), "- item\n>   with ~~strike~~");
+        assert_eq!(source(text, &item.children[0]), "item\n>   with ");
+
+        let table = spans.last().unwrap();
+        let records: Vec<&str> = table.children.iter().map(|child| source(text, child)).collect();
+        assert_eq!(records, vec!("| A | B |", "| a | b |"));
+        let cell = &table.children[1].children[1];
+        assert_eq!((source(text, cell), cell.span.line, cell.span.column), ("b", 6, 7));
+    }
+
+    #[test]
+    fn test_spans_in_raw_input() {
+        let text = "\u{FEFF}Intro\r\n```\r\ncode\r\n```\r\nEnd";
+        let options = ParserOptions::default();
+        let mds = parse_with(text, &options);
+        let spans = spans(text, &mds, &options);
+        let sources: Vec<&str> = spans.iter().map(|spanned| source(text, spanned)).collect();
+        assert_eq!(sources, vec!("Intro", "```\r\ncode\r\n```", "End"));
+        assert_eq!((spans[2].span.line, spans[2].span.column), (5, 1));
+
+        let text = "<!-- md:off -->\n<b>*x*</b>\n<!-- md:on -->\nEnd";
+        let mds = parse_with(text, &options);
+        let sources: Vec<&str> = super::spans(text, &mds, &options).iter().map(|spanned| source(text, spanned)).collect();
+        assert_eq!(sources, vec!("<!-- md:off -->\n<b>*x*</b>\n<!-- md:on -->", "End"));
+    }
+}
@@ -1,88 +1,248 @@
 use crate::parser::parser::*;
+use super::options::ParserOptions;
 use super::sentence::words;
 use std::collections::HashSet;
+use memchr::memchr2;
+
+fn normalize(text: &str) -> String {
+    text.trim().replace('｜', "|")
+}
 
 fn record<'a, T>(
     texts: &'a str,
     closure: &dyn Fn(&str)->T
 ) -> Option<ParsedResult<'a, Vec<T>>> {
     let (text, rest) = split_first_pattern(texts, "\n");
-    let text = text.trim_end();
-    if !text.starts_with("|") || !text.ends_with("|") { return None }
+    let text = normalize(text);
+    let inner = text.strip_prefix('|')?.strip_suffix('|')?;
+    if inner.ends_with('\\') { return None }
 
-    let end = text.len()-1;
-    let token: Vec<T> = text[1..end].split("|")
+    let token: Vec<T> = cells(inner).iter()
         .map(|text| closure(text.trim()))
         .collect::<Vec<_>>();
     Some(ParsedResult::new(token, rest))
 }
 
-fn header(texts: &str) -> Option<ParsedResult<Record>> {
+// `\|` is a literal pipe inside a cell rather than a column separator.
+fn cells(text: &str) -> Vec<String> {
+    let mut cells = vec!();
+    let mut cell = String::new();
+    let mut rest = text;
+    while let Some(i) = memchr2(b'|', b'\\', rest.as_bytes()) {
+        cell.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(after) = rest.strip_prefix("\\|") {
+            cell.push('|');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('|') {
+            cells.push(std::mem::take(&mut cell));
+            rest = after;
+        } else {
+            cell.push('\\');
+            rest = &rest[1..];
+        }
+    }
+    cell.push_str(rest);
+    cells.push(cell);
+    cells
+}
+
+fn header<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Record<'a>>> {
     let cells = record(
-        texts, &|txt| words(txt)
+        texts, &|txt| words(txt, options).into_owned()
     )?;
-    let record = Record(cells.token);
+    let record = Record(cells.token, vec!());
     Some(ParsedResult::new(record, cells.rest))
 }
 
-fn align(texts: &str, num: usize) -> Option<ParsedResult<Vec<Align>>> {
+fn align(texts: &str, num: usize) -> Option<ParsedResult<'_, Vec<Align>>> {
+    let (line, rest) = split_first_pattern(texts, "\n");
+    let mut line = normalize(line);
+    if !line.contains('|') { return None }
+    if !line.starts_with('|') { line.insert(0, '|') }
+    if !line.ends_with('|') || line.len() < 2 { line.push('|') }
     let result = record(
-        texts, &|text| align_parse(text.trim())
+        &line, &|text| align_parse(text.trim())
     )?;
     let aligns: Vec<Align> = result.token.into_iter()
-        .filter_map(|opt| opt)
+        .flatten()
         .collect();
     if aligns.len() != num { return None }
-    Some(ParsedResult::new(aligns, result.rest))
+    Some(ParsedResult::new(aligns, rest))
 }
 
+// A full-width `：` counts as a colon here, but is kept as written in cells.
 fn align_parse(text: &str) -> Option<Align> {
-    let l = text.starts_with(":");
-    let r = text.ends_with(":");
+    let text = &text.replace('：', ":");
+    if !text.contains('-') { return None }
     let is_only_hyphen = |text: &str| {
         let chars: HashSet<char> = text.chars().collect();
         chars.len() == 1 && chars.contains(&'-')
     };
-    match (l, r) {
-        (false, false) if is_only_hyphen(&text)                        => Some(Align::Left),
-        (false, true)  if is_only_hyphen(&text[..text.len()-1])  => Some(Align::Right),
-        (true, false)  if is_only_hyphen(&text[1..])             => Some(Align::Left),
-        (true, true)   if is_only_hyphen(&text[1..text.len()-1]) => Some(Align::Center),
+    let left = text.strip_prefix(':');
+    let right = text.strip_suffix(':');
+    match (left, right) {
+        (None, None) if is_only_hyphen(text)                 => Some(Align::Left),
+        (None, Some(inner)) if is_only_hyphen(inner)         => Some(Align::Right),
+        (Some(inner), None) if is_only_hyphen(inner)         => Some(Align::Left),
+        (Some(inner), Some(_))
+            if inner.strip_suffix(':').is_some_and(is_only_hyphen) => Some(Align::Center),
+        _ if text.chars().all(|c| "-: ".contains(c)) => Some(Align::Left),
         _ => None,
     }
 }
 
-fn records(mut texts: &str, n: usize) -> Option<ParsedResult<Vec<Record>>> {
+fn records<'a>(mut texts: &'a str, n: usize, options: &ParserOptions) -> Option<ParsedResult<'a, Vec<Record<'a>>>> {
     let mut records:Vec<Record> = vec!();
-    while let Some(result) = record(texts, &|text| words(text)) 
+    while let Some(result) = record(texts, &|text| words(text, options).into_owned()) 
     {
+        let mut cells = result.token;
+        if cells.len()!=n {
+            if !options.ragged_rows { break; }
+            cells.resize_with(n, || words("", options));
+        }
         texts = result.rest;
-        let cells = result.token;
-        if cells.len()!=n { break; }
-        let record = Record(cells);
+        let record = Record(cells, vec!());
         records.push(record);
     }
-    if records.is_empty() { return None }
     Some(ParsedResult::new(records, texts))
 }
 fn record_len(record: &Record) -> usize {
     match record {
-        Record(r) => r.len()
+        Record(r, _) => r.len()
+    }
+}
+
+fn align_issue(texts: &str, num: usize) -> Option<TableIssue> {
+    let result = record(texts, &|text| align_parse(text.trim()))?;
+    if result.token.iter().any(|align| align.is_none()) {
+        return Some(TableIssue::InvalidAlign)
+    }
+    let found = result.token.len();
+    if found != num { return Some(TableIssue::ColumnCount(num, found)) }
+    None
+}
+
+fn loose_table<'a>(
+    header: Record<'a>,
+    mut rows: Vec<Record<'a>>,
+    mut texts: &'a str,
+    issue: TableIssue,
+    options: &ParserOptions
+) -> ParsedResult<'a, Md<'a>> {
+    while let Some(result) = record(texts, &|text| words(text, options).into_owned()) {
+        rows.push(Record(result.token, vec!()));
+        texts = result.rest;
+    }
+    let token = Md::LooseTable(Box::new(LooseTable{header, rows, issue}));
+    ParsedResult::new(token, texts)
+}
+
+// The first body row in a table's source whose cell count differs from the header's, which
+// `ragged_rows` pads or cuts to fit.
+pub(super) fn ragged_row(source: &str) -> Option<TableIssue> {
+    let mut counts = source.lines().filter_map(|line| record(line, &|_| ()).map(|cells| cells.token.len()));
+    let header = counts.next()?;
+    counts.skip(1).enumerate()
+        .find(|(_, found)| *found != header)
+        .map(|(row, found)| TableIssue::RowCells(row, header, found))
+}
+
+fn is_cell(words: &Words, text: &str) -> bool {
+    matches!(words.0.as_slice(), [Word::Normal(cell)] if cell == text)
+}
+
+// Trailing empty cells widen the last filled cell, and a `^` cell extends the cell above.
+fn spans(records: &[Record], rowspan: bool) -> Vec<Vec<Span>> {
+    let mut spans: Vec<Vec<Span>> = vec!();
+    for (r, record) in records.iter().enumerate() {
+        let cells = &record.0;
+        let mut row = vec!(Span::Cell(1, 1); cells.len());
+        if let Some(last) = cells.iter().rposition(|words| !is_cell(words, "")) {
+            row[last] = Span::Cell(cells.len() - last, 1);
+            row[(last + 1)..].fill(Span::Merged);
+        }
+        for c in 0..cells.len() {
+            if !rowspan || !is_cell(&cells[c], "^") || row[c] == Span::Merged { continue }
+            let origin = (0..r).rev().find(|k| spans[*k].get(c) != Some(&Span::Merged));
+            if let Some(Span::Cell(_, rows)) = origin.and_then(|k| spans[k].get_mut(c)) {
+                *rows += 1;
+                row[c] = Span::Merged;
+            }
+        }
+        spans.push(row);
+    }
+    spans
+}
+
+fn apply_spans(records: &mut [Record], rowspan: bool) {
+    let spans = spans(records, rowspan);
+    if spans.iter().flatten().all(|span| span == &Span::Cell(1, 1)) { return }
+    for (record, spans) in records.iter_mut().zip(spans) {
+        record.1 = spans;
+    }
+}
+
+fn caption(texts: &str) -> Option<ParsedResult<'_, &str>> {
+    let (line, rest) = split_first_pattern(texts, "\n");
+    let line = line.trim();
+    let text = if let Some(text) = line.strip_prefix("Table:") {
+        text
+    } else {
+        let text = line.strip_prefix('[')?.strip_suffix(']')?;
+        if text.starts_with('[') || text == "TOC" { return None }
+        text
+    };
+    let text = text.trim();
+    if text.is_empty() { return None }
+    Some(ParsedResult::new(text, rest))
+}
+
+pub fn table<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    if let Some(above) = caption(texts) {
+        let mut result = table_body(above.rest, options)?;
+        if let Md::Table(table) = &mut result.token {
+            table.caption = Some(words(above.token, options));
+            return Some(result)
+        }
+        return None
+    }
+    let mut result = table_body(texts, options)?;
+    if let Md::Table(table) = &mut result.token {
+        if let Some(below) = caption(result.rest) {
+            table.caption = Some(words(below.token, options));
+            result.rest = below.rest;
+        }
     }
+    Some(result)
 }
 
-pub fn table(texts: &str) -> Option<ParsedResult<Md>> {
-    let header_result = header(texts)?;
+fn table_body<'a>(texts: &'a str, options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let header_result = header(texts, options)?;
     let header = header_result.token;
     let column_num = record_len(&header);
 
-    let align_result = align(header_result.rest, column_num)?;
+    let Some(align_result) = align(header_result.rest, column_num) else {
+        if !options.loose_tables { return None }
+        let issue = align_issue(header_result.rest, column_num)?;
+        return Some(loose_table(header, vec!(), header_result.rest, issue, options))
+    };
     let align = align_result.token;
 
-    let records_result = records(align_result.rest, column_num)?;
-    let records = records_result.token;
+    let records_result = records(align_result.rest, column_num, options)?;
+    let mut records = records_result.token;
+    // A row with the wrong cell count would otherwise end the table and leave the rest as text.
+    if let Some(next) = record(records_result.rest, &|_| ()).filter(|_| options.loose_tables) {
+        let issue = TableIssue::RowCells(records.len(), column_num, next.token.len());
+        return Some(loose_table(header, records, records_result.rest, issue, options))
+    }
+    let mut header = header;
+    if options.table_spans {
+        apply_spans(std::slice::from_mut(&mut header), false);
+        apply_spans(&mut records, true);
+    }
 
-    let token = Md::Table(Box::new(Table{header, align, records}));
+    let token = Md::Table(Box::new(Table{header, align, records, caption: None}));
     Some(ParsedResult::new(token, records_result.rest))
 }
 
@@ -98,24 +258,66 @@ mod tests {
         let c = words!(normal_word!("C"));
         let token = record!(a, b, c);
         let rest = "";
-        assert_eq!(header(&"| A | B | C | \n"), Some(ParsedResult{token, rest}));
+        assert_eq!(header("| A | B | C | \n", &ParserOptions::default()), Some(ParsedResult{token, rest}));
 
         let nul = words!(normal_word!(""));
         let b = words!(normal_word!("B"));
         let c = words!(normal_word!("C"));
         let token = record!(nul, b, c);
         let rest = "";
-        assert_eq!(header(&"|  | B | C |\n"), Some(ParsedResult{token, rest}));
-        assert_eq!(header(&"| A | B | C \n"), None);
+        assert_eq!(header("|  | B | C |\n", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+        assert_eq!(header("| A | B | C \n", &ParserOptions::default()), None);
+        assert_eq!(header("|\n", &ParserOptions::default()), None);
+    }
+
+    #[test]
+    fn test_escaped_pipe() {
+        let a = words!(normal_word!("a|b"));
+        let c = words!(normal_word!("c"));
+        let token = record!(a, c);
+        let rest = "";
+        assert_eq!(header("| a\\|b | c |\n", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+        assert_eq!(header("| a | b \\|\n", &ParserOptions::default()), None);
+
+        assert_eq!(cells(" x \\| y | z \\n"), vec!(" x | y ".to_string(), " z \\n".to_string()));
+    }
+
+    #[test]
+    fn test_header_unicode() {
+        let a = words!(normal_word!("A"));
+        let b = words!(normal_word!("B"));
+        let token = record!(a, b);
+        let rest = "";
+        assert_eq!(header("｜ A ｜ B ｜\n", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let a = words!(normal_word!("A"));
+        let b = words!(normal_word!("B"));
+        let token = record!(a, b);
+        let rest = "";
+        assert_eq!(header("\u{a0}|\u{a0}A\u{a0}| B\u{3000}|\u{a0}\n", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let token = record!(words!(normal_word!("時間：10")));
+        assert_eq!(header("| 時間：10 |\n", &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
 
     #[test]
     fn test_align() {
         let token = vec!(Align::Right, Align::Center, Align::Left, Align::Left);
         let rest = "";
-        assert_eq!(align(&"| -: | :-: | :- | --- |\n", 4), Some(ParsedResult{token, rest}));
-        assert_eq!(align(&"| -: | :-b: | :- | - |\n", 4), None);
-        assert_eq!(align(&"|  | :-: | :- | - |\n", 4), None);
+        assert_eq!(align("| -: | :-: | :- | --- |\n", 4), Some(ParsedResult{token, rest}));
+        assert_eq!(align("| -: | :-b: | :- | - |\n", 4), None);
+        assert_eq!(align("|  | :-: | :- | - |\n", 4), None);
+
+        let token = vec!(Align::Right, Align::Center);
+        let rest = "";
+        assert_eq!(align("｜\u{a0}-:\u{a0}｜ :-: ｜\n", 2), Some(ParsedResult{token, rest}));
+
+        let token = vec!(Align::Right, Align::Center);
+        let rest = "";
+        assert_eq!(align("| -： | ：-： |\n", 2), Some(ParsedResult{token, rest}));
+        assert_eq!(align("| -😀 | é- |\n", 2), None);
+        assert_eq!(align_parse(":"), None);
+        assert_eq!(align_parse("：😀："), None);
     }
 
     #[test]
@@ -134,7 +336,24 @@ mod tests {
         let record2 = record!(j, k, l);
         let token = vec!(record0, record1, record2);
         let rest = "";
-        assert_eq!(records(&"| A | B | C |\n| a | b | c |\n| j | k | l |\n", 3), Some(ParsedResult{token, rest}));
+        assert_eq!(records("| A | B | C |\n| a | b | c |\n| j | k | l |\n", 3, &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_ragged_records() {
+        let a = words!(normal_word!("a"));
+        let nul = words!(normal_word!(""));
+        let record0 = record!(a, nul);
+        let b = words!(normal_word!("b"));
+        let c = words!(normal_word!("c"));
+        let record1 = record!(b, c);
+        let token = vec!(record0, record1);
+        let rest = "End";
+        let options = ParserOptions{ ragged_rows: true, ..Default::default() };
+        assert_eq!(records("| a |\n| b | c | d |\nEnd", 2, &options), Some(ParsedResult{token, rest}));
+        let token = vec!();
+        let rest = "| a |\n| b | c | d |\nEnd";
+        assert_eq!(records("| a |\n| b | c | d |\nEnd", 2, &ParserOptions::default()), Some(ParsedResult{token, rest}));
     }
     #[test]
     fn test_table() {
@@ -154,8 +373,104 @@ mod tests {
         let record1 = record!(j, k, l);
         let records = vec!(record0, record1);
 
-        let token = Md::Table(Box::new(Table{header, align, records}));
+        let token = Md::Table(Box::new(Table{header, align, records, caption: None}));
+        let rest = "";
+        assert_eq!(table("| A | B | C | \n|-:|--|:-:|\n| a | b | c |\n| j | k | l |\n", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+    }
+
+    #[test]
+    fn test_spans() {
+        let options = ParserOptions{ table_spans: true, ..Default::default() };
+        let text = "| A | B | C |\n| - | - | - |\n| a | b | |\n| ^ | c | d |\n| ^ | e | f |";
+        let Some(ParsedResult{token: Md::Table(spanned), ..}) = table(text, &options) else { panic!("expected a table") };
+        assert_eq!(spanned.header.1, vec!());
+        assert_eq!(spanned.records[0].1, vec!(Span::Cell(1, 3), Span::Cell(2, 1), Span::Merged));
+        assert_eq!(spanned.records[1].1, vec!(Span::Merged, Span::Cell(1, 1), Span::Cell(1, 1)));
+        assert_eq!(spanned.records[2].1, vec!(Span::Merged, Span::Cell(1, 1), Span::Cell(1, 1)));
+
+        let Some(ParsedResult{token: Md::Table(plain), ..}) = table(text, &ParserOptions::default()) else { panic!("expected a table") };
+        assert!(plain.records.iter().all(|record| record.1.is_empty()));
+    }
+
+    #[test]
+    fn test_header_only_table() {
+        let header = record!(words!(normal_word!("A")), words!(normal_word!("B")));
+        let token = Md::Table(Box::new(Table{header, align: vec!(Align::Left, Align::Right), records: vec!(), caption: None}));
+        let rest = "End";
+        assert_eq!(table("| A | B |\n---|--:\nEnd", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        let header = record!(words!(normal_word!("A")), words!(normal_word!("B")));
+        let records = vec!(record!(words!(normal_word!("a")), words!(normal_word!("b"))));
+        let token = Md::Table(Box::new(Table{header, align: vec!(Align::Left, Align::Left), records, caption: None}));
         let rest = "";
-        assert_eq!(table(&"| A | B | C | \n|-:|--|:-:|\n| a | b | c |\n| j | k | l |\n"), Some(ParsedResult{token, rest}));
+        assert_eq!(table("| A | B |\n| -:- | - - |\n| a | b |", &ParserOptions::default()), Some(ParsedResult{token, rest}));
+
+        assert_eq!(table("| A |\n| : |", &ParserOptions::default()), None);
+        assert_eq!(table("| A |\n---", &ParserOptions::default()), None);
+    }
+
+    #[test]
+    fn test_caption() {
+        let table_with = |caption: &str| {
+            let header = record!(words!(normal_word!("A")));
+            let records = vec!(record!(words!(normal_word!("a"))));
+            let caption = Some(words!(normal_word!(caption)));
+            Md::Table(Box::new(Table{header, align: vec!(Align::Left), records, caption}))
+        };
+        let options = ParserOptions::default();
+
+        let token = table_with("Results");
+        let rest = "End";
+        assert_eq!(table("[Results]\n| A |\n| - |\n| a |\nEnd", &options), Some(ParsedResult{token, rest}));
+
+        let token = table_with("Results");
+        let rest = "End";
+        assert_eq!(table("| A |\n| - |\n| a |\nTable: Results\nEnd", &options), Some(ParsedResult{token, rest}));
+
+        assert_eq!(table("[Results]\nHello", &options), None);
+        assert_eq!(table("[TOC]\n| A |\n| - |\n| a |", &options), None);
+    }
+
+    #[test]
+    fn test_loose_table() {
+        let text = "| A | B |\n| - | -b |\n| a | b |\nEnd";
+        assert_eq!(table(text, &ParserOptions::default()), None);
+
+        let a = words!(normal_word!("A"));
+        let b = words!(normal_word!("B"));
+        let header = record!(a, b);
+        let align0 = words!(normal_word!("-"));
+        let align1 = words!(normal_word!("-b"));
+        let a = words!(normal_word!("a"));
+        let b = words!(normal_word!("b"));
+        let rows = vec!(record!(align0, align1), record!(a, b));
+        let issue = TableIssue::InvalidAlign;
+        let token = Md::LooseTable(Box::new(LooseTable{header, rows, issue}));
+        let rest = "End";
+        let options = ParserOptions{ loose_tables: true, ..Default::default() };
+        assert_eq!(table(text, &options), Some(ParsedResult{token, rest}));
+
+        let a = words!(normal_word!("A"));
+        let b = words!(normal_word!("B"));
+        let header = record!(a, b);
+        let align0 = words!(normal_word!("---"));
+        let a = words!(normal_word!("a"));
+        let rows = vec!(record!(align0), record!(a));
+        let issue = TableIssue::ColumnCount(2, 1);
+        let token = Md::LooseTable(Box::new(LooseTable{header, rows, issue}));
+        let rest = "";
+        assert_eq!(table("| A | B |\n| --- |\n| a |", &options), Some(ParsedResult{token, rest}));
+
+        assert_eq!(table("| A | B |\nEnd", &options), None);
+
+        let header = record!(words!(normal_word!("A")), words!(normal_word!("B")));
+        let row0 = record!(words!(normal_word!("a")), words!(normal_word!("b")));
+        let row1 = record!(words!(normal_word!("c")));
+        let row2 = record!(words!(normal_word!("d")), words!(normal_word!("e")));
+        let issue = TableIssue::RowCells(1, 2, 1);
+        let token = Md::LooseTable(Box::new(LooseTable{header, rows: vec!(row0, row1, row2), issue}));
+        let text = "| A | B |\n| - | - |\n| a | b |\n| c |\n| d | e |\nEnd";
+        assert_eq!(table(text, &options), Some(ParsedResult{token, rest: "End"}));
+        assert_eq!(TableIssue::RowCells(1, 2, 1).to_string(), "row 2 has 1 cells, header has 2");
     }
 }
\ No newline at end of file
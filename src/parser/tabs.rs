@@ -0,0 +1,56 @@
+use crate::parser::parser::*;
+use super::code::{fence, is_close};
+
+fn expand_line(line: &str, tab_width: usize) -> String {
+    let mut expanded = String::new();
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let width = tab_width - column % tab_width;
+            expanded.push_str(&" ".repeat(width));
+            column += width;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+pub fn expand_tabs(text: &str, tab_width: usize) -> String {
+    if tab_width == 0 || !text.contains('\t') { return text.to_string() }
+    let mut lines: Vec<String> = vec!();
+    let mut open: Option<(char, usize)> = None;
+    let mut rest = text;
+    loop {
+        let (line, next) = split_first_pattern(rest, "\n");
+        match open {
+            Some((c, n)) => {
+                if is_close(line, c, n) { open = None }
+                lines.push(line.to_string());
+            },
+            None => {
+                open = fence(line).map(|(c, n, _)| (c, n));
+                lines.push(expand_line(line, tab_width));
+            },
+        }
+        if next.is_empty() { break }
+        rest = next;
+    }
+    let mut expanded = lines.join("\n");
+    if text.ends_with('\n') { expanded.push('\n') }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("-\titem\n\t- child", 4), "-   item\n    - child");
+        assert_eq!(expand_tabs("| a\t| b |", 2), "| a | b |");
+        assert_eq!(expand_tabs("\ta", 0), "\ta");
+        assert_eq!(expand_tabs("```\n\tcode\n```\n\tafter\n", 4), "```\n\tcode\n```\n    after\n");
+    }
+}
@@ -0,0 +1,73 @@
+use crate::parser::parser::*;
+use crate::convert::text::words_to_text;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+// Numbers compare by value, anything else by its text; a number sorts before text.
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl<'a> Table<'a> {
+    /// Sorts the body rows by the plain text of a column, keeping equal rows in order. A row
+    /// without that column sorts as an empty cell. Cell spans move with their rows.
+    pub fn sort_by_column(&mut self, column: usize, order: SortOrder) {
+        let key = |record: &Record| record.0.get(column).map(words_to_text).unwrap_or_default();
+        let mut keyed: Vec<(String, Record<'a>)> = self.records.drain(..).map(|record| (key(&record), record)).collect();
+        keyed.sort_by(|(a, _), (b, _)| match order {
+            SortOrder::Ascending => compare_cells(a, b),
+            SortOrder::Descending => compare_cells(b, a),
+        });
+        self.records = keyed.into_iter().map(|(_, record)| record).collect();
+    }
+
+    /// Keeps the body rows for which `predicate` holds; the header always stays.
+    pub fn filter_rows(&mut self, predicate: impl FnMut(&Record<'a>) -> bool) {
+        self.records.retain(predicate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::format::to_markdown;
+
+    fn table(text: &str) -> Table<'_> {
+        match parse(text).remove(0) {
+            Md::Table(table) => *table,
+            md => panic!("expected a table, got {:?}", md),
+        }
+    }
+
+    fn rows(table: &Table) -> Vec<String> {
+        table.records.iter().map(|record| words_to_text(&record.0[0])).collect()
+    }
+
+    #[test]
+    fn test_sort_by_column() {
+        let mut sorted = table("| Name | Size |\n| - | - |\n| b | 10 |\n| a | 9 |\n| c | n/a |\n| d | 9 |");
+        sorted.sort_by_column(1, SortOrder::Ascending);
+        assert_eq!(rows(&sorted), vec!("a", "d", "b", "c"));
+        sorted.sort_by_column(0, SortOrder::Descending);
+        assert_eq!(rows(&sorted), vec!("d", "c", "b", "a"));
+        sorted.sort_by_column(5, SortOrder::Ascending);
+        assert_eq!(rows(&sorted), vec!("d", "c", "b", "a"));
+    }
+
+    #[test]
+    fn test_filter_rows() {
+        let mut filtered = table("| Name | Done |\n| - | - |\n| a | yes |\n| b | no |\n| c | yes |");
+        filtered.filter_rows(|record| words_to_text(&record.0[1]) == "yes");
+        assert_eq!(to_markdown(&[Md::Table(Box::new(filtered))]), "| Name | Done |\n| --- | --- |\n| a | yes |\n| c | yes |");
+    }
+}
@@ -0,0 +1,24 @@
+use crate::parser::parser::*;
+use super::options::ParserOptions;
+
+pub fn toc<'a>(texts: &'a str, _options: &ParserOptions) -> Option<ParsedResult<'a, Md<'a>>> {
+    let (line, rest) = split_first_pattern(texts, "\n");
+    match line.trim() {
+        "[TOC]" | "[[_TOC_]]" => Some(ParsedResult::new(Md::Toc, rest)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toc() {
+        let options = ParserOptions::default();
+        assert_eq!(toc("[TOC]\n# Title", &options), Some(ParsedResult{token: Md::Toc, rest: "# Title"}));
+        assert_eq!(toc(" [[_TOC_]] ", &options), Some(ParsedResult{token: Md::Toc, rest: ""}));
+        assert_eq!(toc("[TOC] here", &options), None);
+        assert_eq!(toc("[toc]", &options), None);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::parser::parser::*;
+
+// Hooks called for each node of a document, in source order. Every hook defaults to walking
+// the node's children, so an implementation overrides only the nodes it cares about and calls
+// the matching `visit_*` or `walk_*` function to keep descending. `'a` is the document's
+// lifetime, so a visitor can keep references into it.
+pub trait Visitor<'a> {
+    fn visit_md(&mut self, md: &'a Md) {
+        walk_md(self, md)
+    }
+
+    fn visit_heading(&mut self, _level: usize, words: &'a Words) {
+        self.visit_words(words)
+    }
+
+    fn visit_sentence(&mut self, words: &'a Words) {
+        self.visit_words(words)
+    }
+
+    fn visit_table(&mut self, table: &'a Table) {
+        walk_table(self, table)
+    }
+
+    fn visit_loose_table(&mut self, table: &'a LooseTable) {
+        walk_loose_table(self, table)
+    }
+
+    // The header and each body row of a table.
+    fn visit_record(&mut self, record: &'a Record) {
+        walk_record(self, record)
+    }
+
+    fn visit_table_cell(&mut self, words: &'a Words) {
+        self.visit_words(words)
+    }
+
+    fn visit_list(&mut self, items: &'a Items) {
+        walk_list(self, items)
+    }
+
+    fn visit_item(&mut self, item: &'a Item) {
+        walk_item(self, item)
+    }
+
+    fn visit_container(&mut self, _name: &'a str, mds: &'a [Md]) {
+        walk(self, mds)
+    }
+
+    fn visit_details(&mut self, _open: bool, summary: &'a Words, mds: &'a [Md]) {
+        self.visit_words(summary);
+        walk(self, mds)
+    }
+
+    fn visit_quote(&mut self, mds: &'a [Md]) {
+        walk(self, mds)
+    }
+
+    fn visit_footnote(&mut self, _label: &'a str, words: &'a Words) {
+        self.visit_words(words)
+    }
+
+    fn visit_code(&mut self, _lang: &'a str, _code: &'a str) {}
+
+    fn visit_abbreviation(&mut self, _abbr: &'a str, _title: &'a str) {}
+
+    fn visit_front_matter(&mut self, _matter: &'a str) {}
+
+    fn visit_raw(&mut self, _text: &'a str) {}
+
+    fn visit_custom_block(&mut self, _name: &'a str, _source: &'a str) {}
+
+    fn visit_toc(&mut self) {}
+
+    fn visit_words(&mut self, words: &'a Words) {
+        walk_words(self, words)
+    }
+
+    fn visit_word(&mut self, word: &'a Word) {
+        walk_word(self, word)
+    }
+
+    fn visit_text(&mut self, _text: &'a str) {}
+
+    fn visit_link(&mut self, words: &'a Words, _url: &'a str) {
+        self.visit_words(words)
+    }
+
+    fn visit_image(&mut self, _alt: &'a str, _src: &'a str, _size: &'a ImageSize) {}
+
+    fn visit_footnote_ref(&mut self, _label: &'a str) {}
+
+    fn visit_task(&mut self, _checked: bool) {}
+}
+
+pub fn walk<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, mds: &'a [Md]) {
+    mds.iter().for_each(|md| visitor.visit_md(md))
+}
+
+pub fn walk_md<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, md: &'a Md) {
+    match md {
+        Md::Heading(level, words) => visitor.visit_heading(*level, words),
+        Md::Sentence(words) => visitor.visit_sentence(words),
+        Md::Table(table) => visitor.visit_table(table),
+        Md::LooseTable(table) => visitor.visit_loose_table(table),
+        Md::List(items) => visitor.visit_list(items),
+        Md::Container(name, mds) => visitor.visit_container(name, mds),
+        Md::Details(open, summary, mds) => visitor.visit_details(*open, summary, mds),
+        Md::Toc => visitor.visit_toc(),
+        Md::Abbreviation(abbr, title) => visitor.visit_abbreviation(abbr, title),
+        Md::Footnote(label, words) => visitor.visit_footnote(label, words),
+        Md::Code(lang, code, _) => visitor.visit_code(lang, code),
+        Md::Quote(mds) => visitor.visit_quote(mds),
+        Md::FrontMatter(_, matter) => visitor.visit_front_matter(matter),
+        Md::Raw(text) => visitor.visit_raw(text),
+        Md::Custom(name, source) => visitor.visit_custom_block(name, source),
+    }
+}
+
+// The caption is visited after the rows.
+pub fn walk_table<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, table: &'a Table) {
+    visitor.visit_record(&table.header);
+    table.records.iter().for_each(|record| visitor.visit_record(record));
+    if let Some(caption) = &table.caption {
+        visitor.visit_words(caption);
+    }
+}
+
+pub fn walk_loose_table<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, table: &'a LooseTable) {
+    visitor.visit_record(&table.header);
+    table.rows.iter().for_each(|record| visitor.visit_record(record));
+}
+
+pub fn walk_record<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, record: &'a Record) {
+    record.0.iter().for_each(|words| visitor.visit_table_cell(words))
+}
+
+pub fn walk_list<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, items: &'a Items) {
+    items.0.iter().for_each(|item| visitor.visit_item(item))
+}
+
+pub fn walk_item<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, item: &'a Item) {
+    visitor.visit_words(&item.0);
+    walk(visitor, &item.1)
+}
+
+pub fn walk_words<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, words: &'a Words) {
+    words.0.iter().for_each(|word| visitor.visit_word(word))
+}
+
+pub fn walk_word<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, word: &'a Word) {
+    match word {
+        Word::Normal(text) => visitor.visit_text(text),
+        Word::Italic(words)
+        | Word::Bold(words)
+        | Word::StrikeThough(words)
+        | Word::Underline(words)
+        | Word::Highlight(words)
+        | Word::Subscript(words)
+        | Word::Superscript(words)
+        | Word::Insert(words) => visitor.visit_words(words),
+        Word::Kbd(key) | Word::Custom(_, key) => visitor.visit_text(key),
+        Word::Emoji(_) => {},
+        Word::FootnoteRef(label) => visitor.visit_footnote_ref(label),
+        Word::Task(checked) => visitor.visit_task(*checked),
+        Word::Entity(_, text) => visitor.visit_text(text),
+        Word::Link(words, url) => visitor.visit_link(words, url),
+        Word::Image(alt, src, size) => visitor.visit_image(alt, src, size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Links<'a>(Vec<&'a str>);
+
+    impl<'a> Visitor<'a> for Links<'a> {
+        fn visit_link(&mut self, words: &'a Words, url: &'a str) {
+            self.0.push(url);
+            self.visit_words(words)
+        }
+
+        fn visit_image(&mut self, _alt: &str, src: &'a str, _size: &ImageSize) {
+            self.0.push(src);
+        }
+    }
+
+    #[derive(Default)]
+    struct WordCount {
+        words: usize,
+        cells: usize,
+    }
+
+    impl Visitor<'_> for WordCount {
+        fn visit_text(&mut self, text: &str) {
+            self.words += text.split_whitespace().count();
+        }
+
+        fn visit_table_cell(&mut self, words: &Words) {
+            self.cells += 1;
+            self.visit_words(words)
+        }
+    }
+
+    #[test]
+    fn test_links() {
+        let mds = parse("See [the docs](https://a.example) and ![logo](logo.png)\n> - [**nested**](b.html)");
+        let mut links = Links::default();
+        walk(&mut links, &mds);
+        assert_eq!(links.0, vec!("https://a.example", "logo.png", "b.html"));
+    }
+
+    #[test]
+    fn test_word_count() {
+        let mds = parse("# Two words\n| A | B c |\n| - | - |\n| d | e |\n```\nnot counted\n```\n- *three more words*");
+        let mut count = WordCount::default();
+        walk(&mut count, &mds);
+        assert_eq!((count.words, count.cells), (10, 4));
+    }
+}
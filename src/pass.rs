@@ -0,0 +1,317 @@
+use crate::parser::parser::*;
+use crate::convert::convert::mds_to_html_with;
+use crate::convert::document::front_matter_fields;
+use crate::convert::options::{RenderError, RenderOptions};
+use std::collections::BTreeMap;
+
+// A transformation of the parsed document, run by `Pipeline` between parsing and rendering.
+// A pass keeps nothing about the document it ran on, so one value can be shared by several
+// pipelines, and a crate can ship passes as plain types implementing this. Closures taking
+// `&mut Vec<Md>` are passes too.
+pub trait Pass: Send + Sync {
+    fn run(&self, mds: &mut Vec<Md<'_>>);
+}
+
+impl<F: Fn(&mut Vec<Md<'_>>) + Send + Sync> Pass for F {
+    fn run(&self, mds: &mut Vec<Md<'_>>) {
+        self(mds)
+    }
+}
+
+fn words_mut<'a, F: FnMut(&mut Words<'a>)>(words: &mut Words<'a>, f: &mut F) {
+    f(words);
+    for word in &mut words.0 {
+        match word {
+            Word::Italic(words)
+            | Word::Bold(words)
+            | Word::StrikeThough(words)
+            | Word::Underline(words)
+            | Word::Highlight(words)
+            | Word::Subscript(words)
+            | Word::Superscript(words)
+            | Word::Insert(words)
+            | Word::Link(words, _) => words_mut(words, f),
+            Word::Normal(_) | Word::Kbd(_) | Word::Custom(..) | Word::Emoji(_) | Word::FootnoteRef(_) | Word::Task(_) | Word::Entity(..) | Word::Image(..) => {},
+        }
+    }
+}
+
+fn records_mut<'a, 'r, F: FnMut(&mut Words<'a>)>(records: impl Iterator<Item = &'r mut Record<'a>>, f: &mut F)
+where
+    'a: 'r,
+{
+    records.flat_map(|record| &mut record.0).for_each(|cell| words_mut(cell, f));
+}
+
+// Calls `f` on every run of words in the document, each run before the runs nested in it.
+pub fn for_each_words_mut<'a, F: FnMut(&mut Words<'a>)>(mds: &mut [Md<'a>], f: &mut F) {
+    for md in mds {
+        match md {
+            Md::Heading(_, words) | Md::Sentence(words) | Md::Footnote(_, words) => words_mut(words, f),
+            Md::Table(table) => {
+                let Table{ header, records, caption, .. } = table.as_mut();
+                records_mut(std::iter::once(header).chain(records), f);
+                if let Some(caption) = caption { words_mut(caption, f) }
+            },
+            Md::LooseTable(table) => {
+                let LooseTable{ header, rows, .. } = table.as_mut();
+                records_mut(std::iter::once(header).chain(rows), f);
+            },
+            Md::List(items) => for Item(words, mds) in &mut items.0 {
+                words_mut(words, f);
+                for_each_words_mut(mds, f);
+            },
+            Md::Details(_, summary, mds) => {
+                words_mut(summary, f);
+                for_each_words_mut(mds, f);
+            },
+            Md::Container(_, mds) | Md::Quote(mds) => for_each_words_mut(mds, f),
+            Md::Toc | Md::Abbreviation(..) | Md::Code(..) | Md::FrontMatter(..) | Md::Raw(_) | Md::Custom(..) => {},
+        }
+    }
+}
+
+// Calls `f` on every word of the document, each word before the words nested in it.
+pub fn for_each_word_mut<'a, F: FnMut(&mut Word<'a>)>(mds: &mut [Md<'a>], f: &mut F) {
+    for_each_words_mut(mds, &mut |words| words.0.iter_mut().for_each(&mut *f));
+}
+
+// Adds a `[TOC]` after the first top-level `#` heading, or at the top when there is none,
+// unless the document already has one.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct InsertToc;
+
+impl Pass for InsertToc {
+    fn run(&self, mds: &mut Vec<Md<'_>>) {
+        if mds.contains(&Md::Toc) { return }
+        let title = mds.iter().position(|md| matches!(md, Md::Heading(1, _))).map(|i| i + 1);
+        let top = usize::from(matches!(mds.first(), Some(Md::FrontMatter(..))));
+        mds.insert(title.unwrap_or(top), Md::Toc);
+    }
+}
+
+// Maps every link url and image source through the function, e.g. to move a site or
+// point at a cdn.
+#[derive(Debug, Clone, Copy)]
+pub struct RewriteLinks(pub fn(&str) -> String);
+
+impl Pass for RewriteLinks {
+    fn run(&self, mds: &mut Vec<Md<'_>>) {
+        for_each_word_mut(mds, &mut |word| match word {
+            Word::Link(_, url) | Word::Image(_, url, _) => *url = (self.0)(url),
+            _ => {},
+        });
+    }
+}
+
+// Replaces the `:name:` shortcodes that have an emoji with the character itself, so every
+// output format shows it and not only html.
+#[cfg(feature = "emoji")]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ReplaceEmoji;
+
+#[cfg(feature = "emoji")]
+impl Pass for ReplaceEmoji {
+    fn run(&self, mds: &mut Vec<Md<'_>>) {
+        for_each_word_mut(mds, &mut |word| {
+            if let Word::Emoji(name) = word {
+                if let Some(emoji) = crate::emoji::shortcode(name) {
+                    *word = Word::Normal(emoji.into());
+                }
+            }
+        });
+    }
+}
+
+// Joins adjacent `Normal` words, which the parser leaves apart at some punctuation.
+fn merge_normal(words: &mut Words) {
+    let mut merged: Vec<Word> = Vec::with_capacity(words.0.len());
+    for word in std::mem::take(&mut words.0) {
+        match (merged.last_mut(), word) {
+            (Some(Word::Normal(last)), Word::Normal(text)) => last.to_mut().push_str(&text),
+            (_, word) => merged.push(word),
+        }
+    }
+    words.0 = merged;
+}
+
+// `text` with each `{{name}}` that has a value replaced, or `None` when nothing changed.
+fn fill<'v>(text: &str, value: &dyn Fn(&str) -> Option<&'v str>) -> Option<String> {
+    let (mut filled, mut rest, mut changed) = (String::new(), text, false);
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else { break };
+        filled.push_str(&rest[..start]);
+        match value(rest[start + 2..start + end].trim()) {
+            Some(value) => {
+                filled.push_str(value);
+                changed = true;
+            },
+            None => filled.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    changed.then(|| filled + rest)
+}
+
+// Replaces `{{name}}` placeholders in the text, link urls and image sources with the value
+// from the map, or else the front matter field of that name, so one document can render
+// with different versions or dates. Unknown names and code are left as they are.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Substitute(pub BTreeMap<String, String>);
+
+impl Pass for Substitute {
+    fn run(&self, mds: &mut Vec<Md<'_>>) {
+        let fields = front_matter_fields(mds);
+        let value = |name: &str| self.0.get(name).or_else(|| fields.iter().find(|(key, _)| key == name).map(|(_, value)| value))
+            .map(String::as_str);
+        for_each_words_mut(mds, &mut |words| {
+            if !words.0.iter().any(|word| matches!(word, Word::Normal(text) if text.contains("{{"))) { return }
+            // The parser may have split a placeholder, e.g. at the `_` of `{{release_date}}`.
+            merge_normal(words);
+            for word in &mut words.0 {
+                if let Word::Normal(text) = word {
+                    if let Some(filled) = fill(text, &value) { *text = filled.into() }
+                }
+            }
+        });
+        for_each_word_mut(mds, &mut |word| match word {
+            Word::Link(_, url) | Word::Image(_, url, _) => if let Some(filled) = fill(url, &value) { *url = filled },
+            _ => {},
+        });
+    }
+}
+
+// The byte range of the first case-insensitive match of `query` in `text`.
+fn find_ignore_case(text: &str, query: &str) -> Option<(usize, usize)> {
+    text.char_indices().find_map(|(start, _)| {
+        let mut rest = text[start..].chars();
+        let mut end = start;
+        for q in query.chars() {
+            let c = rest.next()?;
+            if !c.to_lowercase().eq(q.to_lowercase()) { return None }
+            end += c.len_utf8();
+        }
+        Some((start, end))
+    })
+}
+
+// Wraps each case-insensitive match of the query in text with a highlight, rendered as
+// `<mark>`, keeping the formatting around it, as for a search results page. Code, link urls
+// and image alt text are not searched.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MarkMatches(pub String);
+
+impl Pass for MarkMatches {
+    fn run(&self, mds: &mut Vec<Md<'_>>) {
+        if self.0.is_empty() { return }
+        for_each_words_mut(mds, &mut |words| {
+            // The inside of a highlight this pass made, which is visited next.
+            if let [Word::Normal(text)] = words.0.as_slice() {
+                if find_ignore_case(text, &self.0) == Some((0, text.len())) { return }
+            }
+            if !words.0.iter().any(|word| matches!(word, Word::Normal(text) if find_ignore_case(text, &self.0).is_some())) {
+                return
+            }
+            merge_normal(words);
+            let mut marked = Vec::with_capacity(words.0.len());
+            for word in std::mem::take(&mut words.0) {
+                let Word::Normal(text) = word else {
+                    marked.push(word);
+                    continue
+                };
+                let mut rest: &str = &text;
+                while let Some((start, end)) = find_ignore_case(rest, &self.0) {
+                    if start > 0 { marked.push(Word::Normal(rest[..start].to_string().into())) }
+                    marked.push(Word::Highlight(Words(vec!(Word::Normal(rest[start..end].to_string().into())))));
+                    rest = &rest[end..];
+                }
+                if !rest.is_empty() { marked.push(Word::Normal(rest.to_string().into())) }
+            }
+            words.0 = marked;
+        });
+    }
+}
+
+// Renders the document with the matches of `query` marked, leaving `mds` as it is.
+pub fn mds_to_html_with_matches(mds: &[Md], query: &str, options: &RenderOptions) -> Result<String, RenderError> {
+    let mut marked = mds.to_vec();
+    MarkMatches(query.to_string()).run(&mut marked);
+    mds_to_html_with(&marked, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{words, normal_word};
+
+    #[test]
+    fn test_insert_toc() {
+        let mut mds = parse("Intro\n# Title\nText");
+        InsertToc.run(&mut mds);
+        assert_eq!(mds[2], Md::Toc);
+        InsertToc.run(&mut mds);
+        assert_eq!(mds.iter().filter(|md| **md == Md::Toc).count(), 1);
+
+        let mut mds = parse("---\ntitle: a\n---\nText");
+        InsertToc.run(&mut mds);
+        assert_eq!(mds[1], Md::Toc);
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let mut mds = parse("> - [a](/x) and **![b](/y.png)**");
+        RewriteLinks(|url| format!("https://cdn.example.com{}", url)).run(&mut mds);
+        let html = crate::mds_to_html(&mds);
+        assert!(html.contains("href=\"https://cdn.example.com/x\""));
+        assert!(html.contains("src=\"https://cdn.example.com/y.png\""));
+    }
+
+    #[test]
+    fn test_closure_pass() {
+        let upper = |mds: &mut Vec<Md<'_>>| for_each_word_mut(mds, &mut |word| {
+            if let Word::Normal(text) = word { *text = text.to_uppercase().into() }
+        });
+        let mut mds = parse("*hi* there");
+        upper.run(&mut mds);
+        assert_eq!(mds, vec!(Md::Sentence(words!(Word::Italic(words!(normal_word!("HI"))), normal_word!(" THERE")))));
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut mds = parse("---\nversion: 1.0\nname: app\n---\n# {{name}} {{ version }}\n\
+            Released {{release_date}}, see [notes](/v{{version}}.html) and {{unknown}}.\n```\n{{version}}\n```");
+        let values = BTreeMap::from([("version".to_string(), "2.0".to_string()), ("release_date".to_string(), "today".to_string())]);
+        Substitute(values).run(&mut mds);
+        assert_eq!(mds[1], Md::Heading(1, words!(normal_word!("app 2.0"))));
+        assert_eq!(mds[2], Md::Sentence(words!(
+            normal_word!("Released today, see "),
+            Word::Link(words!(normal_word!("notes")), "/v2.0.html".to_string()),
+            normal_word!(" and {{unknown}}.")
+        )));
+        assert_eq!(mds[3], Md::Code("".to_string(), "{{version}}".to_string(), vec!()));
+    }
+
+    #[cfg(feature = "emoji")]
+    #[test]
+    fn test_replace_emoji() {
+        let mut mds = parse(":wave: :nope:");
+        ReplaceEmoji.run(&mut mds);
+        assert_eq!(mds, vec!(Md::Sentence(words!(normal_word!("👋"), normal_word!(" "), Word::Emoji("nope".to_string())))));
+    }
+
+    #[test]
+    fn test_mark_matches() {
+        let mds = parse("# Rust search\nSearch *in **nested search** text* and [a SEARCH link](/search)\n```\nsearch\n```");
+        let html = mds_to_html_with_matches(&mds, "search", &RenderOptions::default()).unwrap();
+        assert!(html.starts_with("<h1 id=\"rust-search\">Rust <mark>search</mark></h1>"), "{}", html);
+        assert!(html.contains("<mark>Search</mark> <i>in <b>nested <mark>search</mark></b> text</i>"), "{}", html);
+        assert!(html.contains("<a href=\"/search\">a <mark>SEARCH</mark> link</a>"), "{}", html);
+        assert!(html.ends_with("<pre><code>search</code></pre>"), "{}", html);
+        assert_eq!(find_ignore_case("Straße", "SSE"), None);
+        assert_eq!(find_ignore_case("Ünïcode", "ünï"), Some((0, 5)));
+
+        let mut unchanged = mds.clone();
+        MarkMatches("".to_string()).run(&mut unchanged);
+        assert_eq!(unchanged, mds);
+    }
+}
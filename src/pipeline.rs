@@ -0,0 +1,406 @@
+use crate::parser::parser::{parse_with, Item, Items, ListKind, Md, Word, Words};
+use crate::parser::options::ParserOptions;
+use crate::convert::convert::mds_to_html_with;
+use crate::convert::options::{RenderError, RenderOptions};
+use crate::convert::document::{front_matter_fields, title};
+use crate::pass::Pass;
+use crate::include::{expand_file, IncludeError};
+use crate::cache::{cache_key, RenderCache};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+const INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+pub enum PipelineError {
+    Io(io::Error),
+    Render(RenderError),
+    Include(IncludeError),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineError::Io(err) => write!(f, "{}", err),
+            PipelineError::Render(err) => write!(f, "{}", err),
+            PipelineError::Include(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+impl From<io::Error> for PipelineError {
+    fn from(err: io::Error) -> Self {
+        PipelineError::Io(err)
+    }
+}
+
+impl From<RenderError> for PipelineError {
+    fn from(err: RenderError) -> Self {
+        PipelineError::Render(err)
+    }
+}
+
+impl From<IncludeError> for PipelineError {
+    fn from(err: IncludeError) -> Self {
+        PipelineError::Include(err)
+    }
+}
+
+#[derive(Clone)]
+pub struct Pipeline {
+    options: RenderOptions,
+    parser: ParserOptions,
+    passes: Vec<Arc<dyn Pass>>,
+    include_root: Option<PathBuf>,
+    interval: Duration,
+    cache: Option<Arc<RenderCache>>,
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("options", &self.options)
+            .field("parser", &self.parser)
+            .field("passes", &self.passes.len())
+            .field("include_root", &self.include_root)
+            .field("interval", &self.interval)
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md")
+}
+
+// Every file under `dir`, relative to it, in sorted order.
+fn files(dir: &Path, relative: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir.join(relative))?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            files(dir, &path, found)?;
+        } else {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+// The index of a site: its pages as links, each followed by its date if it has one.
+fn index(pages: Vec<(Option<String>, String, PathBuf)>) -> Vec<Md<'static>> {
+    let items = pages.into_iter().map(|(date, title, path)| {
+        let href = path.to_string_lossy().replace('\\', "/");
+        let mut words = vec!(Word::Link(Words(vec!(Word::Normal(title.into()))), href));
+        words.extend(date.map(|date| Word::Normal(format!(" ({})", date).into())));
+        Item(Words(words), vec!())
+    })
+    .collect();
+    vec!(Md::Heading(1, Words(vec!(Word::Normal("Index".into())))), Md::List(Items(items, ListKind::default())))
+}
+
+impl Pipeline {
+    pub fn new(options: RenderOptions) -> Self {
+        Pipeline{ options, parser: ParserOptions::default(), passes: vec!(), include_root: None, interval: INTERVAL, cache: None }
+    }
+
+    pub fn parser_options(mut self, parser: ParserOptions) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    // Expands `<!-- include: path.md -->` lines in converted files, allowing files under `root`.
+    pub fn includes(mut self, root: impl Into<PathBuf>) -> Self {
+        self.include_root = Some(root.into());
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // Returns html from `cache` for documents rendered before with the same options, and
+    // stores the rest there. Passes aren't part of the key, so pipelines with different
+    // passes need caches of their own.
+    pub fn cache(mut self, cache: RenderCache) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    // Adds a pass run on every parsed document, after the ones added before it.
+    pub fn pass(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Arc::new(pass));
+        self
+    }
+
+    // The document with every pass run on it, for rendering to formats other than html.
+    pub fn parse<'a>(&self, text: &'a str) -> Vec<Md<'a>> {
+        let mut mds = parse_with(text, &self.parser);
+        self.passes.iter().for_each(|pass| pass.run(&mut mds));
+        mds
+    }
+
+    pub fn render(&self, text: &str) -> Result<String, RenderError> {
+        let Some(cache) = &self.cache else { return mds_to_html_with(&self.parse(text), &self.options) };
+        let key = cache_key(text, &format!("{:?}{:?}", self.options, self.parser));
+        if let Some(html) = cache.get(key) { return Ok(html) }
+        let html = mds_to_html_with(&self.parse(text), &self.options)?;
+        // A cache that can't be written to only costs the next render its speed.
+        cache.insert(key, &html).ok();
+        Ok(html)
+    }
+
+    pub fn convert(&self, path: &Path) -> Result<String, PipelineError> {
+        let text = match &self.include_root {
+            Some(root) => expand_file(path, root)?,
+            None => fs::read_to_string(path)?,
+        };
+        Ok(self.render(&text)?)
+    }
+
+    // Converts every `.md` file under `input` to an `.html` file at the same place under
+    // `output`, with links between the documents pointing at the html files. Returns the
+    // written paths.
+    pub fn convert_dir(&self, input: &Path, output: &Path) -> Result<Vec<PathBuf>, PipelineError> {
+        let mut options = self.options.clone();
+        options.html.md_links = true;
+        let pipeline = Pipeline{ options, ..self.clone() };
+        let mut files = vec!();
+        self::files(input, Path::new(""), &mut files)?;
+        let mut written = vec!();
+        for file in files.into_iter().filter(|file| is_markdown(file)) {
+            let html = pipeline.convert(&input.join(&file))?;
+            let path = output.join(file.with_extension("html"));
+            if let Some(parent) = path.parent() { fs::create_dir_all(parent)? }
+            fs::write(&path, html)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    // Builds a site from the `input` content directory: the markdown files are converted as
+    // by `convert_dir`, the other files are copied to the same place under `output`, and
+    // unless there is an `index.md`, an `index.html` lists the pages by their front matter
+    // `date`, newest first, then by title. Returns the written paths.
+    pub fn build_site(&self, input: &Path, output: &Path) -> Result<Vec<PathBuf>, PipelineError> {
+        let mut written = self.convert_dir(input, output)?;
+        let mut files = vec!();
+        self::files(input, Path::new(""), &mut files)?;
+        let mut pages = vec!();
+        for file in files {
+            if is_markdown(&file) {
+                let text = fs::read_to_string(input.join(&file))?;
+                let mds = self.parse(&text);
+                let date = front_matter_fields(&mds).into_iter().find(|(key, _)| key == "date").map(|(_, date)| date);
+                let title = title(&mds).unwrap_or_else(|| file.file_stem().unwrap_or_default().to_string_lossy().to_string());
+                pages.push((date, title, file.with_extension("html")));
+                continue
+            }
+            let path = output.join(&file);
+            if let Some(parent) = path.parent() { fs::create_dir_all(parent)? }
+            fs::copy(input.join(&file), &path)?;
+            written.push(path);
+        }
+        if pages.iter().any(|(_, _, path)| path == Path::new("index.html")) { return Ok(written) }
+        pages.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        let path = output.join("index.html");
+        fs::create_dir_all(output)?;
+        fs::write(&path, mds_to_html_with(&index(pages), &self.options)?)?;
+        written.push(path);
+        Ok(written)
+    }
+
+    // Converts every path once, then again whenever its mtime changes,
+    // until the callback breaks.
+    pub fn watch<P, F>(&self, paths: &[P], mut callback: F)
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path, Result<String, PipelineError>) -> ControlFlow<()>,
+    {
+        let mut watched: Vec<(PathBuf, Option<SystemTime>)> = paths.iter()
+            .map(|path| (path.as_ref().to_path_buf(), None))
+            .collect();
+        let mut first = true;
+        loop {
+            for (path, last) in watched.iter_mut() {
+                let now = modified(path);
+                if !first && now == *last { continue }
+                *last = now;
+                if callback(path, self.convert(path)).is_break() { return }
+            }
+            first = false;
+            sleep(self.interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pass::{InsertToc, RewriteLinks};
+    use crate::convert::options::HtmlOptions;
+    use std::fs::File;
+
+    fn temp_file(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("app-pipeline-{}-{}", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_convert() {
+        let path = temp_file("convert.md", "**Hello**");
+        let pipeline = Pipeline::new(RenderOptions::default());
+        assert_eq!(pipeline.convert(&path).unwrap(), "<b>Hello</b><br />".to_string());
+
+        let pipeline = Pipeline::new(RenderOptions{ max_bytes: Some(1), ..Default::default() });
+        assert!(matches!(pipeline.convert(&path), Err(PipelineError::Render(RenderError::TooManyBytes(1)))));
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(pipeline.convert(&path), Err(PipelineError::Io(_))));
+    }
+
+    #[test]
+    fn test_render() {
+        let options = RenderOptions{ document: Some(Default::default()), ..Default::default() };
+        let html = Pipeline::new(options).render("# Hi").unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Hi</title>"));
+
+        let text = "| A | B |\n| - |\n| a | b |";
+        let pipeline = Pipeline::new(RenderOptions::default());
+        assert!(!pipeline.render(text).unwrap().contains("<table"));
+        let pipeline = pipeline.parser_options(ParserOptions{ loose_tables: true, ..Default::default() });
+        assert!(pipeline.render(text).unwrap().contains("<table"));
+    }
+
+    #[test]
+    fn test_includes() {
+        let root = std::env::temp_dir().join(format!("app-pipeline-{}-includes", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("book.md"), "# Book\n<!-- include: chapter.md -->").unwrap();
+        fs::write(root.join("chapter.md"), "**Chapter**").unwrap();
+        let pipeline = Pipeline::new(RenderOptions::default());
+        assert_eq!(pipeline.convert(&root.join("book.md")).unwrap(), "<h1 id=\"book\">Book</h1>\n<!-- include: chapter.md --><br />");
+        let pipeline = pipeline.includes(&root);
+        assert_eq!(pipeline.convert(&root.join("book.md")).unwrap(), "<h1 id=\"book\">Book</h1>\n<b>Chapter</b><br />");
+        fs::write(root.join("chapter.md"), "<!-- include: book.md -->").unwrap();
+        assert!(matches!(pipeline.convert(&root.join("book.md")), Err(PipelineError::Include(IncludeError::Cycle(_)))));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_passes() {
+        let drop_code = |mds: &mut Vec<Md<'_>>| mds.retain(|md| !matches!(md, Md::Code(..)));
+        let pipeline = Pipeline::new(RenderOptions::default())
+            .pass(RewriteLinks(|url| url.replace("http:", "https:")))
+            .pass(drop_code)
+            .pass(InsertToc);
+        let html = pipeline.render("# Title
+```
+code
+```
+[a](http://a.com)").unwrap();
+        assert_eq!(html, "<h1 id=\"title\">Title</h1>\n<nav class=\"toc\">\n<ul>\n<li><a href=\"#title\">Title</a></li>\n</ul>\n</nav>\n<a href=\"https://a.com\">a</a><br />");
+        assert_eq!(pipeline.parse("Text")[0], Md::Toc);
+        assert!(format!("{:?}", pipeline).contains("passes: 3"));
+    }
+
+    #[test]
+    fn test_cache() {
+        let pipeline = Pipeline::new(RenderOptions::default()).cache(RenderCache::new());
+        assert_eq!(pipeline.render("**a**").unwrap(), "<b>a</b><br />");
+        let cache = pipeline.cache.clone().unwrap();
+        assert_eq!(cache.len(), 1);
+        let key = cache_key("**a**", &format!("{:?}{:?}", pipeline.options, pipeline.parser));
+        cache.insert(key, "cached").unwrap();
+        assert_eq!(pipeline.render("**a**").unwrap(), "cached");
+        assert_eq!(pipeline.clone().render("**a**").unwrap(), "cached");
+
+        let semantic = RenderOptions{ html: HtmlOptions{ semantic_tags: true, ..Default::default() }, ..Default::default() };
+        let pipeline = Pipeline{ options: semantic, ..pipeline };
+        assert_eq!(pipeline.render("**a**").unwrap(), "<strong>a</strong><br />");
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_convert_dir() {
+        let root = std::env::temp_dir().join(format!("app-pipeline-{}-dir", std::process::id()));
+        let (input, output) = (root.join("docs"), root.join("site"));
+        fs::create_dir_all(input.join("guide/deep")).unwrap();
+        fs::write(input.join("index.md"), "[Setup](guide/setup.md#install)").unwrap();
+        fs::write(input.join("guide/setup.md"), "[Home](../index.md)").unwrap();
+        fs::write(input.join("guide/deep/notes.md"), "Notes").unwrap();
+        fs::write(input.join("guide/image.png"), "").unwrap();
+
+        let written = Pipeline::new(RenderOptions::default()).convert_dir(&input, &output).unwrap();
+        assert_eq!(written, vec!(
+            output.join("guide/deep/notes.html"), output.join("guide/setup.html"), output.join("index.html"),
+        ));
+        assert_eq!(fs::read_to_string(output.join("index.html")).unwrap(), "<a href=\"guide/setup.html#install\">Setup</a><br />");
+        assert_eq!(fs::read_to_string(output.join("guide/setup.html")).unwrap(), "<a href=\"../index.html\">Home</a><br />");
+        assert!(!output.join("guide/image.png").exists());
+
+        assert!(matches!(Pipeline::new(RenderOptions::default()).convert_dir(&root.join("none"), &output), Err(PipelineError::Io(_))));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_build_site() {
+        let root = std::env::temp_dir().join(format!("app-pipeline-{}-site", std::process::id()));
+        let (input, output) = (root.join("content"), root.join("public"));
+        fs::create_dir_all(input.join("posts")).unwrap();
+        fs::write(input.join("about.md"), "# About").unwrap();
+        fs::write(input.join("posts/first.md"), "---\ntitle: First\ndate: 2024-01-05\n---\nHello").unwrap();
+        fs::write(input.join("posts/second.md"), "---\ndate: 2024-03-01\n---\n# Second\n[About](../about.md)").unwrap();
+        fs::write(input.join("posts/logo.png"), "png").unwrap();
+
+        let written = Pipeline::new(RenderOptions::default()).build_site(&input, &output).unwrap();
+        assert_eq!(written, vec!(
+            output.join("about.html"), output.join("posts/first.html"), output.join("posts/second.html"),
+            output.join("posts/logo.png"), output.join("index.html"),
+        ));
+        assert_eq!(fs::read_to_string(output.join("posts/logo.png")).unwrap(), "png");
+        assert!(fs::read_to_string(output.join("posts/second.html")).unwrap().contains("<a href=\"../about.html\">About</a>"));
+        assert_eq!(fs::read_to_string(output.join("index.html")).unwrap(), "<h1 id=\"index\">Index</h1>\n<ul>\n\
+            <li><a href=\"posts/second.html\">Second</a> (2024-03-01)</li>\n\
+            <li><a href=\"posts/first.html\">First</a> (2024-01-05)</li>\n\
+            <li><a href=\"about.html\">About</a></li>\n</ul>\n");
+
+        fs::write(input.join("index.md"), "# Home").unwrap();
+        Pipeline::new(RenderOptions::default()).build_site(&input, &output).unwrap();
+        assert_eq!(fs::read_to_string(output.join("index.html")).unwrap(), "<h1 id=\"home\">Home</h1>");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_watch() {
+        let path = temp_file("watch.md", "Hello");
+        let pipeline = Pipeline::new(RenderOptions::default()).interval(Duration::from_millis(10));
+        let mut htmls = vec!();
+        pipeline.watch(&[&path], |changed, html| {
+            assert_eq!(changed, path.as_path());
+            htmls.push(html.unwrap());
+            if htmls.len() == 2 { return ControlFlow::Break(()) }
+            fs::write(&path, "World").unwrap();
+            let later = SystemTime::now() + Duration::from_secs(1);
+            File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+            ControlFlow::Continue(())
+        });
+        assert_eq!(htmls, vec!("Hello<br />".to_string(), "World<br />".to_string()));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+use crate::convert::options::RenderOptions;
+use crate::convert::renderer::escape_html;
+use crate::pipeline::Pipeline;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+// Polls `/__changed` and reloads the page once the answer differs from the first one.
+const RELOAD_SCRIPT: &str = "<script>
+(() => {
+  let seen = null;
+  setInterval(async () => {
+    try {
+      const stamp = await (await fetch('/__changed')).text();
+      if (seen !== null && stamp !== seen) location.reload();
+      seen = stamp;
+    } catch (e) {}
+  }, 500);
+})();
+</script>";
+
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status: u16, content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Response{ status, content_type, body: body.into() }
+    }
+
+    fn not_found() -> Self {
+        Response::new(404, "text/plain; charset=utf-8", "not found")
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()).unwrap_or("") {
+        "html" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+pub(crate) fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = vec!();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = path.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+// The newest modification time under `path`, as nanoseconds since the epoch.
+fn newest(path: &Path) -> u128 {
+    let Ok(meta) = fs::metadata(path) else { return 0 };
+    let own = meta.modified().ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos());
+    if !meta.is_dir() { return own }
+    let entries = fs::read_dir(path).into_iter().flatten().flatten();
+    entries.map(|entry| newest(&entry.path())).fold(own, u128::max)
+}
+
+// Serves a markdown file, or a directory of them, as html pages that reload themselves
+// when the sources change. Each request renders the file again, so there is nothing to
+// rebuild.
+pub struct Preview {
+    root: PathBuf,
+    pipeline: Pipeline,
+}
+
+impl Preview {
+    pub fn new(root: impl Into<PathBuf>, options: RenderOptions) -> Self {
+        let mut options = options;
+        options.document.get_or_insert_with(Default::default);
+        options.html.md_links = true;
+        Preview{ root: root.into(), pipeline: Pipeline::new(options) }
+    }
+
+    fn page(&self, path: &Path) -> Response {
+        match self.pipeline.convert(path) {
+            Ok(html) => {
+                let html = match html.rfind("</body>") {
+                    Some(end) => format!("{}{}\n{}", &html[..end], RELOAD_SCRIPT, &html[end..]),
+                    None => format!("{}\n{}", html, RELOAD_SCRIPT),
+                };
+                Response::new(200, "text/html; charset=utf-8", html)
+            },
+            Err(err) => Response::new(500, "text/plain; charset=utf-8", err.to_string()),
+        }
+    }
+
+    fn listing(&self, dir: &Path, url: &str) -> Response {
+        let Ok(entries) = fs::read_dir(dir) else { return Response::not_found() };
+        let mut names: Vec<String> = entries.flatten().filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if entry.path().is_dir() { return Some(format!("{}/", name)) }
+            Some(name.strip_suffix(".md")?.to_string() + ".html")
+        })
+        .collect();
+        names.sort();
+        let base = url.trim_end_matches('/');
+        let items: String = names.iter()
+            .map(|name| format!("<li><a href=\"{}/{}\">{}</a></li>\n", base, escape_html(name), escape_html(name)))
+            .collect();
+        let html = format!("<!DOCTYPE html>\n<ul>\n{}</ul>\n{}\n", items, RELOAD_SCRIPT);
+        Response::new(200, "text/html; charset=utf-8", html)
+    }
+
+    // The response to a GET of `url`. `.html` paths render the `.md` file next to them, and
+    // directories their `index.md` or a listing.
+    pub fn respond(&self, url: &str) -> Response {
+        let path = url.split(['?', '#']).next().unwrap_or("");
+        if path == "/__changed" {
+            return Response::new(200, "text/plain; charset=utf-8", newest(&self.root).to_string())
+        }
+        if self.root.is_file() {
+            return if path == "/" { self.page(&self.root) } else { Response::not_found() }
+        }
+        let Some(relative) = percent_decode(path.trim_start_matches('/')) else {
+            return Response::new(400, "text/plain; charset=utf-8", "bad path")
+        };
+        let relative = Path::new(&relative);
+        if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Response::not_found()
+        }
+        let target = self.root.join(relative);
+        if target.is_dir() {
+            let index = target.join("index.md");
+            return if index.is_file() { self.page(&index) } else { self.listing(&target, path) }
+        }
+        let source = target.with_extension("md");
+        if relative.extension().is_some_and(|ext| ext == "html") && source.is_file() {
+            return self.page(&source)
+        }
+        if relative.extension().is_some_and(|ext| ext == "md") && target.is_file() {
+            return self.page(&target)
+        }
+        match fs::read(&target) {
+            Ok(bytes) => Response::new(200, content_type(&target), bytes),
+            Err(_) => Response::not_found(),
+        }
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        // A client that connects and never sends, like a browser's preconnect, gives up its
+        // connection rather than keeping it open for good.
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        let mut reader = BufReader::new(&stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        // Reads the headers too, so closing the connection does not reset it.
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 { header.clear() }
+        let response = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["GET", url, _] => self.respond(url),
+            _ => Response::new(400, "text/plain; charset=utf-8", "only GET is served"),
+        };
+        let mut stream = stream;
+        write!(
+            stream, "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+            response.status, response.reason(), response.content_type, response.body.len()
+        )?;
+        stream.write_all(&response.body)?;
+        stream.flush()
+    }
+
+    // Answers each connection on its own thread, so a slow client doesn't hold up the
+    // others, until the listener fails.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let stream = stream?;
+                // A client hanging up early only loses its own response.
+                scope.spawn(move || { let _ = self.handle(stream); });
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("app-preview-{}-{}", std::process::id(), name));
+        fs::create_dir_all(dir.join("guide")).unwrap();
+        dir
+    }
+
+    fn body(response: Response) -> String {
+        assert_eq!(response.status, 200);
+        String::from_utf8(response.body).unwrap()
+    }
+
+    #[test]
+    fn test_respond() {
+        let dir = temp_dir("respond");
+        fs::write(dir.join("index.md"), "# Home\n[Setup](guide/setup.md)").unwrap();
+        fs::write(dir.join("guide/setup.md"), "# Setup").unwrap();
+        fs::write(dir.join("guide/logo.png"), "png").unwrap();
+        let preview = Preview::new(&dir, RenderOptions::default());
+
+        let home = body(preview.respond("/"));
+        assert!(home.contains("<title>Home</title>"));
+        assert!(home.contains("href=\"guide/setup.html\""));
+        assert!(home.contains("/__changed"));
+        assert!(home.find("/__changed") < home.find("</body>"));
+        assert!(body(preview.respond("/guide/setup.html")).contains("<h1 id=\"setup\">Setup</h1>"));
+        assert!(body(preview.respond("/guide/setup.md?x=1")).contains("<title>Setup</title>"));
+        assert!(body(preview.respond("/guide/")).contains("<a href=\"/guide/setup.html\">setup.html</a>"));
+
+        let logo = preview.respond("/guide/logo.png");
+        assert_eq!((logo.content_type, logo.body), ("image/png", b"png".to_vec()));
+        assert_eq!(preview.respond("/../secret").status, 404);
+        assert_eq!(preview.respond("/missing.html").status, 404);
+        assert_eq!(preview.respond("/%zz").status, 400);
+
+        let before = body(preview.respond("/__changed"));
+        fs::write(dir.join("guide/new.md"), "New").unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::options().write(true).open(dir.join("guide/new.md")).unwrap().set_modified(later).unwrap();
+        assert_ne!(body(preview.respond("/__changed")), before);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_serve_file() {
+        let dir = temp_dir("serve");
+        let file = dir.join("notes.md");
+        fs::write(&file, "**Notes**").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let preview = Preview::new(&file, RenderOptions::default());
+        preview.handle(listener.accept().unwrap().0).unwrap();
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/html"));
+        assert!(response.contains("<b>Notes</b>"));
+        assert_eq!(preview.respond("/other.html").status, 404);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_serve_idle_connection() {
+        let dir = temp_dir("idle");
+        let file = dir.join("notes.md");
+        fs::write(&file, "**Notes**").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let preview = Preview::new(&file, RenderOptions::default());
+        thread::spawn(move || preview.serve(listener));
+        let _idle = TcpStream::connect(addr).unwrap();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream.write_all(b"GET /__changed HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use crate::parser::options::ParserOptions;
+use crate::parser::parser::*;
+use crate::parser::span::{spans, NodeRef, SourceSpan, Spanned};
+use crate::query::kind;
+
+// A word of prose and where it is in the input. `context` holds the kinds of the nodes
+// around it, outermost first, like `["list", "item", "bold"]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'t> {
+    pub word: &'t str,
+    pub span: SourceSpan,
+    pub context: Vec<&'static str>,
+}
+
+fn is_url(chunk: &str) -> bool {
+    chunk.contains("://") || chunk.starts_with("www.") || chunk.contains('@')
+}
+
+// Runs of letters and digits, keeping apostrophes and hyphens between them, as in `don't`
+// or `well-known`; runs without a letter are left out.
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut found = vec!();
+    let mut start = None;
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    for (i, &(offset, c)) in chars.iter().enumerate() {
+        let joiner = matches!(c, '\'' | '’' | '-')
+            && start.is_some()
+            && chars.get(i + 1).is_some_and(|(_, next)| next.is_alphanumeric());
+        match (c.is_alphanumeric() || joiner, start) {
+            (true, None) => start = Some(offset),
+            (false, Some(begin)) => {
+                found.push((begin, &text[begin..offset]));
+                start = None;
+            },
+            _ => {},
+        }
+    }
+    if let Some(begin) = start { found.push((begin, &text[begin..])) }
+    found.into_iter().filter(|(_, word)| word.chars().any(char::is_alphabetic)).collect()
+}
+
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let line_start = text[..offset].rfind('\n').map_or(0, |index| index + 1);
+    (text[..offset].matches('\n').count() + 1, text[line_start..offset].chars().count() + 1)
+}
+
+fn collect<'t>(text: &'t str, spanned: &Spanned, context: &mut Vec<&'static str>, tokens: &mut Vec<Token<'t>>) {
+    if let NodeRef::Word(Word::Normal(_)) = spanned.node {
+        for (index, word) in words(&text[spanned.span.start..spanned.span.end]) {
+            let start = spanned.span.start + index;
+            // The parser may have split a url into several words, so look at all of it.
+            let chunk_start = text[..start].char_indices().rev().find(|(_, c)| c.is_whitespace()).map_or(0, |(index, c)| index + c.len_utf8());
+            let chunk_end = text[start..].find(char::is_whitespace).map_or(text.len(), |index| start + index);
+            if is_url(&text[chunk_start..chunk_end]) { continue }
+            let (line, column) = position(text, start);
+            let span = SourceSpan{ start, end: start + word.len(), line, column };
+            tokens.push(Token{ word, span, context: context.clone() });
+        }
+        return
+    }
+    context.push(kind(&spanned.node));
+    for child in &spanned.children {
+        collect(text, child, context, tokens)
+    }
+    context.pop();
+}
+
+// The words of the prose in `text`, which `mds` was parsed from, in document order, for spell
+// checkers and linters. Code, urls, link targets, image sources, keys and emoji are skipped.
+pub fn tokens<'t>(text: &'t str, mds: &[Md], options: &ParserOptions) -> impl Iterator<Item = Token<'t>> {
+    let mut tokens = vec!();
+    for spanned in spans(text, mds, options) {
+        collect(text, &spanned, &mut vec!(), &mut tokens)
+    }
+    tokens.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words() {
+        let words: Vec<&str> = words("Don't re-use 'quoted' -- well-known, 42 x2!").into_iter().map(|(_, word)| word).collect();
+        assert_eq!(words, vec!("Don't", "re-use", "quoted", "well-known", "x2"));
+    }
+
+    #[test]
+    fn test_tokens() {
+        let text = "# Teh title\n\nSee\u{a0} https://example.com or **bold [lnik](/url)**.\n```\nnot prose\n```\n- item";
+        let options = ParserOptions::default();
+        let mds = parse_with(text, &options);
+        let tokens: Vec<Token> = tokens(text, &mds, &options).collect();
+        let words: Vec<&str> = tokens.iter().map(|token| token.word).collect();
+        assert_eq!(words, vec!("Teh", "title", "See", "or", "bold", "lnik", "item"));
+        for token in &tokens {
+            assert_eq!(&text[token.span.start..token.span.end], token.word);
+        }
+        assert_eq!((tokens[0].span.line, tokens[0].span.column, tokens[0].context.clone()), (1, 3, vec!("heading")));
+        assert_eq!((tokens[5].span.line, tokens[5].span.column), (3, 37));
+        assert_eq!(tokens[5].context, vec!("sentence", "bold", "link"));
+        assert_eq!(tokens[6].context, vec!("list", "item"));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::parser::parser::parse_with;
+use crate::parser::options::ParserOptions;
+use crate::convert::convert::mds_to_html;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyNone};
+use serde_json::Value;
+
+// The json value as plain Python objects: objects become dicts and arrays lists.
+fn to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        Value::Null => PyNone::get(py).to_owned().into_any(),
+        Value::Bool(b) => b.into_pyobject(py)?.to_owned().into_any(),
+        Value::Number(n) => match n.as_u64() {
+            Some(n) => n.into_pyobject(py)?.into_any(),
+            None => n.as_f64().into_pyobject(py)?.into_any(),
+        },
+        Value::String(s) => s.into_pyobject(py)?.into_any(),
+        Value::Array(values) => {
+            let list = PyList::empty(py);
+            for value in values { list.append(to_py(py, value)?)?; }
+            list.into_any()
+        },
+        Value::Object(fields) => {
+            let dict = PyDict::new(py);
+            for (key, value) in fields { dict.set_item(key, to_py(py, value)?)?; }
+            dict.into_any()
+        },
+    })
+}
+
+fn options(loose_tables: bool, commonmark_underscores: bool) -> ParserOptions {
+    ParserOptions{ loose_tables, commonmark_underscores, ..Default::default() }
+}
+
+// A list with one dict per block, keyed by its variant like `to_json`,
+// e.g. `[{"Heading": [1, [{"Normal": "Title"}]]}]`.
+#[pyfunction]
+#[pyo3(name = "parse", signature = (text, loose_tables = false, commonmark_underscores = false))]
+fn py_parse<'py>(py: Python<'py>, text: &str, loose_tables: bool, commonmark_underscores: bool) -> PyResult<Bound<'py, PyAny>> {
+    let mds = parse_with(text, &options(loose_tables, commonmark_underscores));
+    let value = serde_json::to_value(&mds).expect("the markdown ast always serializes");
+    to_py(py, &value)
+}
+
+#[pyfunction]
+#[pyo3(signature = (text, loose_tables = false, commonmark_underscores = false))]
+fn to_html(text: &str, loose_tables: bool, commonmark_underscores: bool) -> String {
+    mds_to_html(&parse_with(text, &options(loose_tables, commonmark_underscores)))
+}
+
+#[pymodule]
+fn app(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(py_parse, module)?)?;
+    module.add_function(wrap_pyfunction!(to_html, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: &str) -> String {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "app").unwrap();
+            app(&module).unwrap();
+            let locals = PyDict::new(py);
+            locals.set_item("app", module).unwrap();
+            let code = std::ffi::CString::new(code).unwrap();
+            py.eval(&code, None, Some(&locals)).unwrap().to_string()
+        })
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(run("app.parse('# Title')"), "[{'Heading': [1, [{'Normal': 'Title'}]]}]");
+        assert_eq!(run("app.parse('**Hi**')[0]['Sentence'][0]['Bold']"), "[{'Normal': 'Hi'}]");
+        assert_eq!(run("app.parse('| A |\\n|:-:|\\n| a |')[0]['Table']['align']"), "['Center']");
+        assert_eq!(run("app.parse('')"), "[]");
+    }
+
+    #[test]
+    fn test_to_html() {
+        assert_eq!(run("app.to_html('# Title')"), "<h1 id=\"title\">Title</h1>");
+        assert_eq!(run("app.to_html('__a__')"), "<u>a</u><br />");
+        assert_eq!(run("app.to_html('__a__', commonmark_underscores=True)"), "<b>a</b><br />");
+    }
+}
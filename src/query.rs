@@ -0,0 +1,243 @@
+use crate::parser::parser::*;
+use crate::parser::span::NodeRef;
+use std::fmt;
+use std::str::FromStr;
+
+const KINDS: &[&str] = &[
+    "heading", "sentence", "table", "list", "container", "details", "toc", "abbreviation",
+    "footnote", "front-matter", "code", "quote", "raw", "custom", "item", "row", "cell", "text",
+    "italic", "bold", "strikethrough", "underline", "highlight", "subscript", "superscript",
+    "insert", "kbd", "emoji", "footnote-ref", "task", "entity", "link", "image",
+];
+
+// The name a selector uses for the node, as in `heading` or `link`.
+pub fn kind(node: &NodeRef) -> &'static str {
+    match node {
+        NodeRef::Md(md) => match md {
+            Md::Heading(..) => "heading",
+            Md::Sentence(_) => "sentence",
+            Md::Table(_) | Md::LooseTable(_) => "table",
+            Md::List(_) => "list",
+            Md::Container(..) => "container",
+            Md::Details(..) => "details",
+            Md::Toc => "toc",
+            Md::Abbreviation(..) => "abbreviation",
+            Md::Footnote(..) => "footnote",
+            Md::FrontMatter(..) => "front-matter",
+            Md::Code(..) => "code",
+            Md::Quote(_) => "quote",
+            Md::Raw(_) => "raw",
+            Md::Custom(..) => "custom",
+        },
+        NodeRef::Item(_) => "item",
+        NodeRef::Record(_) => "row",
+        NodeRef::Cell(_) => "cell",
+        NodeRef::Word(word) => match word {
+            Word::Normal(_) => "text",
+            Word::Italic(_) => "italic",
+            Word::Bold(_) => "bold",
+            Word::StrikeThough(_) => "strikethrough",
+            Word::Underline(_) => "underline",
+            Word::Highlight(_) => "highlight",
+            Word::Subscript(_) => "subscript",
+            Word::Superscript(_) => "superscript",
+            Word::Insert(_) => "insert",
+            Word::Kbd(_) => "kbd",
+            Word::Emoji(_) => "emoji",
+            Word::FootnoteRef(_) => "footnote-ref",
+            Word::Task(_) => "task",
+            Word::Entity(..) => "entity",
+            Word::Link(..) => "link",
+            Word::Image(..) => "image",
+            Word::Custom(..) => "custom",
+        },
+    }
+}
+
+// The attributes a selector can test: `level` of headings, `lang` of code, `name` of
+// containers and custom syntax, `open` of details, `label` of footnotes, `ordered` of lists,
+// `url` of links, `src` and `alt` of images.
+pub fn attr(node: &NodeRef, name: &str) -> Option<String> {
+    match (node, name) {
+        (NodeRef::Md(Md::Heading(level, _)), "level") => Some(level.to_string()),
+        (NodeRef::Md(Md::Code(lang, ..)), "lang") => Some(lang.clone()),
+        (NodeRef::Md(Md::Container(name, _)), "name") => Some(name.clone()),
+        (NodeRef::Md(Md::Custom(name, _)), "name") => Some(name.clone()),
+        (NodeRef::Md(Md::Details(open, ..)), "open") => Some(open.to_string()),
+        (NodeRef::Md(Md::Footnote(label, _)), "label") => Some(label.clone()),
+        (NodeRef::Md(Md::List(items)), "ordered") => Some(matches!(items.1, ListKind::Ordered(_)).to_string()),
+        (NodeRef::Word(Word::Link(_, url)), "url") => Some(url.clone()),
+        (NodeRef::Word(Word::Image(_, src, _)), "src") => Some(src.clone()),
+        (NodeRef::Word(Word::Image(alt, ..)), "alt") => Some(alt.clone()),
+        (NodeRef::Word(Word::FootnoteRef(label)), "label") => Some(label.clone()),
+        (NodeRef::Word(Word::Task(checked)), "checked") => Some(checked.to_string()),
+        (NodeRef::Word(Word::Entity(reference, _)), "reference") => Some(reference.clone()),
+        (NodeRef::Word(Word::Entity(_, text)), "text") => Some(text.clone()),
+        (NodeRef::Word(Word::Emoji(name)), "name") => Some(name.clone()),
+        (NodeRef::Word(Word::Custom(name, _)), "name") => Some(name.clone()),
+        _ => None,
+    }
+}
+
+fn push_words<'a>(words: &'a Words<'a>, nodes: &mut Vec<NodeRef<'a>>) {
+    for word in &words.0 {
+        nodes.push(NodeRef::Word(word));
+        match word {
+            Word::Italic(words) | Word::Bold(words) | Word::StrikeThough(words) | Word::Underline(words)
+            | Word::Highlight(words) | Word::Subscript(words) | Word::Superscript(words)
+            | Word::Insert(words) | Word::Link(words, _) => push_words(words, nodes),
+            _ => {},
+        }
+    }
+}
+
+fn push_record<'a>(record: &'a Record<'a>, nodes: &mut Vec<NodeRef<'a>>) {
+    nodes.push(NodeRef::Record(record));
+    for cell in &record.0 {
+        nodes.push(NodeRef::Cell(cell));
+        push_words(cell, nodes);
+    }
+}
+
+fn push_mds<'a>(mds: &'a [Md<'a>], nodes: &mut Vec<NodeRef<'a>>) {
+    for md in mds {
+        nodes.push(NodeRef::Md(md));
+        match md {
+            Md::Heading(_, words) | Md::Sentence(words) | Md::Footnote(_, words) => push_words(words, nodes),
+            Md::Table(table) => {
+                table.caption.iter().for_each(|caption| push_words(caption, nodes));
+                std::iter::once(&table.header).chain(&table.records).for_each(|record| push_record(record, nodes));
+            },
+            Md::LooseTable(table) => std::iter::once(&table.header).chain(&table.rows).for_each(|record| push_record(record, nodes)),
+            Md::List(items) => items.0.iter().for_each(|item| {
+                nodes.push(NodeRef::Item(item));
+                push_words(&item.0, nodes);
+                push_mds(&item.1, nodes);
+            }),
+            Md::Details(_, summary, mds) => {
+                push_words(summary, nodes);
+                push_mds(mds, nodes);
+            },
+            Md::Container(_, mds) | Md::Quote(mds) => push_mds(mds, nodes),
+            _ => {},
+        }
+    }
+}
+
+// Every node in document order, each before the nodes inside it.
+pub fn nodes<'a>(mds: &'a [Md<'a>]) -> Vec<NodeRef<'a>> {
+    let mut nodes = vec!();
+    push_mds(mds, &mut nodes);
+    nodes
+}
+
+pub fn find_all<'a>(mds: &'a [Md<'a>], predicate: impl Fn(&NodeRef<'a>) -> bool) -> Vec<NodeRef<'a>> {
+    nodes(mds).into_iter().filter(predicate).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorError(pub String);
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid selector: {}", self.0)
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+// A node kind, or `*` for any, with attributes that must all be equal, as in
+// `heading[level=2]` or `link[url="https://example.com"]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selector {
+    kind: Option<String>,
+    attrs: Vec<(String, String)>,
+}
+
+impl Selector {
+    // Any node, narrowed down with `kind` and `attr`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn kind(mut self, kind: &str) -> Self {
+        self.kind = Some(kind.to_string());
+        self
+    }
+
+    pub fn attr(mut self, name: &str, value: &str) -> Self {
+        self.attrs.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn matches(&self, node: &NodeRef) -> bool {
+        self.kind.as_ref().is_none_or(|kind| kind == self::kind(node))
+            && self.attrs.iter().all(|(name, value)| attr(node, name).as_ref() == Some(value))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(text: &str) -> Result<Self, SelectorError> {
+        let text = text.trim();
+        let (kind, mut rest) = text.split_at(text.find('[').unwrap_or(text.len()));
+        let mut selector = match kind {
+            "*" => Selector::new(),
+            _ if KINDS.contains(&kind) => Selector::new().kind(kind),
+            _ => return Err(SelectorError(format!("unknown node kind `{}`", kind))),
+        };
+        while !rest.is_empty() {
+            let error = || SelectorError(format!("malformed attribute in `{}`", text));
+            let (attr, after) = rest.strip_prefix('[').and_then(|rest| rest.split_once(']')).ok_or_else(error)?;
+            let (name, value) = attr.split_once('=').ok_or_else(error)?;
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value);
+            selector = selector.attr(name.trim(), value);
+            rest = after;
+        }
+        Ok(selector)
+    }
+}
+
+pub fn select<'a>(mds: &'a [Md<'a>], selector: &str) -> Result<Vec<NodeRef<'a>>, SelectorError> {
+    let selector: Selector = selector.parse()?;
+    Ok(find_all(mds, |node| selector.matches(node)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let mds = parse("# Title\n## One\n- item with [a link](/a)\n> ## Nested\n\n| A | [B](/b) |\n| - | - |\n| 1 | 2 |\n```rust\nfn main() {}\n```");
+        let headings = select(&mds, "heading[level=2]").unwrap();
+        let texts: Vec<String> = headings.iter().map(|node| match node {
+            NodeRef::Md(Md::Heading(_, words)) => crate::convert::text::words_to_text(words),
+            _ => unreachable!(),
+        })
+        .collect();
+        assert_eq!(texts, vec!("One".to_string(), "Nested".to_string()));
+        assert_eq!(select(&mds, "table").unwrap().len(), 1);
+        assert_eq!(select(&mds, "cell").unwrap().len(), 4);
+        assert_eq!(select(&mds, "link").unwrap().len(), 2);
+        assert_eq!(select(&mds, "link[url=\"/b\"]").unwrap().len(), 1);
+        assert_eq!(select(&mds, "code[lang=rust]").unwrap(), vec!(NodeRef::Md(mds.last().unwrap())));
+        assert_eq!(select(&mds, "*[level=1]").unwrap(), vec!(NodeRef::Md(&mds[0])));
+        assert_eq!(select(&mds, "heading[level=5]").unwrap(), vec!());
+    }
+
+    #[test]
+    fn test_selector() {
+        assert_eq!("heading[level=2]".parse(), Ok(Selector::new().kind("heading").attr("level", "2")));
+        assert_eq!(" image[src = \"a b.png\"][alt=x] ".parse(), Ok(Selector::new().kind("image").attr("src", "a b.png").attr("alt", "x")));
+        assert_eq!("headline".parse::<Selector>(), Err(SelectorError("unknown node kind `headline`".to_string())));
+        assert!("heading[level]".parse::<Selector>().is_err());
+        assert!("heading[level=2".parse::<Selector>().is_err());
+
+        let mds = parse("Text and *emphasis*");
+        let italic = find_all(&mds, |node| Selector::new().kind("italic").matches(node));
+        assert_eq!(italic.len(), 1);
+    }
+}
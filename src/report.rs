@@ -0,0 +1,159 @@
+use crate::parser::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::parser::error::{ParseError, ParseErrorKind};
+use std::fmt;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn color(self) -> &'static str {
+        match self {
+            Severity::Error => "\x1b[1;31m",
+            Severity::Warning => "\x1b[1;33m",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+// A problem to point out in the source: the 1-based line and column it starts at, and how
+// many characters of that line the caret underlines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub severity: Severity,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    pub width: usize,
+    pub note: Option<String>,
+}
+
+impl From<&ParseError> for Annotation {
+    fn from(err: &ParseError) -> Self {
+        let note = match &err.kind {
+            ParseErrorKind::UnclosedCodeFence => Some("close the block with a fence as long as the opening one"),
+            ParseErrorKind::UnclosedContainer => Some("close the container with a `:::` line"),
+            ParseErrorKind::MalformedTable => Some("a table needs a `| - |` alignment row under the header, and as many cells in each row"),
+            ParseErrorKind::Limit(_) => None,
+        };
+        let width = err.snippet.chars().count().saturating_sub(err.column - 1);
+        Annotation{
+            severity: Severity::Error, message: err.kind.to_string(), line: err.line, column: err.column,
+            width, note: note.map(str::to_string),
+        }
+    }
+}
+
+impl From<&Diagnostic> for Annotation {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        let note = match &diagnostic.kind {
+            DiagnosticKind::UnclosedEmphasis(_) => Some("the marker is shown as text"),
+            DiagnosticKind::RaggedTable(_) => Some("missing cells are left empty and extra ones dropped"),
+            DiagnosticKind::UnknownFootnote(_) => Some("add a `[^label]: text` definition"),
+            DiagnosticKind::SkippedHeadingLevel(..) => Some("screen readers navigate by heading level"),
+            DiagnosticKind::MissingAltText(_) => Some("describe the image between the brackets"),
+            DiagnosticKind::BareUrlLink(_) => Some("screen readers spell the url out; say where it leads"),
+        };
+        let width = diagnostic.snippet.chars().count().saturating_sub(diagnostic.column - 1);
+        let span = diagnostic.span.end.saturating_sub(diagnostic.span.start);
+        Annotation{
+            severity: Severity::Warning, message: diagnostic.kind.to_string(), line: diagnostic.line,
+            column: diagnostic.column, width: width.min(span), note: note.map(str::to_string),
+        }
+    }
+}
+
+// Shows the annotation rustc-style, under `name:line:column` with the source line and a
+// caret line below it; `color` adds ANSI colors for a terminal.
+//
+// ```text
+// error: code fence is never closed
+//  --> notes.md:3:1
+//   |
+// 3 | ```rust
+//   | ^^^^^^^
+//   = note: close the block with a fence as long as the opening one
+// ```
+pub fn annotate(name: &str, text: &str, annotation: &Annotation, color: bool) -> String {
+    let paint = |style: &str, part: &str| if color { format!("{}{}{}", style, part, RESET) } else { part.to_string() };
+    let number = annotation.line.to_string();
+    let gutter = " ".repeat(number.len());
+    let bar = paint(BLUE, "|");
+    let source = text.lines().nth(annotation.line.saturating_sub(1)).unwrap_or("");
+    let indent: String = source.chars().take(annotation.column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let caret = "^".repeat(annotation.width.max(1));
+    let mut report = format!(
+        "{}{}\n{}{} {}:{}:{}\n{} {}\n{} {} {}\n{} {} {}{}\n",
+        paint(annotation.severity.color(), &annotation.severity.to_string()),
+        paint(BOLD, &format!(": {}", annotation.message)),
+        gutter, paint(BLUE, "-->"), name, annotation.line, annotation.column,
+        gutter, bar,
+        paint(BLUE, &number), bar, source,
+        gutter, bar, indent, paint(annotation.severity.color(), &caret),
+    );
+    if let Some(note) = &annotation.note {
+        report.push_str(&format!("{} {} note: {}\n", gutter, paint(BLUE, "="), note));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::diagnostics::parse_with_diagnostics;
+    use crate::parser::error::try_parse;
+    use crate::parser::options::ParserOptions;
+
+    #[test]
+    fn test_annotate_parse_error() {
+        let text = "# Title\n\n```rust\nfn main() {}";
+        let err = try_parse(text).unwrap_err();
+        let expect = "\
+error: code fence is never closed
+ --> notes.md:3:1
+  |
+3 | ```rust
+  | ^^^^^^^
+  = note: close the block with a fence as long as the opening one
+";
+        assert_eq!(annotate("notes.md", text, &Annotation::from(&err), false), expect);
+
+        let colored = annotate("notes.md", text, &Annotation::from(&err), true);
+        assert!(colored.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: code fence is never closed\x1b[0m\n"));
+        assert!(colored.contains("\x1b[1;34m3\x1b[0m \x1b[1;34m|\x1b[0m ```rust\n"));
+    }
+
+    #[test]
+    fn test_annotate_diagnostic() {
+        let text = "Intro\n\n  Some **bold text\n";
+        let (_, diagnostics) = parse_with_diagnostics(text, &ParserOptions::default());
+        let diagnostic = diagnostics.iter().next().unwrap();
+        let annotation = Annotation::from(diagnostic);
+        assert_eq!(annotation.severity, Severity::Warning);
+        let report = annotate("a.md", text, &annotation, false);
+        assert!(report.starts_with("warning: `**` is never closed\n --> a.md:3:"), "{}", report);
+        assert!(report.ends_with("  = note: the marker is shown as text\n"));
+
+        let annotation = Annotation{
+            severity: Severity::Error, message: "tabs".to_string(), line: 12, column: 2, width: 0, note: None,
+        };
+        let text = "\n".repeat(11) + "\tx";
+        assert_eq!(annotate("t.md", &text, &annotation, false), "error: tabs\n  --> t.md:12:2\n   |\n12 | \tx\n   | \t^\n");
+    }
+}
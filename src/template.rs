@@ -0,0 +1,103 @@
+use crate::parser::parser::parse;
+use crate::convert::convert::mds_to_html_with;
+use crate::convert::options::{RenderError, RenderOptions};
+
+pub const FILTER_NAME: &str = "markdown";
+
+pub fn render(text: &str, options: &RenderOptions) -> Result<String, RenderError> {
+    mds_to_html_with(&parse(text), options)
+}
+
+#[cfg(feature = "tera")]
+pub struct TeraMarkdown(pub RenderOptions);
+
+#[cfg(feature = "tera")]
+impl tera::Filter for TeraMarkdown {
+    fn filter(
+        &self,
+        value: &tera::Value,
+        _args: &std::collections::HashMap<String, tera::Value>
+    ) -> tera::Result<tera::Value> {
+        let text = value.as_str()
+            .ok_or_else(|| tera::Error::msg("markdown filter expects a string"))?;
+        let html = render(text, &self.0).map_err(|err| tera::Error::msg(err.to_string()))?;
+        Ok(tera::Value::String(html))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "tera")]
+pub fn register_tera(tera: &mut tera::Tera, options: RenderOptions) {
+    tera.register_filter(FILTER_NAME, TeraMarkdown(options));
+}
+
+#[cfg(feature = "askama")]
+pub mod askama_filters {
+    use super::*;
+    use askama::filters::Safe;
+
+    pub const RENDER_OPTIONS: &str = "render_options";
+
+    pub fn markdown<T: std::fmt::Display>(
+        text: T,
+        values: &dyn askama::Values
+    ) -> askama::Result<Safe<String>> {
+        let default = RenderOptions::default();
+        let options = askama::get_value::<RenderOptions>(values, RENDER_OPTIONS).unwrap_or(&default);
+        let html = render(&text.to_string(), options).map_err(askama::Error::custom)?;
+        Ok(Safe(html))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::options::LimitAction;
+
+    #[test]
+    fn test_render() {
+        let options = RenderOptions::default();
+        assert_eq!(render("# Hello", &options), Ok("<h1 id=\"hello\">Hello</h1>".to_string()));
+
+        let options = RenderOptions{ max_bytes: Some(4), on_limit: LimitAction::Error, ..Default::default() };
+        assert_eq!(render("# Hello", &options), Err(RenderError::TooManyBytes(4)));
+    }
+
+    #[test]
+    #[cfg(feature = "tera")]
+    fn test_tera_filter() {
+        let mut tera = tera::Tera::default();
+        tera.add_raw_template("page", "<main>{{ body | markdown }}</main>").unwrap();
+        register_tera(&mut tera, RenderOptions::default());
+        let mut context = tera::Context::new();
+        context.insert("body", "# Hello\n**World**");
+        let html = tera.render("page", &context).unwrap();
+        assert_eq!(html, "<main><h1 id=\"hello\">Hello</h1>\n<b>World</b><br /></main>");
+    }
+
+    #[test]
+    #[cfg(feature = "askama")]
+    fn test_askama_filter() {
+        use askama::Template;
+
+        mod filters {
+            pub use crate::template::askama_filters::*;
+        }
+
+        #[derive(Template)]
+        #[template(source = "<main>{{ body|markdown }}</main>", ext = "html")]
+        struct Page<'a> {
+            body: &'a str,
+        }
+
+        let page = Page{ body: "# Hello\n**World**" };
+        assert_eq!(page.render().unwrap(), "<main><h1 id=\"hello\">Hello</h1>\n<b>World</b><br /></main>");
+
+        let options = RenderOptions{ max_bytes: Some(4), ..Default::default() };
+        let values: [(&str, &dyn std::any::Any); 1] = [(askama_filters::RENDER_OPTIONS, &options)];
+        assert!(page.render_with_values(&values).is_err());
+    }
+}
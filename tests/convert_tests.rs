@@ -0,0 +1,193 @@
+use app::*;
+use app::{items,words,normal_word,record};
+
+// One of every block and inline variant, so every converter match is exercised.
+fn every_variant() -> Vec<Md<'static>> {
+    let inline = words!(
+        normal_word!("text"),
+        Word::Italic(words!(normal_word!("italic"))),
+        Word::Bold(words!(normal_word!("bold"))),
+        Word::StrikeThough(words!(normal_word!("strike"))),
+        Word::Underline(words!(normal_word!("underline"))),
+        Word::Highlight(words!(normal_word!("highlight"))),
+        Word::Subscript(words!(normal_word!("sub"))),
+        Word::Superscript(words!(normal_word!("sup"))),
+        Word::Insert(words!(normal_word!("insert"))),
+        Word::Kbd("Ctrl".to_string()),
+        Word::Custom("issue".to_string(), "#1".to_string()),
+        Word::Emoji("smile".to_string()),
+        Word::FootnoteRef("1".to_string()),
+        Word::FootnoteRef("missing".to_string()),
+        Word::Task(true),
+        Word::Entity("&lt;".to_string(), "<".to_string()),
+        Word::Link(words!(normal_word!("link")), "https://example.com".to_string()),
+        Word::Image("alt".to_string(), "a.png".to_string(), ImageSize{ width: Some("50%".to_string()), height: None })
+    );
+    let table = Table{
+        header: record!(words!(normal_word!("A")), words!(normal_word!("B"))),
+        align: vec!(Align::Left, Align::Right),
+        records: vec!(record!(words!(normal_word!("1")), words!(normal_word!("2")))),
+        caption: Some(words!(normal_word!("caption"))),
+    };
+    let loose = LooseTable{
+        header: record!(words!(normal_word!("A"))),
+        rows: vec!(record!(words!(normal_word!("1")), words!(normal_word!("2")))),
+        issue: TableIssue::ColumnCount(1, 2),
+    };
+    vec!(
+        Md::FrontMatter(FrontMatterFormat::Yaml, "title: doc".to_string()),
+        Md::Raw("<div>raw</div>".to_string()),
+        Md::Custom("diagram".to_string(), "a -> b".to_string()),
+        Md::Toc,
+        Md::Heading(1, words!(normal_word!("Title"))),
+        Md::Sentence(inline),
+        Md::Table(Box::new(table)),
+        Md::LooseTable(Box::new(loose)),
+        Md::List(items!(Item(words!(normal_word!("item")), vec!(Md::Sentence(words!(normal_word!("nested"))))))),
+        Md::List(Items(vec!(Item(words!(normal_word!("first")), vec!())), ListKind::Ordered(3))),
+        Md::Container("note".to_string(), vec!(Md::Sentence(words!(normal_word!("inside"))))),
+        Md::Details(true, words!(normal_word!("summary")), vec!(Md::Sentence(words!(normal_word!("body"))))),
+        Md::Abbreviation("HTML".to_string(), "Hyper Text Markup Language".to_string()),
+        Md::Footnote("1".to_string(), words!(normal_word!("note"))),
+        Md::Code("rust".to_string(), "fn main() {}\n".to_string(), vec!((1, 1))),
+        Md::Quote(vec!(Md::Sentence(words!(normal_word!("quoted"))))),
+    )
+}
+
+#[test]
+fn test_every_variant_converts() {
+    let mds = every_variant();
+    assert!(mds_to_html(&mds).contains("<h1"));
+    let options = RenderOptions{ number_headings: true, heading_anchors: true, ..Default::default() };
+    assert!(mds_to_html_with(&mds, &options).is_ok());
+    assert!(!mds_to_tree(&mds, &options).unwrap().is_empty());
+    assert!(to_ansi(&mds).contains("Title"));
+    assert!(to_latex(&mds).contains("Title"));
+    assert!(to_man(&mds, 1).contains("quoted"));
+    assert!(to_asciidoc(&mds).contains("Title"));
+    assert!(to_rst(&mds).contains("Title"));
+    assert!(to_markdown(&mds).contains("# Title"));
+    #[cfg(feature = "docx")]
+    assert!(to_docx(&mds).is_ok());
+    #[cfg(feature = "pdf")]
+    assert!(to_pdf(&mds).starts_with(b"%PDF"));
+}
+
+#[test]
+fn test_parsed_text_always_converts() {
+    let texts = [
+        "**unclosed", "~~", "[^", "[text](", "![alt](", "__", "`", "[", "^", "~", "==", "++",
+    ];
+    for text in texts {
+        let mds = parse(text);
+        assert!(!mds_to_html(&mds).is_empty(), "{}", text);
+    }
+}
+
+// Random documents from `Arbitrary`, over bytes from a fixed xorshift seed so failures repeat.
+#[cfg(feature = "testing")]
+#[test]
+fn test_arbitrary_documents_convert() {
+    use arbitrary::{Arbitrary, Unstructured};
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    for _ in 0..200 {
+        let bytes: Vec<u8> = (0..512).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+        let Ok(mds) = Vec::<Md>::arbitrary(&mut Unstructured::new(&bytes)) else { continue };
+        let options = RenderOptions{ number_headings: true, heading_anchors: true, ..Default::default() };
+        mds_to_html(&mds);
+        assert!(mds_to_html_with(&mds, &options).is_ok());
+        to_ansi(&mds);
+        to_latex(&mds);
+        to_man(&mds, 1);
+        to_asciidoc(&mds);
+        to_rst(&mds);
+        parse(&to_markdown(&mds));
+        dump(&mds);
+        #[cfg(feature = "json")]
+        assert_eq!(from_json(&to_json(&mds)).unwrap(), mds);
+    }
+}
+
+fn issue(text: &str) -> Option<usize> {
+    let digits = text[1..].bytes().take_while(u8::is_ascii_digit).count();
+    (digits > 0).then_some(digits + 1)
+}
+
+fn issue_link(source: &str) -> String {
+    format!("<a href=\"https://github.com/hitsan/app/issues/{}\">{}</a>", &source[1..], source)
+}
+
+#[test]
+fn test_custom_inline_syntax() {
+    let parser = Parser::builder().inline_extension("issue", '#', issue).build();
+    let mds = parser.parse("Fixed in #12 and #3.");
+    assert_eq!(mds_to_html(&mds), "Fixed in <span class=\"issue\">#12</span> and <span class=\"issue\">#3</span>.<br />");
+
+    let mut options = RenderOptions::default();
+    options.custom_words.insert("issue".to_string(), issue_link);
+    let expect = "Fixed in <a href=\"https://github.com/hitsan/app/issues/12\">#12</a> and <a href=\"https://github.com/hitsan/app/issues/3\">#3</a>.<br />";
+    assert_eq!(mds_to_html_with(&mds, &options).unwrap(), expect);
+    assert_eq!(to_markdown(&mds), "Fixed in #12 and #3.");
+}
+
+fn diagram(text: &str) -> Option<usize> {
+    text.starts_with("%%").then(|| text.find("\n\n").unwrap_or(text.len()))
+}
+
+fn mermaid(source: &str) -> String {
+    format!("<pre class=\"mermaid\">{}</pre>", app::convert::renderer::escape_html(source.trim_start_matches('%')))
+}
+
+#[test]
+fn test_custom_block_syntax() {
+    let parser = Parser::builder().block_extension("diagram", 0, diagram).build();
+    let mds = parser.parse("%%a --> b\n\nText");
+    assert_eq!(mds_to_html(&mds), "<div class=\"diagram\">%%a --> b</div>\n<br />\nText<br />");
+
+    let mut options = RenderOptions::default();
+    options.custom_blocks.insert("diagram".to_string(), mermaid);
+    assert!(mds_to_html_with(&mds, &options).unwrap().starts_with("<pre class=\"mermaid\">a --&gt; b</pre>"));
+    assert_eq!(to_markdown(&mds), "%%a --> b\n\nText");
+}
+
+// Runs the CommonMark spec examples and prints where the crate diverges. Needs the spec's
+// `spec.txt`: `COMMONMARK_SPEC=path/to/spec.txt cargo test --test convert_tests -- --ignored --nocapture`.
+#[test]
+fn test_entities() {
+    let mds = parse("&lt;b&gt;not bold&lt;/b&gt; &amp;amp; &copy; &#x1F600;");
+    assert_eq!(mds_to_html(&mds), "&lt;b&gt;not bold&lt;/b&gt; &amp;amp; © 😀<br />");
+    assert_eq!(mds.to_plain_text(), "<b>not bold</b> &amp; © 😀");
+}
+
+#[test]
+fn test_gfm() {
+    let options = ParserOptions::gfm();
+    let mds = parse_with("- [x] ~~Ship~~ it[^1]\n- [ ] See <https://example.com>\n\n[^1]: Soon", &options);
+    let html = mds_to_html(&mds);
+    assert!(html.contains("<li><input type=\"checkbox\" disabled checked /> <s>Ship</s> it"), "{}", html);
+    assert!(html.contains("<li><input type=\"checkbox\" disabled /> See <a href=\"https://example.com\">https://example.com</a></li>"), "{}", html);
+    assert!(html.contains("Soon"), "{}", html);
+    assert_eq!(app::convert::sanitize::sanitize_html(&html), html);
+}
+
+#[test]
+#[ignore]
+fn test_commonmark_spec() {
+    use app::commonmark::{parse_spec, run_spec};
+    let path = std::env::var("COMMONMARK_SPEC").expect("COMMONMARK_SPEC should point at the spec's spec.txt");
+    let spec = std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path, err));
+    let examples = parse_spec(&spec);
+    assert!(!examples.is_empty(), "{} has no examples", path);
+    let report = run_spec(&examples);
+    for (number, html) in &report.failed {
+        let example = &examples[number - 1];
+        println!("example {} ({}):\n{}--- expected\n{}--- got\n{}\n", number, example.section, example.markdown, example.html, html);
+    }
+    println!("{}", report);
+}
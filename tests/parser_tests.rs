@@ -1,5 +1,6 @@
 use app::parser::parser::*;
-use app::{items,words,normal_word};
+use app::parser::options::{LimitExceeded, ParserOptions};
+use app::{items,words,normal_word,record};
 
 #[test]
 fn test_parser() {
@@ -8,31 +9,31 @@ fn test_parser() {
     let bold_line = Word::Underline(bold);
     let words = words!(bold_line);
     let md = Md::Sentence(words);
-    assert_eq!(parse(&"__**Hello World!**__"), vec!(md));
+    assert_eq!(parse("__**Hello World!**__"), vec!(md));
 
     let normal = words!(normal_word!("Hello World!"));
     let line = words!(Word::Underline(normal));
     let line_bold = Word::Bold(line);
     let words = words!(line_bold);
     let md = Md::Sentence(words);
-    assert_eq!(parse(&"**__Hello World!__**"), vec!(md));
+    assert_eq!(parse("**__Hello World!__**"), vec!(md));
 
     let normal = words!(normal_word!("Hello World!"));
     let line_normal = words!(Word::Underline(normal));
     let bold_line_normal = words!(Word::Bold(line_normal));
     let strike_bold_line_normal = words!(Word::StrikeThough(bold_line_normal));
     let md = Md::Sentence(strike_bold_line_normal);
-    assert_eq!(parse(&"~~**__Hello World!__**~~"), vec!(md));
+    assert_eq!(parse("~~**__Hello World!__**~~"), vec!(md));
 
     let hello = normal_word!("Hello ");
     let world = Word::Bold(words!(normal_word!("World!")));
     let word = words!(hello, world);
     let md = Md::Sentence(word);
-    assert_eq!(parse(&"Hello **World!**"), vec!(md));
+    assert_eq!(parse("Hello **World!**"), vec!(md));
 
     let normal = words!(normal_word!("Hello World!"));
     let md = Md::Heading(1, normal);
-    assert_eq!(parse(&"# Hello World!"), vec!(md));
+    assert_eq!(parse("# Hello World!"), vec!(md));
 }
 
 #[test]
@@ -47,36 +48,389 @@ fn test_parsing_multiline() {
     let bold_sentence = Md::Sentence(bold);
 
     let mds = vec!(head, sentence, bold_sentence);
-    assert_eq!(parse(&"# Hello World!\nrust parser\n**lines**"), mds);
+    assert_eq!(parse("# Hello World!\nrust parser\n**lines**"), mds);
 }
 #[test]
 fn test_table() {
     let a = words!(normal_word!("A"));
     let b = words!(normal_word!("B"));
     let c = words!(normal_word!("C"));
-    let header = Record(vec!(a, b, c));
+    let header = Record(vec!(a, b, c), vec!());
     let align = vec!(Align::Right, Align::Left, Align::Center);
     let d = words!(normal_word!("d"));
     let e = words!(normal_word!("e"));
     let f = words!(normal_word!("f"));
-    let record0 = Record(vec!(d, e, f));
+    let record0 = Record(vec!(d, e, f), vec!());
     let j = words!(normal_word!("j"));
     let k = words!(normal_word!("k"));
     let l = words!(normal_word!("l"));
-    let record1 = Record(vec!(j, k, l));
+    let record1 = Record(vec!(j, k, l), vec!());
     let records = vec!(record0, record1);
-    let md = Md::Table(Box::new(Table{header, align, records}));
+    let md = Md::Table(Box::new(Table{header, align, records, caption: None}));
     let test_word = "| A | B | C | \n|-:|--|:-:|\n| d | e | f |\n| j | k | l |\n";
-    assert_eq!(parse(&test_word), vec!(md));
+    assert_eq!(parse(test_word), vec!(md));
+}
+
+#[test]
+fn test_table_fullwidth_pipes() {
+    let a = words!(normal_word!("あ"));
+    let b = words!(normal_word!("い"));
+    let header = Record(vec!(a, b), vec!());
+    let align = vec!(Align::Left, Align::Left);
+    let c = words!(normal_word!("う"));
+    let d = words!(normal_word!("え"));
+    let records = vec!(Record(vec!(c, d), vec!()));
+    let md = Md::Table(Box::new(Table{header, align, records, caption: None}));
+    assert_eq!(parse("｜ あ ｜ い ｜\n｜---｜---｜\n｜\u{a0}う\u{a0}｜ え ｜\n"), vec!(md));
 }
 
 #[test]
 fn test_list() {
     let world = words!(normal_word!("World"));
-    let item0 = Item(world, items!());
+    let item0 = Item(world, vec!());
     let children = items!(item0);
     let hello = words!(normal_word!("Hello"));
-    let item = Item(hello, children);
+    let item = Item(hello, vec!(Md::List(children)));
     let md = Md::List(items!(item));
-    assert_eq!(parse(&"- Hello\n  - World"), vec!(md));
-}
\ No newline at end of file
+    assert_eq!(parse("- Hello\n  - World"), vec!(md));
+}
+
+#[test]
+fn test_container() {
+    let hello = Md::Sentence(words!(normal_word!("Hello")));
+    let world = Md::Sentence(words!(normal_word!("World")));
+    let md = Md::Container("warning".to_string(), vec!(hello, world));
+    let end = Md::Sentence(words!(normal_word!("End")));
+    assert_eq!(parse("::: warning\nHello\nWorld\n:::\nEnd"), vec!(md, end));
+}
+
+#[test]
+fn test_loose_table() {
+    let text = "| A | B |\n| -: | :-b: |\n| a | b |";
+    let a = words!(normal_word!("A"));
+    let b = words!(normal_word!("B"));
+    let header = Record(vec!(a, b), vec!());
+    let line = normal_word!("| A | B |");
+    let first = Md::Sentence(words!(line));
+    assert_eq!(parse(text)[0], first);
+
+    let options = ParserOptions{ loose_tables: true, ..Default::default() };
+    match &parse_with(text, &options)[..] {
+        [Md::LooseTable(table)] => {
+            assert_eq!(table.header, header);
+            assert_eq!(table.rows.len(), 2);
+            assert_eq!(table.issue, TableIssue::InvalidAlign);
+        },
+        mds => panic!("unexpected {:?}", mds),
+    }
+}
+
+#[test]
+fn test_ragged_table() {
+    let text = "| A | B |\n| - | - |\n| a |\n| b | c | d |";
+    let Md::Table(table) = &parse(text)[0] else { panic!("expected a table") };
+    assert_eq!(table.records, vec!());
+
+    let options = ParserOptions{ ragged_rows: true, ..Default::default() };
+    let Md::Table(table) = &parse_with(text, &options)[0] else { panic!("expected a table") };
+    let a = words!(normal_word!("a"));
+    let nul = words!(normal_word!(""));
+    let b = words!(normal_word!("b"));
+    let c = words!(normal_word!("c"));
+    assert_eq!(table.records, vec!(record!(a, nul), record!(b, c)));
+}
+
+#[test]
+fn test_crlf() {
+    let lf = "# Title\n| A | B |\n|-|-|\n| a | b |\n- item\n  more\nEnd";
+    assert_eq!(parse(&lf.replace('\n', "\r\n")), parse(lf));
+    assert_eq!(parse(&lf.replace('\n', "\r")), parse(lf));
+
+    let head = Md::Heading(1, words!(normal_word!("Title")));
+    let sentence = Md::Sentence(words!(normal_word!("End")));
+    assert_eq!(parse("# Title\r\nEnd\r\n"), vec!(head, sentence));
+}
+
+#[test]
+fn test_tabs() {
+    assert_eq!(parse("-\titem\n\t\t- child"), parse("- item\n    - child"));
+    assert_eq!(parse("| A\t| B |\n|-|-|\n| a\t| b |"), parse("| A | B |\n|-|-|\n| a | b |"));
+
+    let code = Md::Code("".to_string(), "\tindented".to_string(), vec!());
+    assert_eq!(parse("```\n\tindented\n```"), vec!(code));
+}
+
+#[test]
+fn test_bom() {
+    let head = Md::Heading(1, words!(normal_word!("Title")));
+    assert_eq!(parse("\u{FEFF}# Title"), vec!(head));
+
+    let text = "# Ti\u{200B}tle\u{2060}";
+    let head = Md::Heading(1, words!(normal_word!(text[2..])));
+    assert_eq!(parse(text), vec!(head));
+
+    let options = ParserOptions{ strip_zero_width: true, ..Default::default() };
+    let head = Md::Heading(1, words!(normal_word!("Title")));
+    assert_eq!(parse_with(text, &options), vec!(head));
+}
+
+#[test]
+fn test_non_ascii_never_panics() {
+    let texts = [
+        "| ： | 😀 |\n| -： | ：😀 |\n| é | ü |",
+        "｜😀｜\n｜：-：｜\n｜あ｜",
+        "- 項目\n\u{3000}\n  - 子\n\u{3000}\u{3000}続き",
+        "# 見出し 😀\n**太字** ~下~ ^上^ [[キー]] :😀:",
+        "> ｜a｜\n> 😀\n::: 注意\n本文\n:::",
+    ];
+    for text in texts {
+        parse(text);
+        for end in text.char_indices().map(|(i, _)| i) {
+            parse(&text[..end]);
+        }
+    }
+}
+
+#[test]
+fn test_pathological_nesting() {
+    let depth = 10_000;
+    let quotes = format!("{}deep", "> ".repeat(depth));
+    parse(&quotes);
+
+    let marks = ["**", "==", "++", "~~", "__"];
+    let open: String = (0..depth).map(|i| marks[i % marks.len()]).collect();
+    let close: String = (0..depth).rev().map(|i| marks[i % marks.len()]).collect();
+    parse(&format!("{}deep{}", open, close));
+
+    let lists: String = (0..500).map(|i| format!("{}- item\n", "  ".repeat(i))).collect();
+    parse(&lists);
+}
+
+#[test]
+fn test_unclosed_delimiters() {
+    let next = Md::Sentence(words!(normal_word!("next line")));
+    let mds = parse("**bold without close\nnext line");
+    assert_eq!(mds, vec!(Md::Sentence(words!(normal_word!("**"), normal_word!("bold without close"))), next.clone()));
+    let mds = parse("| broken table\nnext line");
+    assert_eq!(mds, vec!(Md::Sentence(words!(normal_word!("| broken table"))), next.clone()));
+    let mds = parse("[a link](page.md\nnext line");
+    assert_eq!(mds, vec!(Md::Sentence(words!(normal_word!("[a link](page.md"))), next));
+}
+
+#[test]
+fn test_limits() {
+    let text = "# Title\n| A | B |\n|-|-|\n| a | b |\n| c | d |\n> **quoted**";
+    let options = ParserOptions::default();
+    assert_eq!(try_parse_with(text, &options), Ok(parse(text)));
+
+    let options = ParserOptions{ max_input_bytes: Some(10), ..Default::default() };
+    assert_eq!(try_parse_with(text, &options), Err(LimitExceeded::InputBytes(10)));
+
+    let options = ParserOptions{ max_table_rows: Some(1), ..Default::default() };
+    assert_eq!(try_parse_with(text, &options), Err(LimitExceeded::TableRows(1)));
+
+    let options = ParserOptions{ max_table_columns: Some(1), ..Default::default() };
+    assert_eq!(try_parse_with(text, &options), Err(LimitExceeded::TableColumns(1)));
+
+    // heading 2, table 1 + 3 records + 6 cells, quote 1 + sentence 1 + bold 2
+    let options = ParserOptions{ max_nodes: Some(16), ..Default::default() };
+    assert!(try_parse_with(text, &options).is_ok());
+    let options = ParserOptions{ max_nodes: Some(15), ..Default::default() };
+    assert_eq!(try_parse_with(text, &options), Err(LimitExceeded::Nodes(15)));
+    assert_eq!(LimitExceeded::Nodes(15).to_string(), "document exceeds 15 nodes");
+}
+
+#[test]
+fn test_ast_in_collections() {
+    use std::collections::HashSet;
+    let mds = parse("# Title\nSame\nSame\n| A |\n| - |\n| a |\nSame");
+    let unique: HashSet<Md> = mds.iter().cloned().collect();
+    assert_eq!(unique.len(), 3);
+
+    let mut table = Table::default();
+    table.header.0.push(words!(normal_word!("A")));
+    table.align.push(Align::Left);
+    let mut doc = mds.clone();
+    doc.push(Md::Table(Box::new(table)));
+    assert_eq!(doc[..mds.len()], mds[..]);
+    assert_eq!(Items::default(), items!());
+    assert!(Words::default().0.is_empty());
+
+    let (_, positions) = parse_with_positions("b\n\na", &ParserOptions::default());
+    let mut sorted = positions.clone();
+    sorted.sort_by(|a, b| b.cmp(a));
+    sorted.sort();
+    assert_eq!(sorted, positions);
+}
+
+#[test]
+fn test_borrowed_text() {
+    use std::borrow::Cow;
+    let is_borrowed = |md: &Md| matches!(md, Md::Sentence(Words(words)) if matches!(words[0], Word::Normal(Cow::Borrowed(_))));
+    let text = String::from("Plain text\n> quoted");
+    let mds = parse(&text);
+    assert!(is_borrowed(&mds[0]));
+
+    // Text that had to be normalized, or was joined from several lines, is owned.
+    assert!(!is_borrowed(&parse("Plain\ttext")[0]));
+    let owned: Vec<Md<'static>> = mds.iter().cloned().map(Md::into_owned).collect();
+    drop(text);
+    assert_eq!(owned, parse("Plain text\n> quoted"));
+}
+
+// Random strings of markup characters: `parse` must not panic on any input.
+#[test]
+fn test_parse_never_panics() {
+    let alphabet = [
+        "*", "**", "_", "~", "~~", "^", "=", "+", "|", "\\", "`", "```", ":::", "???", "[", "]",
+        "(", ")", "<", ">", "!", "#", "-", ":", "^[", "[^", "{", "}", "$", "&", "\"",
+        " ", "  ", "\t", "\n", "\n\n", "\r", "1.", "a", "word", "é", "あ", "🦀", "\u{FEFF}", "\u{200B}",
+        "｜", "---", "+++", "- [ ] ", "*[", "<kbd>", ":smile:",
+    ];
+    let all = ParserOptions{
+        loose_tables: true,
+        ragged_rows: true,
+        table_spans: true,
+        commonmark_underscores: true,
+        strip_zero_width: true,
+        ..Default::default()
+    };
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    for _ in 0..2_000 {
+        let mut text = String::new();
+        for _ in 0..seed % 60 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            text.push_str(alphabet[(seed % alphabet.len() as u64) as usize]);
+        }
+        parse(&text);
+        parse_with(&text, &all);
+    }
+}
+
+// An `@@@` fence up to and including its closing line.
+fn diagram(text: &str) -> Option<usize> {
+    let body = text.strip_prefix("@@@")?;
+    let close = body.find("\n@@@")? + "\n@@@".len();
+    Some(3 + close + body[close..].find('\n').unwrap_or(body.len() - close))
+}
+
+fn line(text: &str) -> usize {
+    text.find('\n').unwrap_or(text.len())
+}
+
+fn note(text: &str) -> Option<usize> {
+    text.starts_with("# NOTE").then(|| line(text))
+}
+
+fn shout(text: &str) -> Option<usize> {
+    text.starts_with('#').then(|| line(text))
+}
+
+#[test]
+fn test_block_extensions() {
+    let parser = app::Parser::builder()
+        .block_extension("diagram", 0, diagram)
+        .block_extension("note", -1, note)
+        .block_extension("shout", -2, shout)
+        .build();
+    let mds = parser.parse("@@@ flow\na -> b\n@@@\n> @@@\n> c\n> @@@\n# NOTE x\nbye");
+    let expect = vec!(
+        Md::Custom("diagram".to_string(), "@@@ flow\na -> b\n@@@".to_string()),
+        Md::Quote(vec!(Md::Custom("diagram".to_string(), "@@@\nc\n@@@".to_string()))),
+        Md::Heading(1, words!(normal_word!("NOTE x"))),
+        Md::Sentence(words!(normal_word!("bye"))),
+    );
+    assert_eq!(mds, expect);
+
+    // A non-negative priority runs before the built-in blocks, higher first.
+    let parser = app::Parser::builder().block_extension("shout", 1, shout).block_extension("note", 2, note).build();
+    assert_eq!(parser.parse("# NOTE x\n# Hi"), vec!(
+        Md::Custom("note".to_string(), "# NOTE x".to_string()),
+        Md::Custom("shout".to_string(), "# Hi".to_string()),
+    ));
+    assert_eq!(parse("@@@\n@@@"), vec!(Md::Sentence(words!(normal_word!("@@@"))), Md::Sentence(words!(normal_word!("@@@")))));
+}
+
+mod macros {
+    // Only the macros are in scope, so they must name the AST types themselves.
+    use app::{md, normal_word, record, words};
+
+    #[test]
+    fn test_construction_macros() {
+        let mds = vec!(md!(h2: "Usage"), md!(quote: [md!(p: "Note")]), md!(p: ""), md!(list: ["a", "b",]));
+        assert_eq!(mds, app::parse("## Usage\n> Note\n\n- a\n- b"));
+        let row = record!(words!(normal_word!("x")), words!(normal_word!(2)));
+        assert_eq!(row, app::Record(vec!(app::Words(vec!(app::Word::Normal("x".into()))), app::Words(vec!(app::Word::Normal("2".into())))), vec!()));
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing_spans() {
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{field::{Field, Visit}, Event, Metadata};
+
+    // Records each new span as its name and fields, e.g. `block offset=8`.
+    struct Spans(Arc<Mutex<Vec<String>>>);
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl tracing::Subscriber for Spans {
+        fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.0.lock().unwrap();
+            let mut fields = Fields(span.metadata().name().to_string());
+            span.record(&mut fields);
+            spans.push(fields.0);
+            Id::from_u64(spans.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let spans = Arc::new(Mutex::new(vec!()));
+    tracing::subscriber::with_default(Spans(spans.clone()), || {
+        app::mds_to_html_with(&parse("# Title\n**bold**"), &Default::default()).unwrap();
+    });
+    let spans = spans.lock().unwrap();
+    assert_eq!(spans[..4], ["parse bytes=16", "block offset=0", "inline bytes=5", "block offset=8"]);
+    assert!(spans.contains(&"render blocks=2".to_string()));
+    assert!(spans.contains(&"render_block index=1".to_string()));
+}
+
+// Brackets that never close are looked up once per line, not searched for again from every
+// `[`, so reading them takes time in proportion to the line.
+#[test]
+fn test_pathological_brackets() {
+    for unit in ["[a]", "[a](", "[[", "[^", "![a](b){x", "[a](([a](( x"] {
+        let text = unit.repeat(200_000 / unit.len());
+        let start = std::time::Instant::now();
+        parse(&text);
+        let elapsed = start.elapsed();
+        assert!(elapsed < std::time::Duration::from_secs(2), "{:?} took {:?}", unit, elapsed);
+    }
+}
+
+// Openers that never close are searched past once per kind of closer, not by every closer.
+#[test]
+fn test_pathological_delimiters() {
+    for unit in ["~a^", "*a_", "**a*", "^a ~b c^"] {
+        let text = unit.repeat(200_000 / unit.len());
+        let start = std::time::Instant::now();
+        parse(&text);
+        let elapsed = start.elapsed();
+        assert!(elapsed < std::time::Duration::from_secs(2), "{:?} took {:?}", unit, elapsed);
+    }
+}